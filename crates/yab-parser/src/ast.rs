@@ -1,78 +1,465 @@
 #![allow(dead_code)]
 
+use std::ops::RangeBounds;
+
 use serde::{Deserialize, Serialize};
+use strum_macros::IntoStaticStr;
+
+pub use crate::location::{Position, Span};
 
-#[derive(Debug, Deserialize, Serialize)]
+mod estree;
+pub mod visit;
+
+/// Any node in the AST.  Converting one to a `&'static str` gives the name of
+/// its type, like `"BinaryExpression"`.
+#[derive(Debug, PartialEq, Deserialize, Serialize, IntoStaticStr)]
 #[serde(tag = "type")]
 pub enum Node {
     Program(Program),
+    SequenceExpression(SequenceExpression),
+    AssignmentExpression(AssignmentExpression),
+    ConditionalExpression(ConditionalExpression),
+    LogicalExpression(LogicalExpression),
     BinaryExpression(BinaryExpression),
+    UnaryExpression(UnaryExpression),
+    UpdateExpression(UpdateExpression),
+    AwaitExpression(AwaitExpression),
     CallExpression(CallExpression),
+    NewExpression(NewExpression),
+    MemberExpression(MemberExpression),
+    OptionalMemberExpression(OptionalMemberExpression),
+    OptionalCallExpression(OptionalCallExpression),
+    TaggedTemplateExpression(TaggedTemplateExpression),
+    TemplateLiteral(TemplateLiteral),
+    ArrayExpression(ArrayExpression),
+    ObjectExpression(ObjectExpression),
+    Property(Property),
+    ObjectPattern(ObjectPattern),
+    ArrayPattern(ArrayPattern),
+    RestElement(RestElement),
+    AssignmentPattern(AssignmentPattern),
+    ThisExpression(ThisExpression),
     NumericLiteral(NumericLiteral),
+    BigIntLiteral(BigIntLiteral),
+    StringLiteral(StringLiteral),
+    BooleanLiteral(BooleanLiteral),
+    NullLiteral(NullLiteral),
+    RegExpLiteral(RegExpLiteral),
     Identifier(Identifier),
+    ImportDeclaration(ImportDeclaration),
+    ImportSpecifier(ImportSpecifier),
+    ImportDefaultSpecifier(ImportDefaultSpecifier),
+    ImportNamespaceSpecifier(ImportNamespaceSpecifier),
+    ExportNamedDeclaration(ExportNamedDeclaration),
+    ExportSpecifier(ExportSpecifier),
+    ExportDefaultDeclaration(ExportDefaultDeclaration),
+    ExportAllDeclaration(ExportAllDeclaration),
     FunctionDeclaration(FunctionDeclaration),
+    ArrowFunctionExpression(ArrowFunctionExpression),
+    VariableDeclaration(VariableDeclaration),
+    BlockStatement(BlockStatement),
+    EmptyStatement(EmptyStatement),
+    IfStatement(IfStatement),
+    WhileStatement(WhileStatement),
+    DoWhileStatement(DoWhileStatement),
     ReturnStatement(ReturnStatement),
+    ThrowStatement(ThrowStatement),
+    BreakStatement(BreakStatement),
+    ContinueStatement(ContinueStatement),
     ExpressionStatement(ExpressionStatement),
-    Paramter(Parameter),
+    Parameter(Parameter),
+    JSXElement(JSXElement),
+    JSXFragment(JSXFragment),
+    JSXAttribute(JSXAttribute),
+    JSXSpreadAttribute(JSXSpreadAttribute),
+    JSXExpressionContainer(JSXExpressionContainer),
+    JSXText(JSXText),
+    JSXIdentifier(JSXIdentifier),
+    JSXMemberExpression(JSXMemberExpression),
+    JSXNamespacedName(JSXNamespacedName),
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct Identifier {
-    value: String,
+    pub(crate) value: String,
+    pub(crate) span: Span,
 }
 
 impl Identifier {
     pub fn new(value: String) -> Self {
-        Self { value }
+        Self {
+            value,
+            span: Span::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.value
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct Parameter {
-    pat: Box<Node>,
+    pub(crate) pat: Box<Node>,
+    pub(crate) span: Span,
 }
 
 impl Parameter {
     pub fn new(pat: Node) -> Self {
-        Self { pat: Box::new(pat) }
+        Self {
+            pat: Box::new(pat),
+            span: Span::default(),
+        }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct VariableDeclarator {
-    id: Identifier,
-    init: Option<Node>,
+    /// An [`Identifier`], or an [`ObjectPattern`] or [`ArrayPattern`] for a
+    /// destructuring declaration.
+    pub(crate) id: Box<Node>,
+    pub(crate) init: Option<Node>,
+    pub(crate) span: Span,
+}
+
+impl VariableDeclarator {
+    pub fn new(id: Node, init: Option<Node>) -> Self {
+        Self {
+            id: Box::new(id),
+            init,
+            span: Span::default(),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct NumericLiteral {
-    value: f64,
+    pub(crate) value: f64,
+    pub(crate) span: Span,
 }
 
 impl NumericLiteral {
     pub fn new(value: f64) -> Self {
-        Self { value }
+        Self {
+            value,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct BigIntLiteral {
+    /// The literal in decimal (e.g. `16n` for `0x10n`), since it won't fit in
+    /// an f64.
+    pub(crate) value: String,
+    pub(crate) span: Span,
+}
+
+impl BigIntLiteral {
+    pub fn new(value: String) -> Self {
+        Self {
+            value,
+            span: Span::default(),
+        }
+    }
+}
+
+/// A string literal, with its escape sequences already interpreted.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct StringLiteral {
+    pub(crate) value: String,
+    /// The UTF-16 code units of the value, if it has a lone surrogate, which
+    /// `value` has a U+FFFD in place of.
+    pub(crate) code_units: Option<Vec<u16>>,
+    pub(crate) span: Span,
+}
+
+impl StringLiteral {
+    pub fn new(value: String) -> Self {
+        Self {
+            value,
+            code_units: None,
+            span: Span::default(),
+        }
+    }
+
+    pub fn with_code_units(mut self, code_units: Option<Vec<u16>>) -> Self {
+        self.code_units = code_units;
+        self
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// The value as the UTF-16 code units a JavaScript string is made of.
+    pub fn to_utf16(&self) -> Vec<u16> {
+        match &self.code_units {
+            Some(code_units) => code_units.clone(),
+            None => self.value.encode_utf16().collect(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct BooleanLiteral {
+    pub(crate) value: bool,
+    pub(crate) span: Span,
+}
+
+impl BooleanLiteral {
+    pub fn new(value: bool) -> Self {
+        Self {
+            value,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize, Default)]
+pub struct NullLiteral {
+    pub(crate) span: Span,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct RegExpLiteral {
+    pub(crate) pattern: String,
+    pub(crate) flags: String,
+    pub(crate) span: Span,
+}
+
+impl RegExpLiteral {
+    pub fn new(pattern: String, flags: String) -> Self {
+        Self {
+            pattern,
+            flags,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize, Default)]
+pub struct ThisExpression {
+    pub(crate) span: Span,
+}
+
+/// One of the string parts of a template literal.  A template with `n`
+/// expressions always has `n + 1` of these, the last of which is the tail.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct TemplateElement {
+    /// The text with its escape sequences interpreted, or `None` in a tagged
+    /// template if one of them isn't valid.
+    pub(crate) cooked: Option<String>,
+    /// The UTF-16 code units of the cooked text, if it has a lone surrogate,
+    /// which `cooked` has a U+FFFD in place of.
+    pub(crate) cooked_code_units: Option<Vec<u16>>,
+    /// The text as written, except that line terminators are all `\n`.
+    pub(crate) raw: String,
+    pub(crate) tail: bool,
+    pub(crate) span: Span,
+}
+
+impl TemplateElement {
+    /// Creates a template element without escape sequences, so that its raw
+    /// and cooked text are the same.
+    pub fn new(raw: String, tail: bool) -> Self {
+        Self {
+            cooked: Some(raw.clone()),
+            cooked_code_units: None,
+            raw,
+            tail,
+            span: Span::default(),
+        }
+    }
+
+    pub fn with_cooked(mut self, cooked: Option<String>) -> Self {
+        self.cooked = cooked;
+        self
+    }
+
+    pub fn with_cooked_code_units(mut self, code_units: Option<Vec<u16>>) -> Self {
+        self.cooked_code_units = code_units;
+        self
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct TemplateLiteral {
+    pub(crate) quasis: Vec<TemplateElement>,
+    pub(crate) expressions: Vec<Node>,
+    pub(crate) span: Span,
+}
+
+impl TemplateLiteral {
+    pub fn new(quasis: Vec<TemplateElement>, expressions: Vec<Node>) -> Self {
+        Self {
+            quasis,
+            expressions,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct TaggedTemplateExpression {
+    pub(crate) tag: Box<Node>,
+    pub(crate) quasi: TemplateLiteral,
+    pub(crate) span: Span,
+}
+
+impl TaggedTemplateExpression {
+    pub fn new(tag: Node, quasi: TemplateLiteral) -> Self {
+        Self {
+            tag: Box::new(tag),
+            quasi,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ArrayExpression {
+    /// `None` for elisions, e.g. the hole in `[a, , b]`.
+    pub(crate) elements: Vec<Option<Node>>,
+    pub(crate) span: Span,
+}
+
+impl ArrayExpression {
+    pub fn new(elements: Vec<Option<Node>>) -> Self {
+        Self {
+            elements,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ObjectExpression {
+    pub(crate) properties: Vec<Node>,
+    pub(crate) span: Span,
+}
+
+impl ObjectExpression {
+    pub fn new(properties: Vec<Node>) -> Self {
+        Self {
+            properties,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct Property {
+    pub(crate) key: Box<Node>,
+    pub(crate) value: Box<Node>,
+    /// Whether the key was written as `[expr]`.
+    pub(crate) computed: bool,
+    /// Whether this was written as `{ a }` rather than `{ a: a }`.
+    pub(crate) shorthand: bool,
+    pub(crate) span: Span,
+}
+
+impl Property {
+    pub fn new(key: Node, value: Node, computed: bool, shorthand: bool) -> Self {
+        Self {
+            key: Box::new(key),
+            value: Box::new(value),
+            computed,
+            shorthand,
+            span: Span::default(),
+        }
+    }
+}
+
+/// A destructuring pattern like `{ a, b: [c] }`.  The properties are
+/// [`Property`] nodes whose values are the targets, plus an optional
+/// [`RestElement`] at the end.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ObjectPattern {
+    pub(crate) properties: Vec<Node>,
+    pub(crate) span: Span,
+}
+
+impl ObjectPattern {
+    pub fn new(properties: Vec<Node>) -> Self {
+        Self {
+            properties,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ArrayPattern {
+    /// `None` for elisions, e.g. the hole in `[a, , b] = c`.
+    pub(crate) elements: Vec<Option<Node>>,
+    pub(crate) span: Span,
+}
+
+impl ArrayPattern {
+    pub fn new(elements: Vec<Option<Node>>) -> Self {
+        Self {
+            elements,
+            span: Span::default(),
+        }
+    }
+}
+
+/// `...rest` at the end of a pattern or parameter list.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct RestElement {
+    pub(crate) argument: Box<Node>,
+    pub(crate) span: Span,
+}
+
+impl RestElement {
+    pub fn new(argument: Node) -> Self {
+        Self {
+            argument: Box::new(argument),
+            span: Span::default(),
+        }
+    }
+}
+
+/// A pattern with a default value, like the `a = 1` in `function f(a = 1)`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct AssignmentPattern {
+    pub(crate) lhs: Box<Node>,
+    pub(crate) rhs: Box<Node>,
+    pub(crate) span: Span,
+}
+
+impl AssignmentPattern {
+    pub fn new(lhs: Node, rhs: Node) -> Self {
+        Self {
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            span: Span::default(),
+        }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct ExpressionStatement {
-    expression: Box<Node>,
+    pub(crate) expression: Box<Node>,
+    pub(crate) span: Span,
 }
 
 impl ExpressionStatement {
     pub fn new(expr: Node) -> Self {
         Self {
             expression: Box::new(expr),
+            span: Span::default(),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct BinaryExpression {
-    lhs: Box<Node>,
-    rhs: Box<Node>,
-    operator: String,
+    pub(crate) lhs: Box<Node>,
+    pub(crate) rhs: Box<Node>,
+    pub(crate) operator: String,
+    pub(crate) span: Span,
 }
 
 impl BinaryExpression {
@@ -81,56 +468,420 @@ impl BinaryExpression {
             lhs: Box::new(lhs),
             rhs: Box::new(rhs),
             operator,
+            span: Span::default(),
+        }
+    }
+}
+
+/// `&&`, `||` and `??`, which are kept apart from [`BinaryExpression`]
+/// because they short circuit.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct LogicalExpression {
+    pub(crate) lhs: Box<Node>,
+    pub(crate) rhs: Box<Node>,
+    pub(crate) operator: String,
+    pub(crate) span: Span,
+}
+
+impl LogicalExpression {
+    pub fn new(lhs: Node, rhs: Node, operator: String) -> Self {
+        Self {
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            operator,
+            span: Span::default(),
+        }
+    }
+}
+
+/// `=` and the compound assignment operators like `+=` and `??=`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct AssignmentExpression {
+    pub(crate) lhs: Box<Node>,
+    pub(crate) rhs: Box<Node>,
+    pub(crate) operator: String,
+    pub(crate) span: Span,
+}
+
+impl AssignmentExpression {
+    pub fn new(lhs: Node, rhs: Node, operator: String) -> Self {
+        Self {
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            operator,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ConditionalExpression {
+    pub(crate) test: Box<Node>,
+    pub(crate) consequent: Box<Node>,
+    pub(crate) alternate: Box<Node>,
+    pub(crate) span: Span,
+}
+
+impl ConditionalExpression {
+    pub fn new(test: Node, consequent: Node, alternate: Node) -> Self {
+        Self {
+            test: Box::new(test),
+            consequent: Box::new(consequent),
+            alternate: Box::new(alternate),
+            span: Span::default(),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Expressions separated by the comma operator, e.g. `a, b, c`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct SequenceExpression {
+    pub(crate) expressions: Vec<Node>,
+    pub(crate) span: Span,
+}
+
+impl SequenceExpression {
+    pub fn new(expressions: Vec<Node>) -> Self {
+        Self {
+            expressions,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct UnaryExpression {
+    pub(crate) argument: Box<Node>,
+    pub(crate) operator: String,
+    pub(crate) span: Span,
+}
+
+impl UnaryExpression {
+    pub fn new(argument: Node, operator: String) -> Self {
+        Self {
+            argument: Box::new(argument),
+            operator,
+            span: Span::default(),
+        }
+    }
+}
+
+/// `++` and `--`, in either prefix or postfix position.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct UpdateExpression {
+    pub(crate) argument: Box<Node>,
+    pub(crate) operator: String,
+    pub(crate) prefix: bool,
+    pub(crate) span: Span,
+}
+
+impl UpdateExpression {
+    pub fn new(argument: Node, operator: String, prefix: bool) -> Self {
+        Self {
+            argument: Box::new(argument),
+            operator,
+            prefix,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct AwaitExpression {
+    pub(crate) argument: Box<Node>,
+    pub(crate) span: Span,
+}
+
+impl AwaitExpression {
+    pub fn new(argument: Node) -> Self {
+        Self {
+            argument: Box::new(argument),
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct CallExpression {
-    callee: Identifier,
-    arguments: Vec<Node>,
+    pub(crate) callee: Box<Node>,
+    pub(crate) arguments: Vec<Node>,
+    pub(crate) span: Span,
 }
 
 impl CallExpression {
-    pub fn new(callee: String, arguments: Vec<Node>) -> Self {
+    pub fn new(callee: Node, arguments: Vec<Node>) -> Self {
+        Self {
+            callee: Box::new(callee),
+            arguments,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct NewExpression {
+    pub(crate) callee: Box<Node>,
+    pub(crate) arguments: Vec<Node>,
+    pub(crate) span: Span,
+}
+
+impl NewExpression {
+    pub fn new(callee: Node, arguments: Vec<Node>) -> Self {
+        Self {
+            callee: Box::new(callee),
+            arguments,
+            span: Span::default(),
+        }
+    }
+}
+
+/// Property access, either `object.property` or `object[property]`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct MemberExpression {
+    pub(crate) object: Box<Node>,
+    pub(crate) property: Box<Node>,
+    /// Whether the property was written as `[expr]`.
+    pub(crate) computed: bool,
+    pub(crate) span: Span,
+}
+
+impl MemberExpression {
+    pub fn new(object: Node, property: Node, computed: bool) -> Self {
+        Self {
+            object: Box::new(object),
+            property: Box::new(property),
+            computed,
+            span: Span::default(),
+        }
+    }
+}
+
+/// A member access inside an optional chain, like both accesses in `a?.b.c`.
+/// Everything after a `?.` up to the end of the chain is skipped when the
+/// value before the `?.` is nullish, so the whole chain is made of these
+/// optional nodes, and `optional` marks the ones that were written with `?.`.
+/// Parentheses end the chain, so `(a?.b).c` is a plain [`MemberExpression`]
+/// of an optional one.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct OptionalMemberExpression {
+    pub(crate) object: Box<Node>,
+    pub(crate) property: Box<Node>,
+    pub(crate) computed: bool,
+    pub(crate) optional: bool,
+    pub(crate) span: Span,
+}
+
+impl OptionalMemberExpression {
+    pub fn new(object: Node, property: Node, computed: bool, optional: bool) -> Self {
+        Self {
+            object: Box::new(object),
+            property: Box::new(property),
+            computed,
+            optional,
+            span: Span::default(),
+        }
+    }
+}
+
+/// A call inside an optional chain, like `a?.()` or the call in `a?.b()`.
+/// See [`OptionalMemberExpression`].
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct OptionalCallExpression {
+    pub(crate) callee: Box<Node>,
+    pub(crate) arguments: Vec<Node>,
+    pub(crate) optional: bool,
+    pub(crate) span: Span,
+}
+
+impl OptionalCallExpression {
+    pub fn new(callee: Node, arguments: Vec<Node>, optional: bool) -> Self {
         Self {
-            callee: Identifier::new(callee),
+            callee: Box::new(callee),
             arguments,
+            optional,
+            span: Span::default(),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct ReturnStatement {
-    argument: Box<Node>,
+    pub(crate) argument: Option<Box<Node>>,
+    pub(crate) span: Span,
 }
 
 impl ReturnStatement {
-    pub fn new(expr: Node) -> Self {
+    pub fn new(expr: Option<Node>) -> Self {
+        Self {
+            argument: expr.map(Box::new),
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ThrowStatement {
+    pub(crate) argument: Box<Node>,
+    pub(crate) span: Span,
+}
+
+impl ThrowStatement {
+    pub fn new(argument: Node) -> Self {
+        Self {
+            argument: Box::new(argument),
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct BreakStatement {
+    pub(crate) label: Option<Identifier>,
+    pub(crate) span: Span,
+}
+
+impl BreakStatement {
+    pub fn new(label: Option<Identifier>) -> Self {
+        Self {
+            label,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ContinueStatement {
+    pub(crate) label: Option<Identifier>,
+    pub(crate) span: Span,
+}
+
+impl ContinueStatement {
+    pub fn new(label: Option<Identifier>) -> Self {
         Self {
-            argument: Box::new(expr),
+            label,
+            span: Span::default(),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, PartialEq, Deserialize, Serialize, Default)]
 pub struct BlockStatement {
-    statements: Vec<Node>,
+    pub(crate) statements: Vec<Node>,
+    pub(crate) span: Span,
+}
+
+impl BlockStatement {
+    pub fn new(statements: Vec<Node>) -> Self {
+        Self {
+            statements,
+            span: Span::default(),
+        }
+    }
+
+    pub fn statements(&self) -> &[Node] {
+        &self.statements
+    }
+
+    pub fn statements_mut(&mut self) -> &mut Vec<Node> {
+        &mut self.statements
+    }
+
+    /// Replaces the statements in `range` with `statements`, returning the
+    /// ones removed.  An empty range inserts, and an empty `statements`
+    /// removes.
+    pub fn splice(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        statements: impl IntoIterator<Item = Node>,
+    ) -> Vec<Node> {
+        self.statements.splice(range, statements).collect()
+    }
+}
+
+/// A lone `;`.
+#[derive(Debug, PartialEq, Deserialize, Serialize, Default)]
+pub struct EmptyStatement {
+    pub(crate) span: Span,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct IfStatement {
+    pub(crate) test: Box<Node>,
+    pub(crate) consequent: Box<Node>,
+    pub(crate) alternate: Option<Box<Node>>,
+    pub(crate) span: Span,
+}
+
+impl IfStatement {
+    pub fn new(test: Node, consequent: Node, alternate: Option<Node>) -> Self {
+        Self {
+            test: Box::new(test),
+            consequent: Box::new(consequent),
+            alternate: alternate.map(Box::new),
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct WhileStatement {
+    pub(crate) test: Box<Node>,
+    pub(crate) body: Box<Node>,
+    pub(crate) span: Span,
+}
+
+impl WhileStatement {
+    pub fn new(test: Node, body: Node) -> Self {
+        Self {
+            test: Box::new(test),
+            body: Box::new(body),
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct DoWhileStatement {
+    pub(crate) body: Box<Node>,
+    pub(crate) test: Box<Node>,
+    pub(crate) span: Span,
+}
+
+impl DoWhileStatement {
+    pub fn new(body: Node, test: Node) -> Self {
+        Self {
+            body: Box::new(body),
+            test: Box::new(test),
+            span: Span::default(),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct FunctionDeclaration {
-    identifier: Identifier,
-    params: Vec<Parameter>,
-    body: BlockStatement,
+    /// Only `None` for `export default function () {}`.
+    pub(crate) identifier: Option<Identifier>,
+    pub(crate) params: Vec<Parameter>,
+    pub(crate) body: BlockStatement,
+    pub(crate) span: Span,
 }
 
 impl FunctionDeclaration {
     pub fn new(ident: String) -> Self {
         Self {
-            identifier: Identifier::new(ident),
+            identifier: Some(Identifier::new(ident)),
             params: Vec::new(),
             body: BlockStatement::default(),
+            span: Span::default(),
+        }
+    }
+
+    pub fn anonymous() -> Self {
+        Self {
+            identifier: None,
+            params: Vec::new(),
+            body: BlockStatement::default(),
+            span: Span::default(),
         }
     }
 
@@ -141,20 +892,590 @@ impl FunctionDeclaration {
     pub fn body_append(&mut self, stmt: Node) {
         self.body.statements.push(stmt)
     }
+
+    /// Sets the spans of the function's name and body, which the parser only
+    /// knows once it has parsed them.
+    pub fn set_inner_spans(&mut self, identifier: Span, body: Span) {
+        if let Some(ident) = &mut self.identifier {
+            ident.span = identifier;
+        }
+        self.body.span = body;
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ArrowFunctionExpression {
+    pub(crate) params: Vec<Parameter>,
+    /// Either a [`BlockStatement`], or an expression for a concise body like
+    /// the one in `x => x * 2`.
+    pub(crate) body: Box<Node>,
+    #[serde(rename = "async")]
+    pub(crate) is_async: bool,
+    pub(crate) span: Span,
+}
+
+impl ArrowFunctionExpression {
+    pub fn new(params: Vec<Parameter>, body: Node, is_async: bool) -> Self {
+        Self {
+            params,
+            body: Box::new(body),
+            is_async,
+            span: Span::default(),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub struct VariableDeclaration {
-    declarations: Vec<VariableDeclarator>,
+    /// One of "var", "let" or "const".
+    pub(crate) kind: String,
+    pub(crate) declarations: Vec<VariableDeclarator>,
+    pub(crate) span: Span,
+}
+
+impl VariableDeclaration {
+    pub fn new(kind: String, declarations: Vec<VariableDeclarator>) -> Self {
+        Self {
+            kind,
+            declarations,
+            span: Span::default(),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, PartialEq, Deserialize, Serialize, Default)]
 pub struct Program {
-    body: Vec<Node>,
+    pub(crate) body: Vec<Node>,
+    pub(crate) span: Span,
 }
 
 impl Program {
     pub fn append(&mut self, stmt: Node) {
         self.body.push(stmt);
     }
+
+    pub fn body(&self) -> &[Node] {
+        &self.body
+    }
+
+    pub fn body_mut(&mut self) -> &mut Vec<Node> {
+        &mut self.body
+    }
+
+    /// Like [`BlockStatement::splice`], for the top level statements.
+    pub fn splice(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        statements: impl IntoIterator<Item = Node>,
+    ) -> Vec<Node> {
+        self.body.splice(range, statements).collect()
+    }
 }
+
+/// `import a, { b as c } from "mod"`, or just `import "mod"` for its side
+/// effects, in which case there are no specifiers.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ImportDeclaration {
+    pub(crate) specifiers: Vec<Node>,
+    pub(crate) source: StringLiteral,
+    pub(crate) span: Span,
+}
+
+impl ImportDeclaration {
+    pub fn new(specifiers: Vec<Node>, source: StringLiteral) -> Self {
+        Self {
+            specifiers,
+            source,
+            span: Span::default(),
+        }
+    }
+
+    /// The module being imported, like `"mod"` in `import a from "mod"`.
+    pub fn source(&self) -> &StringLiteral {
+        &self.source
+    }
+}
+
+/// `b as c` in `import { b as c } from "mod"`.  `imported` is either an
+/// [`Identifier`] or a [`StringLiteral`].
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ImportSpecifier {
+    pub(crate) imported: Box<Node>,
+    pub(crate) local: Identifier,
+    pub(crate) span: Span,
+}
+
+impl ImportSpecifier {
+    pub fn new(imported: Node, local: Identifier) -> Self {
+        Self {
+            imported: Box::new(imported),
+            local,
+            span: Span::default(),
+        }
+    }
+}
+
+/// `a` in `import a from "mod"`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ImportDefaultSpecifier {
+    pub(crate) local: Identifier,
+    pub(crate) span: Span,
+}
+
+impl ImportDefaultSpecifier {
+    pub fn new(local: Identifier) -> Self {
+        Self {
+            local,
+            span: Span::default(),
+        }
+    }
+}
+
+/// `* as ns` in `import * as ns from "mod"`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ImportNamespaceSpecifier {
+    pub(crate) local: Identifier,
+    pub(crate) span: Span,
+}
+
+impl ImportNamespaceSpecifier {
+    pub fn new(local: Identifier) -> Self {
+        Self {
+            local,
+            span: Span::default(),
+        }
+    }
+}
+
+/// Either an exported declaration, like `export const a = 1`, or a list of
+/// specifiers, like `export { a, b as c }`, optionally re-exported from
+/// another module with `from "mod"`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ExportNamedDeclaration {
+    pub(crate) declaration: Option<Box<Node>>,
+    pub(crate) specifiers: Vec<Node>,
+    pub(crate) source: Option<StringLiteral>,
+    pub(crate) span: Span,
+}
+
+impl ExportNamedDeclaration {
+    pub fn new(
+        declaration: Option<Node>,
+        specifiers: Vec<Node>,
+        source: Option<StringLiteral>,
+    ) -> Self {
+        Self {
+            declaration: declaration.map(Box::new),
+            specifiers,
+            source,
+            span: Span::default(),
+        }
+    }
+
+    /// The module being re-exported from, if there is a `from` clause.
+    pub fn source(&self) -> Option<&StringLiteral> {
+        self.source.as_ref()
+    }
+}
+
+/// `b as c` in `export { b as c }`.  Both sides are either an [`Identifier`]
+/// or a [`StringLiteral`].
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ExportSpecifier {
+    pub(crate) local: Box<Node>,
+    pub(crate) exported: Box<Node>,
+    pub(crate) span: Span,
+}
+
+impl ExportSpecifier {
+    pub fn new(local: Node, exported: Node) -> Self {
+        Self {
+            local: Box::new(local),
+            exported: Box::new(exported),
+            span: Span::default(),
+        }
+    }
+}
+
+/// `export default` followed by either a function declaration or an
+/// expression.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ExportDefaultDeclaration {
+    pub(crate) declaration: Box<Node>,
+    pub(crate) span: Span,
+}
+
+impl ExportDefaultDeclaration {
+    pub fn new(declaration: Node) -> Self {
+        Self {
+            declaration: Box::new(declaration),
+            span: Span::default(),
+        }
+    }
+}
+
+/// `export * from "mod"`, or `export * as ns from "mod"`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ExportAllDeclaration {
+    pub(crate) exported: Option<Box<Node>>,
+    pub(crate) source: StringLiteral,
+    pub(crate) span: Span,
+}
+
+impl ExportAllDeclaration {
+    pub fn new(exported: Option<Node>, source: StringLiteral) -> Self {
+        Self {
+            exported: exported.map(Box::new),
+            source,
+            span: Span::default(),
+        }
+    }
+
+    pub fn source(&self) -> &StringLiteral {
+        &self.source
+    }
+}
+
+/// A JSX element, like `<a href="b">c</a>` or `<A />`.  The closing tag isn't
+/// kept, since it has to match the opening one.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct JSXElement {
+    /// A [`JSXIdentifier`], [`JSXMemberExpression`] or [`JSXNamespacedName`].
+    pub(crate) name: Box<Node>,
+    /// [`JSXAttribute`] and [`JSXSpreadAttribute`] nodes.
+    pub(crate) attributes: Vec<Node>,
+    /// [`JSXText`], [`JSXExpressionContainer`], [`JSXElement`] and
+    /// [`JSXFragment`] nodes.
+    pub(crate) children: Vec<Node>,
+    pub(crate) self_closing: bool,
+    pub(crate) span: Span,
+}
+
+impl JSXElement {
+    pub fn new(name: Node, attributes: Vec<Node>, children: Vec<Node>, self_closing: bool) -> Self {
+        Self {
+            name: Box::new(name),
+            attributes,
+            children,
+            self_closing,
+            span: Span::default(),
+        }
+    }
+}
+
+/// `<>...</>`, which groups its children without an element around them.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct JSXFragment {
+    pub(crate) children: Vec<Node>,
+    pub(crate) span: Span,
+}
+
+impl JSXFragment {
+    pub fn new(children: Vec<Node>) -> Self {
+        Self {
+            children,
+            span: Span::default(),
+        }
+    }
+}
+
+/// `b="c"` in `<a b="c" />`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct JSXAttribute {
+    /// A [`JSXIdentifier`] or [`JSXNamespacedName`].
+    pub(crate) name: Box<Node>,
+    /// A [`StringLiteral`], [`JSXExpressionContainer`], [`JSXElement`] or
+    /// [`JSXFragment`], or `None` for an attribute without a value, which
+    /// means `true`.
+    pub(crate) value: Option<Box<Node>>,
+    pub(crate) span: Span,
+}
+
+impl JSXAttribute {
+    pub fn new(name: Node, value: Option<Node>) -> Self {
+        Self {
+            name: Box::new(name),
+            value: value.map(Box::new),
+            span: Span::default(),
+        }
+    }
+}
+
+/// `{...b}` in `<a {...b} />`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct JSXSpreadAttribute {
+    pub(crate) argument: Box<Node>,
+    pub(crate) span: Span,
+}
+
+impl JSXSpreadAttribute {
+    pub fn new(argument: Node) -> Self {
+        Self {
+            argument: Box::new(argument),
+            span: Span::default(),
+        }
+    }
+}
+
+/// An expression in braces, as an attribute value or a child.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct JSXExpressionContainer {
+    /// `None` for braces with nothing but comments in them, which is only
+    /// allowed for children.
+    pub(crate) expression: Option<Box<Node>>,
+    pub(crate) span: Span,
+}
+
+impl JSXExpressionContainer {
+    pub fn new(expression: Option<Node>) -> Self {
+        Self {
+            expression: expression.map(Box::new),
+            span: Span::default(),
+        }
+    }
+}
+
+/// The text between tags, exactly as written.  Trimming whitespace and
+/// decoding HTML entities is left to whatever lowers the JSX.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct JSXText {
+    pub(crate) value: String,
+    pub(crate) span: Span,
+}
+
+impl JSXText {
+    pub fn new(value: String) -> Self {
+        Self {
+            value,
+            span: Span::default(),
+        }
+    }
+}
+
+/// A name in a tag, which unlike an [`Identifier`] may contain dashes.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct JSXIdentifier {
+    pub(crate) value: String,
+    pub(crate) span: Span,
+}
+
+impl JSXIdentifier {
+    pub fn new(value: String) -> Self {
+        Self {
+            value,
+            span: Span::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.value
+    }
+}
+
+/// A dotted element name, like `a.b.c` in `<a.b.c />`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct JSXMemberExpression {
+    /// A [`JSXIdentifier`] or another [`JSXMemberExpression`].
+    pub(crate) object: Box<Node>,
+    pub(crate) property: JSXIdentifier,
+    pub(crate) span: Span,
+}
+
+impl JSXMemberExpression {
+    pub fn new(object: Node, property: JSXIdentifier) -> Self {
+        Self {
+            object: Box::new(object),
+            property,
+            span: Span::default(),
+        }
+    }
+}
+
+/// A name with a namespace, like `svg:rect` or `xlink:href`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct JSXNamespacedName {
+    pub(crate) namespace: JSXIdentifier,
+    pub(crate) name: JSXIdentifier,
+    pub(crate) span: Span,
+}
+
+impl JSXNamespacedName {
+    pub fn new(namespace: JSXIdentifier, name: JSXIdentifier) -> Self {
+        Self {
+            namespace,
+            name,
+            span: Span::default(),
+        }
+    }
+}
+
+/// Gives each node type a `span()` getter, and a `with_span()` for the parser
+/// to set it with once it knows where the node ends.
+macro_rules! impl_span {
+    ($($name:ident),* $(,)?) => {
+        $(
+            impl $name {
+                pub fn span(&self) -> Span {
+                    self.span
+                }
+
+                pub fn with_span(mut self, span: Span) -> Self {
+                    self.span = span;
+                    self
+                }
+            }
+        )*
+    };
+}
+
+impl_span!(
+    Identifier,
+    Parameter,
+    VariableDeclarator,
+    NumericLiteral,
+    BigIntLiteral,
+    StringLiteral,
+    BooleanLiteral,
+    NullLiteral,
+    RegExpLiteral,
+    ThisExpression,
+    TemplateElement,
+    TemplateLiteral,
+    TaggedTemplateExpression,
+    ArrayExpression,
+    ObjectExpression,
+    Property,
+    ObjectPattern,
+    ArrayPattern,
+    RestElement,
+    AssignmentPattern,
+    ExpressionStatement,
+    BinaryExpression,
+    LogicalExpression,
+    AssignmentExpression,
+    ConditionalExpression,
+    SequenceExpression,
+    UnaryExpression,
+    UpdateExpression,
+    AwaitExpression,
+    CallExpression,
+    NewExpression,
+    MemberExpression,
+    OptionalMemberExpression,
+    OptionalCallExpression,
+    ReturnStatement,
+    ThrowStatement,
+    BreakStatement,
+    ContinueStatement,
+    BlockStatement,
+    EmptyStatement,
+    IfStatement,
+    WhileStatement,
+    DoWhileStatement,
+    FunctionDeclaration,
+    ArrowFunctionExpression,
+    VariableDeclaration,
+    Program,
+    ImportDeclaration,
+    ImportSpecifier,
+    ImportDefaultSpecifier,
+    ImportNamespaceSpecifier,
+    ExportNamedDeclaration,
+    ExportSpecifier,
+    ExportDefaultDeclaration,
+    ExportAllDeclaration,
+    JSXElement,
+    JSXFragment,
+    JSXAttribute,
+    JSXSpreadAttribute,
+    JSXExpressionContainer,
+    JSXText,
+    JSXIdentifier,
+    JSXMemberExpression,
+    JSXNamespacedName,
+);
+
+macro_rules! node_span {
+    ($($variant:ident),* $(,)?) => {
+        impl Node {
+            /// Where in the source this node was parsed from.  Nodes built
+            /// by hand have a default span, with every field zero.
+            pub fn span(&self) -> Span {
+                match self {
+                    $(Node::$variant(node) => node.span(),)*
+                }
+            }
+
+            pub fn with_span(self, span: Span) -> Self {
+                match self {
+                    $(Node::$variant(node) => Node::$variant(node.with_span(span)),)*
+                }
+            }
+        }
+    };
+}
+
+node_span!(
+    Program,
+    SequenceExpression,
+    AssignmentExpression,
+    ConditionalExpression,
+    LogicalExpression,
+    BinaryExpression,
+    UnaryExpression,
+    UpdateExpression,
+    AwaitExpression,
+    CallExpression,
+    NewExpression,
+    MemberExpression,
+    OptionalMemberExpression,
+    OptionalCallExpression,
+    TaggedTemplateExpression,
+    TemplateLiteral,
+    ArrayExpression,
+    ObjectExpression,
+    Property,
+    ObjectPattern,
+    ArrayPattern,
+    RestElement,
+    AssignmentPattern,
+    ThisExpression,
+    NumericLiteral,
+    BigIntLiteral,
+    StringLiteral,
+    BooleanLiteral,
+    NullLiteral,
+    RegExpLiteral,
+    Identifier,
+    ImportDeclaration,
+    ImportSpecifier,
+    ImportDefaultSpecifier,
+    ImportNamespaceSpecifier,
+    ExportNamedDeclaration,
+    ExportSpecifier,
+    ExportDefaultDeclaration,
+    ExportAllDeclaration,
+    FunctionDeclaration,
+    ArrowFunctionExpression,
+    VariableDeclaration,
+    BlockStatement,
+    EmptyStatement,
+    IfStatement,
+    WhileStatement,
+    DoWhileStatement,
+    ReturnStatement,
+    ThrowStatement,
+    BreakStatement,
+    ContinueStatement,
+    ExpressionStatement,
+    Parameter,
+    JSXElement,
+    JSXFragment,
+    JSXAttribute,
+    JSXSpreadAttribute,
+    JSXExpressionContainer,
+    JSXText,
+    JSXIdentifier,
+    JSXMemberExpression,
+    JSXNamespacedName,
+);
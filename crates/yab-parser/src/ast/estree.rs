@@ -0,0 +1,645 @@
+//! Converts the AST to JSON in the shape of ESTree, the AST that acorn, espree
+//! and most other JavaScript tooling share, so that our output can be diffed
+//! against theirs or fed to tools that expect it.  The serde output of
+//! [`Node`] follows our own structs instead, and is mostly for debugging.
+//!
+//! See: https://github.com/estree/estree
+
+use serde_json::{json, Value};
+
+use super::*;
+
+impl Node {
+    /// Converts the node to an ESTree node.  Like acorn's, each node has
+    /// `start` and `end` offsets and a `loc`, whose lines count from one and
+    /// columns from zero.
+    ///
+    /// This differs from acorn's output in a few ways:
+    ///
+    /// * Literals don't have a `raw` field, since the AST doesn't keep the
+    ///   source text of anything but template elements.
+    ///
+    /// * Offsets count bytes and columns count characters, where acorn counts
+    ///   UTF-16 code units for both, so they only agree on ASCII text.
+    ///
+    /// * The opening and closing parts of JSX elements and fragments aren't
+    ///   located, since the AST doesn't record where they are.
+    pub fn to_estree_json(&self) -> Value {
+        self.estree(false)
+    }
+
+    /// `in_chain` is set inside an optional chain.  We mark every node in a
+    /// chain as optional like Babel does, while ESTree wraps the whole chain
+    /// in a `ChainExpression` instead.
+    fn estree(&self, in_chain: bool) -> Value {
+        let value = match self {
+            Node::Program(program) => {
+                let is_module = program.body.iter().any(|statement| {
+                    matches!(
+                        statement,
+                        Node::ImportDeclaration(_)
+                            | Node::ExportNamedDeclaration(_)
+                            | Node::ExportDefaultDeclaration(_)
+                            | Node::ExportAllDeclaration(_)
+                    )
+                });
+                json!({
+                    "type": "Program",
+                    "sourceType": if is_module { "module" } else { "script" },
+                    "body": nodes(&program.body),
+                })
+            }
+            Node::SequenceExpression(sequence) => json!({
+                "type": "SequenceExpression",
+                "expressions": nodes(&sequence.expressions),
+            }),
+            Node::AssignmentExpression(assignment) => json!({
+                "type": "AssignmentExpression",
+                "operator": assignment.operator,
+                "left": assignment.lhs.to_estree_json(),
+                "right": assignment.rhs.to_estree_json(),
+            }),
+            Node::ConditionalExpression(conditional) => json!({
+                "type": "ConditionalExpression",
+                "test": conditional.test.to_estree_json(),
+                "consequent": conditional.consequent.to_estree_json(),
+                "alternate": conditional.alternate.to_estree_json(),
+            }),
+            Node::LogicalExpression(logical) => json!({
+                "type": "LogicalExpression",
+                "operator": logical.operator,
+                "left": logical.lhs.to_estree_json(),
+                "right": logical.rhs.to_estree_json(),
+            }),
+            Node::BinaryExpression(binary) => json!({
+                "type": "BinaryExpression",
+                "operator": binary.operator,
+                "left": binary.lhs.to_estree_json(),
+                "right": binary.rhs.to_estree_json(),
+            }),
+            Node::UnaryExpression(unary) => json!({
+                "type": "UnaryExpression",
+                "operator": unary.operator,
+                "prefix": true,
+                "argument": unary.argument.to_estree_json(),
+            }),
+            Node::UpdateExpression(update) => json!({
+                "type": "UpdateExpression",
+                "operator": update.operator,
+                "prefix": update.prefix,
+                "argument": update.argument.to_estree_json(),
+            }),
+            Node::AwaitExpression(await_expression) => json!({
+                "type": "AwaitExpression",
+                "argument": await_expression.argument.to_estree_json(),
+            }),
+            Node::CallExpression(call) => json!({
+                "type": "CallExpression",
+                "callee": call.callee.to_estree_json(),
+                "arguments": nodes(&call.arguments),
+                "optional": false,
+            }),
+            Node::NewExpression(new) => json!({
+                "type": "NewExpression",
+                "callee": new.callee.to_estree_json(),
+                "arguments": nodes(&new.arguments),
+            }),
+            Node::MemberExpression(member) => json!({
+                "type": "MemberExpression",
+                "object": member.object.to_estree_json(),
+                "property": member.property.to_estree_json(),
+                "computed": member.computed,
+                "optional": false,
+            }),
+            Node::OptionalMemberExpression(member) => chain(
+                in_chain,
+                member.span,
+                json!({
+                    "type": "MemberExpression",
+                    "object": member.object.estree(true),
+                    "property": member.property.to_estree_json(),
+                    "computed": member.computed,
+                    "optional": member.optional,
+                }),
+            ),
+            Node::OptionalCallExpression(call) => chain(
+                in_chain,
+                call.span,
+                json!({
+                    "type": "CallExpression",
+                    "callee": call.callee.estree(true),
+                    "arguments": nodes(&call.arguments),
+                    "optional": call.optional,
+                }),
+            ),
+            Node::TaggedTemplateExpression(tagged) => json!({
+                "type": "TaggedTemplateExpression",
+                "tag": tagged.tag.to_estree_json(),
+                "quasi": template_literal(&tagged.quasi),
+            }),
+            Node::TemplateLiteral(template) => template_literal(template),
+            Node::ArrayExpression(array) => json!({
+                "type": "ArrayExpression",
+                "elements": optional_nodes(&array.elements),
+            }),
+            Node::ObjectExpression(object) => json!({
+                "type": "ObjectExpression",
+                "properties": nodes(&object.properties),
+            }),
+            Node::Property(property) => json!({
+                "type": "Property",
+                "key": property.key.to_estree_json(),
+                "value": property.value.to_estree_json(),
+                "kind": "init",
+                "method": false,
+                "shorthand": property.shorthand,
+                "computed": property.computed,
+            }),
+            Node::ObjectPattern(pattern) => json!({
+                "type": "ObjectPattern",
+                "properties": nodes(&pattern.properties),
+            }),
+            Node::ArrayPattern(pattern) => json!({
+                "type": "ArrayPattern",
+                "elements": optional_nodes(&pattern.elements),
+            }),
+            Node::RestElement(rest) => json!({
+                "type": "RestElement",
+                "argument": rest.argument.to_estree_json(),
+            }),
+            Node::AssignmentPattern(pattern) => json!({
+                "type": "AssignmentPattern",
+                "left": pattern.lhs.to_estree_json(),
+                "right": pattern.rhs.to_estree_json(),
+            }),
+            Node::ThisExpression(_) => json!({ "type": "ThisExpression" }),
+            Node::NumericLiteral(literal) => literal_json(number(literal.value)),
+            Node::BigIntLiteral(literal) => {
+                let mut value = literal_json(Value::Null);
+                value["bigint"] = json!(bigint_digits(&literal.value));
+                value
+            }
+            Node::StringLiteral(literal) => string_literal(literal),
+            Node::BooleanLiteral(literal) => literal_json(json!(literal.value)),
+            Node::NullLiteral(_) => literal_json(Value::Null),
+            // The value would be a RegExp object, which JSON can't hold.
+            Node::RegExpLiteral(literal) => {
+                let mut value = literal_json(Value::Null);
+                value["regex"] = json!({ "pattern": literal.pattern, "flags": literal.flags });
+                value
+            }
+            Node::Identifier(identifier) => identifier_json(identifier),
+            Node::ImportDeclaration(import) => json!({
+                "type": "ImportDeclaration",
+                "specifiers": nodes(&import.specifiers),
+                "source": string_literal(&import.source),
+            }),
+            Node::ImportSpecifier(specifier) => json!({
+                "type": "ImportSpecifier",
+                "imported": specifier.imported.to_estree_json(),
+                "local": identifier_json(&specifier.local),
+            }),
+            Node::ImportDefaultSpecifier(specifier) => json!({
+                "type": "ImportDefaultSpecifier",
+                "local": identifier_json(&specifier.local),
+            }),
+            Node::ImportNamespaceSpecifier(specifier) => json!({
+                "type": "ImportNamespaceSpecifier",
+                "local": identifier_json(&specifier.local),
+            }),
+            Node::ExportNamedDeclaration(export) => json!({
+                "type": "ExportNamedDeclaration",
+                "declaration": export.declaration.as_deref().map(Node::to_estree_json),
+                "specifiers": nodes(&export.specifiers),
+                "source": export.source.as_ref().map(string_literal),
+            }),
+            Node::ExportSpecifier(specifier) => json!({
+                "type": "ExportSpecifier",
+                "local": specifier.local.to_estree_json(),
+                "exported": specifier.exported.to_estree_json(),
+            }),
+            Node::ExportDefaultDeclaration(export) => json!({
+                "type": "ExportDefaultDeclaration",
+                "declaration": export.declaration.to_estree_json(),
+            }),
+            Node::ExportAllDeclaration(export) => json!({
+                "type": "ExportAllDeclaration",
+                "exported": export.exported.as_deref().map(Node::to_estree_json),
+                "source": string_literal(&export.source),
+            }),
+            Node::FunctionDeclaration(function) => json!({
+                "type": "FunctionDeclaration",
+                "id": function.identifier.as_ref().map(identifier_json),
+                "params": params(&function.params),
+                "body": block(&function.body),
+                "expression": false,
+                "generator": false,
+                "async": false,
+            }),
+            Node::ArrowFunctionExpression(arrow) => json!({
+                "type": "ArrowFunctionExpression",
+                "id": null,
+                "params": params(&arrow.params),
+                "body": arrow.body.to_estree_json(),
+                "expression": !matches!(*arrow.body, Node::BlockStatement(_)),
+                "generator": false,
+                "async": arrow.is_async,
+            }),
+            Node::VariableDeclaration(declaration) => json!({
+                "type": "VariableDeclaration",
+                "declarations": declaration
+                    .declarations
+                    .iter()
+                    .map(|declarator| located(json!({
+                        "type": "VariableDeclarator",
+                        "id": declarator.id.to_estree_json(),
+                        "init": declarator.init.as_ref().map(Node::to_estree_json),
+                    }), declarator.span))
+                    .collect::<Vec<_>>(),
+                "kind": declaration.kind,
+            }),
+            Node::BlockStatement(statement) => block(statement),
+            Node::EmptyStatement(_) => json!({ "type": "EmptyStatement" }),
+            Node::IfStatement(statement) => json!({
+                "type": "IfStatement",
+                "test": statement.test.to_estree_json(),
+                "consequent": statement.consequent.to_estree_json(),
+                "alternate": statement.alternate.as_deref().map(Node::to_estree_json),
+            }),
+            Node::WhileStatement(statement) => json!({
+                "type": "WhileStatement",
+                "test": statement.test.to_estree_json(),
+                "body": statement.body.to_estree_json(),
+            }),
+            Node::DoWhileStatement(statement) => json!({
+                "type": "DoWhileStatement",
+                "body": statement.body.to_estree_json(),
+                "test": statement.test.to_estree_json(),
+            }),
+            Node::ReturnStatement(statement) => json!({
+                "type": "ReturnStatement",
+                "argument": statement.argument.as_deref().map(Node::to_estree_json),
+            }),
+            Node::ThrowStatement(statement) => json!({
+                "type": "ThrowStatement",
+                "argument": statement.argument.to_estree_json(),
+            }),
+            Node::BreakStatement(statement) => json!({
+                "type": "BreakStatement",
+                "label": statement.label.as_ref().map(identifier_json),
+            }),
+            Node::ContinueStatement(statement) => json!({
+                "type": "ContinueStatement",
+                "label": statement.label.as_ref().map(identifier_json),
+            }),
+            Node::ExpressionStatement(statement) => json!({
+                "type": "ExpressionStatement",
+                "expression": statement.expression.to_estree_json(),
+            }),
+            // ESTree has no parameter node; the pattern is the parameter.
+            Node::Parameter(parameter) => parameter.pat.to_estree_json(),
+            Node::JSXElement(element) => json!({
+                "type": "JSXElement",
+                "openingElement": {
+                    "type": "JSXOpeningElement",
+                    "name": element.name.to_estree_json(),
+                    "attributes": nodes(&element.attributes),
+                    "selfClosing": element.self_closing,
+                },
+                "closingElement": if element.self_closing {
+                    Value::Null
+                } else {
+                    json!({ "type": "JSXClosingElement", "name": element.name.to_estree_json() })
+                },
+                "children": nodes(&element.children),
+            }),
+            Node::JSXFragment(fragment) => json!({
+                "type": "JSXFragment",
+                "openingFragment": { "type": "JSXOpeningFragment" },
+                "closingFragment": { "type": "JSXClosingFragment" },
+                "children": nodes(&fragment.children),
+            }),
+            Node::JSXAttribute(attribute) => json!({
+                "type": "JSXAttribute",
+                "name": attribute.name.to_estree_json(),
+                "value": attribute.value.as_deref().map(Node::to_estree_json),
+            }),
+            Node::JSXSpreadAttribute(spread) => json!({
+                "type": "JSXSpreadAttribute",
+                "argument": spread.argument.to_estree_json(),
+            }),
+            Node::JSXExpressionContainer(container) => json!({
+                "type": "JSXExpressionContainer",
+                "expression": match &container.expression {
+                    Some(expression) => expression.to_estree_json(),
+                    None => json!({ "type": "JSXEmptyExpression" }),
+                },
+            }),
+            Node::JSXText(text) => json!({
+                "type": "JSXText",
+                "value": text.value,
+                "raw": text.value,
+            }),
+            Node::JSXIdentifier(identifier) => jsx_identifier(identifier),
+            Node::JSXMemberExpression(member) => json!({
+                "type": "JSXMemberExpression",
+                "object": member.object.to_estree_json(),
+                "property": jsx_identifier(&member.property),
+            }),
+            Node::JSXNamespacedName(name) => json!({
+                "type": "JSXNamespacedName",
+                "namespace": jsx_identifier(&name.namespace),
+                "name": jsx_identifier(&name.name),
+            }),
+        };
+
+        // A parameter is its pattern, which is located already.
+        if matches!(self, Node::Parameter(_)) {
+            return value;
+        }
+        located(value, self.span())
+    }
+}
+
+/// Adds acorn's location fields for `span` to `node`.
+fn located(mut node: Value, span: Span) -> Value {
+    let position = |position: Position| json!({ "line": position.line, "column": position.column.saturating_sub(1) });
+    node["start"] = json!(span.start.index);
+    node["end"] = json!(span.end.index);
+    node["loc"] = json!({ "start": position(span.start), "end": position(span.end) });
+    node
+}
+
+/// Wraps the outermost node of an optional chain in a `ChainExpression`,
+/// which covers the same `span`.
+fn chain(in_chain: bool, span: Span, expression: Value) -> Value {
+    if in_chain {
+        return expression;
+    }
+
+    json!({ "type": "ChainExpression", "expression": located(expression, span) })
+}
+
+fn nodes(nodes: &[Node]) -> Vec<Value> {
+    nodes.iter().map(Node::to_estree_json).collect()
+}
+
+fn optional_nodes(nodes: &[Option<Node>]) -> Vec<Value> {
+    nodes
+        .iter()
+        .map(|node| node.as_ref().map_or(Value::Null, Node::to_estree_json))
+        .collect()
+}
+
+fn params(params: &[Parameter]) -> Vec<Value> {
+    params
+        .iter()
+        .map(|param| param.pat.to_estree_json())
+        .collect()
+}
+
+fn block(block: &BlockStatement) -> Value {
+    located(
+        json!({
+            "type": "BlockStatement",
+            "body": nodes(&block.statements),
+        }),
+        block.span,
+    )
+}
+
+fn identifier_json(identifier: &Identifier) -> Value {
+    located(
+        json!({ "type": "Identifier", "name": identifier.value }),
+        identifier.span,
+    )
+}
+
+fn jsx_identifier(identifier: &JSXIdentifier) -> Value {
+    located(
+        json!({ "type": "JSXIdentifier", "name": identifier.value }),
+        identifier.span,
+    )
+}
+
+fn literal_json(value: Value) -> Value {
+    json!({ "type": "Literal", "value": value })
+}
+
+/// Writes integers without a fraction, as `JSON.stringify` does, so `1` is
+/// `1` rather than `1.0`.
+fn number(value: f64) -> Value {
+    const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+    if value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER {
+        json!(value as i64)
+    } else {
+        json!(value)
+    }
+}
+
+fn string_literal(literal: &StringLiteral) -> Value {
+    located(literal_json(json!(literal.value)), literal.span)
+}
+
+fn template_literal(template: &TemplateLiteral) -> Value {
+    let quasis: Vec<_> = template
+        .quasis
+        .iter()
+        .map(|quasi| {
+            located(
+                json!({
+                    "type": "TemplateElement",
+                    "value": { "raw": quasi.raw, "cooked": quasi.cooked },
+                    "tail": quasi.tail,
+                }),
+                quasi.span,
+            )
+        })
+        .collect();
+
+    located(
+        json!({
+            "type": "TemplateLiteral",
+            "quasis": quasis,
+            "expressions": nodes(&template.expressions),
+        }),
+        template.span,
+    )
+}
+
+/// ESTree gives a bigint as its value in decimal, without the `n`.
+fn bigint_digits(literal: &str) -> &str {
+    literal.trim_end_matches('n')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse, parse_expression, without_spans::to_estree_json};
+
+    use super::*;
+
+    fn estree(src: &str) -> Value {
+        to_estree_json(&parse_expression(src, "script.js").unwrap())
+    }
+
+    fn ident(name: &str) -> Value {
+        json!({ "type": "Identifier", "name": name })
+    }
+
+    #[test]
+    fn test_expressions() {
+        assert_eq!(
+            estree("a = b + 1"),
+            json!({
+                "type": "AssignmentExpression",
+                "operator": "=",
+                "left": ident("a"),
+                "right": {
+                    "type": "BinaryExpression",
+                    "operator": "+",
+                    "left": ident("b"),
+                    "right": { "type": "Literal", "value": 1 },
+                },
+            })
+        );
+        assert_eq!(
+            estree("/a/g")["regex"],
+            json!({ "pattern": "a", "flags": "g" })
+        );
+        assert_eq!(estree("0x1_0n")["bigint"], json!("16"));
+        for (src, argument) in [
+            ("-1", json!({ "type": "Literal", "value": 1 })),
+            ("-1e3", json!({ "type": "Literal", "value": 1000 })),
+            (
+                "-0x10n",
+                json!({ "type": "Literal", "value": null, "bigint": "16" }),
+            ),
+        ] {
+            assert_eq!(
+                estree(src),
+                json!({ "type": "UnaryExpression", "operator": "-", "prefix": true, "argument": argument }),
+                "{}",
+                src
+            );
+        }
+        assert_eq!(
+            estree("(a = 1) => a")["params"],
+            json!([{ "type": "AssignmentPattern", "left": ident("a"), "right": { "type": "Literal", "value": 1 } }])
+        );
+        assert_eq!(estree("(a = 1) => a")["expression"], json!(true));
+        assert_eq!(
+            estree(r"tag`\u{41}${a}\unicode`")["quasi"]["quasis"],
+            json!([
+                { "type": "TemplateElement", "value": { "raw": r"\u{41}", "cooked": "A" }, "tail": false },
+                { "type": "TemplateElement", "value": { "raw": r"\unicode", "cooked": null }, "tail": true },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_optional_chains() {
+        assert_eq!(
+            estree("a?.b()"),
+            json!({
+                "type": "ChainExpression",
+                "expression": {
+                    "type": "CallExpression",
+                    "callee": {
+                        "type": "MemberExpression",
+                        "object": ident("a"),
+                        "property": ident("b"),
+                        "computed": false,
+                        "optional": true,
+                    },
+                    "arguments": [],
+                    "optional": false,
+                },
+            })
+        );
+
+        // Parentheses end the chain.
+        let member = estree("(a?.b).c");
+        assert_eq!(member["type"], json!("MemberExpression"));
+        assert_eq!(member["object"]["type"], json!("ChainExpression"));
+    }
+
+    #[test]
+    fn test_locations() {
+        let binary = parse_expression("a?.b +\n  'c'", "script.js")
+            .unwrap()
+            .to_estree_json();
+        let location = |node: &Value| {
+            (
+                node["start"].clone(),
+                node["end"].clone(),
+                node["loc"].clone(),
+            )
+        };
+        assert_eq!(
+            location(&binary),
+            (
+                json!(0),
+                json!(12),
+                json!({ "start": { "line": 1, "column": 0 }, "end": { "line": 2, "column": 5 } })
+            )
+        );
+        // The chain and the member expression inside it cover the same source.
+        assert_eq!(binary["left"]["type"], json!("ChainExpression"));
+        assert_eq!(
+            location(&binary["left"]),
+            location(&binary["left"]["expression"])
+        );
+        assert_eq!(binary["left"]["end"], json!(4));
+        assert_eq!(
+            location(&binary["right"]),
+            (
+                json!(9),
+                json!(12),
+                json!({ "start": { "line": 2, "column": 2 }, "end": { "line": 2, "column": 5 } })
+            )
+        );
+    }
+
+    #[test]
+    fn test_program() {
+        let program = Node::Program(
+            parse(
+                "import a from 'a';\nexport function f(...b) { return; }",
+                "module.js",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            to_estree_json(&program),
+            json!({
+                "type": "Program",
+                "sourceType": "module",
+                "body": [
+                    {
+                        "type": "ImportDeclaration",
+                        "specifiers": [{ "type": "ImportDefaultSpecifier", "local": ident("a") }],
+                        "source": { "type": "Literal", "value": "a" },
+                    },
+                    {
+                        "type": "ExportNamedDeclaration",
+                        "declaration": {
+                            "type": "FunctionDeclaration",
+                            "id": ident("f"),
+                            "params": [{ "type": "RestElement", "argument": ident("b") }],
+                            "body": {
+                                "type": "BlockStatement",
+                                "body": [{ "type": "ReturnStatement", "argument": null }],
+                            },
+                            "expression": false,
+                            "generator": false,
+                            "async": false,
+                        },
+                        "specifiers": [],
+                        "source": null,
+                    },
+                ],
+            })
+        );
+    }
+}
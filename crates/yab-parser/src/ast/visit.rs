@@ -0,0 +1,455 @@
+//! Visitors over the AST.  Implement [`Visit`], overriding the hooks for the
+//! node kinds you care about, and hand it to [`walk_program`] or
+//! [`walk_node`], which call the hooks for every node in source order.
+//! [`VisitMut`] is the same, but its hooks can rewrite the nodes they're
+//! given, for transform passes.
+//!
+//! ```
+//! use yab_parser::ast::{visit::{walk_program, Visit}, Identifier};
+//!
+//! #[derive(Default)]
+//! struct CountIdentifiers(usize);
+//!
+//! impl Visit for CountIdentifiers {
+//!     fn enter_identifier(&mut self, _node: &Identifier) {
+//!         self.0 += 1;
+//!     }
+//! }
+//!
+//! let program = yab_parser::parser::parse("a(b, c.d)", "script.js").unwrap();
+//! let mut counter = CountIdentifiers::default();
+//! walk_program(&mut counter, &program);
+//! assert_eq!(counter.0, 4);
+//! ```
+
+use super::*;
+
+/// Calls the hooks of `visitor` for `node` and everything inside it.
+pub fn walk_node<V: Visit + ?Sized>(visitor: &mut V, node: &Node) {
+    node.walk(visitor);
+}
+
+/// Calls the hooks of `visitor` for `program` and everything inside it.
+pub fn walk_program<V: Visit + ?Sized>(visitor: &mut V, program: &Program) {
+    program.walk(visitor);
+}
+
+/// Like [`walk_node`], but for a [`VisitMut`].
+pub fn walk_node_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut Node) {
+    node.walk_mut(visitor);
+}
+
+/// Like [`walk_program`], but for a [`VisitMut`].
+pub fn walk_program_mut<V: VisitMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    program.walk_mut(visitor);
+}
+
+/// Something that contains nodes, which it walks in source order.
+trait Walk {
+    fn walk<V: Visit + ?Sized>(&self, visitor: &mut V);
+}
+
+impl<T: Walk> Walk for Box<T> {
+    fn walk<V: Visit + ?Sized>(&self, visitor: &mut V) {
+        (**self).walk(visitor);
+    }
+}
+
+impl<T: Walk> Walk for Option<T> {
+    fn walk<V: Visit + ?Sized>(&self, visitor: &mut V) {
+        if let Some(node) = self {
+            node.walk(visitor);
+        }
+    }
+}
+
+impl<T: Walk> Walk for Vec<T> {
+    fn walk<V: Visit + ?Sized>(&self, visitor: &mut V) {
+        for node in self {
+            node.walk(visitor);
+        }
+    }
+}
+
+trait WalkMut {
+    fn walk_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V);
+}
+
+impl<T: WalkMut> WalkMut for Box<T> {
+    fn walk_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        (**self).walk_mut(visitor);
+    }
+}
+
+impl<T: WalkMut> WalkMut for Option<T> {
+    fn walk_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        if let Some(node) = self {
+            node.walk_mut(visitor);
+        }
+    }
+}
+
+impl<T: WalkMut> WalkMut for Vec<T> {
+    fn walk_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        for node in self {
+            node.walk_mut(visitor);
+        }
+    }
+}
+
+/// Declares the `enter_*` and `exit_*` hooks of [`Visit`] and [`VisitMut`] for
+/// each node type, and walks the node type's children, given as the fields
+/// holding them.
+macro_rules! visit {
+    ($($ty:ident: $enter:ident, $exit:ident, [$($field:ident),*];)*) => {
+        /// Hooks called while walking the AST.  Each node type gets an
+        /// `enter_*` hook, called before walking its children, and an
+        /// `exit_*` hook, called after.  Every hook does nothing by default.
+        ///
+        /// [`Visit::enter_node`] and [`Visit::exit_node`] are called around
+        /// every [`Node`], on top of the hooks for its type.
+        pub trait Visit {
+            fn enter_node(&mut self, _node: &Node) {}
+            fn exit_node(&mut self, _node: &Node) {}
+
+            $(
+                fn $enter(&mut self, _node: &$ty) {}
+                fn $exit(&mut self, _node: &$ty) {}
+            )*
+        }
+
+        /// Like [`Visit`], but the hooks get mutable references, so they
+        /// can rewrite the AST as it's walked.
+        ///
+        /// [`VisitMut::enter_node`] and [`VisitMut::exit_node`] can replace
+        /// the node they're given outright.  A node replaced on entry is
+        /// walked in place of the original, while one replaced on exit
+        /// isn't walked at all.  To drop or insert statements, edit the
+        /// list they're in from the hooks for [`BlockStatement`] or
+        /// [`Program`], with [`BlockStatement::splice`] for example.
+        pub trait VisitMut {
+            fn enter_node(&mut self, _node: &mut Node) {}
+            fn exit_node(&mut self, _node: &mut Node) {}
+
+            $(
+                fn $enter(&mut self, _node: &mut $ty) {}
+                fn $exit(&mut self, _node: &mut $ty) {}
+            )*
+        }
+
+        /// Resets the span of every node it walks, so tests can compare parsed
+        /// ASTs with ones built by hand.
+        #[cfg(test)]
+        pub(crate) struct ClearSpans;
+
+        #[cfg(test)]
+        impl VisitMut for ClearSpans {
+            $(
+                fn $enter(&mut self, node: &mut $ty) {
+                    node.span = Span::default();
+                }
+            )*
+        }
+
+        $(
+            impl Walk for $ty {
+                fn walk<V: Visit + ?Sized>(&self, visitor: &mut V) {
+                    visitor.$enter(self);
+                    $(self.$field.walk(visitor);)*
+                    visitor.$exit(self);
+                }
+            }
+
+            impl WalkMut for $ty {
+                fn walk_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+                    visitor.$enter(self);
+                    $(self.$field.walk_mut(visitor);)*
+                    visitor.$exit(self);
+                }
+            }
+        )*
+    };
+}
+
+visit! {
+    Program: enter_program, exit_program, [body];
+    Identifier: enter_identifier, exit_identifier, [];
+    Parameter: enter_parameter, exit_parameter, [pat];
+    VariableDeclarator: enter_variable_declarator, exit_variable_declarator, [id, init];
+    NumericLiteral: enter_numeric_literal, exit_numeric_literal, [];
+    BigIntLiteral: enter_big_int_literal, exit_big_int_literal, [];
+    StringLiteral: enter_string_literal, exit_string_literal, [];
+    BooleanLiteral: enter_boolean_literal, exit_boolean_literal, [];
+    NullLiteral: enter_null_literal, exit_null_literal, [];
+    RegExpLiteral: enter_reg_exp_literal, exit_reg_exp_literal, [];
+    ThisExpression: enter_this_expression, exit_this_expression, [];
+    TemplateElement: enter_template_element, exit_template_element, [];
+    TemplateLiteral: enter_template_literal, exit_template_literal, [quasis, expressions];
+    TaggedTemplateExpression:
+        enter_tagged_template_expression, exit_tagged_template_expression, [tag, quasi];
+    ArrayExpression: enter_array_expression, exit_array_expression, [elements];
+    ObjectExpression: enter_object_expression, exit_object_expression, [properties];
+    Property: enter_property, exit_property, [key, value];
+    ObjectPattern: enter_object_pattern, exit_object_pattern, [properties];
+    ArrayPattern: enter_array_pattern, exit_array_pattern, [elements];
+    RestElement: enter_rest_element, exit_rest_element, [argument];
+    AssignmentPattern: enter_assignment_pattern, exit_assignment_pattern, [lhs, rhs];
+    ExpressionStatement: enter_expression_statement, exit_expression_statement, [expression];
+    BinaryExpression: enter_binary_expression, exit_binary_expression, [lhs, rhs];
+    LogicalExpression: enter_logical_expression, exit_logical_expression, [lhs, rhs];
+    AssignmentExpression: enter_assignment_expression, exit_assignment_expression, [lhs, rhs];
+    ConditionalExpression:
+        enter_conditional_expression, exit_conditional_expression, [test, consequent, alternate];
+    SequenceExpression: enter_sequence_expression, exit_sequence_expression, [expressions];
+    UnaryExpression: enter_unary_expression, exit_unary_expression, [argument];
+    UpdateExpression: enter_update_expression, exit_update_expression, [argument];
+    AwaitExpression: enter_await_expression, exit_await_expression, [argument];
+    CallExpression: enter_call_expression, exit_call_expression, [callee, arguments];
+    NewExpression: enter_new_expression, exit_new_expression, [callee, arguments];
+    MemberExpression: enter_member_expression, exit_member_expression, [object, property];
+    OptionalMemberExpression:
+        enter_optional_member_expression, exit_optional_member_expression, [object, property];
+    OptionalCallExpression:
+        enter_optional_call_expression, exit_optional_call_expression, [callee, arguments];
+    ReturnStatement: enter_return_statement, exit_return_statement, [argument];
+    ThrowStatement: enter_throw_statement, exit_throw_statement, [argument];
+    BreakStatement: enter_break_statement, exit_break_statement, [label];
+    ContinueStatement: enter_continue_statement, exit_continue_statement, [label];
+    BlockStatement: enter_block_statement, exit_block_statement, [statements];
+    EmptyStatement: enter_empty_statement, exit_empty_statement, [];
+    IfStatement: enter_if_statement, exit_if_statement, [test, consequent, alternate];
+    WhileStatement: enter_while_statement, exit_while_statement, [test, body];
+    DoWhileStatement: enter_do_while_statement, exit_do_while_statement, [body, test];
+    FunctionDeclaration:
+        enter_function_declaration, exit_function_declaration, [identifier, params, body];
+    ArrowFunctionExpression:
+        enter_arrow_function_expression, exit_arrow_function_expression, [params, body];
+    VariableDeclaration: enter_variable_declaration, exit_variable_declaration, [declarations];
+    ImportDeclaration: enter_import_declaration, exit_import_declaration, [specifiers, source];
+    ImportSpecifier: enter_import_specifier, exit_import_specifier, [imported, local];
+    ImportDefaultSpecifier: enter_import_default_specifier, exit_import_default_specifier, [local];
+    ImportNamespaceSpecifier:
+        enter_import_namespace_specifier, exit_import_namespace_specifier, [local];
+    ExportNamedDeclaration:
+        enter_export_named_declaration, exit_export_named_declaration,
+        [declaration, specifiers, source];
+    ExportSpecifier: enter_export_specifier, exit_export_specifier, [local, exported];
+    ExportDefaultDeclaration:
+        enter_export_default_declaration, exit_export_default_declaration, [declaration];
+    ExportAllDeclaration:
+        enter_export_all_declaration, exit_export_all_declaration, [exported, source];
+    JSXElement: enter_jsx_element, exit_jsx_element, [name, attributes, children];
+    JSXFragment: enter_jsx_fragment, exit_jsx_fragment, [children];
+    JSXAttribute: enter_jsx_attribute, exit_jsx_attribute, [name, value];
+    JSXSpreadAttribute: enter_jsx_spread_attribute, exit_jsx_spread_attribute, [argument];
+    JSXExpressionContainer:
+        enter_jsx_expression_container, exit_jsx_expression_container, [expression];
+    JSXText: enter_jsx_text, exit_jsx_text, [];
+    JSXIdentifier: enter_jsx_identifier, exit_jsx_identifier, [];
+    JSXMemberExpression: enter_jsx_member_expression, exit_jsx_member_expression, [object, property];
+    JSXNamespacedName: enter_jsx_namespaced_name, exit_jsx_namespaced_name, [namespace, name];
+}
+
+/// Walks a [`Node`] by walking the node type it holds.
+macro_rules! walk_node {
+    ($($variant:ident),* $(,)?) => {
+        impl Walk for Node {
+            fn walk<V: Visit + ?Sized>(&self, visitor: &mut V) {
+                visitor.enter_node(self);
+                match self {
+                    $(Node::$variant(node) => node.walk(visitor),)*
+                }
+                visitor.exit_node(self);
+            }
+        }
+
+        impl WalkMut for Node {
+            fn walk_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+                visitor.enter_node(self);
+                match self {
+                    $(Node::$variant(node) => node.walk_mut(visitor),)*
+                }
+                visitor.exit_node(self);
+            }
+        }
+    };
+}
+
+walk_node!(
+    Program,
+    SequenceExpression,
+    AssignmentExpression,
+    ConditionalExpression,
+    LogicalExpression,
+    BinaryExpression,
+    UnaryExpression,
+    UpdateExpression,
+    AwaitExpression,
+    CallExpression,
+    NewExpression,
+    MemberExpression,
+    OptionalMemberExpression,
+    OptionalCallExpression,
+    TaggedTemplateExpression,
+    TemplateLiteral,
+    ArrayExpression,
+    ObjectExpression,
+    Property,
+    ObjectPattern,
+    ArrayPattern,
+    RestElement,
+    AssignmentPattern,
+    ThisExpression,
+    NumericLiteral,
+    BigIntLiteral,
+    StringLiteral,
+    BooleanLiteral,
+    NullLiteral,
+    RegExpLiteral,
+    Identifier,
+    ImportDeclaration,
+    ImportSpecifier,
+    ImportDefaultSpecifier,
+    ImportNamespaceSpecifier,
+    ExportNamedDeclaration,
+    ExportSpecifier,
+    ExportDefaultDeclaration,
+    ExportAllDeclaration,
+    FunctionDeclaration,
+    ArrowFunctionExpression,
+    VariableDeclaration,
+    BlockStatement,
+    EmptyStatement,
+    IfStatement,
+    WhileStatement,
+    DoWhileStatement,
+    ReturnStatement,
+    ThrowStatement,
+    BreakStatement,
+    ContinueStatement,
+    ExpressionStatement,
+    Parameter,
+    JSXElement,
+    JSXFragment,
+    JSXAttribute,
+    JSXSpreadAttribute,
+    JSXExpressionContainer,
+    JSXText,
+    JSXIdentifier,
+    JSXMemberExpression,
+    JSXNamespacedName,
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::without_spans::parse;
+
+    use super::*;
+
+    /// Collects the module specifiers a module depends on.
+    #[derive(Default)]
+    struct Imports(Vec<String>);
+
+    impl Visit for Imports {
+        fn enter_import_declaration(&mut self, node: &ImportDeclaration) {
+            self.0.push(node.source().value().to_string());
+        }
+
+        fn enter_export_named_declaration(&mut self, node: &ExportNamedDeclaration) {
+            if let Some(source) = node.source() {
+                self.0.push(source.value().to_string());
+            }
+        }
+
+        fn enter_export_all_declaration(&mut self, node: &ExportAllDeclaration) {
+            self.0.push(node.source().value().to_string());
+        }
+    }
+
+    #[test]
+    fn test_collect_imports() {
+        let program = parse(
+            "import a from 'a';\nimport 'b';\nexport { c } from 'c';\nexport * from 'd';\n\
+             export const e = 1;",
+            "module.js",
+        )
+        .unwrap();
+
+        let mut imports = Imports::default();
+        walk_program(&mut imports, &program);
+        assert_eq!(imports.0, ["a", "b", "c", "d"]);
+    }
+
+    /// Records the order hooks are called in.
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+
+    impl Visit for Trace {
+        fn enter_binary_expression(&mut self, node: &BinaryExpression) {
+            self.0.push(format!("enter {}", node.operator));
+        }
+
+        fn exit_binary_expression(&mut self, node: &BinaryExpression) {
+            self.0.push(format!("exit {}", node.operator));
+        }
+
+        fn enter_identifier(&mut self, node: &Identifier) {
+            self.0.push(node.name().to_string());
+        }
+    }
+
+    #[test]
+    fn test_walk_order() {
+        let program = parse("function f(a) { return a + b * c; }", "script.js").unwrap();
+
+        let mut trace = Trace::default();
+        walk_program(&mut trace, &program);
+        assert_eq!(
+            trace.0,
+            ["f", "a", "enter +", "a", "enter *", "b", "c", "exit *", "exit +"]
+        );
+    }
+
+    /// Replaces `DEBUG` with `false`, and drops `if (false)` statements.
+    struct StripDebug;
+
+    impl VisitMut for StripDebug {
+        fn enter_node(&mut self, node: &mut Node) {
+            if matches!(node, Node::Identifier(ident) if ident.name() == "DEBUG") {
+                *node = Node::BooleanLiteral(BooleanLiteral::new(false));
+            }
+        }
+
+        fn exit_block_statement(&mut self, node: &mut BlockStatement) {
+            node.statements_mut().retain(|statement| {
+                !matches!(
+                    statement,
+                    Node::IfStatement(IfStatement { test, alternate: None, .. })
+                        if matches!(**test, Node::BooleanLiteral(BooleanLiteral { value: false, .. }))
+                )
+            });
+        }
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let mut program =
+            parse("function f() { if (DEBUG) log(); g(DEBUG); }", "script.js").unwrap();
+        walk_program_mut(&mut StripDebug, &mut program);
+        assert_eq!(
+            program,
+            parse("function f() { g(false); }", "script.js").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_splice() {
+        let mut program = parse("a(); b();", "script.js").unwrap();
+        let directive = parse("'use strict';", "script.js").unwrap().splice(.., []);
+        program.splice(0..0, directive);
+        let removed = program.splice(2.., []);
+
+        assert_eq!(program, parse("'use strict'; a();", "script.js").unwrap());
+        assert_eq!(removed, parse("b();", "script.js").unwrap().body);
+    }
+}
@@ -1,40 +1,33 @@
-use color_eyre::Result;
-use yab_parser::ast;
+use miette::{miette, IntoDiagnostic, Result};
+use yab_parser::{ast, source::read_source_file};
 
+/// Parses the file given as the last argument and prints its AST as JSON, in
+/// the shape of ESTree with `--estree`.
 fn main() -> Result<()> {
-    /*
-    // Represents the code:
-    function foo(a) {
-        return a + 1
+    let mut args: Vec<_> = std::env::args().skip(1).collect();
+    let estree = args.first().is_some_and(|arg| arg == "--estree");
+    if estree {
+        args.remove(0);
+    }
+    let input_file_path = match args.as_slice() {
+        [path] => path.clone(),
+        _ => return Err(miette!("usage: ast [--estree] <file>")),
+    };
+    let source = read_source_file(&input_file_path)?;
+    for warning in source.warnings {
+        eprintln!("{:?}", warning);
     }
 
-    foo(1);
-    */
-    let mut function = ast::FunctionDeclaration::new("foo".to_string());
-
-    function.args_append(ast::Parameter::new(ast::Node::Identifier(
-        ast::Identifier::new("a".to_string()),
-    )));
-
-    function.body_append(ast::Node::ReturnStatement(ast::ReturnStatement::new(
-        ast::Node::BinaryExpression(ast::BinaryExpression::new(
-            ast::Node::Identifier(ast::Identifier::new("a".to_string())),
-            ast::Node::NumericLiteral(ast::NumericLiteral::new(1.0)),
-            "+".to_string(),
-        )),
-    )));
-
-    let mut program = ast::Program::default();
-    program.append(ast::Node::FunctionDeclaration(function));
-    program.append(ast::Node::ExpressionStatement(
-        ast::ExpressionStatement::new(ast::Node::CallExpression(ast::CallExpression::new(
-            "foo".to_string(),
-            vec![ast::Node::NumericLiteral(ast::NumericLiteral::new(1.0))],
-        ))),
-    ));
-
-    let program_node = ast::Node::Program(program);
-    let pretty_program = serde_json::to_string_pretty(&program_node)?;
+    let program = ast::Node::Program(yab_parser::parser::parse(
+        &source.contents,
+        input_file_path,
+    )?);
+    let pretty_program = if estree {
+        serde_json::to_string_pretty(&program.to_estree_json())
+    } else {
+        serde_json::to_string_pretty(&program)
+    }
+    .into_diagnostic()?;
     println!("{}", &pretty_program);
 
     Ok(())
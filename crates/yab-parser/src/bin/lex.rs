@@ -0,0 +1,25 @@
+use miette::{miette, IntoDiagnostic, Result};
+use yab_parser::{lexer::tokenize_with_options, parser::lexer_options, source::read_source_file};
+
+/// Lexes the file given as the only argument and prints its tokens as JSON,
+/// each with the span it was lexed from.
+fn main() -> Result<()> {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let input_file_path = match args.as_slice() {
+        [path] => path.clone(),
+        _ => return Err(miette!("usage: lex <file>")),
+    };
+    let source = read_source_file(&input_file_path)?;
+    for warning in source.warnings {
+        eprintln!("{:?}", warning);
+    }
+
+    let options = lexer_options(&input_file_path);
+    let tokens = tokenize_with_options(&source.contents, input_file_path, options)?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&tokens).into_diagnostic()?
+    );
+
+    Ok(())
+}
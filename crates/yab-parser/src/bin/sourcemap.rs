@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use miette::{miette, IntoDiagnostic, Result};
+use yab_parser::sourcemap::{decode_data_url, source_mapping_url, SourceMap};
+
+const USAGE: &str = "usage: sourcemap lookup <file> <line> <column>";
+
+/// Reads the source map for an emitted file, from its `sourceMappingURL`
+/// comment or else from `<file>.map`.  A file ending in `.map` is taken to be
+/// the source map itself.
+fn read_source_map(file_path: &str) -> Result<SourceMap> {
+    if file_path.ends_with(".map") {
+        let src = std::fs::read_to_string(file_path).into_diagnostic()?;
+        return SourceMap::parse(&src, file_path);
+    }
+
+    let code = std::fs::read_to_string(file_path).into_diagnostic()?;
+    match source_mapping_url(&code) {
+        Some(url) if url.starts_with("data:") => {
+            SourceMap::parse(&decode_data_url(url)?, file_path)
+        }
+        Some(url) => {
+            let map_path = Path::new(file_path)
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(url);
+            let src = std::fs::read_to_string(&map_path).into_diagnostic()?;
+            SourceMap::parse(&src, map_path.to_string_lossy())
+        }
+        None => {
+            let map_path = format!("{}.map", file_path);
+            let src = std::fs::read_to_string(&map_path).into_diagnostic()?;
+            SourceMap::parse(&src, map_path)
+        }
+    }
+}
+
+fn parse_position(arg: Option<String>) -> Result<usize> {
+    arg.and_then(|arg| arg.parse::<usize>().ok())
+        .filter(|position| *position > 0)
+        .ok_or_else(|| miette!("{}", USAGE))
+}
+
+/// Prints where the given position in an emitted file came from.  Lines and
+/// columns count from one, as they do in stack traces.
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() != Some("lookup") {
+        return Err(miette!("{}", USAGE));
+    }
+
+    let file_path = args.next().ok_or_else(|| miette!("{}", USAGE))?;
+    let line = parse_position(args.next())?;
+    let column = parse_position(args.next())?;
+
+    let source_map = read_source_map(&file_path)?;
+    let location = source_map
+        .lookup(line - 1, column - 1)
+        .ok_or_else(|| miette!("{}:{}:{} has no original location", file_path, line, column))?;
+
+    match location.name {
+        Some(name) => println!(
+            "{}:{}:{} ({})",
+            location.source,
+            location.line + 1,
+            location.column + 1,
+            name
+        ),
+        None => println!(
+            "{}:{}:{}",
+            location.source,
+            location.line + 1,
+            location.column + 1
+        ),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use miette::{miette, IntoDiagnostic, Result};
+use yab_parser::{
+    ast::{
+        visit::{walk_program, Visit},
+        Node,
+    },
+    lexer::tokenize_stream_with_options,
+    parser::{lexer_options, parser_options, Parser},
+    source::read_source_file,
+};
+
+const USAGE: &str = "usage: stats <file or directory>";
+
+/// The extensions of the files we know how to parse.
+const EXTENSIONS: [&str; 8] = ["js", "mjs", "cjs", "jsx", "ts", "mts", "cts", "tsx"];
+
+/// Counts nodes by type, and how deeply they nest.
+#[derive(Default)]
+struct NodeStats {
+    counts: BTreeMap<&'static str, usize>,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Visit for NodeStats {
+    fn enter_node(&mut self, node: &Node) {
+        *self.counts.entry(node.into()).or_default() += 1;
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+    }
+
+    fn exit_node(&mut self, _node: &Node) {
+        self.depth -= 1;
+    }
+}
+
+struct FileStats {
+    path: PathBuf,
+    bytes: usize,
+    tokens: BTreeMap<&'static str, usize>,
+    nodes: NodeStats,
+    lex_time: Duration,
+    parse_time: Duration,
+}
+
+/// Megabytes per second, for `bytes` processed in `time`.
+fn throughput(bytes: usize, time: Duration) -> f64 {
+    bytes as f64 / 1_000_000.0 / time.as_secs_f64().max(f64::EPSILON)
+}
+
+/// Collects the source files under `path`, skipping `node_modules` and hidden
+/// directories.  A path to a file is taken as is, whatever its extension.
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = std::fs::read_dir(path)
+        .into_diagnostic()?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .into_diagnostic()?;
+    entries.sort();
+
+    for entry in entries {
+        let name = entry.file_name().unwrap_or_default().to_string_lossy();
+        if entry.is_dir() {
+            if name != "node_modules" && !name.starts_with('.') {
+                collect_files(&entry, files)?;
+            }
+        } else if entry
+            .extension()
+            .is_some_and(|extension| EXTENSIONS.iter().any(|e| extension == *e))
+        {
+            files.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+/// Lexes and parses a file, timing each separately.  Parsing reuses the
+/// tokens from lexing, so its time doesn't include lexing.
+fn file_stats(path: &Path) -> Result<FileStats> {
+    let file_name = path.to_string_lossy().to_string();
+    let source = read_source_file(path)?.contents;
+
+    let start = Instant::now();
+    let stream = tokenize_stream_with_options(&source, &file_name, lexer_options(&file_name))?;
+    let lex_time = start.elapsed();
+
+    let mut tokens = BTreeMap::new();
+    for token in &stream.tokens {
+        *tokens.entry(token.into()).or_default() += 1;
+    }
+
+    let start = Instant::now();
+    let program =
+        Parser::with_options(stream, &file_name, parser_options(&file_name)).parse_program()?;
+    let parse_time = start.elapsed();
+
+    let mut nodes = NodeStats::default();
+    walk_program(&mut nodes, &program);
+
+    Ok(FileStats {
+        path: path.to_path_buf(),
+        bytes: source.len(),
+        tokens,
+        nodes,
+        lex_time,
+        parse_time,
+    })
+}
+
+/// Prints counts by kind, most common first.
+fn print_counts(title: &str, counts: &BTreeMap<&'static str, usize>) {
+    let mut counts: Vec<_> = counts.iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(a.1));
+
+    println!("\n{}:", title);
+    for (kind, count) in counts {
+        println!("  {:<28} {:>10}", kind, count);
+    }
+}
+
+/// Reports token and node counts, nesting depth, and lexing and parsing
+/// throughput for a file, or for every source file in a directory.  Files
+/// are listed slowest to parse first, which is where pathological inputs
+/// show up.
+fn main() -> Result<()> {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let path = match args.as_slice() {
+        [path] => PathBuf::from(path),
+        _ => return Err(miette!(USAGE)),
+    };
+
+    let mut files = Vec::new();
+    collect_files(&path, &mut files)?;
+
+    let mut stats = Vec::new();
+    for file in files {
+        match file_stats(&file) {
+            Ok(file_stats) => stats.push(file_stats),
+            Err(err) => eprintln!("{}: {}", file.display(), err),
+        }
+    }
+    stats.sort_by(|a, b| {
+        throughput(a.bytes, a.parse_time).total_cmp(&throughput(b.bytes, b.parse_time))
+    });
+
+    println!(
+        "{:>10} {:>8} {:>8} {:>6} {:>10} {:>10}  file",
+        "bytes", "tokens", "nodes", "depth", "lex MB/s", "parse MB/s"
+    );
+    let mut tokens = BTreeMap::new();
+    let mut nodes = BTreeMap::new();
+    for file in &stats {
+        println!(
+            "{:>10} {:>8} {:>8} {:>6} {:>10.2} {:>10.2}  {}",
+            file.bytes,
+            file.tokens.values().sum::<usize>(),
+            file.nodes.counts.values().sum::<usize>(),
+            file.nodes.max_depth,
+            throughput(file.bytes, file.lex_time),
+            throughput(file.bytes, file.parse_time),
+            file.path.display()
+        );
+
+        for (kind, count) in &file.tokens {
+            *tokens.entry(*kind).or_default() += count;
+        }
+        for (kind, count) in &file.nodes.counts {
+            *nodes.entry(*kind).or_default() += count;
+        }
+    }
+
+    let bytes = stats.iter().map(|file| file.bytes).sum();
+    let lex_time = stats.iter().map(|file| file.lex_time).sum();
+    let parse_time = stats.iter().map(|file| file.parse_time).sum();
+    println!(
+        "\nfiles: {}, bytes: {}, lexed at {:.2} MB/s, parsed at {:.2} MB/s",
+        stats.len(),
+        bytes,
+        throughput(bytes, lex_time),
+        throughput(bytes, parse_time)
+    );
+    print_counts("Tokens", &tokens);
+    print_counts("Nodes", &nodes);
+
+    Ok(())
+}
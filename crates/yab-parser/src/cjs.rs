@@ -0,0 +1,285 @@
+//! Finds the names a CommonJS module exports without running it, so that an
+//! ES module importing it by name can be given real bindings rather than only
+//! the default export.
+//!
+//! Like Node's cjs-module-lexer, this works on tokens rather than the AST, so
+//! it doesn't need to be able to parse the whole module, and it only
+//! recognizes the patterns that compilers and hand-written modules commonly
+//! use:
+//!
+//! * `exports.a = ...`, `exports["a"] = ...`, and the same through
+//!   `module.exports`.
+//!
+//! * `Object.defineProperty(exports, "a", ...)`.
+//!
+//! * `module.exports = { a, b: c, "d": e }`, where only the keys that are
+//!   plain names or strings count.
+//!
+//! * `module.exports = require("./a")`, which passes on the exports of
+//!   another module.
+//!
+//! Exports that are only known at runtime, like `exports[name] = ...`, can't
+//! be found this way.
+
+use miette::Result;
+
+use crate::lexer::{tokenize, OperatorType, PunctuationType, Token};
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CjsExports {
+    /// The names the module exports, in the order they are first assigned.
+    pub exports: Vec<String>,
+    /// The specifiers of the modules whose exports this module passes on as
+    /// its own.
+    pub reexports: Vec<String>,
+}
+
+/// Finds the exports of the CommonJS module `src`.  Fails only if the module
+/// can't be lexed.
+pub fn detect_exports(src: &str, file_name: impl Into<String>) -> Result<CjsExports> {
+    let tokens = tokenize(src, file_name)?
+        .into_iter()
+        .filter(|token| !matches!(token, Token::Comment(_)))
+        .collect();
+    let mut scanner = Scanner {
+        tokens,
+        exports: CjsExports::default(),
+    };
+    for n in 0..scanner.tokens.len() {
+        scanner.scan_at(n);
+    }
+
+    Ok(scanner.exports)
+}
+
+/// The name a token spells, if it's a word that could be a property name:
+/// an identifier, a keyword, or a literal like `true`.
+pub(crate) fn word(token: &Token) -> Option<String> {
+    match token {
+        Token::Ident(ident) => Some(ident.lexeme.to_string()),
+        Token::Keyword(keyword) => Some(keyword.kind.to_string()),
+        Token::ValueLiteral(value) => Some(value.kind.to_string()),
+        Token::Operator(operator) => {
+            let name = operator.kind.to_string();
+            name.chars()
+                .all(|c| c.is_ascii_alphabetic())
+                .then_some(name)
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn string(token: &Token) -> Option<String> {
+    match token {
+        Token::StringLiteral(string) => Some(string.lexeme.to_string()),
+        _ => None,
+    }
+}
+
+struct Scanner<'src> {
+    tokens: Vec<Token<'src>>,
+    exports: CjsExports,
+}
+
+impl Scanner<'_> {
+    fn is_word_at(&self, n: usize, expected: &str) -> bool {
+        self.tokens.get(n).and_then(word).as_deref() == Some(expected)
+    }
+
+    fn is_punctuation_at(&self, n: usize, kind: PunctuationType) -> bool {
+        matches!(self.tokens.get(n), Some(Token::Punctuation(p)) if p.kind == kind)
+    }
+
+    fn is_assignment_at(&self, n: usize) -> bool {
+        matches!(
+            self.tokens.get(n),
+            Some(Token::Operator(operator)) if operator.kind == OperatorType::Assignment
+        )
+    }
+
+    fn add_export(&mut self, name: String) {
+        if !self.exports.exports.contains(&name) {
+            self.exports.exports.push(name);
+        }
+    }
+
+    /// If `exports` or `module.exports` starts at `n`, returns the offset just
+    /// past it, and whether it was `module.exports`.  A property of anything
+    /// else that happens to be called `exports` doesn't count.
+    fn exports_object_end(&self, n: usize) -> Option<(usize, bool)> {
+        if n > 0 && self.is_punctuation_at(n - 1, PunctuationType::Dot) {
+            return None;
+        }
+
+        if self.is_word_at(n, "exports") {
+            Some((n + 1, false))
+        } else if self.is_word_at(n, "module")
+            && self.is_punctuation_at(n + 1, PunctuationType::Dot)
+            && self.is_word_at(n + 2, "exports")
+        {
+            Some((n + 3, true))
+        } else {
+            None
+        }
+    }
+
+    fn scan_at(&mut self, n: usize) {
+        if let Some((end, is_module_exports)) = self.exports_object_end(n) {
+            self.scan_exports_member(end, is_module_exports);
+        } else if self.is_word_at(n, "Object")
+            && self.is_punctuation_at(n + 1, PunctuationType::Dot)
+            && self.is_word_at(n + 2, "defineProperty")
+            && self.is_punctuation_at(n + 3, PunctuationType::OpenParen)
+        {
+            if let Some((end, _)) = self.exports_object_end(n + 4) {
+                if self.is_punctuation_at(end, PunctuationType::Comma) {
+                    if let Some(name) = self.tokens.get(end + 1).and_then(string) {
+                        self.add_export(name);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Looks for an assignment to a property of the exports object, or for
+    /// `module.exports`, to the object itself, where the object ends just
+    /// before `n`.
+    fn scan_exports_member(&mut self, n: usize, is_module_exports: bool) {
+        if self.is_punctuation_at(n, PunctuationType::Dot) && self.is_assignment_at(n + 2) {
+            if let Some(name) = self.tokens.get(n + 1).and_then(word) {
+                self.add_export(name);
+            }
+        } else if self.is_punctuation_at(n, PunctuationType::OpenBracket)
+            && self.is_punctuation_at(n + 2, PunctuationType::CloseBracket)
+            && self.is_assignment_at(n + 3)
+        {
+            if let Some(name) = self.tokens.get(n + 1).and_then(string) {
+                self.add_export(name);
+            }
+        } else if is_module_exports && self.is_assignment_at(n) {
+            if self.is_punctuation_at(n + 1, PunctuationType::OpenBrace) {
+                self.scan_object_literal(n + 1);
+            } else if self.is_word_at(n + 1, "require")
+                && self.is_punctuation_at(n + 2, PunctuationType::OpenParen)
+                && self.is_punctuation_at(n + 4, PunctuationType::CloseParen)
+            {
+                if let Some(specifier) = self.tokens.get(n + 3).and_then(string) {
+                    self.exports.reexports.push(specifier);
+                }
+            }
+        }
+    }
+
+    /// Exports the keys of the object literal whose `{` is at `open`, skipping
+    /// properties whose keys aren't plain names or strings.
+    fn scan_object_literal(&mut self, open: usize) {
+        let mut n = open + 1;
+        loop {
+            let ends_key = self.is_punctuation_at(n + 1, PunctuationType::Comma)
+                || self.is_punctuation_at(n + 1, PunctuationType::Colon)
+                || self.is_punctuation_at(n + 1, PunctuationType::CloseBrace);
+            if ends_key {
+                let token = &self.tokens[n];
+                if let Some(name) = word(token).or_else(|| string(token)) {
+                    self.add_export(name);
+                }
+            }
+
+            // Skip the value, up to the `,` before the next property or the
+            // `}` closing the object.
+            let mut depth = 0usize;
+            loop {
+                let Some(token) = self.tokens.get(n) else {
+                    return;
+                };
+                if let Token::Punctuation(p) = token {
+                    match p.kind {
+                        PunctuationType::OpenParen
+                        | PunctuationType::OpenBracket
+                        | PunctuationType::OpenBrace => depth += 1,
+                        PunctuationType::CloseParen
+                        | PunctuationType::CloseBracket
+                        | PunctuationType::CloseBrace => match depth.checked_sub(1) {
+                            Some(inner) => depth = inner,
+                            None => return,
+                        },
+                        PunctuationType::Comma if depth == 0 => {
+                            n += 1;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                n += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exports(src: &str) -> Vec<String> {
+        detect_exports(src, "module.cjs").unwrap().exports
+    }
+
+    #[test]
+    fn test_export_assignments() {
+        assert_eq!(
+            exports(
+                r#"
+                exports.a = 1;
+                module.exports.b = function () {};
+                exports["c-d"] = 2;
+                module.exports['default'] = 3;
+                exports.a = 4;
+                "#
+            ),
+            ["a", "b", "c-d", "default"]
+        );
+        assert_eq!(
+            exports("foo.exports.a = 1; exports.b == 1; exports[name] = 2;"),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_define_property() {
+        assert_eq!(
+            exports(
+                r#"
+                Object.defineProperty(exports, "__esModule", { value: true });
+                Object.defineProperty(module.exports, 'a', { get: function () { return a; } });
+                "#
+            ),
+            ["__esModule", "a"]
+        );
+    }
+
+    #[test]
+    fn test_object_literal() {
+        assert_eq!(
+            exports(
+                r#"module.exports = { a, b: f(1, { c: 2 }), "d": [e, g], default: h, ...i, [j]: k };"#
+            ),
+            ["a", "b", "d", "default"]
+        );
+    }
+
+    #[test]
+    fn test_reexports() {
+        let detected = detect_exports(
+            "module.exports = require('./a'); exports.b = 1;",
+            "module.cjs",
+        )
+        .unwrap();
+        assert_eq!(
+            detected,
+            CjsExports {
+                exports: vec!["b".to_string()],
+                reexports: vec!["./a".to_string()],
+            }
+        );
+    }
+}
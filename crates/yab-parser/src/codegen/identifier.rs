@@ -0,0 +1,243 @@
+//! Rules for when a property name can be written as a bare identifier, and how
+//! to write it when it can't.
+
+use std::fmt::Write;
+
+use crate::lexer::utils::{is_identifier_part, is_identifier_start};
+
+use super::{
+    string::{write_string_literal, StringOptions},
+    Charset, Target,
+};
+
+/// Words ES3 doesn't allow after a `.` or as unquoted object literal keys,
+/// including its long list of "future" reserved words.  ES5 relaxed this to
+/// allow any IdentifierName in both places.
+static ES3_RESERVED_WORDS: &[&str] = &[
+    "abstract",
+    "boolean",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "final",
+    "finally",
+    "float",
+    "for",
+    "function",
+    "goto",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "int",
+    "interface",
+    "long",
+    "native",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "static",
+    "super",
+    "switch",
+    "synchronized",
+    "this",
+    "throw",
+    "throws",
+    "transient",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "volatile",
+    "while",
+    "with",
+];
+
+/// Whether `name` is an IdentifierName for `target`, i.e. something that
+/// could be written as a property name after a `.` in ES5 and later.
+pub fn is_identifier_name(name: &str, target: Target) -> bool {
+    // Before ES2015, source text was a sequence of UTF-16 code units, so a
+    // character outside the BMP (which takes two) can't be part of a name.
+    let representable = |c: char| target >= Target::Es2015 || (c as u32) <= 0xFFFF;
+
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if is_identifier_start(first) && representable(first) => {
+            chars.all(|c| is_identifier_part(c) && representable(c))
+        }
+        _ => false,
+    }
+}
+
+/// Whether `name` can be written unquoted as a property name, after a `.` or
+/// as an object literal key, for `target`.
+pub fn is_valid_property_name(name: &str, target: Target) -> bool {
+    is_identifier_name(name, target)
+        && (target >= Target::Es5 || !ES3_RESERVED_WORDS.contains(&name))
+}
+
+/// Writes `name`, which must be an identifier name, escaping any non-ASCII
+/// characters as `\uXXXX` (or `\u{XXXXX}` outside the BMP) if the charset
+/// calls for it.
+pub fn write_identifier(out: &mut String, name: &str, charset: Charset) {
+    if charset == Charset::Utf8 || name.is_ascii() {
+        out.push_str(name);
+        return;
+    }
+
+    for c in name.chars() {
+        match c as u32 {
+            _ if c.is_ascii() => out.push(c),
+            code_point @ ..=0xFFFF => _ = write!(out, "\\u{:04X}", code_point),
+            code_point => _ = write!(out, "\\u{{{:X}}}", code_point),
+        }
+    }
+}
+
+/// Whether `key` can be written as a numeric literal in an object literal and
+/// still name the same property.  Only canonical integers qualify: `{ 1e3: x }`
+/// defines a property named "1000" and `{ 01: x }` one named "1".  Keys are
+/// limited to 15 digits so they are always exactly representable.
+fn is_canonical_numeric_key(key: &str) -> bool {
+    key == "0"
+        || (!key.is_empty()
+            && key.len() <= 15
+            && !key.starts_with('0')
+            && key.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Writes `key` as an object literal property key: bare if it's a valid
+/// property name for `target`, as a number if that means the same thing, and
+/// quoted otherwise.
+pub fn write_property_key(out: &mut String, key: &str, target: Target, charset: Charset) {
+    if is_valid_property_name(key, target) {
+        write_identifier(out, key, charset);
+    } else if is_canonical_numeric_key(key) {
+        out.push_str(key);
+    } else {
+        write_string_literal(out, key, string_options(charset));
+    }
+}
+
+/// Writes an access of the property named `property`: `.property` if the
+/// name allows it for `target`, and `["property"]` otherwise.
+pub fn write_member_access(out: &mut String, property: &str, target: Target, charset: Charset) {
+    if is_valid_property_name(property, target) {
+        out.push('.');
+        write_identifier(out, property, charset);
+    } else {
+        out.push('[');
+        write_string_literal(out, property, string_options(charset));
+        out.push(']');
+    }
+}
+
+fn string_options(charset: Charset) -> StringOptions {
+    StringOptions {
+        allow_template: false,
+        charset,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn property_key(key: &str, target: Target) -> String {
+        let mut out = String::new();
+        write_property_key(&mut out, key, target, Charset::Utf8);
+        out
+    }
+
+    fn member_access(property: &str, target: Target) -> String {
+        let mut out = String::new();
+        write_member_access(&mut out, property, target, Charset::Utf8);
+        out
+    }
+
+    #[test]
+    fn test_is_identifier_name() {
+        for name in ["a", "_private", "$", "camelCase1", "café", "class"] {
+            assert!(is_identifier_name(name, Target::Es3), "{}", name);
+        }
+        for name in ["", "1a", "a-b", "a b", "\u{1F600}"] {
+            assert!(!is_identifier_name(name, Target::Es2015), "{:?}", name);
+        }
+
+        // U+10400 DESERET CAPITAL LETTER LONG I is a letter outside the BMP.
+        assert!(is_identifier_name("\u{10400}", Target::Es2015));
+        assert!(!is_identifier_name("\u{10400}", Target::Es5));
+    }
+
+    #[test]
+    fn test_reserved_words_by_target() {
+        assert_eq!(property_key("class", Target::Es3), r#""class""#);
+        assert_eq!(property_key("class", Target::Es5), "class");
+        assert_eq!(property_key("goto", Target::Es3), r#""goto""#);
+        assert_eq!(property_key("goto", Target::Es2015), "goto");
+
+        assert_eq!(member_access("class", Target::Es3), r#"["class"]"#);
+        assert_eq!(member_access("class", Target::Es5), ".class");
+        assert_eq!(member_access("default", Target::Es2015), ".default");
+    }
+
+    #[test]
+    fn test_numeric_keys() {
+        assert_eq!(property_key("0", Target::Es2015), "0");
+        assert_eq!(property_key("123", Target::Es2015), "123");
+        assert_eq!(property_key("01", Target::Es2015), r#""01""#);
+        assert_eq!(property_key("1e3", Target::Es2015), r#""1e3""#);
+        assert_eq!(property_key("1.5", Target::Es2015), r#""1.5""#);
+        assert_eq!(property_key("-1", Target::Es2015), r#""-1""#);
+        assert_eq!(
+            property_key("1234567890123456", Target::Es2015),
+            r#""1234567890123456""#
+        );
+
+        // Numbers can never follow a dot.
+        assert_eq!(member_access("0", Target::Es2015), r#"["0"]"#);
+    }
+
+    #[test]
+    fn test_ascii_only_names() {
+        let mut out = String::new();
+        write_identifier(&mut out, "café\u{10400}", Charset::Ascii);
+        assert_eq!(out, r"caf\u00E9\u{10400}");
+
+        let mut out = String::new();
+        write_property_key(&mut out, "ñ", Target::Es5, Charset::Ascii);
+        write_member_access(&mut out, "ñ", Target::Es5, Charset::Ascii);
+        write_member_access(&mut out, "ñ-ñ", Target::Es5, Charset::Ascii);
+        assert_eq!(out, r#"\u00F1.\u00F1["\xF1-\xF1"]"#);
+    }
+
+    #[test]
+    fn test_quoted_names() {
+        assert_eq!(property_key("a-b", Target::Es2015), r#""a-b""#);
+        assert_eq!(property_key("", Target::Es2015), r#""""#);
+        assert_eq!(member_access("a\"b", Target::Es2015), r#"['a"b']"#);
+        assert_eq!(property_key("\u{10400}", Target::Es5), "\"\u{10400}\"");
+    }
+}
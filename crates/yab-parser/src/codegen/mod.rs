@@ -0,0 +1,36 @@
+//! Utilities for printing JavaScript source text.  These are the pieces that
+//! have to get the language's lexical rules exactly right (when a name needs
+//! quoting, how to escape a string) so that whatever ends up printing the AST
+//! doesn't have to.
+
+mod identifier;
+mod string;
+
+pub use self::{
+    identifier::{
+        is_identifier_name, is_valid_property_name, write_identifier, write_member_access,
+        write_property_key,
+    },
+    string::{write_string_literal, write_utf16_string_literal, StringOptions},
+};
+
+/// Which characters may be written to the output as is.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    #[default]
+    Utf8,
+    /// Only ASCII, for output that has to survive servers and tools that
+    /// mishandle UTF-8.  Anything else is written as an escape sequence.
+    Ascii,
+}
+
+/// The version of the language generated code has to run on.  Only the
+/// versions that changed a rule something in this module cares about are
+/// listed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Target {
+    Es3,
+    Es5,
+    #[default]
+    Es2015,
+}
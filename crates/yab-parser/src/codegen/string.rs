@@ -0,0 +1,263 @@
+use std::fmt::Write;
+
+use super::Charset;
+
+/// Options for [`write_string_literal`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StringOptions {
+    /// Whether a template literal may be written instead of a string literal
+    /// when that needs fewer escapes.  Only turn this on where an expression
+    /// is expected (a template can't be an object key or a module specifier),
+    /// and when targeting ES2015 or later.
+    pub allow_template: bool,
+    pub charset: Charset,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quote {
+    Double,
+    Single,
+    Backtick,
+}
+
+impl Quote {
+    fn as_char(self) -> char {
+        match self {
+            Quote::Double => '"',
+            Quote::Single => '\'',
+            Quote::Backtick => '`',
+        }
+    }
+}
+
+/// Picks the quote that needs the fewest escapes for `value`.  Ties go to
+/// double quotes, then single quotes.
+fn choose_quote<I>(units: I, options: StringOptions) -> Quote
+where
+    I: Iterator<Item = u16>,
+{
+    let mut single = 0;
+    let mut double = 0;
+    let mut backtick = 0;
+    let mut previous = 0;
+    for unit in units {
+        match unit {
+            0x27 => single += 1,
+            0x22 => double += 1,
+            0x60 => backtick += 1,
+            // `${` has to be escaped in a template.
+            0x7B if previous == 0x24 => backtick += 1,
+            // A template can hold a raw newline where a string needs `\n`.
+            0x0A => backtick -= 1,
+            _ => {}
+        }
+        previous = unit;
+    }
+
+    if options.allow_template && backtick < single.min(double) {
+        Quote::Backtick
+    } else if single < double {
+        Quote::Single
+    } else {
+        Quote::Double
+    }
+}
+
+/// Writes `value` as a string literal (or a template literal, if allowed and
+/// shorter) that evaluates to exactly `value`.
+///
+/// Line and paragraph separators are always escaped: they're only allowed
+/// unescaped in string literals since ES2019, and still end the line in a
+/// lot of tools that process JavaScript without parsing it.
+pub fn write_string_literal(out: &mut String, value: &str, options: StringOptions) {
+    write_utf16(out, value.encode_utf16(), options);
+}
+
+/// Like [`write_string_literal`], but for a value given as UTF-16 code units,
+/// which is what a JavaScript string really is.  Unlike a `&str` this can hold
+/// lone surrogates, which are written as `\u` escapes.
+pub fn write_utf16_string_literal(out: &mut String, value: &[u16], options: StringOptions) {
+    write_utf16(out, value.iter().copied(), options);
+}
+
+fn write_utf16<I>(out: &mut String, units: I, options: StringOptions)
+where
+    I: Iterator<Item = u16> + Clone,
+{
+    let quote = choose_quote(units.clone(), options);
+    out.push(quote.as_char());
+
+    let mut chars = char::decode_utf16(units).peekable();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            Ok(c) => c,
+            Err(err) => {
+                _ = write!(out, "\\u{:04X}", err.unpaired_surrogate());
+                continue;
+            }
+        };
+
+        match c {
+            '\\' => out.push_str("\\\\"),
+            c if c == quote.as_char() => {
+                out.push('\\');
+                out.push(c);
+            }
+            '$' if quote == Quote::Backtick && matches!(chars.peek(), Some(Ok('{'))) => {
+                out.push_str("\\$");
+            }
+            '\n' if quote == Quote::Backtick => out.push('\n'),
+            '\n' => out.push_str("\\n"),
+            // Templates normalize raw carriage returns to newlines, so this
+            // has to stay escaped even there.
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{B}' => out.push_str("\\v"),
+            '\u{C}' => out.push_str("\\f"),
+            // `\0` followed by a digit would be read as an octal escape.
+            '\0' if !matches!(chars.peek(), Some(Ok('0'..='9'))) => out.push_str("\\0"),
+            '\u{2028}' | '\u{2029}' => _ = write!(out, "\\u{:04X}", c as u32),
+            c if c.is_control() => _ = write!(out, "\\x{:02X}", c as u32),
+            c if options.charset == Charset::Ascii && !c.is_ascii() => write_escaped_char(out, c),
+            c => out.push(c),
+        }
+    }
+
+    out.push(quote.as_char());
+}
+
+/// Writes a non-ASCII character as an escape sequence that works in any
+/// string literal, so characters outside the BMP become a surrogate pair
+/// rather than a `\u{...}` escape, which needs ES2015.
+fn write_escaped_char(out: &mut String, c: char) {
+    if (c as u32) <= 0xFF {
+        _ = write!(out, "\\x{:02X}", c as u32);
+        return;
+    }
+
+    let mut units = [0; 2];
+    for unit in c.encode_utf16(&mut units) {
+        _ = write!(out, "\\u{:04X}", unit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::lexer::{tokenize, Token};
+
+    use super::*;
+
+    fn emit(value: &str, options: StringOptions) -> String {
+        let mut out = String::new();
+        write_string_literal(&mut out, value, options);
+        out
+    }
+
+    const TEMPLATES: StringOptions = StringOptions {
+        allow_template: true,
+        charset: Charset::Utf8,
+    };
+
+    const ASCII: StringOptions = StringOptions {
+        allow_template: false,
+        charset: Charset::Ascii,
+    };
+
+    /// Evaluates an emitted string or template literal by running it back
+    /// through the lexer.
+    fn evaluate(literal: &str) -> String {
+        let tokens = tokenize(literal, "script.js").unwrap();
+        match tokens.as_slice() {
+            [Token::StringLiteral(string)] => string.lexeme.to_string(),
+            [Token::TemplateLiteralString(string)] if string.complete => {
+                string.cooked.as_deref().unwrap().to_string()
+            }
+            tokens => panic!("{} did not lex as a single string: {:?}", literal, tokens),
+        }
+    }
+
+    #[test]
+    fn test_quote_selection() {
+        assert_eq!(emit("plain", TEMPLATES), r#""plain""#);
+        assert_eq!(emit(r#"say "hi""#, TEMPLATES), r#"'say "hi"'"#);
+        assert_eq!(emit("it's", TEMPLATES), r#""it's""#);
+        assert_eq!(
+            emit(r#"it's "x" "y""#, StringOptions::default()),
+            r#"'it\'s "x" "y"'"#
+        );
+        assert_eq!(emit(r#"it's "x""#, TEMPLATES), r#"`it's "x"`"#);
+        assert_eq!(emit(r#"'"'"#, TEMPLATES), r#"`'"'`"#);
+    }
+
+    #[test]
+    fn test_templates_for_multiline_strings() {
+        assert_eq!(emit("a\nb", StringOptions::default()), r#""a\nb""#);
+        assert_eq!(emit("a\nb", TEMPLATES), "`a\nb`");
+        assert_eq!(emit("a\r\nb", TEMPLATES), "`a\\r\nb`");
+        assert_eq!(emit("${a}\n\n`", TEMPLATES), r#""${a}\n\n`""#);
+        assert_eq!(emit("${a}\n\n\n`", TEMPLATES), "`\\${a}\n\n\n\\``");
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert_eq!(
+            emit("\\\t\u{8}\u{B}\u{C}\u{1}\u{7F}", StringOptions::default()),
+            r#""\\\t\b\v\f\x01\x7F""#
+        );
+        assert_eq!(emit("\0a\u{0}1", StringOptions::default()), r#""\0a\x001""#);
+        assert_eq!(emit("a\u{2028}b\u{2029}", TEMPLATES), r#""a\u2028b\u2029""#);
+        assert_eq!(emit("é😀", StringOptions::default()), "\"é😀\"");
+    }
+
+    #[test]
+    fn test_lone_surrogates() {
+        let mut out = String::new();
+        write_utf16_string_literal(
+            &mut out,
+            &[0x61, 0xD800, 0x62, 0xDE00, 0xD83D, 0xDE00],
+            StringOptions::default(),
+        );
+        assert_eq!(out, "\"a\\uD800b\\uDE00😀\"");
+
+        let tokens = tokenize(&out, "script.js").unwrap();
+        let [Token::StringLiteral(string)] = tokens.as_slice() else {
+            panic!("{} did not lex as a single string", out);
+        };
+        assert_eq!(
+            string.code_units.as_deref(),
+            Some(&[0x61, 0xD800, 0x62, 0xDE00, 0xD83D, 0xDE00][..])
+        );
+    }
+
+    #[test]
+    fn test_ascii_only() {
+        assert_eq!(emit("café ñ", ASCII), r#""caf\xE9 \xF1""#);
+        assert_eq!(emit("日本", ASCII), r#""\u65E5\u672C""#);
+        assert_eq!(emit("😀", ASCII), r#""\uD83D\uDE00""#);
+        assert_eq!(emit("a\u{2028}", ASCII), r#""a\u2028""#);
+    }
+
+    proptest! {
+        #[test]
+        fn test_emitted_strings_evaluate_to_original(
+            value in r#"[a-z'"`$\{\}\\\n\r\t\x00-\x1F\x7Fé😀\u{2028}\u{2029}0-9 ]{0,24}"#,
+            allow_template in any::<bool>(),
+        ) {
+            let literal = emit(&value, StringOptions { allow_template, charset: Charset::Utf8 });
+            prop_assert_eq!(evaluate(&literal), value);
+        }
+
+        #[test]
+        fn test_ascii_strings_evaluate_to_original(
+            value in r#"[a-z'"`$\{\}\\\n\x00-\x1F\x7F-\u{FF}é\u{100}-\u{2FFF}😀\u{10000}-\u{10FFF}]{0,24}"#,
+            allow_template in any::<bool>(),
+        ) {
+            let literal = emit(&value, StringOptions { allow_template, charset: Charset::Ascii });
+            prop_assert!(literal.is_ascii(), "{} is not ASCII", literal);
+            prop_assert_eq!(evaluate(&literal), value);
+        }
+    }
+}
@@ -0,0 +1,292 @@
+//! Finds the modules a module depends on, without parsing the whole of it.
+//!
+//! Like [`crate::cjs`], this works on tokens, so it understands strings,
+//! comments, templates and line breaks the way the lexer does, but doesn't
+//! need the parser to support every statement in the module.  It finds:
+//!
+//! * `import 'a'` and `import a, { b } from 'a'`, including in TypeScript
+//!   `import type { A } from 'a'`.
+//!
+//! * `export * from 'a'`, `export * as a from 'a'` and `export { a } from 'a'`.
+//!
+//! * `import('a')`, where the specifier is a plain string.
+//!
+//! * `require('a')`.
+//!
+//! Specifiers that are only known at runtime, like `import(name)`, can't be
+//! found this way.
+
+use miette::Result;
+
+use crate::{
+    cjs::{string, word},
+    lexer::{tokenize_with_options, OperatorType, PunctuationType, Token},
+    location::Span,
+    parser::lexer_options,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportKind {
+    /// An `import` declaration.
+    Static,
+    /// An `export ... from` declaration.
+    ReExport,
+    /// An `import()` expression.
+    Dynamic,
+    /// A `require()` call.
+    Require,
+}
+
+/// A module that another module depends on.
+#[derive(Debug, PartialEq)]
+pub struct ImportRecord {
+    pub specifier: String,
+    pub kind: ImportKind,
+    /// Where the specifier is in the importing module, quotes included.
+    pub span: Span,
+}
+
+/// Finds the imports of the module `src`, in the order they appear in it.
+/// Fails only if the module can't be lexed.
+pub fn extract_imports(src: &str, file_name: impl Into<String>) -> Result<Vec<ImportRecord>> {
+    let file_name = file_name.into();
+    let options = lexer_options(&file_name);
+    let tokens = tokenize_with_options(src, file_name, options)?
+        .into_iter()
+        .filter(|token| !matches!(token, Token::Comment(_)))
+        .collect();
+    let mut scanner = Scanner {
+        tokens,
+        imports: Vec::new(),
+    };
+    for n in 0..scanner.tokens.len() {
+        scanner.scan_at(n);
+    }
+
+    Ok(scanner.imports)
+}
+
+struct Scanner<'src> {
+    tokens: Vec<Token<'src>>,
+    imports: Vec<ImportRecord>,
+}
+
+impl Scanner<'_> {
+    fn is_word_at(&self, n: usize, expected: &str) -> bool {
+        self.tokens.get(n).and_then(word).as_deref() == Some(expected)
+    }
+
+    fn is_punctuation_at(&self, n: usize, kind: PunctuationType) -> bool {
+        matches!(self.tokens.get(n), Some(Token::Punctuation(p)) if p.kind == kind)
+    }
+
+    fn is_operator_at(&self, n: usize, kind: OperatorType) -> bool {
+        matches!(self.tokens.get(n), Some(Token::Operator(o)) if o.kind == kind)
+    }
+
+    /// Records the string at `n` as an import, if there is one.
+    fn add_import(&mut self, n: usize, kind: ImportKind) {
+        if let Some(token) = self.tokens.get(n) {
+            if let Some(specifier) = string(token) {
+                self.imports.push(ImportRecord {
+                    specifier,
+                    kind,
+                    span: token.span(),
+                });
+            }
+        }
+    }
+
+    fn scan_at(&mut self, n: usize) {
+        // Properties named like the keywords, as in `a.import()` or
+        // `module.require()`, don't count.
+        if n > 0 && self.is_punctuation_at(n - 1, PunctuationType::Dot) {
+            return;
+        }
+
+        if self.is_word_at(n, "import") {
+            if self.is_punctuation_at(n + 1, PunctuationType::OpenParen) {
+                self.scan_call(n + 1, ImportKind::Dynamic);
+            } else if !self.is_punctuation_at(n + 1, PunctuationType::Dot) {
+                // Anything but `import.meta` is a declaration.
+                match self.tokens.get(n + 1).and_then(string) {
+                    Some(_) => self.add_import(n + 1, ImportKind::Static),
+                    None => self.scan_from_clause(n + 1, ImportKind::Static),
+                }
+            }
+        } else if self.is_word_at(n, "export") {
+            if self.is_operator_at(n + 1, OperatorType::Multiplication)
+                || self.is_punctuation_at(n + 1, PunctuationType::OpenBrace)
+                || (self.is_word_at(n + 1, "type")
+                    && self.is_punctuation_at(n + 2, PunctuationType::OpenBrace))
+            {
+                self.scan_from_clause(n + 1, ImportKind::ReExport);
+            }
+        } else if self.is_word_at(n, "require")
+            && self.is_punctuation_at(n + 1, PunctuationType::OpenParen)
+        {
+            self.scan_call(n + 1, ImportKind::Require);
+        }
+    }
+
+    /// Looks for a call whose only or first argument is a string, where the
+    /// `(` is at `open`.  `import()` can take options after the specifier.
+    fn scan_call(&mut self, open: usize, kind: ImportKind) {
+        if self.is_punctuation_at(open + 2, PunctuationType::CloseParen)
+            || (kind == ImportKind::Dynamic
+                && self.is_punctuation_at(open + 2, PunctuationType::Comma))
+        {
+            self.add_import(open + 1, kind);
+        }
+    }
+
+    /// Skips the bindings or exports of a declaration, starting at `n`, to
+    /// the `from` clause after them.  The declaration ends at the first token
+    /// that can't be part of a list of bindings, and has no `from` clause if
+    /// that isn't `from`.
+    fn scan_from_clause(&mut self, mut n: usize, kind: ImportKind) {
+        let mut in_braces = false;
+        while let Some(token) = self.tokens.get(n) {
+            match token {
+                Token::Punctuation(p) => match p.kind {
+                    PunctuationType::OpenBrace if !in_braces => in_braces = true,
+                    PunctuationType::CloseBrace if in_braces => in_braces = false,
+                    PunctuationType::Comma => {}
+                    _ => return,
+                },
+                Token::Operator(o) => match o.kind {
+                    OperatorType::Multiplication => {}
+                    _ if word(token).is_some() => {}
+                    _ => return,
+                },
+                // Export names can be strings, as in `export { 'a b' } from 'c'`.
+                Token::StringLiteral(_) if in_braces => {}
+                // `from` can also be a binding, as in `import from from 'a'`.
+                _ if !in_braces
+                    && self.is_word_at(n, "from")
+                    && self.tokens.get(n + 1).and_then(string).is_some() =>
+                {
+                    self.add_import(n + 1, kind);
+                    return;
+                }
+                _ if word(token).is_some() => {}
+                _ => return,
+            }
+            n += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn imports(src: &str, file_name: &str) -> Vec<(String, ImportKind)> {
+        extract_imports(src, file_name)
+            .unwrap()
+            .into_iter()
+            .map(|import| (import.specifier, import.kind))
+            .collect()
+    }
+
+    fn import(specifier: &str, kind: ImportKind) -> (String, ImportKind) {
+        (specifier.to_string(), kind)
+    }
+
+    #[test]
+    fn test_import_declarations() {
+        assert_eq!(
+            imports(
+                r#"
+                import 'a';
+                import b, {
+                    c as d,
+                    'e f' as g,
+                } from "b";
+                import * as h from './c';
+                import i, * as j from '../d'
+                import from from 'f';
+                import type { K } from 'e';
+                "#,
+                "module.ts"
+            ),
+            [
+                import("a", ImportKind::Static),
+                import("b", ImportKind::Static),
+                import("./c", ImportKind::Static),
+                import("../d", ImportKind::Static),
+                import("f", ImportKind::Static),
+                import("e", ImportKind::Static),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_re_exports() {
+        assert_eq!(
+            imports(
+                r#"
+                export * from 'a';
+                export * as b from 'b';
+                export { c, d as default, 'e f' as g } from 'c';
+                export { h };
+                export const i = 'd';
+                export default from;
+                "#,
+                "module.js"
+            ),
+            [
+                import("a", ImportKind::ReExport),
+                import("b", ImportKind::ReExport),
+                import("c", ImportKind::ReExport),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dynamic_imports_and_requires() {
+        assert_eq!(
+            imports(
+                r#"
+                const a = await import('a');
+                import("b", { with: { type: "json" } }).then(c);
+                const d = require('c');
+                import(name);
+                require(`d`);
+                a.require('e');
+                import.meta.url;
+                "#,
+                "module.js"
+            ),
+            [
+                import("a", ImportKind::Dynamic),
+                import("b", ImportKind::Dynamic),
+                import("c", ImportKind::Require),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strings_and_comments_are_not_imports() {
+        assert_eq!(
+            imports(
+                r#"
+                // import 'a';
+                /* require('b') */
+                const c = "import d from 'e'";
+                const f = <p>import g from 'h'</p>;
+                "#,
+                "module.jsx"
+            ),
+            []
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let src = "import a from 'b';";
+        let imports = extract_imports(src, "module.js").unwrap();
+        let span = imports[0].span;
+        assert_eq!(&src[span.start.index..span.end.index], "'b'");
+    }
+}
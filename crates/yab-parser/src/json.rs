@@ -0,0 +1,687 @@
+//! A tolerant JSON reader, intended for files like package.json and
+//! tsconfig.json that are "JSON" in name only.  Files in the wild contain byte
+//! order marks, comments, and trailing commas (usually from editors that treat
+//! them as jsonc), so rather than failing on the first of these we accept them
+//! and record a warning.
+//!
+//! Every value keeps the `Span` it was parsed from, so that callers that find a
+//! semantically malformed value (e.g. an `exports` map that is a number) can
+//! point at it in the diagnostic rather than reporting an opaque serde error.
+
+use miette::{miette, ErrReport, LabeledSpan, NamedSource, Result, Severity};
+
+use crate::lexer::{
+    code_iter::{
+        current_span_error, previous_span_error, CodeIter, IntoCodeIterator, Position, Span,
+    },
+    utils::is_line_terminator,
+};
+
+#[derive(Debug, PartialEq)]
+pub struct JsonValue {
+    pub kind: JsonValueKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum JsonValueKind {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// Object members, in source order.  Duplicate keys are preserved here,
+    /// but `JsonValue::get` follows `JSON.parse` and returns the last one.
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Looks up a member of an object by key.  Returns `None` if the value is
+    /// not an object, or if it has no such member.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match &self.kind {
+            JsonValueKind::Object(members) => members
+                .iter()
+                .rev()
+                .find(|(member_key, _)| member_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            JsonValueKind::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match &self.kind {
+            JsonValueKind::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match &self.kind {
+            JsonValueKind::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// Human-readable name of the value's type, for use in diagnostics (e.g.
+    /// "expected a string, found an array").
+    pub fn type_name(&self) -> &'static str {
+        match self.kind {
+            JsonValueKind::Null => "null",
+            JsonValueKind::Bool(_) => "a boolean",
+            JsonValueKind::Number(_) => "a number",
+            JsonValueKind::String(_) => "a string",
+            JsonValueKind::Array(_) => "an array",
+            JsonValueKind::Object(_) => "an object",
+        }
+    }
+}
+
+/// The result of reading a JSON file: the root value, any warnings about
+/// non-standard syntax we tolerated, and enough of the source to render
+/// diagnostics against it later.
+#[derive(Debug)]
+pub struct JsonDocument {
+    pub root: JsonValue,
+    pub warnings: Vec<ErrReport>,
+    source: String,
+    file_path: String,
+}
+
+impl JsonDocument {
+    /// Creates a diagnostic pointing at `value`, for callers that have parsed
+    /// the document successfully but found a value they can't use.
+    pub fn error_at(&self, value: &JsonValue, err_msg: &str) -> ErrReport {
+        miette!(
+            severity = Severity::Error,
+            code = "InvalidJson",
+            labels = vec![LabeledSpan::at(value.span.clone(), err_msg)],
+            "{} at {}:{}:{}",
+            err_msg,
+            self.file_path,
+            value.span.start.line,
+            value.span.start.column
+        )
+        .with_source_code(NamedSource::new(
+            self.file_path.clone(),
+            self.source.clone(),
+        ))
+    }
+
+    pub fn file_path(&self) -> &str {
+        &self.file_path
+    }
+}
+
+struct JsonParser<'src> {
+    chars: CodeIter<'src>,
+    warnings: Vec<ErrReport>,
+}
+
+impl JsonParser<'_> {
+    fn span_from(&self, start: Position) -> Span {
+        Span::new(start, self.chars.current_position(), self.chars.file_path())
+    }
+
+    fn warn(&mut self, start: Position, err_msg: &str) {
+        let warning = self.chars.to_span_warning(err_msg, self.span_from(start));
+        self.warnings.push(warning);
+    }
+
+    /// Skips whitespace and comments, warning about each comment since they
+    /// aren't valid JSON.
+    fn skip_trivia(&mut self) -> Result<()> {
+        loop {
+            match (self.chars.peek(), self.chars.peek_forward(1)) {
+                (Some(' ' | '\t' | '\n' | '\r'), _) => {
+                    _ = self.chars.next();
+                }
+                (Some('/'), Some('/')) => {
+                    let start_pos = self.chars.current_position();
+                    while let Some(c) = self.chars.peek() {
+                        if is_line_terminator(c) {
+                            break;
+                        }
+                        _ = self.chars.next();
+                    }
+                    self.warn(start_pos, "Comments are not allowed in JSON");
+                }
+                (Some('/'), Some('*')) => {
+                    let start_pos = self.chars.current_position();
+                    for _ in 0..2 {
+                        _ = self.chars.next();
+                    }
+
+                    let mut found_end = false;
+                    while let Some(c) = self.chars.next() {
+                        if c == '*' && self.chars.peek() == Some('/') {
+                            _ = self.chars.next();
+                            found_end = true;
+                            break;
+                        }
+                    }
+
+                    if !found_end {
+                        return Err(previous_span_error!(
+                            self.chars,
+                            start_pos,
+                            "Unexpected EOF while parsing block comment",
+                        ));
+                    }
+
+                    self.warn(start_pos, "Comments are not allowed in JSON");
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Consumes `expected` if it is the next character, skipping any leading
+    /// trivia.  Returns whether the character was found.
+    fn eat(&mut self, expected: char) -> Result<bool> {
+        self.skip_trivia()?;
+        if self.chars.peek() == Some(expected) {
+            _ = self.chars.next();
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    fn unexpected(&self, context: &str) -> ErrReport {
+        let start_pos = self.chars.current_position();
+        match self.chars.peek() {
+            Some(c) => current_span_error!(
+                self.chars,
+                start_pos,
+                "Unexpected character '{}' while parsing {}",
+                c,
+                context
+            ),
+            None => current_span_error!(
+                self.chars,
+                start_pos,
+                "Unexpected EOF while parsing {}",
+                context
+            ),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue> {
+        self.skip_trivia()?;
+        let start_pos = self.chars.current_position();
+
+        let kind = match self.chars.peek() {
+            Some('{') => self.parse_object()?,
+            Some('[') => self.parse_array()?,
+            Some('"') => JsonValueKind::String(self.parse_string()?),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number()?,
+            Some(c) if c.is_ascii_alphabetic() => self.parse_literal()?,
+            _ => return Err(self.unexpected("JSON value")),
+        };
+
+        Ok(JsonValue {
+            kind,
+            span: self.span_from(start_pos),
+        })
+    }
+
+    fn parse_literal(&mut self) -> Result<JsonValueKind> {
+        let start_pos = self.chars.current_position();
+        let mut lexeme = String::new();
+        while let Some(c) = self.chars.peek() {
+            if !c.is_ascii_alphabetic() {
+                break;
+            }
+            lexeme.push(c);
+            _ = self.chars.next();
+        }
+
+        match lexeme.as_str() {
+            "true" => Ok(JsonValueKind::Bool(true)),
+            "false" => Ok(JsonValueKind::Bool(false)),
+            "null" => Ok(JsonValueKind::Null),
+            _ => Err(current_span_error!(
+                self.chars,
+                start_pos,
+                "Unexpected identifier '{}' while parsing JSON value",
+                lexeme
+            )),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValueKind> {
+        let start_pos = self.chars.current_position();
+        let mut lexeme = String::new();
+
+        fn push_digits(chars: &mut CodeIter, lexeme: &mut String) -> usize {
+            let mut count = 0;
+            while let Some(c) = chars.peek() {
+                if !c.is_ascii_digit() {
+                    break;
+                }
+                lexeme.push(c);
+                _ = chars.next();
+                count += 1;
+            }
+            count
+        }
+
+        if self.chars.peek() == Some('-') {
+            lexeme.push('-');
+            _ = self.chars.next();
+        }
+
+        let leading_zero = self.chars.peek() == Some('0');
+        let integer_digits = push_digits(&mut self.chars, &mut lexeme);
+        let mut valid = integer_digits > 0 && !(leading_zero && integer_digits > 1);
+
+        if self.chars.peek() == Some('.') {
+            lexeme.push('.');
+            _ = self.chars.next();
+            valid &= push_digits(&mut self.chars, &mut lexeme) > 0;
+        }
+
+        if let Some('e') | Some('E') = self.chars.peek() {
+            lexeme.push('e');
+            _ = self.chars.next();
+            if let Some(sign @ ('+' | '-')) = self.chars.peek() {
+                lexeme.push(sign);
+                _ = self.chars.next();
+            }
+            valid &= push_digits(&mut self.chars, &mut lexeme) > 0;
+        }
+
+        match lexeme.parse::<f64>() {
+            Ok(value) if valid => Ok(JsonValueKind::Number(value)),
+            _ => Err(current_span_error!(
+                self.chars,
+                start_pos,
+                "Invalid number '{}' while parsing JSON value",
+                lexeme
+            )),
+        }
+    }
+
+    fn parse_hex_code_unit(&mut self, start_pos: &Position) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..4 {
+            match self.chars.next() {
+                Some(c) if c.is_ascii_hexdigit() => value = value * 16 + c.to_digit(16).unwrap(),
+                _ => {
+                    return Err(current_span_error!(
+                        self.chars,
+                        start_pos.clone(),
+                        "{}",
+                        "Invalid unicode escape sequence in JSON string"
+                    ))
+                }
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// The code unit of the `\uXXXX` escape coming up next, if it's the low
+    /// half of a surrogate pair.  Nothing is consumed, so any other escape is
+    /// left to be parsed as a character of its own.
+    fn peek_low_surrogate(&self) -> Option<u32> {
+        if self.chars.peek() != Some('\\') || self.chars.peek_forward(1) != Some('u') {
+            return None;
+        }
+
+        let mut value = 0;
+        for n in 2..6 {
+            value = value * 16 + self.chars.peek_forward(n)?.to_digit(16)?;
+        }
+
+        (0xDC00..0xE000).contains(&value).then_some(value)
+    }
+
+    /// Parses a JSON string, including its delimiters.  Unlike Javascript
+    /// strings, only double quotes and a handful of escapes are allowed.
+    fn parse_string(&mut self) -> Result<String> {
+        let start_pos = self.chars.current_position();
+        let mut value = String::new();
+        _ = self.chars.next(); // leading '"'
+
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let escape_pos = self.chars.previous_position();
+                    let escaped = match self.chars.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{0008}',
+                        Some('f') => '\u{000c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let mut code_point = self.parse_hex_code_unit(&escape_pos)?;
+
+                            // Characters outside the BMP are written as a
+                            // surrogate pair of escapes.
+                            if (0xD800..0xDC00).contains(&code_point) {
+                                if let Some(low) = self.peek_low_surrogate() {
+                                    for _ in 0..6 {
+                                        _ = self.chars.next();
+                                    }
+                                    code_point =
+                                        0x10000 + ((code_point - 0xD800) << 10) + (low - 0xDC00);
+                                }
+                            }
+
+                            match std::char::from_u32(code_point) {
+                                Some(c) => c,
+                                None => {
+                                    self.warn(
+                                        escape_pos,
+                                        "Lone surrogate in JSON string replaced with U+FFFD",
+                                    );
+                                    char::REPLACEMENT_CHARACTER
+                                }
+                            }
+                        }
+                        _ => {
+                            return Err(current_span_error!(
+                                self.chars,
+                                escape_pos,
+                                "{}",
+                                "Invalid escape sequence in JSON string"
+                            ))
+                        }
+                    };
+                    value.push(escaped);
+                }
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(previous_span_error!(
+                        self.chars,
+                        start_pos,
+                        "Unescaped control character {:#04x} in JSON string",
+                        c as u32
+                    ))
+                }
+                Some(c) => value.push(c),
+                None => {
+                    return Err(previous_span_error!(
+                        self.chars,
+                        start_pos,
+                        "{}",
+                        "Unexpected EOF while parsing JSON string"
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Consumes a ',' between members of an array or object, returning
+    /// whether the collection continues.  A comma directly before `close` is
+    /// accepted with a warning.
+    fn parse_separator(&mut self, close: char, context: &str) -> Result<bool> {
+        self.skip_trivia()?;
+        let comma_pos = self.chars.current_position();
+
+        if self.eat(',')? {
+            self.skip_trivia()?;
+            if self.chars.peek() == Some(close) {
+                self.warn(comma_pos, "Trailing commas are not allowed in JSON");
+                _ = self.chars.next();
+                return Ok(false);
+            }
+            return Ok(true);
+        }
+
+        if self.eat(close)? {
+            return Ok(false);
+        }
+
+        Err(self.unexpected(context))
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValueKind> {
+        _ = self.chars.next(); // leading '['
+        let mut values = Vec::new();
+
+        if self.eat(']')? {
+            return Ok(JsonValueKind::Array(values));
+        }
+
+        loop {
+            values.push(self.parse_value()?);
+            if !self.parse_separator(']', "JSON array")? {
+                return Ok(JsonValueKind::Array(values));
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValueKind> {
+        _ = self.chars.next(); // leading '{'
+        let mut members = Vec::new();
+
+        if self.eat('}')? {
+            return Ok(JsonValueKind::Object(members));
+        }
+
+        loop {
+            self.skip_trivia()?;
+            if self.chars.peek() != Some('"') {
+                return Err(self.unexpected("JSON object key"));
+            }
+            let key = self.parse_string()?;
+
+            if !self.eat(':')? {
+                return Err(self.unexpected("JSON object, expected ':'"));
+            }
+
+            members.push((key, self.parse_value()?));
+            if !self.parse_separator('}', "JSON object")? {
+                return Ok(JsonValueKind::Object(members));
+            }
+        }
+    }
+}
+
+/// Parses a JSON document, tolerating a leading byte order mark, comments,
+/// and trailing commas.  Tolerated syntax is reported in
+/// `JsonDocument::warnings`; anything else that isn't valid JSON is an `Err`
+/// with a span pointing at the offending character.
+pub fn parse(src: &str, file_name: impl Into<String>) -> Result<JsonDocument> {
+    let file_path = file_name.into();
+    let mut parser = JsonParser {
+        chars: src.into_code_iterator(file_path.clone()),
+        warnings: Vec::new(),
+    };
+
+    if parser.chars.peek() == Some('\u{feff}') {
+        _ = parser.chars.next();
+    }
+
+    let root = parser.parse_value()?;
+
+    parser.skip_trivia()?;
+    if parser.chars.peek().is_some() {
+        return Err(parser.unexpected("end of JSON document"));
+    }
+
+    Ok(JsonDocument {
+        root,
+        warnings: parser.warnings,
+        source: src.to_string(),
+        file_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package_json() {
+        let src = r#"{
+            "name": "yab",
+            "private": true,
+            "version": null,
+            "files": ["dist", "README.md"],
+            "engines": { "node": ">=18" },
+            "sideEffects": -1.5e2
+        }"#;
+        let doc = parse(src, "package.json").unwrap();
+
+        assert!(doc.warnings.is_empty());
+        assert_eq!(doc.root.get("name").unwrap().as_str(), Some("yab"));
+        assert_eq!(
+            doc.root.get("private").unwrap().kind,
+            JsonValueKind::Bool(true)
+        );
+        assert_eq!(doc.root.get("version").unwrap().kind, JsonValueKind::Null);
+        assert_eq!(doc.root.get("files").unwrap().as_array().unwrap().len(), 2);
+        assert_eq!(
+            doc.root
+                .get("engines")
+                .and_then(|engines| engines.get("node"))
+                .and_then(JsonValue::as_str),
+            Some(">=18")
+        );
+        assert_eq!(
+            doc.root.get("sideEffects").unwrap().kind,
+            JsonValueKind::Number(-150.0)
+        );
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let doc = parse(r#""a\"b\\c\/\n\u0041\ud83d\ude00""#, "package.json").unwrap();
+        assert_eq!(doc.root.as_str(), Some("a\"b\\c/\nA😀"));
+    }
+
+    #[test]
+    fn test_lone_surrogates() {
+        for (src, expected) in [
+            (r#""\ud800\u0041""#, "\u{FFFD}A"),
+            (r#""\ud800\ud83d\ude00""#, "\u{FFFD}😀"),
+            (r#""\ude00\ud800""#, "\u{FFFD}\u{FFFD}"),
+        ] {
+            let doc = parse(src, "package.json").unwrap();
+            assert_eq!(doc.root.as_str(), Some(expected), "{}", src);
+            assert!(doc.warnings.iter().all(|w| w
+                .to_string()
+                .contains("Lone surrogate in JSON string replaced with U+FFFD")));
+        }
+    }
+
+    #[test]
+    fn test_duplicate_keys_take_last_value() {
+        let doc = parse(r#"{ "main": "a.js", "main": "b.js" }"#, "package.json").unwrap();
+        assert_eq!(doc.root.get("main").unwrap().as_str(), Some("b.js"));
+    }
+
+    #[test]
+    fn test_tolerates_bom_comments_and_trailing_commas() {
+        let src = "\u{feff}{\n  // the name\n  \"name\": \"yab\", /* block */\n  \"files\": [\"dist\",],\n}";
+        let doc = parse(src, "package.json").unwrap();
+
+        assert_eq!(doc.root.get("name").unwrap().as_str(), Some("yab"));
+        let warnings = doc
+            .warnings
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings[0].contains("Comments are not allowed in JSON at package.json:2:3"));
+        assert!(warnings[1].contains("Comments are not allowed in JSON"));
+        assert!(
+            warnings[2].contains("Trailing commas are not allowed in JSON at package.json:4:19")
+        );
+        assert!(
+            warnings[3].contains("Trailing commas are not allowed in JSON at package.json:4:21")
+        );
+        assert!(doc
+            .warnings
+            .iter()
+            .all(|w| w.severity() == Some(Severity::Warning)));
+    }
+
+    #[test]
+    fn test_missing_colon_is_an_error() {
+        let result = parse("{\n  \"name\" \"yab\"\n}", "package.json");
+        assert!(result.unwrap_err().to_string().contains(
+            "Unexpected character '\"' while parsing JSON object, expected ':' at package.json:2:10"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_values_are_errors() {
+        let cases = vec![
+            ("", "Unexpected EOF while parsing JSON value"),
+            ("[1 2]", "Unexpected character '2' while parsing JSON array"),
+            (
+                "{ name: 1 }",
+                "Unexpected character 'n' while parsing JSON object key",
+            ),
+            ("'yab'", "Unexpected character ''' while parsing JSON value"),
+            (
+                "undefined",
+                "Unexpected identifier 'undefined' while parsing JSON value",
+            ),
+            ("01", "Invalid number '01' while parsing JSON value"),
+            ("1.", "Invalid number '1.' while parsing JSON value"),
+            ("\"abc", "Unexpected EOF while parsing JSON string"),
+            ("\"\\q\"", "Invalid escape sequence in JSON string"),
+            (
+                "\"a\tb\"",
+                "Unescaped control character 0x09 in JSON string",
+            ),
+            (
+                "{} {}",
+                "Unexpected character '{' while parsing end of JSON document",
+            ),
+            ("/* open", "Unexpected EOF while parsing block comment"),
+            (
+                "[1,\u{a0}2]",
+                "Unexpected character '\u{a0}' while parsing JSON value",
+            ),
+            (
+                "\u{c}{}",
+                "Unexpected character '\u{c}' while parsing JSON value",
+            ),
+        ];
+
+        for (src, expected) in cases {
+            let result = parse(src, "package.json");
+            let message = result.unwrap_err().to_string();
+            assert!(message.contains(expected), "{}: {}", src, message);
+        }
+    }
+
+    #[test]
+    fn test_error_at_points_to_value() {
+        let src = "{\n  \"exports\": 42\n}";
+        let doc = parse(src, "package.json").unwrap();
+        let exports = doc.root.get("exports").unwrap();
+        let report = doc.error_at(
+            exports,
+            &format!(
+                "\"exports\" must be a string or an object, found {}",
+                exports.type_name()
+            ),
+        );
+
+        assert_eq!(
+            report.to_string(),
+            "\"exports\" must be a string or an object, found a number at package.json:2:14"
+        );
+        let label = report.labels().unwrap().next().unwrap();
+        assert_eq!(label.offset(), 15);
+        assert_eq!(label.len(), 2);
+    }
+}
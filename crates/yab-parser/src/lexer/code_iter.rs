@@ -1,7 +1,13 @@
+use std::borrow::Cow;
+
 use miette::{miette, ErrReport, LabeledSpan, NamedSource, Severity, SourceSpan};
 use serde::Serialize;
 
-/// Represents the position of a single character in a source file.
+use super::escape_chars::EscapedChar;
+
+/// Represents the position of a single character in a source file.  Lines and
+/// columns count from one, and columns count characters, while `index` is the
+/// byte offset of the character, which is what miette's spans expect.
 #[derive(Debug, Serialize, PartialEq, Clone)]
 pub struct Position {
     pub line: usize,
@@ -20,7 +26,7 @@ impl Default for Position {
 }
 
 /// Represents the location of a token in a source file.
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Serialize, PartialEq, Clone)]
 pub struct Span {
     pub start: Position,
     pub end: Position,
@@ -37,11 +43,11 @@ impl Span {
     }
 }
 
-impl Into<SourceSpan> for Span {
-    fn into(self) -> SourceSpan {
+impl From<Span> for SourceSpan {
+    fn from(span: Span) -> Self {
         SourceSpan::new(
-            self.start.index.into(),
-            (self.end.index - self.start.index).into(),
+            span.start.index.into(),
+            (span.end.index - span.start.index).into(),
         )
     }
 }
@@ -50,22 +56,26 @@ impl Into<SourceSpan> for Span {
 /// functionality not otherwise available in the standard library's collection
 /// of iterators, such as multi-character lookahead, location tracing, and error
 /// reporting integration with miette, our diagnostic library of choice.
+///
+/// The iterator is a cursor over the bytes of the source, which it borrows, so
+/// that tokens can borrow their text from it too rather than copying it.
 #[derive(Debug)]
-pub struct CodeIter {
+pub struct CodeIter<'src> {
+    /// The position of the next character.  Its `index` is the byte offset
+    /// of the character in `source`.
     current_position: Position,
     previous_position: Option<Position>,
-    source: String,
+    source: &'src str,
     file_path: String,
-    chars: Vec<char>,
 }
 
-pub trait IntoCodeIterator {
-    fn into_code_iterator<'chars>(self, file_path: String) -> CodeIter;
+pub trait IntoCodeIterator<'src> {
+    fn into_code_iterator(self, file_path: String) -> CodeIter<'src>;
 }
 
-impl IntoCodeIterator for String {
-    /// Consumes the string to create an iterator over its characters.
-    fn into_code_iterator(self, file_path: String) -> CodeIter {
+impl<'src> IntoCodeIterator<'src> for &'src str {
+    /// Creates an iterator over the string's characters, borrowing it.
+    fn into_code_iterator(self, file_path: String) -> CodeIter<'src> {
         CodeIter {
             current_position: Position {
                 line: 1,
@@ -73,64 +83,142 @@ impl IntoCodeIterator for String {
                 index: 0,
             },
             previous_position: None,
-            chars: self.chars().collect::<Vec<char>>(),
             source: self,
             file_path,
         }
     }
 }
 
-impl IntoCodeIterator for &str {
-    /// Consumes the string to create an iterator over its characters.
-    fn into_code_iterator(self, file_path: String) -> CodeIter {
-        CodeIter {
-            current_position: Position {
-                line: 1,
-                column: 1,
-                index: 0,
-            },
-            previous_position: None,
-            chars: self.chars().collect::<Vec<char>>(),
-            source: self.to_string(),
-            file_path,
+/// Builds the text of a token that can contain escape sequences.  The text is
+/// borrowed from the source, and only copied once an escape sequence makes it
+/// differ from the source.
+pub struct Lexeme<'src> {
+    source: &'src str,
+    /// The byte range of the source the text has matched so far.
+    start: usize,
+    end: usize,
+    owned: Option<String>,
+    /// The text as UTF-16, which is only kept once an escape for a surrogate
+    /// has been added, since `owned` can't hold a lone surrogate.
+    utf16: Option<Vec<u16>>,
+}
+
+impl<'src> Lexeme<'src> {
+    /// Adds a character that appears as is in the source, right after the
+    /// characters added so far.
+    pub fn push(&mut self, c: char) {
+        match &mut self.owned {
+            Some(owned) => {
+                owned.push(c);
+                if let Some(utf16) = &mut self.utf16 {
+                    utf16.extend(c.encode_utf16(&mut [0; 2]).iter());
+                }
+            }
+            None => self.end += c.len_utf8(),
+        }
+    }
+
+    /// Adds the character an escape sequence stands for, if it stands for one
+    /// at all (a line continuation doesn't).
+    ///
+    /// A surrogate that isn't part of a pair is added to the text as U+FFFD.
+    /// The second half of a pair replaces that with the character they make
+    /// together.
+    pub fn push_escaped(&mut self, escaped: Option<EscapedChar>) {
+        let owned = self
+            .owned
+            .get_or_insert_with(|| self.source[self.start..self.end].to_string());
+
+        match escaped {
+            None => {}
+            Some(EscapedChar::Char(c)) => {
+                owned.push(c);
+                if let Some(utf16) = &mut self.utf16 {
+                    utf16.extend(c.encode_utf16(&mut [0; 2]).iter());
+                }
+            }
+            Some(EscapedChar::Surrogate(unit)) => {
+                let utf16 = self
+                    .utf16
+                    .get_or_insert_with(|| owned.encode_utf16().collect());
+                let pair = match utf16.last() {
+                    Some(&high) if (0xD800..0xDC00).contains(&high) => {
+                        char::decode_utf16([high, unit]).next().and_then(|c| c.ok())
+                    }
+                    _ => None,
+                };
+
+                match pair {
+                    Some(c) => {
+                        owned.pop();
+                        owned.push(c);
+                    }
+                    None => owned.push(char::REPLACEMENT_CHARACTER),
+                }
+                utf16.push(unit);
+            }
         }
     }
+
+    pub fn as_str(&self) -> &str {
+        match &self.owned {
+            Some(owned) => owned,
+            None => &self.source[self.start..self.end],
+        }
+    }
+
+    pub fn finish(self) -> Cow<'src, str> {
+        match self.owned {
+            Some(owned) => Cow::Owned(owned),
+            None => Cow::Borrowed(&self.source[self.start..self.end]),
+        }
+    }
+
+    /// Like [`Lexeme::finish`], but also returns the text as UTF-16 if it has
+    /// a lone surrogate, which the returned `str` has a U+FFFD in place of.
+    pub fn finish_with_utf16(mut self) -> (Cow<'src, str>, Option<Vec<u16>>) {
+        let utf16 = self
+            .utf16
+            .take()
+            .filter(|utf16| char::decode_utf16(utf16.iter().copied()).any(|c| c.is_err()));
+        (self.finish(), utf16)
+    }
 }
 
-impl Iterator for CodeIter {
+impl<'src> Iterator for CodeIter<'src> {
     type Item = char;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.previous_position = Some(self.current_position.clone());
-        let char = self.chars.get(self.current_position.index);
-
-        match char {
-            Some('\n') => {
-                self.current_position.index += 1;
-                self.current_position.line += 1;
-                self.current_position.column = 1;
-                Some('\n')
-            }
-            Some(c) => {
-                self.current_position.index += 1;
-                self.current_position.column += 1;
-                Some(*c)
-            }
-            None => return None,
+        let char = self.peek()?;
+        self.current_position.index += char.len_utf8();
+
+        if char == '\n' {
+            self.current_position.line += 1;
+            self.current_position.column = 1;
+        } else {
+            self.current_position.column += 1;
         }
+
+        Some(char)
     }
 }
 
-impl CodeIter {
+impl<'src> CodeIter<'src> {
     /// Returns the next character in the iterator without consuming it.
-    pub fn peek(&self) -> Option<&char> {
-        self.chars.get(self.current_position.index)
+    pub fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
     }
 
     /// Returns the character `n` characters ahead in the iterator without
     /// consuming it.  peek_forward(0) is equivalent to peek().
-    pub fn peek_forward(&self, n: usize) -> Option<&char> {
-        self.chars.get(self.current_position.index + n)
+    pub fn peek_forward(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    /// The source from the next character on.
+    fn rest(&self) -> &'src str {
+        &self.source[self.current_position.index..]
     }
 
     /// Returns the current position of the iterator, expressed as a `Position`
@@ -147,30 +235,87 @@ impl CodeIter {
         }
     }
 
+    /// Moves back to `position`, which has to be one the iterator has already
+    /// been at.
+    pub fn rewind(&mut self, position: Position) {
+        self.current_position = position;
+        self.previous_position = None;
+    }
+
     pub fn file_path(&self) -> &str {
         &self.file_path
     }
 
+    /// Returns the source text from `start` up to `end`.
+    pub fn slice(&self, start: &Position, end: &Position) -> &'src str {
+        &self.source[start.index..end.index]
+    }
+
+    /// Returns the source text from `start` up to the current position.
+    pub fn slice_from(&self, start: &Position) -> &'src str {
+        &self.source[start.index..self.current_position.index]
+    }
+
+    /// Starts building the text of a token at the current position.
+    pub fn start_lexeme(&self) -> Lexeme<'src> {
+        Lexeme {
+            source: self.source,
+            start: self.current_position.index,
+            end: self.current_position.index,
+            owned: None,
+            utf16: None,
+        }
+    }
+
     /// Creates a miette `ErrReport` from a given `Span`
     pub fn to_span_error(&self, err_msg: &str, location: Span) -> ErrReport {
         let column = location.start.column;
         let line = location.start.line;
 
-        self.to_error_with_label(err_msg, LabeledSpan::at(location, err_msg), line, column)
+        self.to_report_with_label(
+            Severity::Error,
+            err_msg,
+            LabeledSpan::at(location, err_msg),
+            line,
+            column,
+        )
+    }
+
+    /// Creates a miette `ErrReport` with a warning severity from a given
+    /// `Span`, for syntax that we can recover from but that the user should
+    /// still hear about.
+    pub fn to_span_warning(&self, err_msg: &str, location: Span) -> ErrReport {
+        let column = location.start.column;
+        let line = location.start.line;
+
+        self.to_report_with_label(
+            Severity::Warning,
+            err_msg,
+            LabeledSpan::at(location, err_msg),
+            line,
+            column,
+        )
     }
 
-    fn to_error_with_label(
+    fn to_report_with_label(
         &self,
+        severity: Severity,
         err_msg: &str,
         label: LabeledSpan,
         line: usize,
         column: usize,
     ) -> ErrReport {
+        let code = match severity {
+            Severity::Error => "SyntaxError",
+            _ => "SyntaxWarning",
+        };
+
         miette!(
-            severity = Severity::Error,
-            code = "SyntaxError",
+            severity = severity,
+            code = code,
             labels = vec![label],
-            "SyntaxError: {} at {}:{}:{}",
+            "{}: {} at {}:{}:{}",
+            code,
             err_msg,
             self.file_path,
             line,
@@ -183,7 +328,7 @@ impl CodeIter {
         // process, though?
         .with_source_code(NamedSource::new(
             self.file_path.clone(),
-            self.source.clone(),
+            self.source.to_string(),
         ))
     }
 }
@@ -220,27 +365,41 @@ mod tests {
 
         assert_eq!(iter.next().unwrap(), 'a');
         assert_eq!(iter.next().unwrap(), 'b');
-        assert_eq!(iter.next().is_none(), true);
+        assert!(iter.next().is_none());
     }
 
     #[test]
     fn test_peek() {
         let src = "ab".to_string();
         let mut iter = src.into_code_iterator("foo.js".into());
-        assert_eq!(iter.peek().unwrap(), &'a');
-        assert_eq!(iter.peek().unwrap(), &'a');
+        assert_eq!(iter.peek().unwrap(), 'a');
+        assert_eq!(iter.peek().unwrap(), 'a');
 
         _ = iter.next();
         _ = iter.next();
 
-        assert_eq!(iter.peek().is_none(), true);
+        assert!(iter.peek().is_none());
     }
 
     #[test]
     fn test_peek_multi() {
         let src = "abc".to_string();
         let iter = src.into_code_iterator("foo.js".into());
-        assert_eq!(iter.peek_forward(2), Some(&'c'));
+        assert_eq!(iter.peek_forward(2), Some('c'));
         assert_eq!(iter.peek_forward(3), None);
     }
+
+    #[test]
+    fn test_multi_byte_source() {
+        let src = "é😀a".to_string();
+        let mut iter = src.into_code_iterator("foo.js".into());
+        assert_eq!(iter.peek_forward(1), Some('😀'));
+        assert_eq!(iter.next(), Some('é'));
+        assert_eq!(iter.current_position().index, 2);
+        assert_eq!(iter.next(), Some('😀'));
+        assert_eq!(iter.current_position().index, 6);
+        assert_eq!(iter.current_position().column, 3);
+        assert_eq!(iter.peek(), Some('a'));
+        assert_eq!(&src[iter.current_position().index..], "a");
+    }
 }
@@ -1,55 +1,90 @@
-use super::{code_iter::CodeIter, utils::is_line_terminator};
+use super::{
+    code_iter::{previous_span_error, CodeIter, Position, Span},
+    utils::is_line_terminator,
+};
+use std::borrow::Cow;
+
+use miette::Result;
 use serde::Serialize;
 
+use crate::location;
+
 #[derive(Debug, PartialEq, Serialize)]
-pub struct Comment {
-    pub value: CommentType,
+pub struct Comment<'src> {
+    pub value: CommentType<'src>,
+    pub span: location::Span,
 }
 
-impl Comment {
-    pub fn new(value: CommentType) -> Self {
-        Self { value }
+impl<'src> Comment<'src> {
+    pub fn new(value: CommentType<'src>) -> Self {
+        Self {
+            value,
+            span: location::Span::default(),
+        }
     }
 }
 
 #[derive(Debug, PartialEq, Serialize)]
-pub enum CommentType {
-    Block(String),
-    Line(String),
-    Hashbang(String),
+pub enum CommentType<'src> {
+    Block(Cow<'src, str>),
+    Line(Cow<'src, str>),
+    Hashbang(Cow<'src, str>),
 }
 
 /// Parses a line comment, assuming that the leading '//' has already been
 /// consumed.
-fn parse_line_comment(chars: &mut CodeIter) -> CommentType {
-    let lexeme = chars
-        .take_while(|c| !is_line_terminator(*c))
-        .collect::<String>();
+fn parse_line_comment<'src>(chars: &mut CodeIter<'src>) -> CommentType<'src> {
+    CommentType::Line(Cow::Borrowed(take_rest_of_line(chars)))
+}
 
-    CommentType::Line(lexeme)
+/// Consumes the rest of the line, including the line terminator, and returns
+/// the text before the line terminator.
+fn take_rest_of_line<'src>(chars: &mut CodeIter<'src>) -> &'src str {
+    let start_pos = chars.current_position();
+    while chars.peek().is_some_and(|c| !is_line_terminator(c)) {
+        _ = chars.next();
+    }
+
+    let lexeme = chars.slice_from(&start_pos);
+    _ = chars.next();
+    lexeme
 }
 
 /// Parses a a block comment, assuming that the leading '/*' has already been
-/// consumed.
-fn parse_block_comment(chars: &mut CodeIter) -> CommentType {
-    let mut lexeme = String::new();
+/// consumed.  `start_pos` is the position of the leading '/*', for reporting
+/// comments that are never closed.
+fn parse_block_comment<'src>(
+    chars: &mut CodeIter<'src>,
+    start_pos: Position,
+) -> Result<CommentType<'src>> {
+    let text_start = chars.current_position();
 
     while let Some(next_char) = chars.next() {
-        if next_char == '*' && chars.peek() == Some(&'/') {
+        if next_char == '*' && chars.peek() == Some('/') {
+            let lexeme = chars.slice(&text_start, &chars.previous_position());
             chars.next();
-            break;
+            return Ok(CommentType::Block(Cow::Borrowed(lexeme)));
         }
-
-        lexeme.push(next_char);
     }
 
-    CommentType::Block(lexeme)
+    Err(previous_span_error!(
+        chars,
+        start_pos,
+        "Unexpected EOF while parsing block comment",
+    ))
 }
 
 /// Attempts to parse the following characters of the iterator into a Javascript
-/// comment token (either a line comment or a block comment), returning None if
-/// the next token is not a comment.
-pub fn try_parse_comment(chars: &mut CodeIter) -> Option<Comment> {
+/// comment token (either a line comment or a block comment).
+///
+/// Returns:
+///
+/// * `Ok(Some(Comment))` if a comment was parsed.
+///
+/// * `Ok(None)` if the next token is not a comment.
+///
+/// * `Err` if the next token is a block comment that is never closed.
+pub fn try_parse_comment<'src>(chars: &mut CodeIter<'src>) -> Result<Option<Comment<'src>>> {
     // question: this doesn't copy the underlying memory we are iterator over,
     // right?  I'm just copying a pointer and some state?
     match (chars.peek(), chars.peek_forward(1)) {
@@ -59,28 +94,27 @@ pub fn try_parse_comment(chars: &mut CodeIter) -> Option<Comment> {
             for _ in 0..2 {
                 _ = chars.next();
             }
-            Some(Comment::new(parse_line_comment(chars)))
+            Ok(Some(Comment::new(parse_line_comment(chars))))
         }
         (Some('/'), Some('*')) => {
+            let start_pos = chars.current_position();
             for _ in 0..2 {
                 _ = chars.next();
             }
-            Some(Comment::new(parse_block_comment(chars)))
+            parse_block_comment(chars, start_pos).map(|c| Some(Comment::new(c)))
         }
-        _ => None,
+        _ => Ok(None),
     }
 }
 
-pub fn try_parse_hashbang_comment(chars: &mut CodeIter) -> Option<Comment> {
+pub fn try_parse_hashbang_comment<'src>(chars: &mut CodeIter<'src>) -> Option<Comment<'src>> {
     match (chars.peek(), chars.peek_forward(1)) {
         (Some('#'), Some('!')) => {
             for _ in 0..2 {
                 _ = chars.next();
             }
-            let lexeme = chars
-                .take_while(|c| !is_line_terminator(*c))
-                .collect::<String>();
-            Some(Comment::new(CommentType::Hashbang(lexeme)))
+            let lexeme = take_rest_of_line(chars);
+            Some(Comment::new(CommentType::Hashbang(Cow::Borrowed(lexeme))))
         }
         _ => None,
     }
@@ -95,11 +129,12 @@ mod tests {
     #[test]
     fn test_parse_line_comment() {
         let mut chars = "// this is a comment\nA".into_code_iterator("script.js".to_string());
-        let comment = try_parse_comment(&mut chars).unwrap();
+        let comment = try_parse_comment(&mut chars).unwrap().unwrap();
         assert_eq!(
             comment,
             Comment {
-                value: CommentType::Line(" this is a comment".to_string())
+                value: CommentType::Line(" this is a comment".into()),
+                span: location::Span::default()
             }
         );
         assert_eq!(chars.next().unwrap(), 'A');
@@ -111,14 +146,25 @@ mod tests {
         A"#;
         let mut chars = src.into_code_iterator("script.js".to_string());
         assert_eq!(
-            try_parse_comment(&mut chars).unwrap(),
+            try_parse_comment(&mut chars).unwrap().unwrap(),
             Comment {
-                value: CommentType::Block(" this is a comment ".to_string())
+                value: CommentType::Block(" this is a comment ".into()),
+                span: location::Span::default()
             }
         );
         assert_eq!(chars.next().unwrap(), '\n');
     }
 
+    #[test]
+    fn test_unterminated_block_comment() {
+        let src = "/* this is a comment\n A";
+        let mut chars = src.into_code_iterator("script.js".to_string());
+        assert!(try_parse_comment(&mut chars)
+            .unwrap_err()
+            .to_string()
+            .contains("Unexpected EOF while parsing block comment at script.js:1:1"));
+    }
+
     #[test]
     fn test_parse_hashbang_comment() {
         let src = r#"#!/usr/bin/env node"#;
@@ -127,7 +173,8 @@ mod tests {
         assert_eq!(
             try_parse_hashbang_comment(&mut chars).unwrap(),
             Comment {
-                value: CommentType::Hashbang("/usr/bin/env node".to_string())
+                value: CommentType::Hashbang("/usr/bin/env node".into()),
+                span: location::Span::default()
             }
         );
     }
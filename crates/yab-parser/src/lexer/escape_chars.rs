@@ -10,6 +10,28 @@ use super::code_iter::{current_span_error, CodeIter, Span};
 use miette::Result;
 use nom::AsChar;
 
+/// What an escape sequence stands for.  JavaScript strings are UTF-16, so a
+/// `\u` escape can stand for half of a surrogate pair, which isn't a `char`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapedChar {
+    Char(char),
+    /// A code unit in the range U+D800 to U+DFFF.  It only makes a character
+    /// together with another escape for the other half of the pair, as in
+    /// `\uD83D\uDE00`.
+    Surrogate(u16),
+}
+
+impl EscapedChar {
+    fn from_code_point(value: u32) -> Self {
+        match std::char::from_u32(value) {
+            Some(c) => EscapedChar::Char(c),
+            // safety: the only code points up to U+10FFFF that aren't chars
+            // are surrogates.
+            None => EscapedChar::Surrogate(value as u16),
+        }
+    }
+}
+
 /// Attempts to parse an octal escape sequence into a single `char`, returning
 /// an Err if the sequence is out of range.  Advances the provided iterator past
 /// the parsed sequence.
@@ -79,11 +101,10 @@ fn parse_hex_escape_sequence(chars: &mut CodeIter) -> Result<char> {
     ))
 }
 
-/// Attempts to parse a unicode escape sequence into a single `char`, returning
-/// `Ok(char)` if the escape sequence can be parsed into a valid code point, and
+/// Attempts to parse a unicode escape sequence into a code point, returning
 /// `Err` if the escape sequence is invalid (either because it is out of range,
-/// or because it is malformed).
-fn parse_unicode_escape_sequence(chars: &mut CodeIter) -> Result<char> {
+/// or because it is malformed).  The code point may be a surrogate.
+fn parse_unicode_escape_sequence(chars: &mut CodeIter) -> Result<u32> {
     let start_pos = chars.current_position();
     let delimiter = match chars.peek() {
         Some('{') => {
@@ -99,7 +120,7 @@ fn parse_unicode_escape_sequence(chars: &mut CodeIter) -> Result<char> {
         'unicode: loop {
             let next_digit = match chars.peek() {
                 Some(c) if c.is_hex_digit() => chars.next().unwrap(),
-                Some(c) if *c == trailing_delimter => {
+                Some(c) if c == trailing_delimter => {
                     // Consume trailing delimiter
                     _ = chars.next();
                     break 'unicode;
@@ -126,12 +147,7 @@ fn parse_unicode_escape_sequence(chars: &mut CodeIter) -> Result<char> {
             ));
         }
 
-        std::char::from_u32(value).ok_or(current_span_error!(
-            chars,
-            start_pos,
-            "{}",
-            "Invalid Unicode code-point"
-        ))
+        Ok(value)
     } else {
         let mut value = 0;
 
@@ -158,39 +174,28 @@ fn parse_unicode_escape_sequence(chars: &mut CodeIter) -> Result<char> {
             value = value * 16 + next_digit.to_digit(16).unwrap();
         }
 
-        if value > 0x10ffff {
-            return Err(current_span_error!(
-                chars,
-                start_pos,
-                "Undefined Unicode code-point",
-            ));
-        }
-
-        std::char::from_u32(value).ok_or(current_span_error!(
-            chars,
-            start_pos,
-            "Invalid Unicode code-point",
-        ))
+        // Four hex digits can't be out of range.
+        Ok(value)
     }
 }
 
-/// Parses a potentially multi-byte escape sequence into a single `char`, such
-/// as octal escapes, unicode escapes, etc.  Returns the provided `init` value
-/// as a fall through if no other matches were found.
-fn parse_multi_byte_escape(chars: &mut CodeIter, init: char) -> Result<char> {
+/// Parses a potentially multi-byte escape sequence into a single character,
+/// such as octal escapes, unicode escapes, etc.  Returns the provided `init`
+/// value as a fall through if no other matches were found.
+fn parse_multi_byte_escape(chars: &mut CodeIter, init: char) -> Result<EscapedChar> {
     if init.is_oct_digit() {
-        return parse_octal_escape_sequence(chars, init);
+        return parse_octal_escape_sequence(chars, init).map(EscapedChar::Char);
     }
 
     if init == 'x' {
-        return parse_hex_escape_sequence(chars);
+        return parse_hex_escape_sequence(chars).map(EscapedChar::Char);
     }
 
     if init == 'u' {
-        return parse_unicode_escape_sequence(chars);
+        return parse_unicode_escape_sequence(chars).map(EscapedChar::from_code_point);
     }
 
-    Ok(init)
+    Ok(EscapedChar::Char(init))
 }
 
 /// Attempts to parse an iterator of characters containing an escape sequence
@@ -200,39 +205,59 @@ fn parse_multi_byte_escape(chars: &mut CodeIter, init: char) -> Result<char> {
 /// Returns:
 ///
 /// * `Ok(Some(char))` if the next characters in the iterator are a valid
-/// escape, and can be parsed into a `char`.
+///   escape, and can be parsed into a `char`.
 ///
 /// * Ok(None) if the next characters in the iterator are a valid escape, but
-/// they should be ignored (e.g. a newline escape sequence).
+///   they should be ignored (e.g. a newline escape sequence).
 ///
 /// * `Err` if the next characters in the iterator are an escape sequence, but
-/// cannot be parsed into a `char`.
+///   cannot be parsed into a `char`, like a surrogate, which only strings and
+///   templates can hold.
 pub fn try_parse_escape(chars: &mut CodeIter) -> Result<Option<char>> {
-    // Start by trying to match against a "basic" escape sequence, before trying
-    // to parse multi-byte sequences like octals, unicode, control codes, etc.
-    match chars.next() {
-        Some('b') => Ok(Some('\u{0008}')),
-        Some('f') => Ok(Some('\u{000c}')),
-        Some('n') => Ok(Some('\u{000a}')),
-        Some('r') => Ok(Some('\u{000d}')),
-        Some('t') => Ok(Some('\u{0009}')),
-        Some('v') => Ok(Some('\u{000b}')),
-        Some('"') => Ok(Some('\u{0022}')),
-        Some('\'') => Ok(Some('\u{0027}')),
-        Some('\u{000A}') => Ok(None),
-        Some('\u{000D}') => Ok(None),
-        Some('\u{2028}') => Ok(None),
-        Some('\u{2029}') => Ok(None),
-        Some(c) => parse_multi_byte_escape(chars, c).map(Some),
-        None => Err(current_span_error!(
+    let start_pos = chars.current_position();
+    match try_parse_string_escape(chars)? {
+        Some(EscapedChar::Char(c)) => Ok(Some(c)),
+        Some(EscapedChar::Surrogate(_)) => Err(current_span_error!(
             chars,
-            chars.current_position(),
-            "{}",
-            "Unexpected EOF while parsing escape sequence"
+            start_pos,
+            "Invalid Unicode code-point",
         )),
+        None => Ok(None),
     }
 }
 
+/// Like [`try_parse_escape`], but for an escape in a string or template, which
+/// can stand for a surrogate.
+pub fn try_parse_string_escape(chars: &mut CodeIter) -> Result<Option<EscapedChar>> {
+    // Start by trying to match against a "basic" escape sequence, before trying
+    // to parse multi-byte sequences like octals, unicode, control codes, etc.
+    let basic = match chars.next() {
+        Some('b') => '\u{0008}',
+        Some('f') => '\u{000c}',
+        Some('n') => '\u{000a}',
+        Some('r') => '\u{000d}',
+        Some('t') => '\u{0009}',
+        Some('v') => '\u{000b}',
+        Some('"') => '\u{0022}',
+        Some('\'') => '\u{0027}',
+        Some('\u{000A}') => return Ok(None),
+        Some('\u{000D}') => return Ok(None),
+        Some('\u{2028}') => return Ok(None),
+        Some('\u{2029}') => return Ok(None),
+        Some(c) => return parse_multi_byte_escape(chars, c).map(Some),
+        None => {
+            return Err(current_span_error!(
+                chars,
+                chars.current_position(),
+                "{}",
+                "Unexpected EOF while parsing escape sequence"
+            ))
+        }
+    };
+
+    Ok(Some(EscapedChar::Char(basic)))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::lexer::code_iter::IntoCodeIterator;
@@ -397,6 +422,28 @@ mod tests {
             .contains("Invalid hexadecimal escape sequence"));
     }
 
+    #[test]
+    fn test_surrogate_escapes() {
+        for (src, expected) in [
+            (r#"uD83D"#, EscapedChar::Surrogate(0xD83D)),
+            (r#"u{DE00}"#, EscapedChar::Surrogate(0xDE00)),
+            (r#"u{1F600}"#, EscapedChar::Char('😀')),
+        ] {
+            let mut chars = src.into_code_iterator("script.js".to_string());
+            assert_eq!(
+                try_parse_string_escape(&mut chars).unwrap().unwrap(),
+                expected
+            );
+        }
+
+        // Identifiers can't hold them.
+        let result = try_parse_escape(&mut "uD83D".into_code_iterator("script.js".to_string()));
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid Unicode code-point"));
+    }
+
     #[test]
     fn test_unicode_escape_does_not_eat_trailing_chars() {
         let src = r#"u00410"#;
@@ -1,16 +1,21 @@
+use std::borrow::Cow;
+
 use miette::Result;
 use serde::Serialize;
-use strum_macros::EnumString;
+use strum_macros::{Display, EnumString};
+
+use crate::location;
 
 use super::{
     code_iter::{current_span_error, CodeIter, Span},
-    escape_chars::try_parse_escape,
+    escape_chars::{try_parse_escape, EscapedChar},
     operator::{Operator, OperatorType},
+    utils::{is_identifier_part, is_identifier_start},
 };
 
 #[derive(Debug, PartialEq)]
-pub enum IdentParseResult {
-    Identifier(Identifier),
+pub enum IdentParseResult<'src> {
+    Identifier(Identifier<'src>),
     Keyword(Keyword),
     ValueLiteral(ValueLiteral),
     Operator(Operator),
@@ -18,16 +23,20 @@ pub enum IdentParseResult {
 
 #[derive(Debug, Serialize, PartialEq)]
 pub struct ValueLiteral {
-    kind: ValueLiteralType,
+    pub kind: ValueLiteralType,
+    pub span: location::Span,
 }
 
 impl ValueLiteral {
     pub fn new(kind: ValueLiteralType) -> Self {
-        Self { kind }
+        Self {
+            kind,
+            span: location::Span::default(),
+        }
     }
 }
 
-#[derive(Debug, Serialize, PartialEq, EnumString)]
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, EnumString, Display)]
 #[strum(serialize_all = "snake_case")]
 pub enum ValueLiteralType {
     True,
@@ -35,46 +44,109 @@ pub enum ValueLiteralType {
     Null,
 }
 
-#[derive(Debug, Serialize, PartialEq, EnumString)]
+/// The reserved words, and the contextual keywords that are only keywords in
+/// certain places.  The reserved words that are operators, like `typeof` and
+/// `in`, are lexed as [`OperatorType`]s instead, and `true`, `false` and
+/// `null` as [`ValueLiteralType`]s.  See:
+/// https://tc39.es/ecma262/#sec-keywords-and-reserved-words
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, EnumString, Display)]
 #[strum(serialize_all = "snake_case")]
 pub enum KeywordType {
     Async,
+    Break,
+    Case,
+    Catch,
+    Class,
     Const,
+    Continue,
+    Debugger,
+    Default,
+    Do,
+    Else,
+    Enum,
+    Export,
+    Extends,
+    Finally,
+    For,
     Function,
+    If,
     Import,
-    Export,
+    Let,
     New,
+    Of,
     Return,
+    Static,
     Super,
+    Switch,
     This,
+    Throw,
+    Try,
+    Var,
+    While,
+    With,
+}
+
+impl KeywordType {
+    /// Whether this is a contextual keyword, which is an ordinary identifier
+    /// everywhere it isn't a keyword, like `of` outside a `for` head or `let`
+    /// in sloppy mode code.
+    pub fn is_contextual(self) -> bool {
+        matches!(self, Self::Async | Self::Let | Self::Of | Self::Static)
+    }
 }
 
 #[derive(Debug, Serialize, PartialEq)]
 pub struct Keyword {
-    kind: KeywordType,
+    pub(crate) kind: KeywordType,
+    pub span: location::Span,
 }
 
 impl Keyword {
     pub fn new(kind: KeywordType) -> Self {
-        Self { kind }
+        Self {
+            kind,
+            span: location::Span::default(),
+        }
     }
 }
 
 #[derive(Debug, PartialEq, Serialize)]
-pub struct Identifier {
-    lexeme: String,
+pub struct Identifier<'src> {
+    pub lexeme: Cow<'src, str>,
+    pub span: location::Span,
 }
 
-impl From<String> for Identifier {
+impl From<String> for Identifier<'_> {
     fn from(value: String) -> Self {
-        Self { lexeme: value }
+        Self {
+            lexeme: Cow::Owned(value),
+            span: location::Span::default(),
+        }
     }
 }
 
-impl From<&str> for Identifier {
-    fn from(value: &str) -> Self {
+impl<'src> From<&'src str> for Identifier<'src> {
+    fn from(value: &'src str) -> Self {
         Self {
-            lexeme: value.to_string(),
+            lexeme: Cow::Borrowed(value),
+            span: location::Span::default(),
+        }
+    }
+}
+
+/// A private class member's name, like `#field` in `this.#field` or
+/// `#field in obj`.  The lexeme doesn't include the `#`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PrivateIdentifier<'src> {
+    pub lexeme: Cow<'src, str>,
+    pub span: location::Span,
+}
+
+impl<'src> PrivateIdentifier<'src> {
+    pub fn new(lexeme: impl Into<Cow<'src, str>>) -> Self {
+        Self {
+            lexeme: lexeme.into(),
+            span: location::Span::default(),
         }
     }
 }
@@ -85,15 +157,70 @@ impl From<&str> for Identifier {
 /// Returns:
 ///
 /// * `Ok(Some(Identifier))` if the iterator starts with a valid identifier.
-/// Stops parsing the identifier as soon as an invalid identifier character is
-/// reached.
+///   Stops parsing the identifier as soon as an invalid identifier character is
+///   reached.
 ///
 /// * `Ok(None)` if the iterator does not begin with a valid identifier character.
 ///
 /// * `Err` if an invalid escape sequence is encountered.
-pub fn try_parse_identifier(chars: &mut CodeIter) -> Result<Option<IdentParseResult>> {
+pub fn try_parse_identifier<'src>(
+    chars: &mut CodeIter<'src>,
+) -> Result<Option<IdentParseResult<'src>>> {
+    let Some(lexeme) = parse_identifier_name(chars)? else {
+        return Ok(None);
+    };
+
+    if let Ok(keyword_type) = KeywordType::try_from(lexeme.as_ref()) {
+        return Ok(Some(IdentParseResult::Keyword(Keyword::new(keyword_type))));
+    }
+
+    if let Ok(operator_type) = OperatorType::try_from(lexeme.as_ref()) {
+        return Ok(Some(IdentParseResult::Operator(Operator::new(
+            operator_type,
+        ))));
+    }
+
+    if let Ok(value_type) = ValueLiteralType::try_from(lexeme.as_ref()) {
+        return Ok(Some(IdentParseResult::ValueLiteral(ValueLiteral::new(
+            value_type,
+        ))));
+    }
+
+    Ok(Some(IdentParseResult::Identifier(Identifier {
+        lexeme,
+        span: location::Span::default(),
+    })))
+}
+
+/// Attempts to parse a private identifier, a `#` followed by an identifier
+/// name.  Any name will do, keywords included, so `#if` is a private
+/// identifier too.
+pub fn try_parse_private_identifier<'src>(
+    chars: &mut CodeIter<'src>,
+) -> Result<Option<PrivateIdentifier<'src>>> {
+    let starts_name = chars
+        .peek_forward(1)
+        .is_some_and(|c| c == '\\' || is_identifier_start(c));
+    if chars.peek() != Some('#') || !starts_name {
+        return Ok(None);
+    }
+
+    _ = chars.next();
+    match parse_identifier_name(chars)? {
+        Some(lexeme) => Ok(Some(PrivateIdentifier::new(lexeme))),
+        None => Err(current_span_error!(
+            chars,
+            chars.current_position(),
+            "Invalid private identifier",
+        )),
+    }
+}
+
+/// Parses the characters of an identifier name, interpreting any escapes, or
+/// returns `None` if the iterator doesn't start with one.
+fn parse_identifier_name<'src>(chars: &mut CodeIter<'src>) -> Result<Option<Cow<'src, str>>> {
     let start_pos = chars.current_position();
-    let mut lexeme = String::new();
+    let mut lexeme = chars.start_lexeme();
 
     let mut at_start = true;
     'ident: while let Some(next_char) = chars.peek() {
@@ -101,9 +228,9 @@ pub fn try_parse_identifier(chars: &mut CodeIter) -> Result<Option<IdentParseRes
 
         let token_pred = |c: char| {
             if at_start {
-                c.is_alphabetic() || c == '_' || c == '$'
+                is_identifier_start(c)
             } else {
-                c.is_alphanumeric() || c == '_' || c == '$'
+                is_identifier_part(c)
             }
         };
 
@@ -123,16 +250,21 @@ pub fn try_parse_identifier(chars: &mut CodeIter) -> Result<Option<IdentParseRes
                         ));
                     }
                     Some(c) => c,
-                    _ => continue 'ident,
+                    None => {
+                        lexeme.push_escaped(None);
+                        continue 'ident;
+                    }
                 }
             }
-            _ => *next_char,
+            _ => next_char,
         };
 
         if token_pred(next_char) {
-            lexeme.push(next_char);
             if requires_advancing {
+                lexeme.push(next_char);
                 _ = chars.next();
+            } else {
+                lexeme.push_escaped(Some(EscapedChar::Char(next_char)));
             }
         } else {
             break;
@@ -141,27 +273,11 @@ pub fn try_parse_identifier(chars: &mut CodeIter) -> Result<Option<IdentParseRes
         at_start = false;
     }
 
-    if lexeme.is_empty() {
+    if lexeme.as_str().is_empty() {
         return Ok(None);
     }
 
-    if let Ok(keyword_type) = KeywordType::try_from(lexeme.as_str()) {
-        return Ok(Some(IdentParseResult::Keyword(Keyword::new(keyword_type))));
-    }
-
-    if let Ok(operator_type) = OperatorType::try_from(lexeme.as_str()) {
-        return Ok(Some(IdentParseResult::Operator(Operator::new(
-            operator_type,
-        ))));
-    }
-
-    if let Ok(value_type) = ValueLiteralType::try_from(lexeme.as_str()) {
-        return Ok(Some(IdentParseResult::ValueLiteral(ValueLiteral::new(
-            value_type,
-        ))));
-    }
-
-    Ok(Some(IdentParseResult::Identifier(lexeme.into())))
+    Ok(Some(lexeme.finish()))
 }
 
 #[cfg(test)]
@@ -262,6 +378,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_keywords_and_contextual_keywords() {
+        for (src, kind) in [
+            ("for", KeywordType::For),
+            ("class", KeywordType::Class),
+            ("debugger", KeywordType::Debugger),
+            ("of", KeywordType::Of),
+            ("static", KeywordType::Static),
+        ] {
+            let mut chars = src.into_code_iterator("script.js".to_string());
+            assert_eq!(
+                try_parse_identifier(&mut chars).unwrap().unwrap(),
+                IdentParseResult::Keyword(Keyword::new(kind))
+            );
+        }
+
+        assert!(KeywordType::Of.is_contextual());
+        assert!(!KeywordType::For.is_contextual());
+
+        // Keyword-like operators stay operators.
+        let mut chars = "delete".into_code_iterator("script.js".to_string());
+        assert!(matches!(
+            try_parse_identifier(&mut chars).unwrap().unwrap(),
+            IdentParseResult::Operator(_)
+        ));
+    }
+
     #[test]
     fn test_parse_value_types() {
         let value_literals = vec![
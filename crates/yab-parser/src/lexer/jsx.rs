@@ -0,0 +1,443 @@
+use std::borrow::Cow;
+
+use miette::Result;
+use serde::Serialize;
+
+use crate::location;
+
+use super::{
+    code_iter::{current_span_error, previous_span_error, CodeIter, Span},
+    operator::{Operator, OperatorType},
+    punctuation::{Punctuation, PunctuationType},
+    string::StringLiteral,
+    utils::{is_identifier_part, is_identifier_start},
+    Token,
+};
+
+// Save allocating a string when we know the lexeme value already.
+static JSX_TAG_START: &str = "<";
+static JSX_TAG_END: &str = ">";
+
+/// The `<` that starts a JSX opening, closing, or self-closing tag.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct JSXTagStart {
+    lexeme: &'static str,
+    pub span: location::Span,
+}
+
+/// The `>` that ends a JSX tag.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct JSXTagEnd {
+    lexeme: &'static str,
+    pub span: location::Span,
+}
+
+impl Default for JSXTagStart {
+    fn default() -> Self {
+        Self {
+            lexeme: JSX_TAG_START,
+            span: location::Span::default(),
+        }
+    }
+}
+
+impl Default for JSXTagEnd {
+    fn default() -> Self {
+        Self {
+            lexeme: JSX_TAG_END,
+            span: location::Span::default(),
+        }
+    }
+}
+
+/// A name inside of a JSX tag, like an element or attribute name.  Unlike
+/// Javascript identifiers, these may contain dashes (e.g. `aria-label`), and
+/// keywords are just names.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct JSXIdentifier<'src> {
+    pub lexeme: Cow<'src, str>,
+    pub span: location::Span,
+}
+
+impl<'src> From<&'src str> for JSXIdentifier<'src> {
+    fn from(value: &'src str) -> Self {
+        Self {
+            lexeme: Cow::Borrowed(value),
+            span: location::Span::default(),
+        }
+    }
+}
+
+/// The text between JSX tags, exactly as it appears in the source.  Whitespace
+/// is significant in JSX, so trimming it (and decoding HTML entities) is up to
+/// whoever consumes the token.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct JSXText<'src> {
+    pub lexeme: Cow<'src, str>,
+    pub span: location::Span,
+}
+
+impl<'src> From<&'src str> for JSXText<'src> {
+    fn from(value: &'src str) -> Self {
+        Self {
+            lexeme: Cow::Borrowed(value),
+            span: location::Span::default(),
+        }
+    }
+}
+
+/// Where the lexer is inside of a JSX element, which decides how the next
+/// characters are lexed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsxContext {
+    /// Between a tag's `<` and `>`.  `closing` is set for a closing tag (e.g.
+    /// `</a>`), and `self_closing` for a tag like `<a />`.
+    Tag { closing: bool, self_closing: bool },
+    /// Between an opening tag and its closing tag, where anything but `<` and
+    /// `{` is text.
+    Children,
+    /// Inside of a `{...}` in a tag or in children, which is lexed as
+    /// Javascript.  `braces` counts the braces opened inside of it so that we
+    /// know which `}` ends it, and `templates` is how many template literal
+    /// expressions were open when it started, since a `}` inside of a template
+    /// opened after it ends the template's expression instead.
+    Expression { braces: usize, templates: usize },
+}
+
+/// Determines whether the `<` at the front of `chars` starts a JSX element.
+/// That's the case when it's somewhere a regex literal could be (i.e. where an
+/// expression starts, rather than after one, where it's a comparison), which
+/// is what `regex_allowed` says, and is followed by a name, or by `>` for a
+/// fragment.
+pub fn jsx_element_starts(chars: &CodeIter, regex_allowed: bool) -> bool {
+    regex_allowed
+        && chars.peek() == Some('<')
+        && matches!(chars.peek_forward(1), Some(c) if c == '>' || is_identifier_start(c))
+}
+
+/// Parses a single token inside of a JSX tag.  Assumes that whitespace and
+/// comments have already been skipped.
+pub fn parse_jsx_tag_token<'src>(chars: &mut CodeIter<'src>) -> Result<Token<'src>> {
+    let start_pos = chars.current_position();
+
+    let token = match chars.peek() {
+        // An element as an attribute value, e.g. `<a b=<c /> />`.
+        Some('<') => Token::JSXTagStart(JSXTagStart::default()),
+        Some('>') => Token::JSXTagEnd(JSXTagEnd::default()),
+        Some('/') => Token::Operator(Operator::new(OperatorType::Division)),
+        Some('=') => Token::Operator(Operator::new(OperatorType::Assignment)),
+        Some('.') => Token::Punctuation(Punctuation::new(PunctuationType::Dot)),
+        Some(':') => Token::Punctuation(Punctuation::new(PunctuationType::Colon)),
+        Some('{') => Token::Punctuation(Punctuation::new(PunctuationType::OpenBrace)),
+        Some('"') | Some('\'') => return parse_jsx_string(chars).map(Token::StringLiteral),
+        Some(c) if is_identifier_start(c) => {
+            return Ok(Token::JSXIdentifier(parse_jsx_identifier(chars)))
+        }
+        Some(c) => {
+            _ = chars.next();
+            return Err(current_span_error!(
+                chars,
+                start_pos,
+                "Unexpected character '{}' in JSX tag",
+                c
+            ));
+        }
+        None => {
+            return Err(current_span_error!(
+                chars,
+                start_pos,
+                "Unexpected EOF while parsing JSX tag",
+            ))
+        }
+    };
+
+    _ = chars.next();
+    Ok(token)
+}
+
+fn parse_jsx_identifier<'src>(chars: &mut CodeIter<'src>) -> JSXIdentifier<'src> {
+    let start_pos = chars.current_position();
+    while chars
+        .peek()
+        .is_some_and(|c| is_identifier_part(c) || c == '-')
+    {
+        _ = chars.next();
+    }
+
+    chars.slice_from(&start_pos).into()
+}
+
+/// Parses an attribute value string.  These differ from Javascript strings in
+/// that they can span lines, and backslashes are just backslashes.
+fn parse_jsx_string<'src>(chars: &mut CodeIter<'src>) -> Result<StringLiteral<'src>> {
+    let start_pos = chars.current_position();
+    let delimiter = chars.next().unwrap();
+    let text_start = chars.current_position();
+
+    while let Some(next_char) = chars.next() {
+        if next_char == delimiter {
+            let lexeme = chars.slice(&text_start, &chars.previous_position());
+            return Ok(StringLiteral::new(lexeme));
+        }
+    }
+
+    Err(previous_span_error!(
+        chars,
+        start_pos,
+        "Unexpected EOF while parsing JSX attribute string",
+    ))
+}
+
+/// Parses the text between tags, up to the next `<` or `{`.
+pub fn parse_jsx_text<'src>(chars: &mut CodeIter<'src>) -> JSXText<'src> {
+    let start_pos = chars.current_position();
+    while chars.peek().is_some_and(|c| c != '<' && c != '{') {
+        _ = chars.next();
+    }
+
+    chars.slice_from(&start_pos).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::{
+        code_iter::IntoCodeIterator, ident::Identifier, regex::regex_allowed_after, tokenize,
+        tokenize_with_options, without_spans, LexerOptions, TemplateLiteralExprClose,
+        TemplateLiteralExprOpen, TemplateLiteralString,
+    };
+
+    use super::*;
+
+    fn tokenize_jsx(src: &str) -> Result<Vec<Token<'_>>> {
+        tokenize_with_options(
+            src,
+            "component.jsx",
+            LexerOptions {
+                jsx: true,
+                ..LexerOptions::default()
+            },
+        )
+    }
+
+    fn tag_start() -> Token<'static> {
+        Token::JSXTagStart(JSXTagStart::default())
+    }
+
+    fn tag_end() -> Token<'static> {
+        Token::JSXTagEnd(JSXTagEnd::default())
+    }
+
+    fn name(lexeme: &str) -> Token<'_> {
+        Token::JSXIdentifier(lexeme.into())
+    }
+
+    fn text(lexeme: &str) -> Token<'_> {
+        Token::JSXText(lexeme.into())
+    }
+
+    fn punctuation(kind: PunctuationType) -> Token<'static> {
+        Token::Punctuation(Punctuation::new(kind))
+    }
+
+    fn operator(kind: OperatorType) -> Token<'static> {
+        Token::Operator(Operator::new(kind))
+    }
+
+    #[test]
+    fn test_jsx_element_starts() {
+        let assign = Token::Operator(Operator::new(OperatorType::Assignment));
+        let ident = Token::Ident(Identifier::from("a"));
+
+        for (src, previous_token, expected) in [
+            ("<a", None, true),
+            ("<>", None, true),
+            ("<a", Some(&assign), true),
+            ("<a", Some(&ident), false),
+            ("< a", None, false),
+            ("<1", None, false),
+            ("a", None, false),
+        ] {
+            let chars = src.into_code_iterator("component.jsx".into());
+            assert_eq!(
+                jsx_element_starts(&chars, regex_allowed_after(previous_token)),
+                expected,
+                "{}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn test_jsx_elements() {
+        assert_eq!(
+            without_spans(tokenize_jsx(r#"<div class="a" aria-hidden>Hi {name}!</div>"#).unwrap()),
+            vec![
+                tag_start(),
+                name("div"),
+                name("class"),
+                operator(OperatorType::Assignment),
+                Token::StringLiteral("a".into()),
+                name("aria-hidden"),
+                tag_end(),
+                text("Hi "),
+                punctuation(PunctuationType::OpenBrace),
+                Token::Ident("name".into()),
+                punctuation(PunctuationType::CloseBrace),
+                text("!"),
+                tag_start(),
+                operator(OperatorType::Division),
+                name("div"),
+                tag_end(),
+            ]
+        );
+
+        assert_eq!(
+            without_spans(tokenize_jsx("<><Foo.Bar {...props} /><svg:a/></>").unwrap()),
+            vec![
+                tag_start(),
+                tag_end(),
+                tag_start(),
+                name("Foo"),
+                punctuation(PunctuationType::Dot),
+                name("Bar"),
+                punctuation(PunctuationType::OpenBrace),
+                punctuation(PunctuationType::Ellipsis),
+                Token::Ident("props".into()),
+                punctuation(PunctuationType::CloseBrace),
+                operator(OperatorType::Division),
+                tag_end(),
+                tag_start(),
+                name("svg"),
+                punctuation(PunctuationType::Colon),
+                name("a"),
+                operator(OperatorType::Division),
+                tag_end(),
+                tag_start(),
+                operator(OperatorType::Division),
+                tag_end(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_jsx_returns_to_javascript() {
+        // The element ends after its closing tag, so the `<` after it is a
+        // comparison again, and the text in between isn't JSX text.
+        assert_eq!(
+            without_spans(tokenize_jsx("a = <b/> < c").unwrap()),
+            vec![
+                Token::Ident("a".into()),
+                operator(OperatorType::Assignment),
+                tag_start(),
+                name("b"),
+                operator(OperatorType::Division),
+                tag_end(),
+                operator(OperatorType::LessThan),
+                Token::Ident("c".into()),
+            ]
+        );
+
+        // Without the option, '<' is always an operator.
+        assert_eq!(
+            without_spans(tokenize("a = <b/>", "script.js").unwrap())[2],
+            operator(OperatorType::LessThan)
+        );
+    }
+
+    #[test]
+    fn test_jsx_expression_containers() {
+        // Braces inside of an expression container don't end it, and neither
+        // does the '}' ending a template literal expression inside of it.
+        assert_eq!(
+            without_spans(tokenize_jsx("<a b={{c: `${d}`}}>{/* e */}</a>").unwrap()),
+            vec![
+                tag_start(),
+                name("a"),
+                name("b"),
+                operator(OperatorType::Assignment),
+                punctuation(PunctuationType::OpenBrace),
+                punctuation(PunctuationType::OpenBrace),
+                Token::Ident("c".into()),
+                punctuation(PunctuationType::Colon),
+                Token::TemplateLiteralString(TemplateLiteralString::new("", false)),
+                Token::TemplateLiteralExprOpen(TemplateLiteralExprOpen::default()),
+                Token::Ident("d".into()),
+                Token::TemplateLiteralExprClose(TemplateLiteralExprClose::default()),
+                Token::TemplateLiteralString(TemplateLiteralString::new("", true)),
+                punctuation(PunctuationType::CloseBrace),
+                punctuation(PunctuationType::CloseBrace),
+                tag_end(),
+                punctuation(PunctuationType::OpenBrace),
+                Token::Comment(crate::lexer::Comment::new(
+                    crate::lexer::CommentType::Block(" e ".into())
+                )),
+                punctuation(PunctuationType::CloseBrace),
+                tag_start(),
+                operator(OperatorType::Division),
+                name("a"),
+                tag_end(),
+            ]
+        );
+
+        // Elements can nest inside of expressions, and inside of template
+        // literal expressions, which end after the element does.
+        let tokens = without_spans(tokenize_jsx("`${<a>{[<b/>]}</a>}`").unwrap());
+        assert_eq!(tokens.len(), 19);
+        assert_eq!(tokens[7], tag_start());
+        assert_eq!(tokens[12], punctuation(PunctuationType::CloseBrace));
+        assert_eq!(
+            tokens[17],
+            Token::TemplateLiteralExprClose(TemplateLiteralExprClose::default())
+        );
+    }
+
+    #[test]
+    fn test_jsx_text_is_not_javascript() {
+        assert_eq!(
+            without_spans(tokenize_jsx("<p>\n  // it's 'not' a comment\n</p>").unwrap()),
+            vec![
+                tag_start(),
+                name("p"),
+                tag_end(),
+                text("\n  // it's 'not' a comment\n"),
+                tag_start(),
+                operator(OperatorType::Division),
+                name("p"),
+                tag_end(),
+            ]
+        );
+
+        assert_eq!(
+            without_spans(
+                tokenize_jsx(
+                    r#"<a b="c\d
+e" />"#
+                )
+                .unwrap()
+            )[4],
+            Token::StringLiteral("c\\d\ne".into())
+        );
+    }
+
+    #[test]
+    fn test_jsx_errors() {
+        for (src, expected) in [
+            (
+                "<a>text",
+                "Unexpected EOF while parsing JSX at component.jsx:1:1",
+            ),
+            (
+                "<a b=",
+                "Unexpected EOF while parsing JSX at component.jsx:1:1",
+            ),
+            (
+                "<a b='c",
+                "Unexpected EOF while parsing JSX attribute string",
+            ),
+            ("<a %>", "Unexpected character '%' in JSX tag"),
+        ] {
+            let err = tokenize_jsx(src).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}: {}", src, err);
+        }
+    }
+}
@@ -1,90 +1,560 @@
-use miette::Result;
+use std::collections::VecDeque;
+
+use miette::{ErrReport, Result};
 use serde::Serialize;
+use strum_macros::IntoStaticStr;
+
+use crate::location;
 
 use self::{
-    code_iter::{current_span_error, IntoCodeIterator, Span},
-    comment::Comment,
-    ident::{IdentParseResult, Identifier, Keyword, ValueLiteral},
-    num::NumberLiteral,
-    operator::Operator,
-    punctuation::Punctuation,
+    code_iter::{current_span_error, CodeIter, IntoCodeIterator, Position, Span},
+    ident::IdentParseResult,
+    jsx::JsxContext,
+    regex::InputGoal,
+};
+
+pub use self::{
+    comment::{Comment, CommentType},
+    ident::{Identifier, Keyword, KeywordType, PrivateIdentifier, ValueLiteral, ValueLiteralType},
+    jsx::{JSXIdentifier, JSXTagEnd, JSXTagStart, JSXText},
+    num::{BigIntStorage, NumberLiteral, NumberLiteralValue},
+    operator::{Operator, OperatorType},
+    punctuation::{Punctuation, PunctuationType},
     regex::RegexLiteral,
     string::StringLiteral,
     template::{TemplateLiteralExprClose, TemplateLiteralExprOpen, TemplateLiteralString},
+    trivia::{tokenize_with_trivia, CommentAttachment, TokensWithTrivia},
 };
 
-mod code_iter;
+pub(crate) mod code_iter;
 mod comment;
 mod escape_chars;
 mod ident;
+mod jsx;
 mod num;
 mod operator;
 mod punctuation;
 mod regex;
 mod string;
 mod template;
-mod utils;
+mod trivia;
+pub(crate) mod utils;
 
-#[derive(Debug, Serialize, PartialEq)]
+/// One token of the source.  Converting one to a `&'static str` gives the
+/// name of its variant, like `"Punctuation"`.
+///
+/// Tokens borrow their text from the source wherever they can, so `'src` is
+/// the lifetime of the source they were lexed from.
+#[derive(Debug, Serialize, PartialEq, IntoStaticStr)]
 #[serde(tag = "type")]
-pub enum Token {
+pub enum Token<'src> {
     Keyword(Keyword),
-    Ident(Identifier),
+    Ident(Identifier<'src>),
+    PrivateIdentifier(PrivateIdentifier<'src>),
     ValueLiteral(ValueLiteral),
     Operator(Operator),
     Punctuation(Punctuation),
-    Comment(Comment),
-    NumericLiteral(NumberLiteral),
-    StringLiteral(StringLiteral),
-    TemplateLiteralString(TemplateLiteralString),
+    Comment(Comment<'src>),
+    NumericLiteral(NumberLiteral<'src>),
+    StringLiteral(StringLiteral<'src>),
+    TemplateLiteralString(TemplateLiteralString<'src>),
     TemplateLiteralExprOpen(TemplateLiteralExprOpen),
     TemplateLiteralExprClose(TemplateLiteralExprClose),
-    RegexLiteral(RegexLiteral),
+    RegexLiteral(RegexLiteral<'src>),
+    JSXTagStart(JSXTagStart),
+    JSXTagEnd(JSXTagEnd),
+    JSXIdentifier(JSXIdentifier<'src>),
+    JSXText(JSXText<'src>),
+}
+
+macro_rules! token_span {
+    ($($variant:ident),* $(,)?) => {
+        impl Token<'_> {
+            /// Where in the source the token was lexed from.  Tokens built by
+            /// hand have a default span, with every field zero.
+            pub fn span(&self) -> location::Span {
+                match self {
+                    $(Token::$variant(token) => token.span,)*
+                }
+            }
+
+            fn span_mut(&mut self) -> &mut location::Span {
+                match self {
+                    $(Token::$variant(token) => &mut token.span,)*
+                }
+            }
+        }
+    };
+}
+
+token_span!(
+    Keyword,
+    Ident,
+    PrivateIdentifier,
+    ValueLiteral,
+    Operator,
+    Punctuation,
+    Comment,
+    NumericLiteral,
+    StringLiteral,
+    TemplateLiteralString,
+    TemplateLiteralExprOpen,
+    TemplateLiteralExprClose,
+    RegexLiteral,
+    JSXTagStart,
+    JSXTagEnd,
+    JSXIdentifier,
+    JSXText,
+);
+
+/// Resets the span of each of `tokens`, so tests can compare them with tokens
+/// built by hand.
+#[cfg(test)]
+fn without_spans(mut tokens: Vec<Token>) -> Vec<Token> {
+    for token in &mut tokens {
+        *token.span_mut() = location::Span::default();
+    }
+    tokens
+}
+
+/// Describes the token the way it should appear in an "Unexpected ..." error
+/// message, e.g. "token ';'" or "identifier 'foo'".
+impl std::fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Keyword(keyword) => write!(f, "token '{}'", keyword.kind),
+            Token::Ident(ident) => write!(f, "identifier '{}'", ident.lexeme),
+            Token::PrivateIdentifier(ident) => {
+                write!(f, "private identifier '#{}'", ident.lexeme)
+            }
+            Token::ValueLiteral(value) => write!(f, "token '{}'", value.kind),
+            Token::Operator(operator) => write!(f, "token '{}'", operator.kind),
+            Token::Punctuation(punctuation) => write!(f, "token '{}'", punctuation.kind),
+            Token::Comment(_) => write!(f, "comment"),
+            Token::NumericLiteral(_) => write!(f, "number"),
+            Token::StringLiteral(_) => write!(f, "string"),
+            Token::TemplateLiteralString(_) => write!(f, "template string"),
+            Token::TemplateLiteralExprOpen(_) => write!(f, "token '${{'"),
+            Token::TemplateLiteralExprClose(_) => write!(f, "token '}}'"),
+            Token::RegexLiteral(_) => write!(f, "regular expression"),
+            Token::JSXTagStart(_) => write!(f, "token '<'"),
+            Token::JSXTagEnd(_) => write!(f, "token '>'"),
+            Token::JSXIdentifier(ident) => write!(f, "JSX identifier '{}'", ident.lexeme),
+            Token::JSXText(_) => write!(f, "JSX text"),
+        }
+    }
+}
+
+/// The tokens of a source file, along with what the parser needs to know about
+/// each of them besides the token itself.
+#[derive(Debug, Default)]
+pub struct TokenStream<'src> {
+    /// The source the tokens were lexed from, which errors about them quote.
+    pub source: &'src str,
+    pub tokens: Vec<Token<'src>>,
+    /// Whether each token is preceded by a line terminator, which the parser
+    /// needs for automatic semicolon insertion.  A comment that spans lines
+    /// counts as a line terminator.
+    pub line_breaks: Vec<bool>,
+}
+
+/// What the lexer accepts.  By default that's the latest edition of standard
+/// Javascript, along with legacy octals and a hashbang line, which scripts can
+/// have, and none of the extensions.
+#[derive(Debug, Clone, Copy)]
+pub struct LexerOptions {
+    /// The edition of the spec to accept, as a year, like 2020 for ES2020.
+    /// Syntax added in later editions is an error.  Editions before ES2015
+    /// are treated as ES2015.
+    pub ecma_version: u16,
+    /// Accept legacy octal literals like `017`, which strict mode forbids.
+    /// Modules never accept them.
+    pub allow_legacy_octals: bool,
+    /// Lex JSX elements (`<div className="a">{b}</div>`) wherever an
+    /// expression can start.
+    pub jsx: bool,
+    /// Lex TypeScript, where a `!` after an expression is a non-null
+    /// assertion, so `a! / b` is a division rather than a regex.
+    pub typescript: bool,
+    /// Accept a `#!` comment on the first line.
+    pub hashbang: bool,
+    /// Lex the source as a module, which is always strict mode code.
+    pub module: bool,
 }
 
-pub fn tokenize(src: &str, file_name: impl Into<String>) -> Result<Vec<Token>> {
-    let mut chars = src.into_code_iterator(file_name.into());
-    let mut tokens = Vec::<Token>::new();
-    let mut template_depth = 0;
+impl LexerOptions {
+    /// The latest edition of the spec the lexer knows about.
+    pub const LATEST_ECMA_VERSION: u16 = 2024;
+}
 
-    'outer: loop {
-        if chars.peek().is_none() {
-            break;
+impl Default for LexerOptions {
+    fn default() -> Self {
+        Self {
+            ecma_version: Self::LATEST_ECMA_VERSION,
+            allow_legacy_octals: true,
+            jsx: false,
+            typescript: false,
+            hashbang: true,
+            module: false,
         }
+    }
+}
+
+pub fn tokenize(src: &str, file_name: impl Into<String>) -> Result<Vec<Token<'_>>> {
+    tokenize_with_options(src, file_name, LexerOptions::default())
+}
 
-        if tokens.is_empty() {
-            if let Some(comment) = comment::try_parse_hashbang_comment(&mut chars) {
+/// Like [`tokenize`], but with syntax extensions enabled by `options`.
+pub fn tokenize_with_options(
+    src: &str,
+    file_name: impl Into<String>,
+    options: LexerOptions,
+) -> Result<Vec<Token<'_>>> {
+    tokenize_stream_with_options(src, file_name, options).map(|stream| stream.tokens)
+}
+
+/// Like [`tokenize`], but also returns the line breaks before the tokens.
+pub fn tokenize_stream(src: &str, file_name: impl Into<String>) -> Result<TokenStream<'_>> {
+    tokenize_stream_with_options(src, file_name, LexerOptions::default())
+}
+
+/// Like [`tokenize_stream`], but with syntax extensions enabled by `options`.
+pub fn tokenize_stream_with_options(
+    src: &str,
+    file_name: impl Into<String>,
+    options: LexerOptions,
+) -> Result<TokenStream<'_>> {
+    let mut lexer = Lexer::with_options(src, file_name, options);
+    let mut stream = TokenStream {
+        source: src,
+        ..TokenStream::default()
+    };
+    // Lexing straight into the stream skips the queue that `next` takes
+    // tokens from one at a time.
+    while !lexer.finished {
+        lexer.lex_onto(&mut stream);
+    }
+
+    match lexer.error {
+        Some(err) => Err(err),
+        None => Ok(stream),
+    }
+}
+
+/// Lexes a source file on demand, a token at a time, for callers that don't
+/// need every token at once.  [`tokenize_stream`] is this, collected.
+///
+/// Iterating ends after the first error, since the lexer can't tell where the
+/// next token would start.
+pub struct Lexer<'src> {
+    chars: CodeIter<'src>,
+    options: LexerOptions,
+    /// Tokens that have been lexed but not returned yet, each with whether a
+    /// line terminator comes before it.  Some tokens are lexed together, like
+    /// the `}` ending a template's expression and the string part after it,
+    /// and peeking can lex any number of tokens ahead.
+    lexed: VecDeque<(Token<'src>, bool)>,
+    /// The tokens lexed in one go by `lex_onto`, kept between calls so that
+    /// lexing doesn't allocate for every token.
+    batch: TokenStream<'src>,
+    /// The error that stopped lexing, which is returned once `lexed` runs
+    /// out.
+    error: Option<ErrReport>,
+    finished: bool,
+    /// Whether a line terminator has come since the last token.  A comment
+    /// that spans lines counts as one.
+    line_break_pending: bool,
+    goal: InputGoal,
+    /// Whether any token has been lexed yet.  Only the first can be a
+    /// hashbang comment.
+    started: bool,
+    /// Whether the last token lexed was the `<` starting a JSX tag.
+    after_tag_start: bool,
+    /// Start positions of the template literals whose `${` expressions we are
+    /// currently inside of, innermost last.  While this is non-empty, a '}'
+    /// closes the innermost expression rather than being punctuation.
+    open_templates: Vec<Position>,
+    /// The JSX elements we are currently inside of, innermost last, along with
+    /// where each element started.
+    jsx_contexts: Vec<(JsxContext, Position)>,
+}
+
+impl<'src> Lexer<'src> {
+    pub fn new(src: &'src str, file_name: impl Into<String>) -> Self {
+        Self::with_options(src, file_name, LexerOptions::default())
+    }
+
+    /// Like [`Lexer::new`], but with syntax extensions enabled by `options`.
+    pub fn with_options(
+        src: &'src str,
+        file_name: impl Into<String>,
+        options: LexerOptions,
+    ) -> Self {
+        Self {
+            chars: src.into_code_iterator(file_name.into()),
+            options,
+            lexed: VecDeque::new(),
+            batch: TokenStream {
+                source: src,
+                ..TokenStream::default()
+            },
+            error: None,
+            finished: false,
+            line_break_pending: false,
+            goal: InputGoal::new(options.typescript),
+            started: false,
+            after_tag_start: false,
+            open_templates: Vec::new(),
+            jsx_contexts: Vec::new(),
+        }
+    }
+
+    /// Returns the next token without consuming it, or `None` at the end of
+    /// the input or at an error, which the next call to `next` returns.
+    pub fn peek(&mut self) -> Option<&Token<'src>> {
+        self.peek_n(0)
+    }
+
+    /// Returns the token `n` tokens past the next one, so `peek_n(0)` is the
+    /// same as `peek()`.
+    pub fn peek_n(&mut self, n: usize) -> Option<&Token<'src>> {
+        self.fill(n + 1);
+        self.lexed.get(n).map(|(token, _)| token)
+    }
+
+    /// Like `next`, but also returns whether a line terminator comes before
+    /// the token, which the parser needs for automatic semicolon insertion.
+    pub fn next_with_line_break(&mut self) -> Option<Result<(Token<'src>, bool)>> {
+        self.fill(1);
+        match self.lexed.pop_front() {
+            Some(lexed) => Some(Ok(lexed)),
+            None => self.error.take().map(Err),
+        }
+    }
+
+    /// Lexes until there are at least `count` tokens waiting in `lexed`, or
+    /// there are no more to lex.
+    fn fill(&mut self, count: usize) {
+        while self.lexed.len() < count && !self.finished {
+            let mut batch = std::mem::take(&mut self.batch);
+            self.lex_onto(&mut batch);
+            self.lexed
+                .extend(batch.tokens.drain(..).zip(batch.line_breaks.drain(..)));
+            self.batch = batch;
+        }
+    }
+
+    /// Lexes whatever comes next in the source onto the end of `stream`,
+    /// giving the tokens their spans and line breaks.  Marks the lexer
+    /// finished at the end of the input or at an error.
+    fn lex_onto(&mut self, stream: &mut TokenStream<'src>) {
+        let first = stream.tokens.len();
+        let token_start = self.chars.current_position();
+        let mut lexed = self.lex_tokens(&token_start, &mut stream.tokens);
+        if lexed.is_ok() {
+            if let Err(err) = self.check_options(&token_start, &stream.tokens[first..]) {
+                stream.tokens.truncate(first);
+                lexed = Err(err);
+            }
+        }
+
+        match lexed {
+            Ok(true) => {}
+            Ok(false) => self.finished = true,
+            Err(err) => {
+                self.error = Some(err);
+                self.finished = true;
+            }
+        }
+
+        let tokens = &mut stream.tokens[first..];
+        let span = location::Span::new(token_start.into(), self.chars.current_position().into());
+        split_span(tokens, span);
+        for token in tokens.iter() {
+            self.goal.advance(token);
+            let line_break = take_line_break(token, &mut self.line_break_pending);
+            stream.line_breaks.push(line_break);
+            self.started = true;
+            self.after_tag_start = matches!(token, Token::JSXTagStart(_));
+        }
+    }
+
+    /// Lexes whatever comes next in the source into `tokens`, which may be
+    /// nothing if it's whitespace, or more than one token.  Returns false at
+    /// the end of the input.
+    fn lex_tokens(
+        &mut self,
+        token_start: &Position,
+        tokens: &mut Vec<Token<'src>>,
+    ) -> Result<bool> {
+        if self.chars.peek().is_none() {
+            if let Some(template_start) = self.open_templates.pop() {
+                return Err(current_span_error!(
+                    self.chars,
+                    template_start,
+                    "Unexpected EOF while parsing template literal expression",
+                ));
+            }
+
+            if let Some((_, element_start)) = self.jsx_contexts.pop() {
+                return Err(current_span_error!(
+                    self.chars,
+                    element_start,
+                    "Unexpected EOF while parsing JSX",
+                ));
+            }
+
+            return Ok(false);
+        }
+
+        // Whitespace and comments are text between JSX tags, so this has to
+        // come before we skip them.
+        if let Some((JsxContext::Children, element_start)) = self.jsx_contexts.last() {
+            let element_start = element_start.clone();
+            match self.chars.peek() {
+                Some('<') => {
+                    _ = self.chars.next();
+                    tokens.push(Token::JSXTagStart(JSXTagStart::default()));
+                    self.jsx_contexts.push((
+                        JsxContext::Tag {
+                            closing: false,
+                            self_closing: false,
+                        },
+                        token_start.clone(),
+                    ));
+                }
+                Some('{') => {
+                    _ = self.chars.next();
+                    tokens.push(Token::Punctuation(Punctuation::new(
+                        PunctuationType::OpenBrace,
+                    )));
+                    self.jsx_contexts.push((
+                        JsxContext::Expression {
+                            braces: 0,
+                            templates: self.open_templates.len(),
+                        },
+                        element_start,
+                    ));
+                }
+                _ => tokens.push(Token::JSXText(jsx::parse_jsx_text(&mut self.chars))),
+            }
+
+            return Ok(true);
+        }
+
+        if !self.started && self.options.hashbang {
+            if let Some(comment) = comment::try_parse_hashbang_comment(&mut self.chars) {
                 tokens.push(Token::Comment(comment));
-                continue 'outer;
+                return Ok(true);
             }
         }
 
-        if let Some(next_char) = chars.peek() {
-            if next_char.is_whitespace() {
-                chars.next();
-                continue 'outer;
+        if let Some(next_char) = self.chars.peek() {
+            // Control characters are only meaningful inside strings,
+            // templates, regular expressions, and comments, which are all
+            // lexed as a unit below, so any we see here are stray.
+            if utils::is_disallowed_control_character(next_char) {
+                let start_pos = self.chars.current_position();
+                let control_char = self.chars.next().unwrap();
+                return Err(current_span_error!(
+                    self.chars,
+                    start_pos,
+                    "Unexpected control character U+{:04X}",
+                    control_char as u32
+                ));
+            }
+
+            if utils::is_whitespace(next_char) {
+                while let Some(c) = self.chars.peek().filter(|c| utils::is_whitespace(*c)) {
+                    self.line_break_pending |= utils::is_line_terminator(c);
+                    self.chars.next();
+                }
+                return Ok(true);
             }
         }
 
-        if let Some(comment) = comment::try_parse_comment(&mut chars) {
+        if let Some(comment) = comment::try_parse_comment(&mut self.chars)? {
             tokens.push(Token::Comment(comment));
-            continue 'outer;
+            return Ok(true);
         }
 
+        if let Some(&(
+            JsxContext::Tag {
+                closing,
+                self_closing,
+            },
+            _,
+        )) = self.jsx_contexts.last()
+        {
+            let token = jsx::parse_jsx_tag_token(&mut self.chars)?;
+            let at_tag_start = self.after_tag_start;
+            let (context, element_start) = self.jsx_contexts.pop().unwrap();
+
+            match &token {
+                // A closing tag ends its element, so the children go too.
+                Token::JSXTagEnd(_) if closing => _ = self.jsx_contexts.pop(),
+                Token::JSXTagEnd(_) if self_closing => {}
+                Token::JSXTagEnd(_) => self
+                    .jsx_contexts
+                    .push((JsxContext::Children, element_start)),
+                Token::Operator(operator) if operator.kind == OperatorType::Division => {
+                    self.jsx_contexts.push((
+                        JsxContext::Tag {
+                            closing: at_tag_start,
+                            self_closing: !at_tag_start,
+                        },
+                        element_start,
+                    ));
+                }
+                Token::Punctuation(punctuation)
+                    if punctuation.kind == PunctuationType::OpenBrace =>
+                {
+                    self.jsx_contexts.push((context, element_start.clone()));
+                    self.jsx_contexts.push((
+                        JsxContext::Expression {
+                            braces: 0,
+                            templates: self.open_templates.len(),
+                        },
+                        element_start,
+                    ));
+                }
+                Token::JSXTagStart(_) => {
+                    self.jsx_contexts.push((context, element_start));
+                    self.jsx_contexts.push((
+                        JsxContext::Tag {
+                            closing: false,
+                            self_closing: false,
+                        },
+                        token_start.clone(),
+                    ));
+                }
+                _ => self.jsx_contexts.push((context, element_start)),
+            }
+
+            tokens.push(token);
+            return Ok(true);
+        }
+
+        let template_start = self.chars.current_position();
         if let Some((template_content, template_expr_open)) =
-            template::try_parse_template_literal_start(&mut chars)?
+            template::try_parse_template_literal_start(&mut self.chars)?
         {
-            template_depth += 1;
             tokens.push(Token::TemplateLiteralString(template_content));
 
             if let Some(template_expr_open) = template_expr_open {
+                self.open_templates.push(template_start);
                 tokens.push(Token::TemplateLiteralExprOpen(template_expr_open));
             }
 
-            continue 'outer;
+            return Ok(true);
+        }
+
+        if let Some(ident) = ident::try_parse_private_identifier(&mut self.chars)? {
+            tokens.push(Token::PrivateIdentifier(ident));
+            return Ok(true);
         }
 
-        if let Some(parse_result) = ident::try_parse_identifier(&mut chars)? {
+        if let Some(parse_result) = ident::try_parse_identifier(&mut self.chars)? {
             match parse_result {
                 IdentParseResult::Identifier(ident) => {
                     tokens.push(Token::Ident(ident));
@@ -100,62 +570,237 @@ pub fn tokenize(src: &str, file_name: impl Into<String>) -> Result<Vec<Token>> {
                 }
             }
 
-            continue 'outer;
+            return Ok(true);
         }
 
-        if template_depth > 0 {
+        // A '}' belongs to whichever of the innermost template literal and
+        // JSX expression was opened last.
+        let in_template_expression = match self.jsx_contexts.last_mut() {
+            Some((JsxContext::Expression { braces, templates }, _)) => {
+                if *templates == self.open_templates.len() {
+                    match self.chars.peek() {
+                        Some('}') if *braces == 0 => {
+                            _ = self.chars.next();
+                            tokens.push(Token::Punctuation(Punctuation::new(
+                                PunctuationType::CloseBrace,
+                            )));
+                            self.jsx_contexts.pop();
+                            return Ok(true);
+                        }
+                        Some('}') => *braces -= 1,
+                        Some('{') => *braces += 1,
+                        _ => {}
+                    }
+                }
+
+                self.open_templates.len() > *templates
+            }
+            _ => !self.open_templates.is_empty(),
+        };
+
+        if in_template_expression {
             if let Some((expr_close, template_content, expr_open)) =
-                template::try_parse_template_literal_expr_end(&mut chars)?
+                template::try_parse_template_literal_expr_end(&mut self.chars)?
             {
-                template_depth -= 1;
                 tokens.push(Token::TemplateLiteralExprClose(expr_close));
                 tokens.push(Token::TemplateLiteralString(template_content));
 
-                if let Some(expr_open) = expr_open {
-                    template_depth += 1;
-                    tokens.push(Token::TemplateLiteralExprOpen(expr_open));
+                match expr_open {
+                    Some(expr_open) => tokens.push(Token::TemplateLiteralExprOpen(expr_open)),
+                    None => _ = self.open_templates.pop(),
                 }
 
-                continue 'outer;
+                return Ok(true);
             }
         }
 
-        if let Some(regexp) = regex::try_parse_regex_literal(&mut chars, tokens.last())? {
+        if self.options.jsx && jsx::jsx_element_starts(&self.chars, self.goal.regex_allowed()) {
+            _ = self.chars.next();
+            tokens.push(Token::JSXTagStart(JSXTagStart::default()));
+            self.jsx_contexts.push((
+                JsxContext::Tag {
+                    closing: false,
+                    self_closing: false,
+                },
+                token_start.clone(),
+            ));
+            return Ok(true);
+        }
+
+        if let Some(regexp) =
+            regex::try_parse_regex_literal(&mut self.chars, self.goal.regex_allowed())?
+        {
             tokens.push(Token::RegexLiteral(regexp));
-            continue 'outer;
+            return Ok(true);
         }
 
-        if let Some(string_literal) = string::try_parse_string(&mut chars)? {
+        if let Some(string_literal) = string::try_parse_string(&mut self.chars)? {
             tokens.push(Token::StringLiteral(string_literal));
-            continue 'outer;
+            return Ok(true);
         }
 
-        if let Some(number_value) = num::try_parse_number(&mut chars)? {
-            tokens.push(Token::NumericLiteral(NumberLiteral::new(number_value)));
+        let legacy_octal = num::starts_legacy_octal(&self.chars);
+        if let Some(number_value) = num::try_parse_number(&mut self.chars)? {
+            let mut number = NumberLiteral::new(number_value, self.chars.slice_from(token_start));
+            number.legacy_octal = legacy_octal;
+            tokens.push(Token::NumericLiteral(number));
 
-            continue 'outer;
+            return Ok(true);
         }
 
-        if let Some(punctuation) = punctuation::try_parse_punctuation(&mut chars) {
+        if let Some(punctuation) = punctuation::try_parse_punctuation(&mut self.chars) {
             tokens.push(Token::Punctuation(punctuation));
-            continue 'outer;
+            return Ok(true);
         }
 
-        if let Some(operator) = operator::try_parse_operator(&mut chars) {
+        if let Some(operator) = operator::try_parse_operator(&mut self.chars) {
             tokens.push(Token::Operator(operator));
-            continue 'outer;
+            return Ok(true);
         }
 
-        eprintln!("last token parsed: {:?}", tokens.last());
-        return Err(current_span_error!(
-            chars,
-            chars.current_position(),
+        Err(current_span_error!(
+            self.chars,
+            self.chars.current_position(),
             "Unrecognized token '{}'",
-            chars.peek().unwrap_or(&'?')
-        ));
+            self.chars.peek().unwrap_or('?')
+        ))
+    }
+}
+
+impl Lexer<'_> {
+    /// Checks `tokens`, which were lexed from `token_start` on, against the
+    /// options that restrict what can be lexed, rather than extending it.
+    fn check_options(&self, token_start: &Position, tokens: &[Token]) -> Result<()> {
+        for token in tokens {
+            let legacy_octal =
+                matches!(token, Token::NumericLiteral(number) if number.legacy_octal);
+            if legacy_octal && self.options.module {
+                return Err(current_span_error!(
+                    self.chars,
+                    token_start.clone(),
+                    "Legacy octal literals are not allowed in modules",
+                ));
+            }
+            if legacy_octal && !self.options.allow_legacy_octals {
+                return Err(current_span_error!(
+                    self.chars,
+                    token_start.clone(),
+                    "Legacy octal literals are not allowed",
+                ));
+            }
+
+            if let Some((ecma_version, syntax)) = required_ecma_version(token) {
+                if ecma_version > self.options.ecma_version {
+                    return Err(current_span_error!(
+                        self.chars,
+                        token_start.clone(),
+                        "{} requires ES{} or later",
+                        syntax,
+                        ecma_version
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The edition of the spec that added the syntax `token` uses, for syntax
+/// added since ES2015, along with a name for it in error messages.
+fn required_ecma_version(token: &Token) -> Option<(u16, &'static str)> {
+    match token {
+        Token::Operator(operator) => match operator.kind {
+            OperatorType::Exponentiation | OperatorType::ExponentiationAssignment => {
+                Some((2016, "The '**' operator"))
+            }
+            OperatorType::OptionalChaining => Some((2020, "Optional chaining")),
+            OperatorType::NullishCoalescing => Some((2020, "The '??' operator")),
+            OperatorType::LogicalAndAssignment
+            | OperatorType::LogicalOrAssignment
+            | OperatorType::NullishCoalescingAssignment => Some((2021, "Logical assignment")),
+            _ => None,
+        },
+        Token::RegexLiteral(regex) if regex.flags.contains('v') => {
+            Some((2024, "The regular expression flag 'v'"))
+        }
+        Token::RegexLiteral(regex) if regex.flags.contains('d') => {
+            Some((2022, "The regular expression flag 'd'"))
+        }
+        Token::RegexLiteral(regex) if regex.flags.contains('s') => {
+            Some((2018, "The regular expression flag 's'"))
+        }
+        Token::NumericLiteral(number) if number.raw.contains('_') => {
+            Some((2021, "A numeric separator"))
+        }
+        Token::NumericLiteral(number) if matches!(number.value, NumberLiteralValue::BigInt(_)) => {
+            Some((2020, "A BigInt literal"))
+        }
+        _ => None,
+    }
+}
+
+impl<'src> Iterator for Lexer<'src> {
+    type Item = Result<Token<'src>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_line_break()
+            .map(|lexed| lexed.map(|(token, _)| token))
+    }
+}
+
+/// Gives each of the `tokens` lexed in one go its part of their `span`.  Only
+/// the parts of a template are lexed together: a `}` ending an expression,
+/// the string after it, and a `${` starting the next expression.  The braces
+/// are ASCII on a single line, so the string gets what's left between them.
+fn split_span(tokens: &mut [Token], span: location::Span) {
+    let shift = |position: location::Position, by: isize| location::Position {
+        column: position.column.wrapping_add_signed(by),
+        index: position.index.wrapping_add_signed(by),
+        ..position
+    };
+
+    let mut rest = span;
+    for token in tokens.iter_mut() {
+        match token {
+            Token::TemplateLiteralExprClose(close) => {
+                close.span = location::Span::new(rest.start, shift(rest.start, 1));
+                rest.start = close.span.end;
+            }
+            Token::TemplateLiteralExprOpen(open) => {
+                open.span = location::Span::new(shift(rest.end, -2), rest.end);
+                rest.end = open.span.start;
+            }
+            _ => {}
+        }
+    }
+    for token in tokens.iter_mut() {
+        if !matches!(
+            token,
+            Token::TemplateLiteralExprClose(_) | Token::TemplateLiteralExprOpen(_)
+        ) {
+            *token.span_mut() = rest;
+        }
     }
+}
 
-    Ok(tokens)
+/// Returns whether a line break comes before `token`, given whether one is
+/// `pending` since the token before it, and updates `pending` for the token
+/// after it.  Comments pass a pending line break on to the token after them.
+fn take_line_break(token: &Token, pending: &mut bool) -> bool {
+    let line_break = *pending;
+    match token {
+        // Line comments stop at the end of the line, and take the line
+        // terminator with them.
+        Token::Comment(comment) => match &comment.value {
+            CommentType::Line(_) | CommentType::Hashbang(_) => *pending = true,
+            CommentType::Block(text) => {
+                *pending |= text.chars().any(utils::is_line_terminator);
+            }
+        },
+        _ => *pending = false,
+    }
+    line_break
 }
 
 #[cfg(test)]
@@ -173,17 +818,380 @@ mod tests {
             (1) / 2
         "#;
         assert_eq!(
-            tokenize(src, "script.js").unwrap(),
+            without_spans(tokenize(src, "script.js").unwrap()),
             vec![
                 Token::Punctuation(Punctuation::new(PunctuationType::OpenParen)),
-                Token::NumericLiteral(NumberLiteral::new(NumberLiteralValue::Primitive(1.0))),
+                Token::NumericLiteral(NumberLiteral::new(NumberLiteralValue::Primitive(1.0), "1")),
                 Token::Punctuation(Punctuation::new(PunctuationType::CloseParen)),
                 Token::Operator(Operator::new(OperatorType::Division)),
-                Token::NumericLiteral(NumberLiteral::new(NumberLiteralValue::Primitive(2.0))),
+                Token::NumericLiteral(NumberLiteral::new(NumberLiteralValue::Primitive(2.0), "2")),
             ]
         )
     }
 
+    #[test]
+    fn test_line_breaks() {
+        let src = "a\nb /* x */ c /*\n*/ d // e\nf\u{2028}g";
+        let stream = tokenize_stream(src, "script.js").unwrap();
+        let line_breaks = stream
+            .tokens
+            .iter()
+            .zip(stream.line_breaks)
+            .filter(|(token, _)| !matches!(token, Token::Comment(_)))
+            .map(|(_, line_break)| line_break)
+            .collect::<Vec<_>>();
+
+        assert_eq!(line_breaks, vec![false, true, false, true, true, true]);
+    }
+
+    #[test]
+    fn test_lexemes_borrow_from_the_source() {
+        use std::borrow::Cow;
+
+        let tokens = tokenize(r"a 'b' '\x63' `d${e}\u0066` /g/ // h", "script.js").unwrap();
+        let lexemes = tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Ident(ident) => Some(&ident.lexeme),
+                Token::StringLiteral(string) => Some(&string.lexeme),
+                Token::TemplateLiteralString(string) => string.cooked.as_ref(),
+                Token::RegexLiteral(regex) => Some(&regex.pattern),
+                Token::Comment(comment) => match &comment.value {
+                    CommentType::Line(text) => Some(text),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .map(|lexeme| (lexeme.as_ref(), matches!(lexeme, Cow::Borrowed(_))))
+            .collect::<Vec<_>>();
+
+        // Only text that escapes change has to be copied.
+        assert_eq!(
+            lexemes,
+            vec![
+                ("a", true),
+                ("b", true),
+                ("c", false),
+                ("d", true),
+                ("e", true),
+                ("f", false),
+                ("g", true),
+                (" h", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_options() {
+        let lex_error = |src, options| {
+            tokenize_with_options(src, "script.js", options)
+                .unwrap_err()
+                .to_string()
+        };
+
+        let es2019 = LexerOptions {
+            ecma_version: 2019,
+            ..LexerOptions::default()
+        };
+        assert!(tokenize_with_options("a ** b", "script.js", es2019).is_ok());
+        assert_eq!(
+            lex_error("a ?? b", es2019),
+            "SyntaxError: The '??' operator requires ES2020 or later at script.js:1:3"
+        );
+        assert_eq!(
+            lex_error("1_000n", es2019),
+            "SyntaxError: A numeric separator requires ES2021 or later at script.js:1:1"
+        );
+
+        assert_eq!(
+            lex_error("/a/d", es2019),
+            "SyntaxError: The regular expression flag 'd' requires ES2022 or later at script.js:1:1"
+        );
+
+        assert!(tokenize("017", "script.js").is_ok());
+        let strict = LexerOptions {
+            allow_legacy_octals: false,
+            ..LexerOptions::default()
+        };
+        assert_eq!(
+            lex_error("017", strict),
+            "SyntaxError: Legacy octal literals are not allowed at script.js:1:1"
+        );
+        let module = LexerOptions {
+            module: true,
+            ..LexerOptions::default()
+        };
+        assert_eq!(
+            lex_error("017", module),
+            "SyntaxError: Legacy octal literals are not allowed in modules at script.js:1:1"
+        );
+
+        assert!(tokenize("#!/usr/bin/env node\na", "script.js").is_ok());
+        let no_hashbang = LexerOptions {
+            hashbang: false,
+            ..LexerOptions::default()
+        };
+        assert!(tokenize_with_options("#!/usr/bin/env node\na", "script.js", no_hashbang).is_err());
+
+        let typescript = LexerOptions {
+            typescript: true,
+            ..LexerOptions::default()
+        };
+        let tokens = tokenize_with_options("a! / b / c", "script.ts", typescript).unwrap();
+        assert!(!tokens
+            .iter()
+            .any(|token| matches!(token, Token::RegexLiteral(_))));
+        let tokens = tokenize_with_options("!/b/", "script.ts", typescript).unwrap();
+        assert!(matches!(tokens[1], Token::RegexLiteral(_)));
+    }
+
+    #[test]
+    fn test_private_identifiers() {
+        assert_eq!(
+            without_spans(tokenize(r"this.#a; #b in c; #if / 2; #\u0064", "script.js").unwrap()),
+            vec![
+                Token::Keyword(Keyword::new(KeywordType::This)),
+                Token::Punctuation(Punctuation::new(PunctuationType::Dot)),
+                Token::PrivateIdentifier(PrivateIdentifier::new("a")),
+                Token::Punctuation(Punctuation::new(PunctuationType::Semicolon)),
+                Token::PrivateIdentifier(PrivateIdentifier::new("b")),
+                Token::Operator(Operator::new(OperatorType::In)),
+                Token::Ident("c".into()),
+                Token::Punctuation(Punctuation::new(PunctuationType::Semicolon)),
+                Token::PrivateIdentifier(PrivateIdentifier::new("if")),
+                Token::Operator(Operator::new(OperatorType::Division)),
+                Token::NumericLiteral(NumberLiteral::new(NumberLiteralValue::Primitive(2.0), "2")),
+                Token::Punctuation(Punctuation::new(PunctuationType::Semicolon)),
+                Token::PrivateIdentifier(PrivateIdentifier::new("d")),
+            ]
+        );
+        assert!(tokenize("# a", "script.js").is_err());
+    }
+
+    #[test]
+    fn test_numbers_keep_their_lexemes() {
+        let tokens = tokenize("0xFF 1_000_000 1.50e3 0o17 10n", "script.js").unwrap();
+        let lexemes = tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::NumericLiteral(number) => Some(number.raw.as_ref()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(lexemes, ["0xFF", "1_000_000", "1.50e3", "0o17", "10n"]);
+    }
+
+    #[test]
+    fn test_decimal_numbers() {
+        for (src, raw, value) in [
+            ("0.5", "0.5", 0.5),
+            (".5", ".5", 0.5),
+            ("5.", "5.", 5.0),
+            ("1E3", "1E3", 1000.0),
+            ("0e3", "0e3", 0.0),
+            (".5E-1", ".5E-1", 0.05),
+            ("08", "08", 8.0),
+            ("09.5", "09.5", 9.5),
+            ("019", "019", 19.0),
+            ("017", "017", 15.0),
+        ] {
+            let tokens = tokenize(src, "script.js").unwrap();
+            match tokens.as_slice() {
+                [Token::NumericLiteral(number)] => {
+                    assert_eq!(number.raw, raw, "{}", src);
+                    assert_eq!(
+                        number.value,
+                        NumberLiteralValue::Primitive(value),
+                        "{}",
+                        src
+                    );
+                }
+                tokens => panic!("{} lexed as {:?}", src, tokens),
+            }
+        }
+
+        // The sign is an operator, so it can't be lost from the value.
+        let tokens = tokenize("-1e3", "script.js").unwrap();
+        match tokens.as_slice() {
+            [Token::Operator(minus), Token::NumericLiteral(number)] => {
+                assert_eq!(minus.kind, OperatorType::Minus);
+                assert_eq!(number.raw, "1e3");
+                assert_eq!(number.value, NumberLiteralValue::Primitive(1000.0));
+            }
+            tokens => panic!("-1e3 lexed as {:?}", tokens),
+        }
+    }
+
+    #[test]
+    fn test_lexer_lexes_on_demand() {
+        let mut lexer = Lexer::new("a\n+ `b${c}d` #", "script.js");
+
+        // Peeking lexes ahead without consuming anything.
+        assert!(matches!(lexer.peek_n(1), Some(Token::Operator(_))));
+        assert!(matches!(lexer.peek(), Some(Token::Ident(_))));
+
+        let (a, line_break) = lexer.next_with_line_break().unwrap().unwrap();
+        assert!(matches!(a, Token::Ident(ident) if ident.lexeme == "a"));
+        assert!(!line_break);
+        let (_, line_break) = lexer.next_with_line_break().unwrap().unwrap();
+        assert!(line_break);
+
+        // The tokens before an error still come out first.
+        let kinds = lexer
+            .by_ref()
+            .take_while(Result::is_ok)
+            .map(|token| <&'static str>::from(token.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                "TemplateLiteralString",
+                "TemplateLiteralExprOpen",
+                "Ident",
+                "TemplateLiteralExprClose",
+                "TemplateLiteralString",
+            ]
+        );
+        assert!(lexer.next().is_none());
+
+        let mut lexer = Lexer::new("a #", "script.js");
+        assert!(lexer.next().unwrap().is_ok());
+        assert!(lexer.peek().is_none());
+        assert!(lexer
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .to_string()
+            .contains("Unrecognized token '#'"));
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn test_spans() {
+        let tokens = tokenize("let é =\n  'ü';", "script.js").unwrap();
+        let spans = tokens
+            .iter()
+            .map(Token::span)
+            .map(|span| {
+                (
+                    (span.start.line, span.start.column, span.start.index),
+                    (span.end.line, span.end.column, span.end.index),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            spans,
+            vec![
+                ((1, 1, 0), (1, 4, 3)),
+                ((1, 5, 4), (1, 6, 6)),
+                ((1, 7, 7), (1, 8, 8)),
+                ((2, 3, 11), (2, 6, 15)),
+                ((2, 6, 15), (2, 7, 16)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_template_spans() {
+        // The parts of a template are lexed a few at a time, but each still
+        // gets a span of its own.
+        let src = "`a${b}c${d}e`";
+        let lexemes = tokenize(src, "script.js")
+            .unwrap()
+            .iter()
+            .map(|token| &src[token.span().start.index..token.span().end.index])
+            .collect::<Vec<_>>();
+        assert_eq!(lexemes, ["`a", "${", "b", "}", "c", "${", "d", "}", "e`"]);
+
+        let tokens = tokenize("`a\n${b}`", "script.js").unwrap();
+        let open = tokens[1].span();
+        assert_eq!(
+            (open.start.line, open.start.column, open.end.column),
+            (2, 1, 3)
+        );
+    }
+
+    #[test]
+    fn test_close_brace_after_completed_template() {
+        let src = "`x`; { }";
+        assert_eq!(
+            without_spans(tokenize(src, "script.js").unwrap()),
+            vec![
+                Token::TemplateLiteralString(TemplateLiteralString::new("x", true)),
+                Token::Punctuation(Punctuation::new(PunctuationType::Semicolon)),
+                Token::Punctuation(Punctuation::new(PunctuationType::OpenBrace)),
+                Token::Punctuation(Punctuation::new(PunctuationType::CloseBrace)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_template_expression() {
+        let src = "a = `x ${b";
+        assert!(tokenize(src, "script.js")
+            .unwrap_err()
+            .to_string()
+            .contains("Unexpected EOF while parsing template literal expression at script.js:1:5"));
+    }
+
+    #[test]
+    fn test_control_characters_outside_literals_are_errors() {
+        let sources = vec![
+            (
+                "a\0",
+                "Unexpected control character U+0000 at script.js:1:2",
+            ),
+            (
+                "a = \u{0007}1",
+                "Unexpected control character U+0007 at script.js:1:5",
+            ),
+            (
+                "\n\u{007F}",
+                "Unexpected control character U+007F at script.js:2:1",
+            ),
+            (
+                "a\u{0085}b",
+                "Unexpected control character U+0085 at script.js:1:2",
+            ),
+        ];
+
+        for (src, expected) in sources {
+            let err = tokenize(src, "script.js").unwrap_err();
+            assert!(err.to_string().contains(expected), "{}", err);
+            // Labels are in bytes, and should cover just the one character.
+            let label = err.labels().unwrap().next().unwrap();
+            let labelled = &src[label.offset()..label.offset() + label.len()];
+            assert_eq!(labelled.chars().count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_control_characters_inside_literals_are_allowed() {
+        let src = "/e\0f/ 'a\0b' `c\0d` // g\0h\n/* i\0j */\t\u{000B}\u{000C}";
+        assert_eq!(
+            without_spans(tokenize(src, "script.js").unwrap()),
+            vec![
+                Token::RegexLiteral(RegexLiteral::new("e\0f", "")),
+                Token::StringLiteral(StringLiteral::new("a\0b")),
+                Token::TemplateLiteralString(TemplateLiteralString::new("c\0d", true)),
+                Token::Comment(Comment::new(CommentType::Line(" g\0h".into()))),
+                Token::Comment(Comment::new(CommentType::Block(" i\0j ".into()))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_byte_order_mark_is_whitespace() {
+        assert_eq!(
+            without_spans(tokenize("\u{feff};", "script.js").unwrap()),
+            vec![Token::Punctuation(Punctuation::new(
+                PunctuationType::Semicolon
+            ))]
+        );
+    }
+
     #[test]
     fn test_file_tokenization() {
         let src = r#"
@@ -196,22 +1204,19 @@ export function foo() {
 "#;
 
         assert_eq!(
-            tokenize(src, "script.js").unwrap(),
+            without_spans(tokenize(src, "script.js").unwrap()),
             vec![
                 Token::Comment(Comment::new(CommentType::Line(
-                    " This is a a comment".to_string()
+                    " This is a a comment".into()
                 ))),
                 Token::Keyword(Keyword::new("const".try_into().unwrap())),
                 Token::Ident("a".into()),
                 Token::Operator(Operator::new(OperatorType::Assignment)),
-                Token::TemplateLiteralString(TemplateLiteralString::new(
-                    "my template: ".into(),
-                    false
-                )),
+                Token::TemplateLiteralString(TemplateLiteralString::new("my template: ", false)),
                 Token::TemplateLiteralExprOpen(TemplateLiteralExprOpen::default()),
                 Token::Ident("b".into()),
                 Token::TemplateLiteralExprClose(TemplateLiteralExprClose::default()),
-                Token::TemplateLiteralString(TemplateLiteralString::new("".into(), true)),
+                Token::TemplateLiteralString(TemplateLiteralString::new("", true)),
                 Token::Punctuation(Punctuation::new(PunctuationType::Semicolon)),
                 Token::Keyword(Keyword::new(ident::KeywordType::Export)),
                 Token::Keyword(Keyword::new(ident::KeywordType::Function)),
@@ -221,16 +1226,19 @@ export function foo() {
                 Token::Punctuation(Punctuation::new(PunctuationType::OpenBrace)),
                 Token::Keyword(Keyword::new("return".try_into().unwrap())),
                 Token::Operator(Operator::new(OperatorType::Await)),
-                Token::RegexLiteral(RegexLiteral::new("hello".into(), "gm".into())),
+                Token::RegexLiteral(RegexLiteral::new("hello", "gm")),
                 Token::Punctuation(Punctuation::new(PunctuationType::Dot)),
                 Token::Ident("test".into()),
                 Token::Punctuation(Punctuation::new(PunctuationType::OpenParen)),
-                Token::StringLiteral(StringLiteral::new("ABC".into())),
+                Token::StringLiteral(StringLiteral::new("ABC")),
                 Token::Punctuation(Punctuation::new(PunctuationType::CloseParen)),
                 Token::Operator(Operator::new(OperatorType::LooseEquality)),
                 Token::ValueLiteral(ValueLiteral::new("true".try_into().unwrap())),
                 Token::Operator(Operator::new(OperatorType::LogicalAnd)),
-                Token::NumericLiteral(NumberLiteral::new(NumberLiteralValue::Primitive(1.2e-3))),
+                Token::NumericLiteral(NumberLiteral::new(
+                    NumberLiteralValue::Primitive(1.2e-3),
+                    "1.2e-3"
+                )),
                 Token::Punctuation(Punctuation::new(PunctuationType::Semicolon)),
                 Token::Punctuation(Punctuation::new(PunctuationType::CloseBrace)),
             ]
@@ -1,17 +1,33 @@
+use std::borrow::Cow;
+
 use miette::{IntoDiagnostic, Result};
 use nom::AsChar;
 use serde::Serialize;
 
+use crate::location;
+
 use super::code_iter::{current_span_error, CodeIter, Span};
 
 #[derive(Debug, PartialEq, Serialize)]
-pub struct NumberLiteral {
+pub struct NumberLiteral<'src> {
     pub value: NumberLiteralValue,
+    /// The number as written in the source, like `0xFF` or `1_000`, so that
+    /// it can be printed the way it was written.
+    pub raw: Cow<'src, str>,
+    /// Whether the number was written as a legacy octal like `017`, which
+    /// strict mode forbids.
+    pub legacy_octal: bool,
+    pub span: location::Span,
 }
 
-impl NumberLiteral {
-    pub fn new(value: NumberLiteralValue) -> Self {
-        Self { value }
+impl<'src> NumberLiteral<'src> {
+    pub fn new(value: NumberLiteralValue, raw: impl Into<Cow<'src, str>>) -> Self {
+        Self {
+            value,
+            raw: raw.into(),
+            legacy_octal: false,
+            span: location::Span::default(),
+        }
     }
 }
 
@@ -24,9 +40,30 @@ pub enum NumberLiteralValue {
 #[derive(Debug, PartialEq)]
 pub struct BigIntStorage {
     pub value: num_bigint::BigInt,
+    /// The base the literal was written in: 2, 8, 10 or 16.
+    pub base: u32,
+    /// The literal the way it was written, but without numeric separators,
+    /// like `0xFFn` or `10n`.
     pub lexeme: String,
 }
 
+impl BigIntStorage {
+    fn new(value: num_bigint::BigInt, base: u32, digits: &str) -> Self {
+        let prefix = match base {
+            2 => "0b",
+            8 => "0o",
+            16 => "0x",
+            _ => "",
+        };
+
+        Self {
+            value,
+            base,
+            lexeme: format!("{}{}n", prefix, digits),
+        }
+    }
+}
+
 impl Serialize for BigIntStorage {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
@@ -48,84 +85,25 @@ impl From<i32> for NumberLiteralValue {
     }
 }
 
-enum Sign {
-    Positive,
-    Negative,
-}
-
-impl std::fmt::Display for Sign {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Self::Positive => "+",
-                Self::Negative => "-",
-            }
-        )
-    }
-}
-
-impl From<Option<char>> for Sign {
-    fn from(s: Option<char>) -> Self {
-        match s {
-            Some('-') => Self::Negative,
-            _ => Self::Positive,
-        }
-    }
-}
-
-impl From<Option<&char>> for Sign {
-    fn from(s: Option<&char>) -> Self {
-        match s {
-            Some('-') => Self::Negative,
-            _ => Self::Positive,
-        }
-    }
-}
-
-impl Sign {
-    fn apply_f64(&self, value: f64) -> f64 {
-        match self {
-            Self::Positive => value,
-            Self::Negative => -value,
-        }
-    }
-
-    fn apply_i64(&self, value: i64) -> i64 {
-        match self {
-            Self::Positive => value,
-            Self::Negative => -(value),
-        }
-    }
-}
-
 fn is_numeric_separator(c: char) -> bool {
     c == '_'
 }
 
-// Attempts to parse the exponent of a scientific notation number.  Assumes that
-// the leading "e" has not yet been consumed.
-fn parse_scientific_exponent(chars: &mut CodeIter) -> Result<i64> {
+// Attempts to parse the exponent of a scientific notation number, like `e-5`,
+// returning it without numeric separators.  Assumes that the leading "e" or
+// "E" has not yet been consumed.
+fn parse_scientific_exponent(chars: &mut CodeIter) -> Result<String> {
     let start_pos = chars.current_position();
-    let mut lexeme = String::new();
-    _ = chars.next(); // trailing 'e'
-
-    let sign = match chars.peek() {
-        Some('+') | Some('-') => Sign::from(chars.next()),
-        _ => Sign::Positive,
-    };
+    _ = chars.next(); // leading 'e'
 
-    while let Some(c) = chars.peek() {
-        if c.is_ascii_digit() {
-            lexeme.push(*c);
-            _ = chars.next();
-        } else {
-            break;
-        }
+    let mut lexeme = String::from("e");
+    if let Some(sign @ ('+' | '-')) = chars.peek() {
+        _ = chars.next();
+        lexeme.push(sign);
     }
 
-    if lexeme.is_empty() {
+    let digits = consume_digits(chars, |c| c.is_ascii_digit())?;
+    if digits.is_empty() {
         return Err(current_span_error!(
             chars,
             start_pos,
@@ -133,7 +111,8 @@ fn parse_scientific_exponent(chars: &mut CodeIter) -> Result<i64> {
         ));
     }
 
-    Ok(sign.apply_i64(lexeme.parse::<i64>().into_diagnostic()?))
+    lexeme.push_str(&digits);
+    Ok(lexeme)
 }
 
 /// Parses a number literal that may contain a trailing "n" to indicate a big
@@ -141,9 +120,8 @@ fn parse_scientific_exponent(chars: &mut CodeIter) -> Result<i64> {
 /// the base parse to.
 fn parse_maybe_big_int(
     chars: &mut CodeIter,
-    mut lexeme: String,
+    lexeme: String,
     base: u32,
-    sign: Sign,
 ) -> Result<NumberLiteralValue> {
     let start_pos = chars.current_position();
     let is_big_int = matches!(chars.peek(), Some('n'));
@@ -151,17 +129,12 @@ fn parse_maybe_big_int(
     match is_big_int {
         true => {
             _ = chars.next();
-            if let Sign::Negative = sign {
-                lexeme.insert(0, '-');
-            }
-
             let value = num_bigint::BigInt::parse_bytes(lexeme.as_bytes(), base).ok_or(
                 current_span_error!(chars, start_pos, "failed to parse '{}' into BigInt", lexeme),
             )?;
-            // TODO: write a "pretty formatter" for big int based on the base,
-            // e.g. we want "0xFFn", not "FF"
-            lexeme.push('n');
-            Ok(NumberLiteralValue::BigInt(BigIntStorage { value, lexeme }))
+            Ok(NumberLiteralValue::BigInt(BigIntStorage::new(
+                value, base, &lexeme,
+            )))
         }
         false => {
             let value = match base {
@@ -169,62 +142,66 @@ fn parse_maybe_big_int(
                 _ => i64::from_str_radix(&lexeme, base).into_diagnostic()? as f64,
             };
 
-            Ok(NumberLiteralValue::Primitive(sign.apply_f64(value)))
+            Ok(NumberLiteralValue::Primitive(value))
         }
     }
 }
 
-fn parse_base_10(chars: &mut CodeIter, sign: Sign) -> Result<NumberLiteralValue> {
-    let mut lexeme = String::new();
+/// Parses a decimal number, which may have a fraction and an exponent, like
+/// `1.5e3`.  The integer part may be left out, as in `.5`, or the fraction, as
+/// in `1.`, but not both.
+fn parse_base_10(chars: &mut CodeIter) -> Result<NumberLiteralValue> {
+    let mut lexeme = consume_digits(chars, |c| c.is_ascii_digit())?;
 
-    'number: while let Some(c) = chars.peek() {
-        if is_numeric_separator(*c) {
-            _ = chars.next();
-            continue 'number;
-        }
-
-        if c.is_ascii_digit() || *c == '.' {
-            lexeme.push(*c);
-            _ = chars.next();
-        } else {
-            break 'number;
-        }
+    if chars.peek() == Some('.') {
+        _ = chars.next();
+        lexeme.push('.');
+        lexeme.push_str(&consume_digits(chars, |c| c.is_ascii_digit())?);
     }
 
-    let exponent = match chars.peek() {
-        Some('e') => Some(parse_scientific_exponent(chars)?),
-        _ => None,
-    };
-
-    match exponent {
-        Some(exponent) => {
-            Ok((lexeme.parse::<f64>().into_diagnostic()? * 10f64.powi(exponent as i32)).into())
+    match chars.peek() {
+        Some('e') | Some('E') => {
+            lexeme.push_str(&parse_scientific_exponent(chars)?);
+            Ok(lexeme.parse::<f64>().into_diagnostic()?.into())
         }
-        None => parse_maybe_big_int(chars, lexeme, 10, sign),
+        _ => parse_maybe_big_int(chars, lexeme, 10),
     }
 }
 
-fn consume_while(iter: &mut CodeIter, predicate: fn(char) -> bool) -> String {
-    let mut lexeme = String::new();
-    while let Some(c) = iter.peek() {
-        if is_numeric_separator(*c) {
-            _ = iter.next();
+/// Consumes a run of digits that match `is_digit`, returning them without any
+/// numeric separators.  A separator is only allowed between two digits, so
+/// `1_000` is fine, but `1__000`, `1_` and the one in `0x_FF` are errors.
+fn consume_digits(chars: &mut CodeIter, is_digit: fn(char) -> bool) -> Result<String> {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek() {
+        if is_numeric_separator(c) {
+            let separator_pos = chars.current_position();
+            _ = chars.next();
+            // Digits only ever end at a separator if a digit follows it, so
+            // having any digits means the separator follows one.
+            if digits.is_empty() || !chars.peek().is_some_and(is_digit) {
+                return Err(current_span_error!(
+                    chars,
+                    separator_pos,
+                    "Numeric separators are only allowed between digits",
+                ));
+            }
             continue;
         }
-        if predicate(*c) {
-            lexeme.push(*c);
-            _ = iter.next();
+        if is_digit(c) {
+            digits.push(c);
+            _ = chars.next();
         } else {
             break;
         }
     }
 
-    lexeme
+    Ok(digits)
 }
 
-fn parse_hex_number(chars: &mut CodeIter, sign: Sign) -> Result<NumberLiteralValue> {
+fn parse_hex_number(chars: &mut CodeIter) -> Result<NumberLiteralValue> {
     let start_pos = chars.current_position();
-    let lexeme = consume_while(chars, |c| c.is_ascii_hexdigit());
+    let lexeme = consume_digits(chars, |c| c.is_ascii_hexdigit())?;
 
     if lexeme.is_empty() {
         return Err(current_span_error!(
@@ -234,12 +211,28 @@ fn parse_hex_number(chars: &mut CodeIter, sign: Sign) -> Result<NumberLiteralVal
         ));
     }
 
-    parse_maybe_big_int(chars, lexeme, 16, sign)
+    parse_maybe_big_int(chars, lexeme, 16)
 }
 
-fn parse_bin_number(chars: &mut CodeIter, sign: Sign) -> Result<NumberLiteralValue> {
+/// Errors on a digit straight after the digits of a binary or octal number,
+/// which isn't valid for its base, like the 2 in `0b12`.  It would otherwise
+/// start a number of its own.
+fn reject_trailing_digit(chars: &CodeIter, base_name: &str) -> Result<()> {
+    match chars.peek() {
+        Some(digit) if digit.is_ascii_digit() => Err(current_span_error!(
+            chars,
+            chars.current_position(),
+            "Invalid digit '{}' in {} numeric literal",
+            digit,
+            base_name
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn parse_bin_number(chars: &mut CodeIter) -> Result<NumberLiteralValue> {
     let start_pos = chars.current_position();
-    let lexeme = consume_while(chars, |c| c == '0' || c == '1');
+    let lexeme = consume_digits(chars, |c| c == '0' || c == '1')?;
 
     if lexeme.is_empty() {
         return Err(current_span_error!(
@@ -249,12 +242,14 @@ fn parse_bin_number(chars: &mut CodeIter, sign: Sign) -> Result<NumberLiteralVal
         ));
     }
 
-    parse_maybe_big_int(chars, lexeme, 2, sign)
+    reject_trailing_digit(chars, "binary")?;
+
+    parse_maybe_big_int(chars, lexeme, 2)
 }
 
-fn parse_oct_number(chars: &mut CodeIter, sign: Sign) -> Result<NumberLiteralValue> {
+fn parse_oct_number(chars: &mut CodeIter) -> Result<NumberLiteralValue> {
     let start_pos = chars.current_position();
-    let lexeme = consume_while(chars, |c| c.is_oct_digit());
+    let lexeme = consume_digits(chars, |c| c.is_oct_digit())?;
 
     if lexeme.is_empty() {
         return Err(current_span_error!(
@@ -264,76 +259,102 @@ fn parse_oct_number(chars: &mut CodeIter, sign: Sign) -> Result<NumberLiteralVal
         ));
     }
 
-    parse_maybe_big_int(chars, lexeme, 8, sign)
+    reject_trailing_digit(chars, "octal")?;
+
+    parse_maybe_big_int(chars, lexeme, 8)
 }
 
 /// Attempts to parse a number out of a lexeme that begins with a leading "0".
-/// For example, the literal number "0", or differently-based values like
-/// hexadecimal or binary.
-fn parse_leading_zero_number(chars: &mut CodeIter, sign: Sign) -> Result<NumberLiteralValue> {
-    // Consume leading zero:
-    _ = chars.next();
-
-    match chars.peek() {
-        Some('x') | Some('X') => {
-            _ = chars.next();
-            parse_hex_number(chars, sign)
-        }
-        Some('b') | Some('B') => {
-            _ = chars.next();
-            parse_bin_number(chars, sign)
-        }
-        Some('o') | Some('O') => {
+/// For example, the literal number "0", decimals like "0.5", or
+/// differently-based values like hexadecimal or binary.
+fn parse_leading_zero_number(chars: &mut CodeIter) -> Result<NumberLiteralValue> {
+    let base = match chars.peek_forward(1) {
+        Some('x') | Some('X') => 16,
+        Some('b') | Some('B') => 2,
+        Some('o') | Some('O') => 8,
+        Some('_') => {
             _ = chars.next();
-            parse_oct_number(chars, sign)
+            return Err(current_span_error!(
+                chars,
+                chars.current_position(),
+                "Numeric separator can not be used after leading 0",
+            ));
         }
-        Some('_') => Err(current_span_error!(
+        Some(c) if c.is_ascii_digit() => return parse_legacy_octal_number(chars),
+        _ => return parse_base_10(chars),
+    };
+
+    // Consume the leading zero and the base:
+    _ = chars.next();
+    _ = chars.next();
+
+    match base {
+        16 => parse_hex_number(chars),
+        2 => parse_bin_number(chars),
+        _ => parse_oct_number(chars),
+    }
+}
+
+/// Parses a number written with a leading zero, like `017`, which is a
+/// legacy octal, unless it has an 8 or a 9 in it, like `019`, which makes it
+/// decimal.  The parser rejects both in strict mode code.
+fn parse_legacy_octal_number(chars: &mut CodeIter) -> Result<NumberLiteralValue> {
+    let start_pos = chars.current_position();
+    let is_decimal = (1..)
+        .map(|n| chars.peek_forward(n))
+        .take_while(|c| c.is_some_and(|c| c.is_ascii_digit() || is_numeric_separator(c)))
+        .any(|c| matches!(c, Some('8') | Some('9')));
+
+    let value = if is_decimal {
+        parse_base_10(chars)?
+    } else {
+        _ = chars.next(); // leading '0'
+        parse_oct_number(chars)?
+    };
+
+    // Only the digits before any fraction or exponent of a decimal count.
+    let integer = chars
+        .slice_from(&start_pos)
+        .split(['.', 'e', 'E'])
+        .next()
+        .unwrap_or_default();
+    if integer.contains('_') {
+        return Err(current_span_error!(
             chars,
-            chars.current_position(),
-            "Numeric separator can not be used after leading 0",
-        )),
-        // TODO: support switching on whether legacy octals are allowed:
-        Some(c) if c.is_ascii_digit() => parse_oct_number(chars, sign),
-        _ => Ok(0.into()),
+            start_pos,
+            "Numeric separators are not allowed in legacy octal literals",
+        ));
     }
+
+    Ok(value)
 }
 
-/// Attempts to parse a number out of an iterator of characters.
+/// Whether the number at the front of `chars` is a legacy octal literal, like
+/// `017`, or a decimal written with a leading zero, like `019`, as opposed to
+/// `0o17`.
+pub fn starts_legacy_octal(chars: &CodeIter) -> bool {
+    chars.peek() == Some('0') && chars.peek_forward(1).is_some_and(|c| c.is_ascii_digit())
+}
+
+/// Attempts to parse a number out of an iterator of characters.  A leading
+/// `+` or `-` is never part of the number: `-1` is the unary minus operator
+/// applied to `1`, and `a-1` is a subtraction.
 ///
 /// Returns:
 ///
 /// * `Ok(Some(NumberLiteral))` - a number literal was successfully parsed out of
-/// the iterator.  The iterator has been advanced to the end of the number.
+///   the iterator.  The iterator has been advanced to the end of the number.
 ///
 /// * `Ok(None)` - the next character of the iterator did not begin a number literal.
 ///
 /// * `Err` - the next character of the iterator began a number literal,
-/// but it was malformed or otherwise unable to be parsed.
+///   but it was malformed or otherwise unable to be parsed.
 pub fn try_parse_number(chars: &mut CodeIter) -> Result<Option<NumberLiteralValue>> {
-    let mut has_sign = false;
-    let sign = match chars.peek() {
-        Some('+') | Some('-') => {
-            has_sign = true;
-            Sign::from(chars.peek())
-        }
-        _ => Sign::Positive,
-    };
-
-    // Wait to consume the sign until we know we have a valid number,
-    // otherwise we might end up consuming an operator.
-    match chars.peek_forward(1) {
-        Some(c) if c.is_ascii_digit() => {
-            if has_sign {
-                _ = chars.next();
-            }
-        }
-        _ => {}
-    };
-
     match chars.peek() {
-        Some(c) if c.is_ascii_digit() && *c != '0' => parse_base_10(chars, sign).map(Some),
-        Some(c) if c.is_ascii_digit() && *c == '0' => {
-            parse_leading_zero_number(chars, sign).map(Some)
+        Some(c) if c.is_ascii_digit() && c != '0' => parse_base_10(chars).map(Some),
+        Some(c) if c.is_ascii_digit() && c == '0' => parse_leading_zero_number(chars).map(Some),
+        Some('.') if chars.peek_forward(1).is_some_and(|c| c.is_ascii_digit()) => {
+            parse_base_10(chars).map(Some)
         }
         _ => Ok(None),
     }
@@ -396,6 +417,7 @@ mod tests {
             try_parse_number(&mut chars).unwrap().unwrap(),
             NumberLiteralValue::BigInt(BigIntStorage {
                 value: num_bigint::BigInt::parse_bytes(b"123", 10).unwrap(),
+                base: 10,
                 lexeme: "123n".to_string(),
             })
         );
@@ -412,30 +434,6 @@ mod tests {
             .contains("failed to parse '123.3' into BigInt"));
     }
 
-    #[test]
-    fn test_parse_negative_integer() {
-        let src = "-123";
-        let mut chars = src.into_code_iterator("script.js".to_string());
-        assert_eq!(
-            try_parse_number(&mut chars).unwrap().unwrap(),
-            (-123).into()
-        );
-    }
-
-    #[test]
-    fn test_negative_big_int() {
-        let src = "-123n";
-        let mut chars = src.into_code_iterator("script.js".to_string());
-
-        assert_eq!(
-            try_parse_number(&mut chars).unwrap().unwrap(),
-            NumberLiteralValue::BigInt(BigIntStorage {
-                value: num_bigint::BigInt::parse_bytes(b"-123", 10).unwrap(),
-                lexeme: "-123n".to_string(),
-            })
-        );
-    }
-
     #[test]
     fn test_negative_scientific_notation() {
         let src = "123e-1";
@@ -460,16 +458,6 @@ mod tests {
         assert_eq!(try_parse_number(&mut chars).unwrap().unwrap(), 255.into());
     }
 
-    #[test]
-    fn test_negative_hexadecimal_number() {
-        let src = "-0xFF";
-        let mut chars = src.into_code_iterator("script.js".to_string());
-        assert_eq!(
-            try_parse_number(&mut chars).unwrap().unwrap(),
-            (-255).into()
-        );
-    }
-
     #[test]
     fn test_hexadecimal_big_int() {
         let src = "0xFFn";
@@ -478,11 +466,28 @@ mod tests {
             try_parse_number(&mut chars).unwrap().unwrap(),
             NumberLiteralValue::BigInt(BigIntStorage {
                 value: num_bigint::BigInt::parse_bytes(b"255", 10).unwrap(),
-                lexeme: "FFn".to_string(),
+                base: 16,
+                lexeme: "0xFFn".to_string(),
             })
         );
     }
 
+    #[test]
+    fn test_big_int_lexemes_keep_their_base() {
+        for (src, expected) in [
+            ("0xFF_FFn", "0xFFFFn"),
+            ("0b1_01n", "0b101n"),
+            ("0O17n", "0o17n"),
+            ("1_000n", "1000n"),
+        ] {
+            let mut chars = src.into_code_iterator("script.js".to_string());
+            match try_parse_number(&mut chars).unwrap().unwrap() {
+                NumberLiteralValue::BigInt(storage) => assert_eq!(storage.lexeme, expected),
+                value => panic!("{} did not parse as a BigInt: {:?}", src, value),
+            }
+        }
+    }
+
     #[test]
     fn test_bin_number() {
         let src = "0b101";
@@ -529,6 +534,53 @@ mod tests {
             .contains("Numeric separator can not be used after leading 0"));
     }
 
+    #[test]
+    fn test_misplaced_numeric_separators() {
+        for src in [
+            "1__2", "1_", "0x_FF", "0b1_", "0o_7", "1_.5", "1._5", "1e_5", "1e5_", "1_n",
+        ] {
+            let mut chars = src.into_code_iterator("script.js".to_string());
+            let result = try_parse_number(&mut chars);
+            assert!(
+                result
+                    .unwrap_err()
+                    .to_string()
+                    .contains("Numeric separators are only allowed between digits"),
+                "{}",
+                src
+            );
+        }
+
+        let mut chars = "1_0.0_1e1_0".into_code_iterator("script.js".to_string());
+        assert_eq!(
+            try_parse_number(&mut chars).unwrap().unwrap(),
+            10.01e10.into()
+        );
+
+        for (src, expected) in [("0.0_1", 0.01), ("0.000_001", 0.000001)] {
+            let mut chars = src.into_code_iterator("script.js".to_string());
+            assert_eq!(
+                try_parse_number(&mut chars).unwrap().unwrap(),
+                expected.into()
+            );
+        }
+
+        for (src, expected) in [
+            ("0b12", "Invalid digit '2' in binary numeric literal"),
+            ("0o1_78", "Invalid digit '8' in octal numeric literal"),
+        ] {
+            let mut chars = src.into_code_iterator("script.js".to_string());
+            let message = try_parse_number(&mut chars).unwrap_err().to_string();
+            assert!(message.contains(expected), "{}: {}", src, message);
+        }
+
+        let mut chars = "01_7".into_code_iterator("script.js".to_string());
+        assert!(try_parse_number(&mut chars)
+            .unwrap_err()
+            .to_string()
+            .contains("Numeric separators are not allowed in legacy octal literals"));
+    }
+
     #[test]
     fn test_binary_invalid_chars() {
         let src = "0b2";
@@ -562,10 +614,11 @@ mod tests {
 
     #[test]
     fn test_number_parser_does_not_consume_operators() {
-        let src = "+ 1";
-        let mut chars = src.into_code_iterator("script.js".to_string());
-        let result = try_parse_number(&mut chars);
-        assert!(result.unwrap().is_none());
-        assert_eq!(chars.peek(), Some(&'+'));
+        for src in ["+ 1", "+1", "-1", "-0xFF", "-1n"] {
+            let mut chars = src.into_code_iterator("script.js".to_string());
+            let result = try_parse_number(&mut chars);
+            assert!(result.unwrap().is_none(), "{}", src);
+            assert_eq!(chars.peek(), src.chars().next());
+        }
     }
 }
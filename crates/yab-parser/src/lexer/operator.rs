@@ -3,10 +3,12 @@ use super::{
     utils::{try_parse_from_prefix_lookup, HasPrefixLookup},
 };
 use serde::Serialize;
-use strum_macros::EnumString;
+use strum_macros::{Display, EnumString};
 use yab_parser_macros::HasPrefixLookup;
 
-#[derive(Debug, Serialize, PartialEq, HasPrefixLookup, EnumString)]
+use crate::location;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, HasPrefixLookup, EnumString, Display)]
 #[strum(serialize_all = "snake_case")]
 pub enum OperatorType {
     #[token(lexeme = "+")]
@@ -45,6 +47,10 @@ pub enum OperatorType {
     #[strum(serialize = "=")]
     Assignment,
 
+    #[token(lexeme = "=>")]
+    #[strum(serialize = "=>")]
+    Arrow,
+
     #[token(lexeme = "*=")]
     #[strum(serialize = "*=")]
     MultiplicationAssignment,
@@ -53,6 +59,14 @@ pub enum OperatorType {
     #[strum(serialize = "/=")]
     DivisionAssignment,
 
+    #[token(lexeme = "%=")]
+    #[strum(serialize = "%=")]
+    ModuloAssignment,
+
+    #[token(lexeme = "**=")]
+    #[strum(serialize = "**=")]
+    ExponentiationAssignment,
+
     #[token(lexeme = "+=")]
     #[strum(serialize = "+=")]
     AdditionAssignment,
@@ -129,6 +143,10 @@ pub enum OperatorType {
     #[strum(serialize = "??")]
     NullishCoalescing,
 
+    #[token(lexeme = "?.")]
+    #[strum(serialize = "?.")]
+    OptionalChaining,
+
     #[token(lexeme = "~")]
     #[strum(serialize = "~")]
     BitwiseNot,
@@ -177,10 +195,6 @@ pub enum OperatorType {
     #[strum(serialize = "?")]
     Ternary,
 
-    #[token(lexeme = "...")]
-    #[strum(serialize = "...")]
-    ObjectSpread,
-
     #[token(lexeme = "await")]
     #[strum(serialize = "await")]
     Await,
@@ -189,6 +203,10 @@ pub enum OperatorType {
     #[strum(serialize = "void")]
     Void,
 
+    #[token(lexeme = "delete")]
+    #[strum(serialize = "delete")]
+    Delete,
+
     #[token(lexeme = "typeof")]
     #[strum(serialize = "typeof")]
     TypeOf,
@@ -208,16 +226,30 @@ pub enum OperatorType {
 
 #[derive(Debug, Serialize, PartialEq)]
 pub struct Operator {
-    kind: OperatorType,
+    pub(crate) kind: OperatorType,
+    pub span: location::Span,
 }
 
 impl Operator {
     pub fn new(kind: OperatorType) -> Self {
-        Self { kind }
+        Self {
+            kind,
+            span: location::Span::default(),
+        }
     }
 }
 
 pub fn try_parse_operator(chars: &mut CodeIter) -> Option<Operator> {
+    // `a?.5:b` is a conditional with `.5` as its consequent, not an optional
+    // chain.
+    if chars.peek() == Some('?')
+        && chars.peek_forward(1) == Some('.')
+        && chars.peek_forward(2).is_some_and(|c| c.is_ascii_digit())
+    {
+        chars.next();
+        return Some(Operator::new(OperatorType::Ternary));
+    }
+
     try_parse_from_prefix_lookup::<OperatorType>(chars).map(Operator::new)
 }
 
@@ -234,6 +266,13 @@ mod tests {
             ("=", OperatorType::Assignment),
             ("==", OperatorType::LooseEquality),
             ("===", OperatorType::StrictEquality),
+            ("=>", OperatorType::Arrow),
+            ("?.", OperatorType::OptionalChaining),
+            ("??=", OperatorType::NullishCoalescingAssignment),
+            ("%=", OperatorType::ModuloAssignment),
+            ("**", OperatorType::Exponentiation),
+            ("**=", OperatorType::ExponentiationAssignment),
+            (">>>=", OperatorType::ShiftRightUnsignedAssignment),
             ("&&", OperatorType::LogicalAnd),
             ("!==", OperatorType::StrictNotEquality),
             ("await", OperatorType::Await),
@@ -246,6 +285,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_optional_chaining_before_digit() {
+        let mut chars = "?.5".into_code_iterator("script.js".to_string());
+        let parsed = try_parse_operator(&mut chars).unwrap();
+        assert_eq!(parsed.kind, OperatorType::Ternary);
+        assert_eq!(chars.peek(), Some('.'));
+    }
+
     #[test]
     fn test_non_existent_operator() {
         let mut chars = "foo".into_code_iterator("script.js".to_string());
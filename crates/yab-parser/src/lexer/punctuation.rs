@@ -3,10 +3,12 @@ use super::{
     utils::{try_parse_from_prefix_lookup, HasPrefixLookup},
 };
 use serde::Serialize;
-use strum_macros::EnumString;
+use strum_macros::{Display, EnumString};
 use yab_parser_macros::HasPrefixLookup;
 
-#[derive(Debug, Serialize, PartialEq, EnumString, HasPrefixLookup)]
+use crate::location;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, EnumString, Display, HasPrefixLookup)]
 pub enum PunctuationType {
     #[token(lexeme = ";")]
     #[strum(serialize = ";")]
@@ -44,6 +46,10 @@ pub enum PunctuationType {
     #[strum(serialize = ".")]
     Dot,
 
+    #[token(lexeme = "...")]
+    #[strum(serialize = "...")]
+    Ellipsis,
+
     #[token(lexeme = ",")]
     #[strum(serialize = ",")]
     Comma,
@@ -52,11 +58,15 @@ pub enum PunctuationType {
 #[derive(Debug, Serialize, PartialEq)]
 pub struct Punctuation {
     pub kind: PunctuationType,
+    pub span: location::Span,
 }
 
 impl Punctuation {
     pub fn new(kind: PunctuationType) -> Self {
-        Self { kind }
+        Self {
+            kind,
+            span: location::Span::default(),
+        }
     }
 }
 
@@ -1,9 +1,15 @@
+use std::borrow::Cow;
+
 use miette::Result;
 use serde::Serialize;
 
+use crate::location;
+
 use super::{
     code_iter::{current_span_error, previous_span_error, CodeIter, Span},
-    punctuation::{Punctuation, PunctuationType},
+    ident::KeywordType,
+    operator::OperatorType,
+    punctuation::PunctuationType,
     utils::is_line_terminator,
     Token,
 };
@@ -13,28 +19,41 @@ use super::{
 /// pattern + the flags so that we can compile the literal into a function call
 /// later if we want.
 #[derive(Debug, Serialize, PartialEq)]
-pub struct RegexLiteral {
-    pattern: String,
-    flags: String,
+pub struct RegexLiteral<'src> {
+    pub pattern: Cow<'src, str>,
+    pub flags: Cow<'src, str>,
+    pub span: location::Span,
 }
 
-impl RegexLiteral {
-    pub fn new(pattern: String, flags: String) -> Self {
-        Self { pattern, flags }
+impl<'src> RegexLiteral<'src> {
+    pub fn new(pattern: impl Into<Cow<'src, str>>, flags: impl Into<Cow<'src, str>>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            flags: flags.into(),
+            span: location::Span::default(),
+        }
     }
 }
 
 /// Parses a regex pattern, assuming that the leading '/' has been consumed.
 /// Consumes the trailing '/' and returns the string in between as a pattern.
 /// Does not parse escape sequences, as the runtime RegEx engine will handle
-/// that.
-fn parse_regex_pattern(chars: &mut CodeIter) -> Result<String> {
+/// that, but a '/' that is escaped or inside a character class, as in
+/// `/\//` or `/[/]/`, doesn't end the pattern.
+///
+/// With `nested_classes`, a `[` in a class starts a nested class, as the set
+/// notation of the `v` flag has it (`/[\p{L}--[aeiou]]/v`), rather than being
+/// a literal `[`.
+fn parse_regex_pattern<'src>(
+    chars: &mut CodeIter<'src>,
+    nested_classes: bool,
+) -> Result<&'src str> {
     let start_pos = chars.current_position();
-    let mut lexeme = String::new();
+    let mut class_depth = 0usize;
+    let mut escaped = false;
 
-    for next_char in chars.by_ref() {
+    while let Some(next_char) = chars.next() {
         match next_char {
-            '/' => return Ok(lexeme),
             c if is_line_terminator(c) => {
                 return Err(previous_span_error!(
                     chars,
@@ -42,7 +61,14 @@ fn parse_regex_pattern(chars: &mut CodeIter) -> Result<String> {
                     "Unexpected line terminator while parsing regular expression",
                 ))
             }
-            c => lexeme.push(c),
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' if nested_classes || class_depth == 0 => class_depth += 1,
+            ']' => class_depth = class_depth.saturating_sub(1),
+            '/' if class_depth == 0 => {
+                return Ok(chars.slice(&start_pos, &chars.previous_position()))
+            }
+            _ => {}
         }
     }
 
@@ -53,17 +79,27 @@ fn parse_regex_pattern(chars: &mut CodeIter) -> Result<String> {
     ))
 }
 
-fn parse_regex_flags(chars: &mut CodeIter) -> Result<String> {
-    let mut lexeme = String::new();
+/// Parses the flags after a regex pattern.  Each flag can only be given once,
+/// and `u` and `v` can't be given together, since `v` is a stricter `u`.
+fn parse_regex_flags<'src>(chars: &mut CodeIter<'src>) -> Result<&'src str> {
+    let start_pos = chars.current_position();
 
     while let Some(next_char) = chars.peek() {
         match next_char {
-            'g' | 'i' | 'm' | 's' | 'u' | 'y' => {
-                lexeme.push(*next_char);
+            'd' | 'g' | 'i' | 'm' | 's' | 'u' | 'v' | 'y' => {
+                let flag_pos = chars.current_position();
                 _ = chars.next();
+                if chars.slice(&start_pos, &flag_pos).contains(next_char) {
+                    return Err(current_span_error!(
+                        chars,
+                        flag_pos,
+                        "Duplicate regular expression flag '{}'",
+                        next_char
+                    ));
+                }
             }
-            c if c.is_whitespace() => return Ok(lexeme),
-            ';' => return Ok(lexeme),
+            c if c.is_whitespace() => break,
+            ';' => break,
             c if c.is_alphabetic() => {
                 return Err(current_span_error!(
                     chars,
@@ -72,11 +108,172 @@ fn parse_regex_flags(chars: &mut CodeIter) -> Result<String> {
                     c
                 ))
             }
-            _c => return Ok(lexeme),
+            _ => break,
         }
     }
 
-    Ok(lexeme)
+    let flags = chars.slice_from(&start_pos);
+    if flags.contains('u') && flags.contains('v') {
+        return Err(current_span_error!(
+            chars,
+            start_pos,
+            "Regular expression flags 'u' and 'v' can't be used together",
+        ));
+    }
+
+    Ok(flags)
+}
+
+/// Determines whether a '/' following `previous_token` can begin a regex
+/// literal.  The lexer doesn't know where it is in the grammar, so instead we
+/// look at the previous significant (non-comment) token:
+///
+/// * A regex may start at the beginning of the input, after any operator
+///   except the postfix-capable `++` and `--`, after punctuation that opens or
+///   separates expressions (`(`, `[`, `{`, `}`, `,`, `;`, `:`), after a
+///   template literal `${`, and after any keyword other than `this` and
+///   `super` (e.g. `return /foo/`).
+///
+/// * `)` and `}` depend on what they close, which [`InputGoal`] keeps track of.
+///   This function gives the answer for `}` closing a block and `)` closing a
+///   call.
+///
+/// * Anything else (identifiers, literals, `this`, `super`, `)`, `]`, `.`,
+///   the end of a template literal, `++` and `--`) ends an expression, so the
+///   '/' must be a division operator.
+///
+/// Getting this wrong silently corrupts the rest of the token stream (e.g.
+/// `a / b / c` lexing as `a` followed by the regex `/ b /`), so any change here
+/// should come with an entry in the context table tests below.
+pub(super) fn regex_allowed_after(previous_token: Option<&Token>) -> bool {
+    match previous_token {
+        None => true,
+        Some(Token::Operator(operator)) => !matches!(
+            operator.kind,
+            OperatorType::Increment | OperatorType::Decrement
+        ),
+        Some(Token::Punctuation(punctuation)) => matches!(
+            punctuation.kind,
+            PunctuationType::OpenParen
+                | PunctuationType::OpenBracket
+                | PunctuationType::OpenBrace
+                | PunctuationType::CloseBrace
+                | PunctuationType::Comma
+                | PunctuationType::Semicolon
+                | PunctuationType::Colon
+        ),
+        // Contextual keywords are usually identifiers when they're followed
+        // by a `/`.
+        Some(Token::Keyword(keyword)) => {
+            !matches!(keyword.kind, KeywordType::This | KeywordType::Super)
+                && !keyword.kind.is_contextual()
+        }
+        Some(Token::TemplateLiteralExprOpen(_)) => true,
+        Some(_) => false,
+    }
+}
+
+/// The goal symbol the spec lexes with: whether a '/' starts a regex literal
+/// (InputElementRegExp) or is division (InputElementDiv).  Mostly that comes
+/// down to [`regex_allowed_after`] the previous token, but after `)` and `}` it
+/// depends on what the bracket closed:
+///
+/// * A `)` ending the head of an `if`, `while`, `for` or `with` is followed by
+///   a statement, as in `if (a) /b/.test(c)`, where after any other `)` an
+///   expression has just ended.
+///
+/// * A `}` closing a block is followed by a statement, where one closing an
+///   object literal ends an expression, as in `({}) / 2`.  A `{` opens a block
+///   where a statement starts, after `)`, `=>`, `else`, `do`, `try` and
+///   `finally`, and an object literal anywhere else.
+///
+/// In TypeScript, a `!` right after an expression is a non-null assertion, as
+/// in `a! / 2`, which ends the expression where a prefix `!` would start one.
+#[derive(Debug)]
+pub(super) struct InputGoal {
+    /// For each `(` and `{` that hasn't been closed yet, innermost last,
+    /// whether a regex can follow the bracket that closes it.
+    brackets: Vec<bool>,
+    regex_allowed: bool,
+    /// Whether the previous token was `if`, `while`, `for` or `with`.
+    after_statement_keyword: bool,
+    /// Whether a `{` would open a block here.
+    block_allowed: bool,
+    typescript: bool,
+}
+
+impl Default for InputGoal {
+    fn default() -> Self {
+        Self {
+            brackets: Vec::new(),
+            regex_allowed: true,
+            after_statement_keyword: false,
+            block_allowed: true,
+            typescript: false,
+        }
+    }
+}
+
+impl InputGoal {
+    pub(super) fn new(typescript: bool) -> Self {
+        Self {
+            typescript,
+            ..Self::default()
+        }
+    }
+
+    /// Whether a '/' after the tokens seen so far starts a regex literal.
+    pub(super) fn regex_allowed(&self) -> bool {
+        self.regex_allowed
+    }
+
+    /// Moves past `token`, which comments don't affect.
+    pub(super) fn advance(&mut self, token: &Token) {
+        let (regex_allowed, after_statement_keyword, block_allowed) = match token {
+            Token::Comment(_) => return,
+            Token::Punctuation(punctuation) => match punctuation.kind {
+                PunctuationType::OpenParen => {
+                    self.brackets.push(self.after_statement_keyword);
+                    (true, false, false)
+                }
+                PunctuationType::OpenBrace => {
+                    self.brackets.push(self.block_allowed);
+                    (true, false, true)
+                }
+                PunctuationType::CloseParen => (self.brackets.pop().unwrap_or(false), false, true),
+                PunctuationType::CloseBrace => {
+                    let closed_block = self.brackets.pop().unwrap_or(true);
+                    (closed_block, false, closed_block)
+                }
+                PunctuationType::Semicolon => (true, false, true),
+                _ => (regex_allowed_after(Some(token)), false, false),
+            },
+            Token::Operator(operator) if operator.kind == OperatorType::Arrow => {
+                (true, false, true)
+            }
+            Token::Operator(operator)
+                if operator.kind == OperatorType::LogicalNot
+                    && self.typescript
+                    && !self.regex_allowed =>
+            {
+                (false, false, false)
+            }
+            Token::Keyword(keyword) => match keyword.kind {
+                KeywordType::If | KeywordType::While | KeywordType::For | KeywordType::With => {
+                    (true, true, false)
+                }
+                KeywordType::Else | KeywordType::Do | KeywordType::Try | KeywordType::Finally => {
+                    (true, false, true)
+                }
+                _ => (regex_allowed_after(Some(token)), false, false),
+            },
+            _ => (regex_allowed_after(Some(token)), false, false),
+        };
+
+        self.regex_allowed = regex_allowed;
+        self.after_statement_keyword = after_statement_keyword;
+        self.block_allowed = block_allowed;
+    }
 }
 
 /// Attempts to parse a regex literal (e.g. "/foo/g").
@@ -85,61 +282,66 @@ fn parse_regex_flags(chars: &mut CodeIter) -> Result<String> {
 ///
 /// * `Ok(Some(RegexLiteral))` if a regex literal was parsed.
 ///
-/// * `Ok(None)` if the next characters are not a regex literal.
+/// * `Ok(None)` if the next characters are not a regex literal, either
+///   because they don't start with '/', or because `regex_allowed` is false,
+///   meaning the lexer is in a position where '/' means division.
 ///
 /// * `Err` if an error occurred while parsing (e.g. if an invalid character or
-/// escape is encountered).
+///   escape is encountered).
 ///
 /// Note: this function is fairly naive about the difference between regex
 /// literals and comments, (e.g. /{pattern/ vs "//"}), so it assumes that the
 /// lexer tries to parse comments higher up in the loop.
-pub fn try_parse_regex_literal(
-    chars: &mut CodeIter,
-    previous_token: Option<&Token>,
-) -> Result<Option<RegexLiteral>> {
-    // regex literals can only appear in expression contexts, so if the previous
-    // token was not a operator or punctuator we can safely assume that we must
-    // parse as division or something else.
+pub fn try_parse_regex_literal<'src>(
+    chars: &mut CodeIter<'src>,
+    regex_allowed: bool,
+) -> Result<Option<RegexLiteral<'src>>> {
+    if !regex_allowed {
+        return Ok(None);
+    }
 
-    match previous_token {
-        Some(Token::Operator(_))
-        | Some(Token::Punctuation(Punctuation {
-            kind: PunctuationType::Comma,
-            ..
-        }))
-        | Some(Token::Punctuation(Punctuation {
-            kind: PunctuationType::OpenParen,
-            ..
-        }))
-        | None => match chars.peek() {
-            Some('/') => {
-                _ = chars.next();
-                let pattern = parse_regex_pattern(chars)?;
-                let flags = parse_regex_flags(chars)?;
+    match chars.peek() {
+        Some('/') => {
+            _ = chars.next();
+            let pattern_start = chars.current_position();
 
-                Ok(Some(RegexLiteral { pattern, flags }))
+            // Whether classes nest depends on the flags, which come after the
+            // pattern, so read it as if they do first, and only keep that if
+            // the flags turn out to have `v`.
+            if let Ok(pattern) = parse_regex_pattern(chars, true) {
+                if let Ok(flags) = parse_regex_flags(chars) {
+                    if flags.contains('v') {
+                        return Ok(Some(RegexLiteral::new(pattern, flags)));
+                    }
+                }
             }
-            _ => Ok(None),
-        },
+            chars.rewind(pattern_start);
+
+            let pattern = parse_regex_pattern(chars, false)?;
+            let flags = parse_regex_flags(chars)?;
+
+            Ok(Some(RegexLiteral::new(pattern, flags)))
+        }
         _ => Ok(None),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::lexer::code_iter::IntoCodeIterator;
+    use crate::lexer::{code_iter::IntoCodeIterator, tokenize};
 
     use super::*;
 
     #[test]
     fn test_try_parse_regex_literal() {
         let mut chars = "/foo/g".into_code_iterator("script.js".to_string());
-        let result = try_parse_regex_literal(&mut chars, None).unwrap().unwrap();
+        let result = try_parse_regex_literal(&mut chars, true).unwrap().unwrap();
         assert_eq!(
             result,
             RegexLiteral {
-                pattern: "foo".to_string(),
-                flags: "g".to_string(),
+                pattern: "foo".into(),
+                flags: "g".into(),
+                span: location::Span::default()
             }
         );
     }
@@ -147,12 +349,13 @@ mod tests {
     #[test]
     fn test_regex_without_flags() {
         let mut chars = "/foo/".into_code_iterator("script.js".to_string());
-        let result = try_parse_regex_literal(&mut chars, None).unwrap().unwrap();
+        let result = try_parse_regex_literal(&mut chars, true).unwrap().unwrap();
         assert_eq!(
             result,
             RegexLiteral {
-                pattern: "foo".to_string(),
-                flags: "".to_string(),
+                pattern: "foo".into(),
+                flags: "".into(),
+                span: location::Span::default()
             }
         );
     }
@@ -160,7 +363,7 @@ mod tests {
     #[test]
     fn test_regex_with_invalid_flags() {
         let mut chars = "/foo/z".into_code_iterator("script.js".to_string());
-        let result = try_parse_regex_literal(&mut chars, None);
+        let result = try_parse_regex_literal(&mut chars, true);
 
         assert!(result
             .unwrap_err()
@@ -171,7 +374,7 @@ mod tests {
     #[test]
     fn test_regex_with_unexpected_line_break() {
         let mut chars = "/foo\n/z".into_code_iterator("script.js".to_string());
-        let result = try_parse_regex_literal(&mut chars, None);
+        let result = try_parse_regex_literal(&mut chars, true);
 
         assert!(result
             .unwrap_err()
@@ -179,17 +382,163 @@ mod tests {
             .contains("Unexpected line terminator while parsing regular expression"));
     }
 
+    #[test]
+    fn test_slashes_that_do_not_end_the_pattern() {
+        for (src, pattern) in [
+            (r"/[a/b]/g", "[a/b]"),
+            (r"/\/foo/", r"\/foo"),
+            (r"/[\]/]/", r"[\]/]"),
+            (r"/\[/", r"\["),
+            (r"/[[]/", r"[[]"),
+            (r"/[[a]/]/g", r"[[a]"),
+            (r"/[\p{L}--[aeiou]]/v", r"[\p{L}--[aeiou]]"),
+            (r"/[[a]/]/v", r"[[a]/]"),
+            (r"/[[a/b]&&[/]]/gv", r"[[a/b]&&[/]]"),
+        ] {
+            let mut chars = src.into_code_iterator("script.js".to_string());
+            let result = try_parse_regex_literal(&mut chars, true).unwrap().unwrap();
+            assert_eq!(result.pattern, pattern, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_regex_flag_validation() {
+        let mut chars = "/a/dv".into_code_iterator("script.js".to_string());
+        let result = try_parse_regex_literal(&mut chars, true).unwrap().unwrap();
+        assert_eq!(result.flags, "dv");
+
+        for (src, error) in [
+            ("/a/gig", "Duplicate regular expression flag 'g'"),
+            (
+                "/a/uv",
+                "Regular expression flags 'u' and 'v' can't be used together",
+            ),
+        ] {
+            let mut chars = src.into_code_iterator("script.js".to_string());
+            let result = try_parse_regex_literal(&mut chars, true);
+            assert!(result.unwrap_err().to_string().contains(error), "{}", src);
+        }
+    }
+
     #[test]
     fn test_regex_flags_do_not_eat_next_chars() {
         let mut chars = "/foo/g.".into_code_iterator("script.js".to_string());
-        let result = try_parse_regex_literal(&mut chars, None).unwrap().unwrap();
+        let result = try_parse_regex_literal(&mut chars, true).unwrap().unwrap();
         assert_eq!(
             result,
             RegexLiteral {
-                pattern: "foo".to_string(),
-                flags: "g".to_string(),
+                pattern: "foo".into(),
+                flags: "g".into(),
+                span: location::Span::default()
             }
         );
         assert_eq!(chars.next(), Some('.'));
     }
+
+    fn count_regexes(src: &str) -> usize {
+        tokenize(src, "script.js")
+            .unwrap()
+            .iter()
+            .filter(|t| matches!(t, Token::RegexLiteral(_)))
+            .count()
+    }
+
+    #[test]
+    fn test_regex_context_table() {
+        // Each entry is the code preceding "/a/g", and whether "/a/g" should be
+        // lexed as a regex literal (true) or as `/ a / g` (false).
+        let contexts = vec![
+            // start of input, punctuation
+            ("", true),
+            ("(", true),
+            ("[", true),
+            ("{", true),
+            ("}", true),
+            ("{ a }", true),
+            ("function f() {}", true),
+            ("if (a) {} else {}", true),
+            ("x = {}", false),
+            ("({ a: {} })", false),
+            ("`${ {} }`", false),
+            (",", true),
+            (";", true),
+            ("x ? y :", true),
+            (")", false),
+            ("f(a)", false),
+            ("(a)", false),
+            ("if (a)", true),
+            ("while (f(a))", true),
+            ("for (;;)", true),
+            ("]", false),
+            ("a.b", false),
+            // operators
+            ("=", true),
+            ("+=", true),
+            ("==", true),
+            ("!", true),
+            ("&&", true),
+            ("||", true),
+            ("??", true),
+            ("?", true),
+            ("+", true),
+            ("-", true),
+            ("*", true),
+            ("%", true),
+            ("<", true),
+            (">=", true),
+            ("&", true),
+            ("|", true),
+            ("^", true),
+            ("~", true),
+            ("a++", false),
+            ("a--", false),
+            // keywords and keyword-like operators
+            ("return", true),
+            ("typeof", true),
+            ("void", true),
+            ("await", true),
+            ("yield", true),
+            ("a in", true),
+            ("a instanceof", true),
+            ("case", true),
+            ("this", false),
+            ("super", false),
+            ("of", false),
+            // identifiers and literals
+            ("a", false),
+            ("$", false),
+            ("1", false),
+            ("0xFF", false),
+            ("'s'", false),
+            ("\"s\"", false),
+            ("`t`", false),
+            ("/re/", false),
+            ("true", false),
+            ("null", false),
+        ];
+
+        for (prefix, expects_regex) in contexts {
+            let src = format!("{} /a/g", prefix);
+            assert_eq!(
+                count_regexes(&src) > count_regexes(prefix),
+                expects_regex,
+                "expected regex = {} after '{}'",
+                expects_regex,
+                prefix
+            );
+        }
+    }
+
+    #[test]
+    fn test_regex_context_in_template_expression() {
+        assert_eq!(count_regexes("`${ /a/g }`"), 1);
+        assert_eq!(count_regexes("`${ b /a/g }`"), 0);
+    }
+
+    #[test]
+    fn test_regex_context_skips_comments() {
+        assert_eq!(count_regexes("return /* comment */ /a/g"), 1);
+        assert_eq!(count_regexes("return // comment\n /a/g"), 1);
+        assert_eq!(count_regexes("a /* comment */ /a/g"), 0);
+    }
 }
@@ -1,36 +1,58 @@
+use std::borrow::Cow;
+
 use miette::Result;
 use serde::Serialize;
 
-use crate::lexer::code_iter::Span;
+use crate::{lexer::code_iter::Span, location};
 
 use super::{
     code_iter::{previous_span_error, CodeIter},
-    escape_chars::try_parse_escape,
+    escape_chars::try_parse_string_escape,
 };
 
 /// Represents a string literal token, with delimiters stripped.
 #[derive(Debug, Serialize, PartialEq)]
-pub struct StringLiteral {
-    lexeme: String,
+pub struct StringLiteral<'src> {
+    pub lexeme: Cow<'src, str>,
+    /// Whether the string has a legacy octal escape like `\01`, or a `\8` or
+    /// `\9`, which strict mode forbids.
+    pub has_octal_escape: bool,
+    /// The UTF-16 code units of the string, if it has a lone surrogate like
+    /// `"\uD800"`, which `lexeme` has a U+FFFD in place of.
+    pub code_units: Option<Vec<u16>>,
+    pub span: location::Span,
 }
 
-impl StringLiteral {
+impl<'src> StringLiteral<'src> {
     /// Creates a new empty string literal.
-    pub fn new(lexeme: String) -> Self {
-        Self { lexeme }
+    pub fn new(lexeme: impl Into<Cow<'src, str>>) -> Self {
+        Self {
+            lexeme: lexeme.into(),
+            has_octal_escape: false,
+            code_units: None,
+            span: location::Span::default(),
+        }
     }
 }
 
-impl From<String> for StringLiteral {
+impl From<String> for StringLiteral<'_> {
     fn from(value: String) -> Self {
-        Self { lexeme: value }
+        Self {
+            lexeme: Cow::Owned(value),
+            has_octal_escape: false,
+            code_units: None,
+            span: location::Span::default(),
+        }
     }
 }
 
-impl From<&str> for StringLiteral {
-    fn from(value: &str) -> Self {
+impl<'src> From<&'src str> for StringLiteral<'src> {
+    fn from(value: &'src str) -> Self {
         Self {
-            lexeme: value.to_string(),
+            lexeme: Cow::Borrowed(value),
+            has_octal_escape: false,
+            code_units: None,
+            span: location::Span::default(),
         }
     }
 }
@@ -40,21 +62,22 @@ impl From<&str> for StringLiteral {
 /// Returns:
 ///
 /// * `Ok(Some(StringLiteral))` if a string was parsed.  The iterator will have
-/// been advanced to the end of the string (including the delimter).
+///   been advanced to the end of the string (including the delimter).
 ///
 /// * `Ok(None)` if no string was parsed.  The iterator will be unchanged.
 ///
 /// * `Err` if an error occurred while parsing the string (e.g. an invalid
-/// escape character or unexpected EOF).
-pub fn try_parse_string(chars: &mut CodeIter) -> Result<Option<StringLiteral>> {
-    let mut lexeme = String::new();
+///   escape character or unexpected EOF).
+pub fn try_parse_string<'src>(chars: &mut CodeIter<'src>) -> Result<Option<StringLiteral<'src>>> {
     let start_pos = chars.current_position();
 
     let delimeter = match chars.peek() {
         Some('\'') | Some('"') => chars.next().unwrap(),
         _ => return Ok(None),
     };
+    let mut lexeme = chars.start_lexeme();
 
+    let mut has_octal_escape = false;
     let mut found_end = false;
     'string: while let Some(next_char) = chars.next() {
         if next_char == delimeter {
@@ -71,9 +94,13 @@ pub fn try_parse_string(chars: &mut CodeIter) -> Result<Option<StringLiteral>> {
         }
 
         if next_char == '\\' {
-            if let Some(escaped_char) = try_parse_escape(chars)? {
-                lexeme.push(escaped_char);
-            }
+            // `\0` on its own is a null character rather than an octal escape.
+            has_octal_escape |= match chars.peek() {
+                Some('0') => chars.peek_forward(1).is_some_and(|c| c.is_ascii_digit()),
+                Some(c) => c.is_ascii_digit(),
+                None => false,
+            };
+            lexeme.push_escaped(try_parse_string_escape(chars)?);
         } else {
             lexeme.push(next_char);
         }
@@ -87,7 +114,11 @@ pub fn try_parse_string(chars: &mut CodeIter) -> Result<Option<StringLiteral>> {
         ));
     }
 
-    Ok(Some(lexeme.into()))
+    let (lexeme, code_units) = lexeme.finish_with_utf16();
+    let mut string = StringLiteral::new(lexeme);
+    string.has_octal_escape = has_octal_escape;
+    string.code_units = code_units;
+    Ok(Some(string))
 }
 
 #[cfg(test)]
@@ -198,4 +229,23 @@ mod tests {
             "hello world".into()
         );
     }
+
+    #[test]
+    fn test_surrogate_escapes() {
+        let src = r#""\uD83D\uDE00 \uD83D\u{DE00}""#;
+        let mut chars = src.into_code_iterator("script.js".to_string());
+        assert_eq!(
+            try_parse_string(&mut chars).unwrap().unwrap(),
+            "😀 😀".into()
+        );
+
+        let src = r#""a\uD800b\uDE00\uD83D""#;
+        let mut chars = src.into_code_iterator("script.js".to_string());
+        let string = try_parse_string(&mut chars).unwrap().unwrap();
+        assert_eq!(string.lexeme, "a\u{FFFD}b\u{FFFD}\u{FFFD}");
+        assert_eq!(
+            string.code_units.unwrap(),
+            [0x61, 0xD800, 0x62, 0xDE00, 0xD83D]
+        );
+    }
 }
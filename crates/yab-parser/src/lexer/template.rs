@@ -1,9 +1,13 @@
+use std::borrow::Cow;
+
 use miette::Result;
 use serde::Serialize;
 
+use crate::location;
+
 use super::{
-    code_iter::{previous_span_error, CodeIter, Span},
-    escape_chars::try_parse_escape,
+    code_iter::{previous_span_error, CodeIter, Lexeme, Position, Span},
+    escape_chars::{try_parse_string_escape, EscapedChar},
 };
 
 // Save allocating a string when we know the lexeme value already.
@@ -11,32 +15,52 @@ static TEMPLATE_LITERAL_EXPR_CLOSE: &str = "}";
 static TEMPLATE_LITERAL_EXPR_OPEN: &str = "${";
 
 #[derive(Debug, PartialEq, Serialize)]
-pub struct TemplateLiteralString {
-    lexeme: String,
+pub struct TemplateLiteralString<'src> {
+    /// The text with its escape sequences interpreted, or `None` if it has one
+    /// that isn't valid, which only tagged templates allow.
+    pub cooked: Option<Cow<'src, str>>,
+    /// The UTF-16 code units of the cooked text, if it has a lone surrogate,
+    /// which `cooked` has a U+FFFD in place of.
+    pub cooked_code_units: Option<Vec<u16>>,
+    /// The text as written, except that line terminators are all `\n`.
+    pub raw: Cow<'src, str>,
     /// Whether the string is complete (reached a "`" or not).
-    complete: bool,
+    pub complete: bool,
+    pub span: location::Span,
 }
 
-impl TemplateLiteralString {
-    pub fn new(lexeme: String, complete: bool) -> Self {
-        Self { lexeme, complete }
+impl<'src> TemplateLiteralString<'src> {
+    /// Creates a template string without escape sequences, so that its raw
+    /// and cooked text are the same.
+    pub fn new(text: impl Into<Cow<'src, str>>, complete: bool) -> Self {
+        let raw = text.into();
+        Self {
+            cooked: Some(raw.clone()),
+            cooked_code_units: None,
+            raw,
+            complete,
+            span: location::Span::default(),
+        }
     }
 }
 
 #[derive(Debug, PartialEq, Serialize)]
 pub struct TemplateLiteralExprOpen {
     lexeme: &'static str,
+    pub span: location::Span,
 }
 
 #[derive(Debug, PartialEq, Serialize)]
 pub struct TemplateLiteralExprClose {
     lexeme: &'static str,
+    pub span: location::Span,
 }
 
 impl Default for TemplateLiteralExprOpen {
     fn default() -> Self {
         Self {
             lexeme: TEMPLATE_LITERAL_EXPR_OPEN,
+            span: location::Span::default(),
         }
     }
 }
@@ -45,6 +69,7 @@ impl Default for TemplateLiteralExprClose {
     fn default() -> Self {
         Self {
             lexeme: TEMPLATE_LITERAL_EXPR_CLOSE,
+            span: location::Span::default(),
         }
     }
 }
@@ -55,12 +80,12 @@ impl Default for TemplateLiteralExprClose {
 ///
 /// Return types have the same semantics as `try_parse_template_literal_start`
 /// et. al.
-pub fn try_parse_template_literal_expr_end(
-    chars: &mut CodeIter,
+pub fn try_parse_template_literal_expr_end<'src>(
+    chars: &mut CodeIter<'src>,
 ) -> Result<
     Option<(
         TemplateLiteralExprClose,
-        TemplateLiteralString,
+        TemplateLiteralString<'src>,
         Option<TemplateLiteralExprOpen>,
     )>,
 > {
@@ -85,41 +110,50 @@ pub fn try_parse_template_literal_expr_end(
 /// Returns:
 ///
 /// * `Ok((TemplateLiteralString, TemplateLiteralExprOpen))` if the next
-/// part of the template ends in expression opener.
+///   part of the template ends in expression opener.
 ///
 /// * `Ok((TemplateLiteralString, None))` if the next part of the template
-/// concludes the template literal.  The lexer is expected to pop off the
-/// template context stack when the end of a template literal is reached, so
-/// that the semantic meaning of '}' is altered.
+///   concludes the template literal.  The lexer is expected to pop off the
+///   template context stack when the end of a template literal is reached, so
+///   that the semantic meaning of '}' is altered.
 ///
 /// * `Err` if the next part of the template literal could not be parsed (e.g.
-/// because of an invalid escape sequence).
-pub fn parse_template_literal_string(
-    chars: &mut CodeIter,
-) -> Result<(TemplateLiteralString, Option<TemplateLiteralExprOpen>)> {
+///   because of an invalid escape sequence).
+pub fn parse_template_literal_string<'src>(
+    chars: &mut CodeIter<'src>,
+) -> Result<(TemplateLiteralString<'src>, Option<TemplateLiteralExprOpen>)> {
     let start_pos = chars.current_position();
-    let mut lexeme = String::new();
+    let mut cooked = Some(chars.start_lexeme());
 
     while let Some(next_char) = chars.next() {
         match next_char {
-            '`' => return Ok((TemplateLiteralString::new(lexeme, true), None)),
-            '$' => match chars.peek() {
-                Some('{') => {
-                    _ = chars.next();
-                    return Ok((
-                        TemplateLiteralString::new(lexeme, false),
-                        Some(TemplateLiteralExprOpen::default()),
-                    ));
-                }
-                _ => lexeme.push('$'),
-            },
+            '`' => return Ok((template_string(chars, &start_pos, cooked, true), None)),
+            '$' if chars.peek() == Some('{') => {
+                let string = template_string(chars, &start_pos, cooked, false);
+                _ = chars.next();
+                return Ok((string, Some(TemplateLiteralExprOpen::default())));
+            }
             '\\' => {
-                // parse escape sequence
-                if let Some(escaped_char) = try_parse_escape(chars)? {
-                    lexeme.push(escaped_char);
+                let escape_start = chars.current_position();
+                match parse_template_escape(chars) {
+                    Some(escaped) => {
+                        if let Some(cooked) = &mut cooked {
+                            cooked.push_escaped(escaped);
+                        }
+                    }
+                    // What follows the backslash is just text, as far as the
+                    // raw string is concerned.
+                    None => {
+                        chars.rewind(escape_start);
+                        cooked = None;
+                    }
+                }
+            }
+            c => {
+                if let Some(cooked) = &mut cooked {
+                    cooked.push(c);
                 }
             }
-            c => lexeme.push(c),
         }
     }
 
@@ -130,28 +164,74 @@ pub fn parse_template_literal_string(
     ))
 }
 
+/// Parses an escape sequence in a template, assuming that the leading backslash
+/// has been consumed, or returns `None` if it isn't valid.  Templates don't
+/// have the legacy octal escapes that strings do, or `\8` and `\9`, so no
+/// digit but a lone `0` can start one.
+fn parse_template_escape(chars: &mut CodeIter) -> Option<Option<EscapedChar>> {
+    match chars.peek() {
+        Some('0') if !chars.peek_forward(1).is_some_and(|c| c.is_ascii_digit()) => {}
+        Some(c) if c.is_ascii_digit() => return None,
+        _ => {}
+    }
+
+    try_parse_string_escape(chars).ok()
+}
+
+/// Builds the string part of a template that started at `start`, where the
+/// last character consumed is the "`" or "$" that ended it.
+fn template_string<'src>(
+    chars: &CodeIter<'src>,
+    start: &Position,
+    cooked: Option<Lexeme<'src>>,
+    complete: bool,
+) -> TemplateLiteralString<'src> {
+    let raw = chars.slice(start, &chars.previous_position());
+    let (cooked, cooked_code_units) = match cooked.map(Lexeme::finish_with_utf16) {
+        Some((cooked, code_units)) => (Some(cooked), code_units),
+        None => (None, None),
+    };
+    TemplateLiteralString {
+        cooked,
+        cooked_code_units,
+        raw: normalize_line_terminators(raw),
+        complete,
+        span: location::Span::default(),
+    }
+}
+
+/// Raw template text counts `\r\n` and `\r` as `\n`, so that it doesn't depend
+/// on the line endings of the file it came from.
+fn normalize_line_terminators(raw: &str) -> Cow<'_, str> {
+    if raw.contains('\r') {
+        Cow::Owned(raw.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(raw)
+    }
+}
+
 /// Attempts to parse the start of a template literal from the top-level of the
 /// lexer loop.
 ///
 /// Returns:
 ///
 /// * `Ok(Some((TemplateLiteralString, TemplateLiteralExprOpen)))` if the next
-/// token is a template literal that ends in an expression opener (`${`).  The
-/// lexer is expected to push onto a stack and then begin tokenzing the
-/// expression.  The stack is used to determine whether the next '}' encountered
-/// is to be interpreted as an template literal expression close token, or as a
-/// punctuation token.
+///   token is a template literal that ends in an expression opener (`${`).  The
+///   lexer is expected to push onto a stack and then begin tokenzing the
+///   expression.  The stack is used to determine whether the next '}' encountered
+///   is to be interpreted as an template literal expression close token, or as a
+///   punctuation token.
 ///
 /// * `Ok(Some((TemplateLiteralString, None)))` if the next token is a template
-/// literal string which is already closed, e.g. `hi there`
+///   literal string which is already closed, e.g. `hi there`
 ///
 /// * `Ok(None)` if the next token is not a template literal at all.
 ///
 /// * `Err` if the next token is a template literal but it could not be parsed
-/// (e.g. due to an invalid escape sequence).
-pub fn try_parse_template_literal_start(
-    chars: &mut CodeIter,
-) -> Result<Option<(TemplateLiteralString, Option<TemplateLiteralExprOpen>)>> {
+///   (e.g. due to an invalid escape sequence).
+pub fn try_parse_template_literal_start<'src>(
+    chars: &mut CodeIter<'src>,
+) -> Result<Option<(TemplateLiteralString<'src>, Option<TemplateLiteralExprOpen>)>> {
     match chars.peek() {
         Some('`') => {
             _ = chars.next();
@@ -215,13 +295,46 @@ mod tests {
         let src = r#"`hi ther\u0065!`"#;
         let chars = &mut src.into_code_iterator("script.js".to_string());
 
-        assert_eq!(
-            try_parse_template_literal_start(chars).unwrap().unwrap(),
-            (
-                TemplateLiteralString::new("hi there!".to_string(), true),
-                None
-            )
-        )
+        let (string, _) = try_parse_template_literal_start(chars).unwrap().unwrap();
+        assert_eq!(string.cooked.as_deref(), Some("hi there!"));
+        assert_eq!(string.raw, r"hi ther\u0065!");
+    }
+
+    #[test]
+    fn test_lone_surrogate_escapes() {
+        let src = r#"`\uD83D\uDE00\uD800`"#;
+        let chars = &mut src.into_code_iterator("script.js".to_string());
+
+        let (string, _) = try_parse_template_literal_start(chars).unwrap().unwrap();
+        assert_eq!(string.cooked.as_deref(), Some("😀\u{FFFD}"));
+        assert_eq!(string.cooked_code_units, Some(vec![0xD83D, 0xDE00, 0xD800]));
+        assert_eq!(string.raw, r"\uD83D\uDE00\uD800");
+    }
+
+    #[test]
+    fn test_invalid_escape_sequences_have_no_cooked_text() {
+        for (src, raw) in [
+            (r"`\unicode and \u{55}`", r"\unicode and \u{55}"),
+            (r"`\x`", r"\x"),
+            (r"`\01 ${", r"\01 "),
+            (r"`\9`", r"\9"),
+        ] {
+            let chars = &mut src.into_code_iterator("script.js".to_string());
+            let (string, _) = try_parse_template_literal_start(chars).unwrap().unwrap();
+            assert_eq!(string.cooked, None, "{}", src);
+            assert_eq!(string.raw, raw, "{}", src);
+        }
+
+        let chars = &mut r"`\0`".into_code_iterator("script.js".to_string());
+        let (string, _) = try_parse_template_literal_start(chars).unwrap().unwrap();
+        assert_eq!(string.cooked.as_deref(), Some("\0"));
+    }
+
+    #[test]
+    fn test_raw_text_normalizes_line_terminators() {
+        let chars = &mut "`a\r\nb\rc`".into_code_iterator("script.js".to_string());
+        let (string, _) = try_parse_template_literal_start(chars).unwrap().unwrap();
+        assert_eq!(string.raw, "a\nb\nc");
     }
 
     #[test]
@@ -0,0 +1,157 @@
+use miette::Result;
+
+use super::{Comment, Lexer, LexerOptions, Token, TokenStream};
+
+/// Which token a comment belongs to, for re-emitting it next to that token
+/// when the tokens are moved around.  Indices are into
+/// [`TokensWithTrivia::tokens`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentAttachment {
+    /// The comment comes before the token, with nothing but comments and
+    /// whitespace between them.
+    Leading(usize),
+    /// The comment comes after the token on the same line, or after the last
+    /// token in the file.
+    Trailing(usize),
+    /// The file has no tokens for the comment to belong to.
+    Detached,
+}
+
+/// The tokens of a source file with its comments set aside, along with which
+/// token each comment belongs to.
+#[derive(Debug, Default)]
+pub struct TokensWithTrivia<'src> {
+    /// Every token but the comments.  A line break in or around a comment
+    /// still counts as one before the token after it.
+    pub tokens: TokenStream<'src>,
+    /// The comments, in the order they appear in the source.
+    pub comments: Vec<Comment<'src>>,
+    /// Where each comment in `comments` belongs, at the same index.
+    pub attachments: Vec<CommentAttachment>,
+}
+
+impl<'src> TokensWithTrivia<'src> {
+    /// The comments before the token at `index`, in source order.
+    pub fn leading_comments(&self, index: usize) -> impl Iterator<Item = &Comment<'src>> {
+        self.attached(CommentAttachment::Leading(index))
+    }
+
+    /// The comments after the token at `index`, in source order.
+    pub fn trailing_comments(&self, index: usize) -> impl Iterator<Item = &Comment<'src>> {
+        self.attached(CommentAttachment::Trailing(index))
+    }
+
+    fn attached(&self, attachment: CommentAttachment) -> impl Iterator<Item = &Comment<'src>> {
+        self.comments
+            .iter()
+            .zip(&self.attachments)
+            .filter(move |(_, attached)| **attached == attachment)
+            .map(|(comment, _)| comment)
+    }
+}
+
+/// Like [`super::tokenize_stream_with_options`], but collects the comments to
+/// a side table instead of leaving them among the tokens.
+///
+/// A comment trails the token before it when it starts on the same line as
+/// that token ends, and leads the next token otherwise.  Comments after the
+/// last token trail it.
+pub fn tokenize_with_trivia(
+    src: &str,
+    file_name: impl Into<String>,
+    options: LexerOptions,
+) -> Result<TokensWithTrivia<'_>> {
+    let mut lexer = Lexer::with_options(src, file_name, options);
+    let mut trivia = TokensWithTrivia::default();
+    trivia.tokens.source = src;
+    // Comments waiting for the next token, which they lead.
+    let mut leading = Vec::new();
+
+    while let Some(lexed) = lexer.next_with_line_break() {
+        let (token, line_break) = lexed?;
+        let token_count = trivia.tokens.tokens.len();
+        match token {
+            Token::Comment(comment) => {
+                let attachment = match token_count.checked_sub(1) {
+                    Some(previous) if !line_break && leading.is_empty() => {
+                        CommentAttachment::Trailing(previous)
+                    }
+                    _ => {
+                        leading.push(trivia.comments.len());
+                        CommentAttachment::Detached
+                    }
+                };
+                trivia.comments.push(comment);
+                trivia.attachments.push(attachment);
+            }
+            token => {
+                for comment in leading.drain(..) {
+                    trivia.attachments[comment] = CommentAttachment::Leading(token_count);
+                }
+                trivia.tokens.tokens.push(token);
+                trivia.tokens.line_breaks.push(line_break);
+            }
+        }
+    }
+
+    if let Some(last) = trivia.tokens.tokens.len().checked_sub(1) {
+        for comment in leading {
+            trivia.attachments[comment] = CommentAttachment::Trailing(last);
+        }
+    }
+
+    Ok(trivia)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::CommentType;
+
+    fn comment_text<'a>(comments: impl Iterator<Item = &'a Comment<'a>>) -> Vec<&'a str> {
+        comments
+            .map(|comment| match &comment.value {
+                CommentType::Block(text)
+                | CommentType::Line(text)
+                | CommentType::Hashbang(text) => text.as_ref(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_comments_attach_to_tokens() {
+        let src = "// a\n/* b */ x; // c\n// d\ny /* e */\n// f\n";
+        let trivia = tokenize_with_trivia(src, "test.js", LexerOptions::default()).unwrap();
+
+        assert_eq!(trivia.tokens.tokens.len(), 3);
+        assert_eq!(trivia.tokens.line_breaks, [true, false, true]);
+        assert_eq!(
+            trivia.attachments,
+            [
+                CommentAttachment::Leading(0),
+                CommentAttachment::Leading(0),
+                CommentAttachment::Trailing(1),
+                CommentAttachment::Leading(2),
+                CommentAttachment::Trailing(2),
+                CommentAttachment::Trailing(2),
+            ]
+        );
+        assert_eq!(comment_text(trivia.leading_comments(0)), [" a", " b "]);
+        assert_eq!(comment_text(trivia.trailing_comments(2)), [" e ", " f"]);
+    }
+
+    #[test]
+    fn test_comments_keep_their_spans() {
+        let src = "x; /* é */";
+        let trivia = tokenize_with_trivia(src, "test.js", LexerOptions::default()).unwrap();
+        let span = trivia.comments[0].span;
+        assert_eq!(&src[span.start.index..span.end.index], "/* é */");
+    }
+
+    #[test]
+    fn test_comments_without_tokens() {
+        let trivia = tokenize_with_trivia("/* a */", "test.js", LexerOptions::default()).unwrap();
+        assert!(trivia.tokens.tokens.is_empty());
+        assert_eq!(trivia.attachments, [CommentAttachment::Detached]);
+    }
+}
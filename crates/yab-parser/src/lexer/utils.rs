@@ -8,6 +8,41 @@ pub fn is_line_terminator(c: char) -> bool {
     c == '\n' || c == '\r' || c == '\u{2028}' || c == '\u{2029}'
 }
 
+/// Predicate to check if a character is whitespace, which includes the line
+/// terminators.  The byte order mark (U+FEFF) counts as whitespace too, though
+/// Rust's definition leaves it out.
+///
+/// See: https://tc39.es/ecma262/#sec-white-space
+pub fn is_whitespace(c: char) -> bool {
+    c.is_whitespace() || c == '\u{feff}'
+}
+
+/// Predicate to check if a character is a control character that may not
+/// appear in source text outside of the contents of strings, templates,
+/// regular expressions, and comments.  Tab, vertical tab, form feed, and the
+/// line terminators are control characters too, but the spec gives them a
+/// meaning as whitespace, so they are allowed.
+///
+/// See: https://tc39.es/ecma262/#sec-white-space
+pub fn is_disallowed_control_character(c: char) -> bool {
+    c.is_control() && !matches!(c, '\t' | '\u{000B}' | '\u{000C}' | '\n' | '\r')
+}
+
+/// Predicate to check if a character can begin an identifier.
+///
+/// See: https://tc39.es/ecma262/#prod-IdentifierStartChar
+pub fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+/// Predicate to check if a character can appear in an identifier after the
+/// first character.
+///
+/// See: https://tc39.es/ecma262/#prod-IdentifierPartChar
+pub fn is_identifier_part(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
 /// Trait that can be implemented by operators and punctuators to look up how
 /// many members of the enum have lexemes that start with a particular prefix.
 /// This allows us to cheaply query in the tokenizer whether a given sequence
@@ -16,74 +51,62 @@ pub trait HasPrefixLookup {
     fn fields_starting_with(ident: &str) -> usize;
 }
 
+/// Parses the longest lexeme of `T` at the front of the iterator, if there is
+/// one.  Candidate lexemes are grown one character at a time for as long as
+/// some member of `T` starts with them, without consuming anything, and then
+/// only the characters of the longest candidate that was a whole lexeme are
+/// consumed.  That way a prefix that isn't a lexeme itself (like `..` on the
+/// way to `...`) just falls back to the longest one that is.
 pub fn try_parse_from_prefix_lookup<T>(chars: &mut CodeIter) -> Option<T>
 where
     for<'a> T: HasPrefixLookup + TryFrom<&'a str>,
-    for<'a> <T as TryFrom<&'a str>>::Error: core::fmt::Debug,
 {
-    match chars.peek() {
-        Some(c) => {
-            // While the iterator is still at {c}, create a potential lexeme out
-            // of the character without consuming it.
-            let mut prefix_lexeme = String::from(*c);
-            let prefix_matches = T::fields_starting_with(&prefix_lexeme);
-
-            if prefix_matches > 0 {
-                // If we have at least one match, then we are safe to progress
-                // and consume the character we just used on the lexeme.
-                // Note: it's only "safe" if there is an actual operator for
-                // every prefix, which I _think_ is true off the top of my head,
-                // but might not be.  e.g. !== falls back to !.
-                _ = chars.next();
-
-                // While we can continue getting characters, check if adding the
-                // next character would still give us a valid operator.
-                'prefix: while let Some(next_char) = chars.peek() {
-                    // We might strip this off later, but tentatively push it
-                    // onto the lexeme:
-                    prefix_lexeme.push(*next_char);
-                    let prefix_matches = T::fields_starting_with(&prefix_lexeme);
-
-                    // If we went from > 0 to 0, then we've gone one character
-                    // too far, so strip off the character we just added and
-                    // return.
-                    if prefix_matches == 0 {
-                        prefix_lexeme = prefix_lexeme[..prefix_lexeme.len() - 1].to_string();
-                        break 'prefix;
-                    } else {
-                        // Otherwise the character is valid, so we can safely
-                        // keep it in the lexeme and consume it for the next
-                        // iteration.
-                        _ = chars.next();
-                        continue 'prefix;
-                    }
-                }
-
-                // We've broke out of the loop, either because we've run out of
-                // characters altogether, or because we've found the longest
-                // operator.
-                let prefix_ref = prefix_lexeme.as_str();
-                let operator_type = T::try_from(prefix_ref).unwrap_or_else(|e| {
-                    panic!("Failed to parse operator: {}: {:?}", prefix_ref, e)
-                });
-                return Some(operator_type);
-            }
-
-            // If prefix matches == 0 from the very first character, don't
-            // consume anything because we don't have a valid operator, and
-            // return
-            None
+    let mut prefix_lexeme = String::new();
+    let mut longest_match = None;
+
+    while let Some(c) = chars.peek_forward(prefix_lexeme.chars().count()) {
+        prefix_lexeme.push(c);
+        if T::fields_starting_with(&prefix_lexeme) == 0 {
+            break;
+        }
+
+        if let Ok(lexeme_type) = T::try_from(prefix_lexeme.as_str()) {
+            longest_match = Some((lexeme_type, prefix_lexeme.chars().count()));
         }
-        // In this case, we don't even have any characters in the iterator, so
-        // return.
-        _ => None,
     }
+
+    let (lexeme_type, len) = longest_match?;
+    for _ in 0..len {
+        _ = chars.next();
+    }
+
+    Some(lexeme_type)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_prefix_lookup_falls_back_to_longest_lexeme() {
+        use crate::lexer::{code_iter::IntoCodeIterator, PunctuationType};
+
+        let mut chars = "...".into_code_iterator("script.js".to_string());
+        assert_eq!(
+            try_parse_from_prefix_lookup::<PunctuationType>(&mut chars),
+            Some(PunctuationType::Ellipsis)
+        );
+        assert!(chars.peek().is_none());
+
+        // `..` is a prefix of `...` but not a lexeme, so this is just a `.`.
+        let mut chars = "..a".into_code_iterator("script.js".to_string());
+        assert_eq!(
+            try_parse_from_prefix_lookup::<PunctuationType>(&mut chars),
+            Some(PunctuationType::Dot)
+        );
+        assert_eq!(chars.peek(), Some('.'));
+    }
+
     #[test]
     fn test_is_line_terminator() {
         assert!(is_line_terminator('\n'));
@@ -92,4 +115,28 @@ mod tests {
         assert!(is_line_terminator('\u{2029}'));
         assert!(!is_line_terminator('a'));
     }
+
+    #[test]
+    fn test_is_whitespace() {
+        assert!(is_whitespace(' '));
+        assert!(is_whitespace('\u{A0}'));
+        assert!(is_whitespace('\u{feff}'));
+        assert!(is_whitespace('\n'));
+        assert!(!is_whitespace('a'));
+    }
+
+    #[test]
+    fn test_is_disallowed_control_character() {
+        assert!(is_disallowed_control_character('\0'));
+        assert!(is_disallowed_control_character('\u{001B}'));
+        assert!(is_disallowed_control_character('\u{007F}'));
+        assert!(is_disallowed_control_character('\u{0085}'));
+        assert!(!is_disallowed_control_character('\t'));
+        assert!(!is_disallowed_control_character('\u{000B}'));
+        assert!(!is_disallowed_control_character('\u{000C}'));
+        assert!(!is_disallowed_control_character('\n'));
+        assert!(!is_disallowed_control_character('\r'));
+        assert!(!is_disallowed_control_character(' '));
+        assert!(!is_disallowed_control_character('a'));
+    }
 }
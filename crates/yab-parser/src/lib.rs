@@ -1,4 +1,12 @@
 // Later on we will review visibiliity:
 pub mod ast;
+pub mod cjs;
+pub mod codegen;
+pub mod imports;
+pub mod json;
 pub mod lexer;
 pub mod location;
+pub mod parser;
+pub mod semantics;
+pub mod source;
+pub mod sourcemap;
@@ -1,13 +1,25 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-/// Represents the position of a single character in a source file.
-#[derive(Debug, Serialize, PartialEq)]
+/// Represents the position of a single character in a source file.  Lines and
+/// columns count from one, and columns count characters, while `index` is the
+/// byte offset of the character.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Position {
     pub line: usize,
     pub column: usize,
     pub index: usize,
 }
 
+impl From<crate::lexer::code_iter::Position> for Position {
+    fn from(position: crate::lexer::code_iter::Position) -> Self {
+        Self {
+            line: position.line,
+            column: position.column,
+            index: position.index,
+        }
+    }
+}
+
 /// Represents the location of a token in a source file.
 #[derive(Debug, Serialize, PartialEq)]
 pub struct Location {
@@ -15,3 +27,17 @@ pub struct Location {
     pub end: Position,
     pub file_path: String,
 }
+
+/// The part of a source file that a token or an AST node was parsed from,
+/// from its first character up to just after its last.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+}
@@ -0,0 +1,1439 @@
+//! Expression parsing.  Each level of the grammar gets its own method, from
+//! the comma operator down to primary expressions, except for the binary
+//! operators, which all share `parse_binary` and the table in
+//! `binary_precedence`.
+
+use miette::Result;
+
+use crate::{
+    ast::{
+        ArrayExpression, ArrowFunctionExpression, AssignmentExpression, AwaitExpression,
+        BigIntLiteral, BinaryExpression, BlockStatement, BooleanLiteral, CallExpression,
+        ConditionalExpression, Identifier, LogicalExpression, MemberExpression, NewExpression,
+        Node, NullLiteral, NumericLiteral, ObjectExpression, OptionalCallExpression,
+        OptionalMemberExpression, Parameter, Property, RegExpLiteral, SequenceExpression,
+        StringLiteral, TaggedTemplateExpression, TemplateElement, TemplateLiteral, ThisExpression,
+        UnaryExpression, UpdateExpression,
+    },
+    lexer::{
+        KeywordType, NumberLiteralValue, OperatorType, PunctuationType, TemplateLiteralString,
+        Token, ValueLiteralType,
+    },
+    location::Position,
+};
+
+use super::{identifier_name, pattern::PatternKind, scope::BindingKind, Parser};
+
+/// Returns the precedence of a binary operator (higher binds tighter), or
+/// `None` if the operator isn't a binary operator.
+///
+/// `??` shares a level with `||`, which is close enough: the two can't be
+/// mixed without parentheses anyway.
+fn binary_precedence(operator: OperatorType) -> Option<u8> {
+    use OperatorType::*;
+
+    let precedence = match operator {
+        LogicalOr | NullishCoalescing => 1,
+        LogicalAnd => 2,
+        BitwiseOr => 3,
+        BitwiseXOr => 4,
+        BitwiseAnd => 5,
+        LooseEquality | LooseNotEquality | StrictEquality | StrictNotEquality => 6,
+        LessThan | LessThanOrEqualTo | GreaterThan | GreaterThanOrEqualTo | InstanceOf | In => 7,
+        BitwiseShiftLeft | BitwiseShiftRight | BitwiseShiftRightUnsigned => 8,
+        Plus | Minus => 9,
+        Multiplication | Division | Modulo => 10,
+        Exponentiation => 11,
+        _ => return None,
+    };
+
+    Some(precedence)
+}
+
+fn is_logical_operator(operator: OperatorType) -> bool {
+    matches!(
+        operator,
+        OperatorType::LogicalAnd | OperatorType::LogicalOr | OperatorType::NullishCoalescing
+    )
+}
+
+fn is_assignment_operator(operator: OperatorType) -> bool {
+    use OperatorType::*;
+
+    matches!(
+        operator,
+        Assignment
+            | MultiplicationAssignment
+            | DivisionAssignment
+            | ModuloAssignment
+            | ExponentiationAssignment
+            | AdditionAssignment
+            | SubtractionAssigment
+            | ShiftLeftAssignment
+            | ShiftRightAssignment
+            | ShiftRightUnsignedAssignment
+            | BitwiseAndAssignment
+            | BitwiseOrAssignment
+            | BitwiseXOrAssignment
+            | LogicalAndAssignment
+            | LogicalOrAssignment
+            | NullishCoalescingAssignment
+    )
+}
+
+/// Whether `node` can be assigned to, or incremented/decremented.
+pub(super) fn is_simple_assignment_target(node: &Node) -> bool {
+    matches!(node, Node::Identifier(_) | Node::MemberExpression(_))
+}
+
+impl Parser<'_> {
+    /// Expression:
+    ///   AssignmentExpression
+    ///   Expression `,` AssignmentExpression
+    pub fn parse_expression(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        let first = self.parse_assignment()?;
+        if !self.is_punctuation(PunctuationType::Comma) {
+            return Ok(first);
+        }
+
+        let mut expressions = vec![first];
+        while self.eat_punctuation(PunctuationType::Comma) {
+            expressions.push(self.parse_assignment()?);
+        }
+
+        Ok(Node::SequenceExpression(
+            SequenceExpression::new(expressions).with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Assignment is right associative, so `a = b = c` is `a = (b = c)`.
+    pub(super) fn parse_assignment(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        if self.is_arrow_function_ahead() {
+            return self.parse_arrow_function();
+        }
+
+        if self.is_assignment_pattern_ahead() {
+            let target = self.parse_pattern(PatternKind::Assignment)?;
+            self.next();
+            let value = self.parse_assignment()?;
+            return Ok(Node::AssignmentExpression(
+                AssignmentExpression::new(target, value, OperatorType::Assignment.to_string())
+                    .with_span(self.span_from(start)),
+            ));
+        }
+
+        let target = self.parse_conditional()?;
+
+        let operator = match self.peek() {
+            Some(Token::Operator(op)) if is_assignment_operator(op.kind) => op.kind,
+            _ => return Ok(target),
+        };
+
+        if !is_simple_assignment_target(&target) {
+            return Err(self.error_at(target.span(), "Invalid left-hand side in assignment"));
+        }
+        self.check_assignment_target(&target)?;
+
+        self.next();
+        let value = self.parse_assignment()?;
+
+        Ok(Node::AssignmentExpression(
+            AssignmentExpression::new(target, value, operator.to_string())
+                .with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Whether the upcoming tokens are the start of an arrow function.  The
+    /// parameters of `(a, b) => a + b` look just like a parenthesized
+    /// expression until the `=>` after them, so this scans ahead to the
+    /// matching `)` to find out which one we're looking at.  A line break
+    /// isn't allowed before the `=>`.
+    fn is_arrow_function_ahead(&self) -> bool {
+        let is_arrow_at = |n| {
+            matches!(self.peek_nth(n), Some(Token::Operator(op)) if op.kind == OperatorType::Arrow)
+                && !self.has_line_break_before_nth(n)
+        };
+
+        let Some(start) = self.type_parameters_end(usize::from(self.is_async_arrow_prefix()))
+        else {
+            return false;
+        };
+        match self.peek_nth(start) {
+            Some(token) if identifier_name(token).is_some() => is_arrow_at(start + 1),
+            Some(Token::Punctuation(p)) if p.kind == PunctuationType::OpenParen => self
+                .find_matching_close(start)
+                .and_then(|close| self.return_type_end(close + 1))
+                .is_some_and(is_arrow_at),
+            _ => false,
+        }
+    }
+
+    /// Whether the next token is the `async` of an async arrow function.  It's
+    /// an identifier instead in `async => async`, and in `async\n(a)`, since
+    /// no line break is allowed after the `async` of an arrow function.
+    fn is_async_arrow_prefix(&self) -> bool {
+        self.is_keyword(KeywordType::Async)
+            && !matches!(self.peek_nth(1), Some(Token::Operator(op)) if op.kind == OperatorType::Arrow)
+            && !self.has_line_break_before_nth(1)
+    }
+
+    /// ArrowFunction:
+    ///   `async`? ArrowParameters `=>` ConciseBody
+    fn parse_arrow_function(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        let is_async = self.is_async_arrow_prefix();
+        if is_async {
+            self.next();
+        }
+        self.skip_type_parameters()?;
+
+        self.push_function_scope(is_async);
+        let params = if self.is_punctuation(PunctuationType::OpenParen) {
+            self.parse_formal_parameters()?
+        } else {
+            let name = self.parse_binding_identifier()?;
+            let span = self.previous_span;
+            self.declare(&name, BindingKind::Parameter)?;
+            let ident = Node::Identifier(Identifier::new(name).with_span(span));
+            vec![Parameter::new(ident).with_span(span)]
+        };
+        self.skip_type_annotation()?;
+
+        if !self.eat_operator(OperatorType::Arrow) {
+            let token = self.next();
+            return Err(self.unexpected(token.as_ref()));
+        }
+
+        let body = if self.is_punctuation(PunctuationType::OpenBrace) {
+            let body_start = self.start_position();
+            let statements = self.parse_function_body()?;
+            Node::BlockStatement(
+                BlockStatement::new(statements).with_span(self.span_from(body_start)),
+            )
+        } else {
+            self.parse_assignment()?
+        };
+        self.pop_function_scope(None, true)?;
+
+        Ok(Node::ArrowFunctionExpression(
+            ArrowFunctionExpression::new(params, body, is_async).with_span(self.span_from(start)),
+        ))
+    }
+
+    fn parse_conditional(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        let test = self.parse_binary(0)?;
+        if !self.eat_operator(OperatorType::Ternary) {
+            return Ok(test);
+        }
+
+        let consequent = self.parse_assignment()?;
+        self.expect_punctuation(PunctuationType::Colon)?;
+        let alternate = self.parse_assignment()?;
+
+        Ok(Node::ConditionalExpression(
+            ConditionalExpression::new(test, consequent, alternate)
+                .with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Parses a chain of binary operators whose precedence is at least
+    /// `min_precedence`.  Operators of equal precedence associate to the left,
+    /// except for `**`, which associates to the right.
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Node> {
+        let start = self.start_position();
+        let mut lhs = self.parse_unary()?;
+
+        // `??` can't be mixed with `&&` or `||` without parentheses.  `??`
+        // only ever shows up in the outermost call, so it's enough to look
+        // for both in this loop, as long as the right side of `??` doesn't
+        // swallow an `&&` on its own.
+        let mut seen_nullish = false;
+        let mut seen_logical = false;
+
+        loop {
+            // `a as T` and `a satisfies T` bind like relational operators.
+            if binary_precedence(OperatorType::LessThan) >= Some(min_precedence)
+                && self.skip_type_assertion()?
+            {
+                continue;
+            }
+
+            let Some((operator, precedence)) = self.peek_binary_operator(min_precedence) else {
+                break;
+            };
+            let token = self.next();
+            match operator {
+                OperatorType::NullishCoalescing => seen_nullish = true,
+                OperatorType::LogicalAnd | OperatorType::LogicalOr => seen_logical = true,
+                _ => {}
+            }
+            if seen_nullish && seen_logical {
+                return Err(self.unexpected(token.as_ref()));
+            }
+
+            let rhs = match operator {
+                OperatorType::Exponentiation => self.parse_binary(precedence)?,
+                // Skip past the level of `&&`.
+                OperatorType::NullishCoalescing => self.parse_binary(precedence + 2)?,
+                _ => self.parse_binary(precedence + 1)?,
+            };
+
+            let span = self.span_from(start);
+            lhs = if is_logical_operator(operator) {
+                Node::LogicalExpression(
+                    LogicalExpression::new(lhs, rhs, operator.to_string()).with_span(span),
+                )
+            } else {
+                Node::BinaryExpression(
+                    BinaryExpression::new(lhs, rhs, operator.to_string()).with_span(span),
+                )
+            };
+        }
+
+        Ok(lhs)
+    }
+
+    /// Returns the next token if it is a binary operator with a precedence of
+    /// at least `min_precedence`, along with that precedence.
+    fn peek_binary_operator(&self, min_precedence: u8) -> Option<(OperatorType, u8)> {
+        match self.peek() {
+            Some(Token::Operator(op)) => binary_precedence(op.kind)
+                .filter(|precedence| *precedence >= min_precedence)
+                .map(|precedence| (op.kind, precedence)),
+            _ => None,
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        let operator = match self.peek() {
+            Some(Token::Operator(op)) => op.kind,
+            _ => return self.parse_postfix(),
+        };
+
+        let expression = match operator {
+            OperatorType::LogicalNot
+            | OperatorType::BitwiseNot
+            | OperatorType::Plus
+            | OperatorType::Minus
+            | OperatorType::TypeOf
+            | OperatorType::Void => {
+                self.next();
+                let argument = self.parse_unary()?;
+                Node::UnaryExpression(
+                    UnaryExpression::new(argument, operator.to_string())
+                        .with_span(self.span_from(start)),
+                )
+            }
+            OperatorType::Delete => {
+                self.next();
+                let argument = self.parse_unary()?;
+                if self.is_strict() && matches!(argument, Node::Identifier(_)) {
+                    return Err(self.error_at(
+                        argument.span(),
+                        "Delete of an unqualified identifier in strict mode",
+                    ));
+                }
+                Node::UnaryExpression(
+                    UnaryExpression::new(argument, operator.to_string())
+                        .with_span(self.span_from(start)),
+                )
+            }
+            OperatorType::Await if self.is_await_reserved() => {
+                self.next();
+                let argument = self.parse_unary()?;
+                Node::AwaitExpression(
+                    AwaitExpression::new(argument).with_span(self.span_from(start)),
+                )
+            }
+            OperatorType::Increment | OperatorType::Decrement => {
+                self.next();
+                let argument = self.parse_unary()?;
+                if !is_simple_assignment_target(&argument) {
+                    return Err(self.error_at(
+                        argument.span(),
+                        "Invalid left-hand side expression in prefix operation",
+                    ));
+                }
+                self.check_assignment_target(&argument)?;
+                // `++a ** 2` is fine, unlike the other unary operators.
+                return Ok(Node::UpdateExpression(
+                    UpdateExpression::new(argument, operator.to_string(), true)
+                        .with_span(self.span_from(start)),
+                ));
+            }
+            _ => return self.parse_postfix(),
+        };
+
+        // `-a ** b` is a syntax error rather than picking one of the two
+        // readings for you.
+        if self.is_operator(OperatorType::Exponentiation) {
+            return Err(self.error(
+                "Unary operator used immediately before exponentiation expression. \
+                 Parenthesis must be used to disambiguate operator precedence",
+            ));
+        }
+
+        Ok(expression)
+    }
+
+    /// A line break before a postfix `++` or `--` ends the statement, so
+    /// `a\n++b` is `a; ++b`.
+    fn parse_postfix(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        let argument = self.parse_left_hand_side()?;
+        if self.has_line_break_before() {
+            return Ok(argument);
+        }
+
+        let operator = match self.peek() {
+            Some(Token::Operator(op))
+                if matches!(op.kind, OperatorType::Increment | OperatorType::Decrement) =>
+            {
+                op.kind
+            }
+            _ => return Ok(argument),
+        };
+
+        if !is_simple_assignment_target(&argument) {
+            return Err(self.error_at(
+                argument.span(),
+                "Invalid left-hand side expression in postfix operation",
+            ));
+        }
+        self.check_assignment_target(&argument)?;
+        self.next();
+
+        Ok(Node::UpdateExpression(
+            UpdateExpression::new(argument, operator.to_string(), false)
+                .with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Member accesses, calls, tagged templates, and `new`, which all bind
+    /// tighter than any operator.
+    pub(super) fn parse_left_hand_side(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        let mut expression = if self.is_keyword(KeywordType::New) {
+            self.parse_new()?
+        } else {
+            self.parse_primary()?
+        };
+
+        // Whether we're past a `?.`, which makes every access and call up to
+        // the end of the chain optional.
+        let mut in_chain = false;
+        loop {
+            expression = self.parse_member_accesses(start, expression, in_chain)?;
+
+            expression = if self.eat_operator(OperatorType::OptionalChaining) {
+                in_chain = true;
+                if self.is_punctuation(PunctuationType::OpenParen) {
+                    let arguments = self.parse_arguments()?;
+                    Node::OptionalCallExpression(
+                        OptionalCallExpression::new(expression, arguments, true)
+                            .with_span(self.span_from(start)),
+                    )
+                } else if self.eat_punctuation(PunctuationType::OpenBracket) {
+                    let property = self.parse_expression()?;
+                    self.expect_punctuation(PunctuationType::CloseBracket)?;
+                    Node::OptionalMemberExpression(
+                        OptionalMemberExpression::new(expression, property, true, true)
+                            .with_span(self.span_from(start)),
+                    )
+                } else {
+                    let property = self.parse_identifier_name_node()?;
+                    Node::OptionalMemberExpression(
+                        OptionalMemberExpression::new(expression, property, false, true)
+                            .with_span(self.span_from(start)),
+                    )
+                }
+            } else if self.is_punctuation(PunctuationType::OpenParen) {
+                let arguments = self.parse_arguments()?;
+                let span = self.span_from(start);
+                if in_chain {
+                    Node::OptionalCallExpression(
+                        OptionalCallExpression::new(expression, arguments, false).with_span(span),
+                    )
+                } else {
+                    Node::CallExpression(CallExpression::new(expression, arguments).with_span(span))
+                }
+            } else {
+                return Ok(expression);
+            };
+        }
+    }
+
+    /// `new Foo.bar(a)` is `new (Foo.bar)(a)`: member accesses bind tighter
+    /// than `new`, but the first argument list belongs to it.
+    fn parse_new(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        self.next();
+
+        let callee_start = self.start_position();
+        let callee = if self.is_keyword(KeywordType::New) {
+            self.parse_new()?
+        } else {
+            self.parse_primary()?
+        };
+        let callee = self.parse_member_accesses(callee_start, callee, false)?;
+        if self.is_operator(OperatorType::OptionalChaining) {
+            self.next();
+            return Err(self.error("Invalid optional chain from new expression"));
+        }
+
+        let arguments = if self.is_punctuation(PunctuationType::OpenParen) {
+            self.parse_arguments()?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Node::NewExpression(
+            NewExpression::new(callee, arguments).with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Parses any `.name`, `[expr]` and tagged template suffixes following
+    /// `object`, which starts at `start`.  Inside an optional chain the
+    /// accesses are optional too.
+    fn parse_member_accesses(
+        &mut self,
+        start: Position,
+        mut object: Node,
+        in_chain: bool,
+    ) -> Result<Node> {
+        let member = |object, property, computed, span| {
+            if in_chain {
+                Node::OptionalMemberExpression(
+                    OptionalMemberExpression::new(object, property, computed, false)
+                        .with_span(span),
+                )
+            } else {
+                Node::MemberExpression(
+                    MemberExpression::new(object, property, computed).with_span(span),
+                )
+            }
+        };
+
+        loop {
+            object = if self.eat_punctuation(PunctuationType::Dot) {
+                let property = self.parse_identifier_name_node()?;
+                member(object, property, false, self.span_from(start))
+            } else if self.eat_punctuation(PunctuationType::OpenBracket) {
+                let property = self.parse_expression()?;
+                self.expect_punctuation(PunctuationType::CloseBracket)?;
+                member(object, property, true, self.span_from(start))
+            } else if self.skip_type_suffix() {
+                object
+            } else if let Some(Token::TemplateLiteralString(_)) = self.peek() {
+                let Some(Token::TemplateLiteralString(head)) = self.next() else {
+                    unreachable!()
+                };
+                if in_chain {
+                    return Err(self.error("Invalid tagged template on optional chain"));
+                }
+                let quasi = self.parse_template_literal(head, true)?;
+                Node::TaggedTemplateExpression(
+                    TaggedTemplateExpression::new(object, quasi).with_span(self.span_from(start)),
+                )
+            } else {
+                return Ok(object);
+            };
+        }
+    }
+
+    /// Arguments:
+    ///   `(` `)`
+    ///   `(` AssignmentExpression ( `,` AssignmentExpression )* `,`? `)`
+    fn parse_arguments(&mut self) -> Result<Vec<Node>> {
+        self.expect_punctuation(PunctuationType::OpenParen)?;
+
+        let mut arguments = Vec::new();
+        while !self.eat_punctuation(PunctuationType::CloseParen) {
+            arguments.push(self.parse_assignment()?);
+            if !self.eat_punctuation(PunctuationType::Comma) {
+                self.expect_punctuation(PunctuationType::CloseParen)?;
+                break;
+            }
+        }
+
+        Ok(arguments)
+    }
+
+    /// Property names after a `.`, and object literal keys, may be any
+    /// IdentifierName, which includes reserved words like `new` and `in`.
+    pub(super) fn parse_identifier_name(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Ident(ident)) => Ok(ident.lexeme.into_owned()),
+            Some(Token::Keyword(keyword)) => Ok(keyword.kind.to_string()),
+            Some(Token::ValueLiteral(value)) => Ok(value.kind.to_string()),
+            Some(Token::Operator(op))
+                if op.kind.to_string().chars().all(|c| c.is_ascii_alphabetic()) =>
+            {
+                Ok(op.kind.to_string())
+            }
+            token => Err(self.unexpected(token.as_ref())),
+        }
+    }
+
+    /// Parses an IdentifierName into an [`Identifier`] node.
+    fn parse_identifier_name_node(&mut self) -> Result<Node> {
+        let name = self.parse_identifier_name()?;
+        Ok(Node::Identifier(
+            Identifier::new(name).with_span(self.previous_span),
+        ))
+    }
+
+    fn parse_primary(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        let token = self.next();
+        if let Some(name) = token.as_ref().and_then(identifier_name) {
+            self.check_identifier_reference(&name)?;
+            return Ok(Node::Identifier(
+                Identifier::new(name).with_span(self.previous_span),
+            ));
+        }
+
+        let node = match token {
+            Some(Token::NumericLiteral(number)) if number.legacy_octal && self.is_strict() => {
+                return Err(self.error("Octal literals are not allowed in strict mode"));
+            }
+            Some(Token::StringLiteral(string)) if string.has_octal_escape && self.is_strict() => {
+                return Err(self.error("Octal escape sequences are not allowed in strict mode"));
+            }
+            Some(Token::NumericLiteral(number)) => match number.value {
+                NumberLiteralValue::Primitive(value) => {
+                    Node::NumericLiteral(NumericLiteral::new(value))
+                }
+                NumberLiteralValue::BigInt(storage) => {
+                    Node::BigIntLiteral(BigIntLiteral::new(format!("{}n", storage.value)))
+                }
+            },
+            Some(Token::StringLiteral(string)) => Node::StringLiteral(
+                StringLiteral::new(string.lexeme.into_owned()).with_code_units(string.code_units),
+            ),
+            Some(Token::ValueLiteral(value)) => match value.kind {
+                ValueLiteralType::True => Node::BooleanLiteral(BooleanLiteral::new(true)),
+                ValueLiteralType::False => Node::BooleanLiteral(BooleanLiteral::new(false)),
+                ValueLiteralType::Null => Node::NullLiteral(NullLiteral::default()),
+            },
+            Some(Token::RegexLiteral(regex)) => Node::RegExpLiteral(RegExpLiteral::new(
+                regex.pattern.into_owned(),
+                regex.flags.into_owned(),
+            )),
+            Some(Token::TemplateLiteralString(head)) => {
+                Node::TemplateLiteral(self.parse_template_literal(head, false)?)
+            }
+            Some(Token::Keyword(keyword)) if keyword.kind == KeywordType::This => {
+                Node::ThisExpression(ThisExpression::default())
+            }
+            Some(Token::JSXTagStart(_)) => self.parse_jsx_element(start)?,
+            Some(Token::Punctuation(punctuation)) => match punctuation.kind {
+                // Parentheses don't make a node of their own, so the
+                // expression keeps the span it was parsed with.
+                PunctuationType::OpenParen => {
+                    let expression = self.parse_expression()?;
+                    self.expect_punctuation(PunctuationType::CloseParen)?;
+                    return Ok(expression);
+                }
+                PunctuationType::OpenBracket => self.parse_array_literal()?,
+                PunctuationType::OpenBrace => self.parse_object_literal()?,
+                _ => return Err(self.unexpected(Some(&Token::Punctuation(punctuation)))),
+            },
+            token => return Err(self.unexpected(token.as_ref())),
+        };
+
+        Ok(node.with_span(self.span_from(start)))
+    }
+
+    /// Parses the rest of a template literal, given its first string part.
+    /// The lexer has already split it into string parts and the tokens of the
+    /// `${}` expressions between them.
+    fn parse_template_literal(
+        &mut self,
+        head: TemplateLiteralString,
+        tagged: bool,
+    ) -> Result<TemplateLiteral> {
+        let start = self.previous_span.start;
+        let mut tail = head.complete;
+        let mut quasis = vec![self.template_element(head, true, tagged)?];
+        let mut expressions = Vec::new();
+
+        while !tail {
+            match self.next() {
+                Some(Token::TemplateLiteralExprOpen(_)) => {}
+                token => return Err(self.unexpected(token.as_ref())),
+            }
+            expressions.push(self.parse_expression()?);
+            match self.next() {
+                Some(Token::TemplateLiteralExprClose(_)) => {}
+                token => return Err(self.unexpected(token.as_ref())),
+            }
+
+            match self.next() {
+                Some(Token::TemplateLiteralString(string)) => {
+                    tail = string.complete;
+                    quasis.push(self.template_element(string, false, tagged)?);
+                }
+                token => return Err(self.unexpected(token.as_ref())),
+            }
+        }
+
+        Ok(TemplateLiteral::new(quasis, expressions).with_span(self.span_from(start)))
+    }
+
+    /// Builds the element for a string part of a template, which was the last
+    /// token consumed, and is the first part of it if `is_head`.  Only tagged
+    /// templates can have invalid escape sequences, which leave the cooked
+    /// text undefined.
+    fn template_element(
+        &self,
+        string: TemplateLiteralString,
+        is_head: bool,
+        tagged: bool,
+    ) -> Result<TemplateElement> {
+        if string.cooked.is_none() && !tagged {
+            return Err(self.error("Invalid escape sequence in template"));
+        }
+
+        // The string's token includes the backticks around the template,
+        // which aren't part of the element.
+        let mut span = self.previous_span;
+        if is_head {
+            span.start.column += 1;
+            span.start.index += 1;
+        }
+        if string.complete {
+            span.end.column -= 1;
+            span.end.index -= 1;
+        }
+
+        Ok(
+            TemplateElement::new(string.raw.into_owned(), string.complete)
+                .with_cooked(string.cooked.map(|cooked| cooked.into_owned()))
+                .with_cooked_code_units(string.cooked_code_units)
+                .with_span(span),
+        )
+    }
+
+    /// Parses an array literal, assuming the `[` has been consumed.
+    fn parse_array_literal(&mut self) -> Result<Node> {
+        let mut elements = Vec::new();
+
+        while !self.eat_punctuation(PunctuationType::CloseBracket) {
+            if self.eat_punctuation(PunctuationType::Comma) {
+                elements.push(None);
+                continue;
+            }
+
+            elements.push(Some(self.parse_assignment()?));
+            if !self.eat_punctuation(PunctuationType::Comma) {
+                self.expect_punctuation(PunctuationType::CloseBracket)?;
+                break;
+            }
+        }
+
+        Ok(Node::ArrayExpression(ArrayExpression::new(elements)))
+    }
+
+    /// Parses an object literal, assuming the `{` has been consumed.
+    fn parse_object_literal(&mut self) -> Result<Node> {
+        let mut properties = Vec::new();
+
+        while !self.eat_punctuation(PunctuationType::CloseBrace) {
+            properties.push(self.parse_property()?);
+            if !self.eat_punctuation(PunctuationType::Comma) {
+                self.expect_punctuation(PunctuationType::CloseBrace)?;
+                break;
+            }
+        }
+
+        Ok(Node::ObjectExpression(ObjectExpression::new(properties)))
+    }
+
+    /// Property:
+    ///   PropertyName `:` AssignmentExpression
+    ///   IdentifierReference
+    fn parse_property(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        // Only a plain identifier can be used as shorthand, `{ if }` is no
+        // more valid than `if` on its own would be.
+        let shorthand = self.peek().and_then(identifier_name);
+
+        let (key, computed) = self.parse_property_key()?;
+        if let Some(name) = shorthand {
+            if !self.is_punctuation(PunctuationType::Colon) {
+                self.check_identifier_reference(&name)?;
+                let span = self.span_from(start);
+                let value = Node::Identifier(Identifier::new(name).with_span(span));
+                return Ok(Node::Property(
+                    Property::new(key, value, false, true).with_span(span),
+                ));
+            }
+        }
+
+        self.expect_punctuation(PunctuationType::Colon)?;
+        let value = self.parse_assignment()?;
+
+        Ok(Node::Property(
+            Property::new(key, value, computed, false).with_span(self.span_from(start)),
+        ))
+    }
+
+    /// PropertyName, returning the key and whether it was computed.
+    pub(super) fn parse_property_key(&mut self) -> Result<(Node, bool)> {
+        match self.peek() {
+            Some(Token::StringLiteral(_)) | Some(Token::NumericLiteral(_)) => {
+                Ok((self.parse_primary()?, false))
+            }
+            Some(Token::Punctuation(p)) if p.kind == PunctuationType::OpenBracket => {
+                self.next();
+                let key = self.parse_assignment()?;
+                self.expect_punctuation(PunctuationType::CloseBracket)?;
+                Ok((key, true))
+            }
+            _ => Ok((self.parse_identifier_name_node()?, false)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{
+        test_helpers::{ident, parse_error},
+        without_spans::parse_expression,
+    };
+
+    use super::*;
+
+    fn parse(src: &str) -> Node {
+        parse_expression(src, "script.js").unwrap()
+    }
+
+    fn num(value: f64) -> Node {
+        Node::NumericLiteral(NumericLiteral::new(value))
+    }
+
+    fn string(value: &str) -> Node {
+        Node::StringLiteral(StringLiteral::new(value.to_string()))
+    }
+
+    fn bin(lhs: Node, operator: &str, rhs: Node) -> Node {
+        Node::BinaryExpression(BinaryExpression::new(lhs, rhs, operator.to_string()))
+    }
+
+    fn logical(lhs: Node, operator: &str, rhs: Node) -> Node {
+        Node::LogicalExpression(LogicalExpression::new(lhs, rhs, operator.to_string()))
+    }
+
+    fn assign(lhs: Node, operator: &str, rhs: Node) -> Node {
+        Node::AssignmentExpression(AssignmentExpression::new(lhs, rhs, operator.to_string()))
+    }
+
+    fn unary(operator: &str, argument: Node) -> Node {
+        Node::UnaryExpression(UnaryExpression::new(argument, operator.to_string()))
+    }
+
+    fn update(operator: &str, argument: Node, prefix: bool) -> Node {
+        Node::UpdateExpression(UpdateExpression::new(
+            argument,
+            operator.to_string(),
+            prefix,
+        ))
+    }
+
+    fn cond(test: Node, consequent: Node, alternate: Node) -> Node {
+        Node::ConditionalExpression(ConditionalExpression::new(test, consequent, alternate))
+    }
+
+    fn call(callee: Node, arguments: Vec<Node>) -> Node {
+        Node::CallExpression(CallExpression::new(callee, arguments))
+    }
+
+    fn member(object: Node, property: &str) -> Node {
+        Node::MemberExpression(MemberExpression::new(object, ident(property), false))
+    }
+
+    #[test]
+    fn test_binary_precedence() {
+        assert_eq!(
+            parse("a + b * c"),
+            bin(ident("a"), "+", bin(ident("b"), "*", ident("c")))
+        );
+        assert_eq!(
+            parse("a * b + c"),
+            bin(bin(ident("a"), "*", ident("b")), "+", ident("c"))
+        );
+        assert_eq!(
+            parse("a == b < c & d"),
+            bin(
+                bin(ident("a"), "==", bin(ident("b"), "<", ident("c"))),
+                "&",
+                ident("d")
+            )
+        );
+        assert_eq!(
+            parse("a << b + c instanceof d"),
+            bin(
+                bin(ident("a"), "<<", bin(ident("b"), "+", ident("c"))),
+                "instanceof",
+                ident("d")
+            )
+        );
+        assert_eq!(
+            parse("(a + b) * c"),
+            bin(bin(ident("a"), "+", ident("b")), "*", ident("c"))
+        );
+
+        // The sign in front of a number is an operator, not part of the
+        // number.
+        assert_eq!(parse("a-1"), bin(ident("a"), "-", num(1.0)));
+        assert_eq!(parse("a -1"), bin(ident("a"), "-", num(1.0)));
+        assert_eq!(parse("2-1"), bin(num(2.0), "-", num(1.0)));
+        assert_eq!(parse("2+1"), bin(num(2.0), "+", num(1.0)));
+        assert_eq!(
+            parse("i<n-1"),
+            bin(ident("i"), "<", bin(ident("n"), "-", num(1.0)))
+        );
+        assert_eq!(
+            parse("f(n-1)"),
+            call(ident("f"), vec![bin(ident("n"), "-", num(1.0))])
+        );
+    }
+
+    #[test]
+    fn test_binary_associativity() {
+        assert_eq!(
+            parse("a - b - c"),
+            bin(bin(ident("a"), "-", ident("b")), "-", ident("c"))
+        );
+        assert_eq!(
+            parse("a ** b ** c"),
+            bin(ident("a"), "**", bin(ident("b"), "**", ident("c")))
+        );
+    }
+
+    #[test]
+    fn test_logical_expressions() {
+        assert_eq!(
+            parse("a || b && c | d"),
+            logical(
+                ident("a"),
+                "||",
+                logical(ident("b"), "&&", bin(ident("c"), "|", ident("d")))
+            )
+        );
+        assert_eq!(parse("a ?? b"), logical(ident("a"), "??", ident("b")));
+        assert_eq!(
+            parse("a ?? b | c ?? d"),
+            logical(
+                logical(ident("a"), "??", bin(ident("b"), "|", ident("c"))),
+                "??",
+                ident("d")
+            )
+        );
+        assert_eq!(
+            parse("(a || b) ?? c"),
+            logical(logical(ident("a"), "||", ident("b")), "??", ident("c"))
+        );
+    }
+
+    #[test]
+    fn test_mixed_nullish_coalescing() {
+        assert_eq!(
+            parse_error("a || b ?? c"),
+            "SyntaxError: Unexpected token '??' at script.js:1:8"
+        );
+        assert_eq!(
+            parse_error("a ?? b && c"),
+            "SyntaxError: Unexpected token '&&' at script.js:1:8"
+        );
+        assert_eq!(
+            parse_error("a && b ?? c"),
+            "SyntaxError: Unexpected token '??' at script.js:1:8"
+        );
+    }
+
+    #[test]
+    fn test_unary_expressions() {
+        assert_eq!(parse("!-a"), unary("!", unary("-", ident("a"))));
+        assert_eq!(
+            parse("typeof a + b"),
+            bin(unary("typeof", ident("a")), "+", ident("b"))
+        );
+        assert_eq!(
+            parse("a ** -b"),
+            bin(ident("a"), "**", unary("-", ident("b")))
+        );
+        assert_eq!(
+            parse("(-a) ** b"),
+            bin(unary("-", ident("a")), "**", ident("b"))
+        );
+        assert_eq!(
+            parse_expression("await a * b", "module.mjs").unwrap(),
+            bin(
+                Node::AwaitExpression(AwaitExpression::new(ident("a"))),
+                "*",
+                ident("b")
+            )
+        );
+        assert!(parse_error("-a ** b").contains("Unary operator used immediately before"));
+
+        assert_eq!(parse("-1"), unary("-", num(1.0)));
+        assert_eq!(parse("+-1"), unary("+", unary("-", num(1.0))));
+        assert_eq!(
+            parse("(-2) ** 2"),
+            bin(unary("-", num(2.0)), "**", num(2.0))
+        );
+        assert!(parse_error("-2 ** 2").contains("Unary operator used immediately before"));
+    }
+
+    #[test]
+    fn test_update_expressions() {
+        assert_eq!(
+            parse("a++ + ++b.c"),
+            bin(
+                update("++", ident("a"), false),
+                "+",
+                update("++", member(ident("b"), "c"), true)
+            )
+        );
+        assert!(parse_error("++a++").contains("Invalid left-hand side expression in prefix"));
+        assert!(parse_error("f()--").contains("Invalid left-hand side expression in postfix"));
+    }
+
+    #[test]
+    fn test_assignment_expressions() {
+        assert_eq!(
+            parse("a = b += c || d"),
+            assign(
+                ident("a"),
+                "=",
+                assign(ident("b"), "+=", logical(ident("c"), "||", ident("d")))
+            )
+        );
+        assert_eq!(
+            parse("a.b[c] ??= 1"),
+            assign(
+                Node::MemberExpression(MemberExpression::new(
+                    member(ident("a"), "b"),
+                    ident("c"),
+                    true
+                )),
+                "??=",
+                num(1.0)
+            )
+        );
+        assert_eq!(
+            parse("a %= b **= 2"),
+            assign(ident("a"), "%=", assign(ident("b"), "**=", num(2.0)))
+        );
+        assert!(parse_error("a + b = c").contains("Invalid left-hand side in assignment"));
+        assert!(parse_error("1 = 2").contains("Invalid left-hand side in assignment"));
+    }
+
+    #[test]
+    fn test_conditional_expressions() {
+        assert_eq!(
+            parse("a ? b : c ? d : e"),
+            cond(
+                ident("a"),
+                ident("b"),
+                cond(ident("c"), ident("d"), ident("e"))
+            )
+        );
+        assert_eq!(
+            parse("a = b || c ? d = 1 : e"),
+            assign(
+                ident("a"),
+                "=",
+                cond(
+                    logical(ident("b"), "||", ident("c")),
+                    assign(ident("d"), "=", num(1.0)),
+                    ident("e")
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn test_sequence_expressions() {
+        assert_eq!(
+            parse("a, b = 1, c"),
+            Node::SequenceExpression(SequenceExpression::new(vec![
+                ident("a"),
+                assign(ident("b"), "=", num(1.0)),
+                ident("c"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_call_and_member_expressions() {
+        assert_eq!(
+            parse("a.b(c, d)(e,)[f]"),
+            Node::MemberExpression(MemberExpression::new(
+                call(
+                    call(member(ident("a"), "b"), vec![ident("c"), ident("d")]),
+                    vec![ident("e")]
+                ),
+                ident("f"),
+                true
+            ))
+        );
+        assert_eq!(
+            parse("a.new.in.true"),
+            member(member(member(ident("a"), "new"), "in"), "true")
+        );
+        assert_eq!(parse("f()"), call(ident("f"), vec![]));
+    }
+
+    fn optional_member(object: Node, property: &str, optional: bool) -> Node {
+        Node::OptionalMemberExpression(OptionalMemberExpression::new(
+            object,
+            ident(property),
+            false,
+            optional,
+        ))
+    }
+
+    #[test]
+    fn test_optional_chaining() {
+        assert_eq!(
+            parse("a?.b.c"),
+            optional_member(optional_member(ident("a"), "b", true), "c", false)
+        );
+        assert_eq!(
+            parse("a?.[0]?.(b)"),
+            Node::OptionalCallExpression(OptionalCallExpression::new(
+                Node::OptionalMemberExpression(OptionalMemberExpression::new(
+                    ident("a"),
+                    num(0.0),
+                    true,
+                    true
+                )),
+                vec![ident("b")],
+                true
+            ))
+        );
+        assert_eq!(
+            parse("a.b?.()()"),
+            Node::OptionalCallExpression(OptionalCallExpression::new(
+                Node::OptionalCallExpression(OptionalCallExpression::new(
+                    member(ident("a"), "b"),
+                    vec![],
+                    true
+                )),
+                vec![],
+                false
+            ))
+        );
+
+        // Parentheses end the chain.
+        assert_eq!(
+            parse("(a?.b).c"),
+            member(optional_member(ident("a"), "b", true), "c")
+        );
+    }
+
+    #[test]
+    fn test_invalid_optional_chains() {
+        assert_eq!(
+            parse_error("a?.b = c"),
+            "SyntaxError: Invalid left-hand side in assignment at script.js:1:1"
+        );
+        assert_eq!(
+            parse_error("new a?.b()"),
+            "SyntaxError: Invalid optional chain from new expression at script.js:1:6"
+        );
+        assert_eq!(
+            parse_error("a?.b`c`"),
+            "SyntaxError: Invalid tagged template on optional chain at script.js:1:5"
+        );
+    }
+
+    #[test]
+    fn test_new_expressions() {
+        assert_eq!(
+            parse("new Foo.bar(a).baz"),
+            member(
+                Node::NewExpression(NewExpression::new(
+                    member(ident("Foo"), "bar"),
+                    vec![ident("a")]
+                )),
+                "baz"
+            )
+        );
+        assert_eq!(
+            parse("new new Foo()()"),
+            Node::NewExpression(NewExpression::new(
+                Node::NewExpression(NewExpression::new(ident("Foo"), vec![])),
+                vec![]
+            ))
+        );
+        assert_eq!(
+            parse("new Foo"),
+            Node::NewExpression(NewExpression::new(ident("Foo"), vec![]))
+        );
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(
+            parse("[1, , 'a', this,]"),
+            Node::ArrayExpression(ArrayExpression::new(vec![
+                Some(num(1.0)),
+                None,
+                Some(string("a")),
+                Some(Node::ThisExpression(ThisExpression::default())),
+            ]))
+        );
+        assert_eq!(
+            parse("{ a, b: true, 'c': null, [d]: 10n, if: 1 }"),
+            Node::ObjectExpression(ObjectExpression::new(vec![
+                Node::Property(Property::new(ident("a"), ident("a"), false, true)),
+                Node::Property(Property::new(
+                    ident("b"),
+                    Node::BooleanLiteral(BooleanLiteral::new(true)),
+                    false,
+                    false
+                )),
+                Node::Property(Property::new(
+                    string("c"),
+                    Node::NullLiteral(NullLiteral::default()),
+                    false,
+                    false
+                )),
+                Node::Property(Property::new(
+                    ident("d"),
+                    Node::BigIntLiteral(BigIntLiteral::new("10n".to_string())),
+                    true,
+                    false
+                )),
+                Node::Property(Property::new(ident("if"), num(1.0), false, false)),
+            ]))
+        );
+        assert_eq!(
+            parse("a = /b/g"),
+            assign(
+                ident("a"),
+                "=",
+                Node::RegExpLiteral(RegExpLiteral::new("b".to_string(), "g".to_string()))
+            )
+        );
+        let Node::StringLiteral(literal) = parse(r"'\uD83D\uDE00\uD800'") else {
+            panic!("expected a string literal");
+        };
+        assert_eq!(literal.value(), "😀\u{FFFD}");
+        assert_eq!(literal.to_utf16(), [0xD83D, 0xDE00, 0xD800]);
+        assert_eq!(
+            parse_error("({ this })"),
+            "SyntaxError: Unexpected token '}' at script.js:1:9"
+        );
+    }
+
+    #[test]
+    fn test_template_literals() {
+        assert_eq!(
+            parse("tag`a${b + c}d${e}`"),
+            Node::TaggedTemplateExpression(TaggedTemplateExpression::new(
+                ident("tag"),
+                TemplateLiteral::new(
+                    vec![
+                        TemplateElement::new("a".to_string(), false),
+                        TemplateElement::new("d".to_string(), false),
+                        TemplateElement::new("".to_string(), true),
+                    ],
+                    vec![bin(ident("b"), "+", ident("c")), ident("e")]
+                )
+            ))
+        );
+        assert_eq!(
+            parse("`plain`"),
+            Node::TemplateLiteral(TemplateLiteral::new(
+                vec![TemplateElement::new("plain".to_string(), true)],
+                vec![]
+            ))
+        );
+        assert_eq!(
+            parse(r"tag`\unicode`"),
+            Node::TaggedTemplateExpression(TaggedTemplateExpression::new(
+                ident("tag"),
+                TemplateLiteral::new(
+                    vec![TemplateElement::new(r"\unicode".to_string(), true).with_cooked(None)],
+                    vec![]
+                )
+            ))
+        );
+        assert_eq!(
+            parse_error(r"`\unicode`"),
+            "SyntaxError: Invalid escape sequence in template at script.js:1:1"
+        );
+    }
+
+    fn arrow(params: &[&str], body: Node, is_async: bool) -> Node {
+        let params = params
+            .iter()
+            .map(|name| Parameter::new(ident(name)))
+            .collect();
+        Node::ArrowFunctionExpression(ArrowFunctionExpression::new(params, body, is_async))
+    }
+
+    #[test]
+    fn test_arrow_functions() {
+        assert_eq!(parse("x => x"), arrow(&["x"], ident("x"), false));
+        assert_eq!(
+            parse("(a, b,) => a + b"),
+            arrow(&["a", "b"], bin(ident("a"), "+", ident("b")), false)
+        );
+        assert_eq!(
+            parse("() => {}"),
+            arrow(
+                &[],
+                Node::BlockStatement(BlockStatement::new(vec![])),
+                false
+            )
+        );
+        assert_eq!(
+            parse("a => b => a"),
+            arrow(&["a"], arrow(&["b"], ident("a"), false), false)
+        );
+        assert_eq!(
+            parse("f((a) => (a), b)"),
+            call(
+                ident("f"),
+                vec![arrow(&["a"], ident("a"), false), ident("b")]
+            )
+        );
+        assert_eq!(
+            parse("x = a ? (b) => c : d"),
+            assign(
+                ident("x"),
+                "=",
+                cond(ident("a"), arrow(&["b"], ident("c"), false), ident("d"))
+            )
+        );
+    }
+
+    #[test]
+    fn test_async_arrow_functions() {
+        assert_eq!(
+            parse("async x => await x"),
+            arrow(
+                &["x"],
+                Node::AwaitExpression(AwaitExpression::new(ident("x"))),
+                true
+            )
+        );
+        assert_eq!(
+            parse("async (a, b) => {}"),
+            arrow(
+                &["a", "b"],
+                Node::BlockStatement(BlockStatement::new(vec![])),
+                true
+            )
+        );
+        // `async` is just a parameter here.
+        assert_eq!(
+            parse("async => async"),
+            arrow(&["async"], ident("async"), false)
+        );
+
+        // No line break is allowed after `async` or before `=>`, so these
+        // are a call to `async` and a parenthesized expression.
+        assert_eq!(
+            parse("x = async\n(a)"),
+            assign(ident("x"), "=", call(ident("async"), vec![ident("a")]))
+        );
+        assert_eq!(
+            parse_error("x = async\n(a) => a"),
+            "SyntaxError: Unexpected token '=>' at script.js:2:5"
+        );
+        assert_eq!(
+            parse_error("(a)\n=> a"),
+            "SyntaxError: Unexpected token '=>' at script.js:2:1"
+        );
+    }
+
+    #[test]
+    fn test_parenthesized_expressions_are_not_arrows() {
+        assert_eq!(
+            parse("(a) + (b, c)"),
+            bin(
+                ident("a"),
+                "+",
+                Node::SequenceExpression(SequenceExpression::new(vec![ident("b"), ident("c")]))
+            )
+        );
+        assert_eq!(
+            parse_error("(a + b) => c"),
+            "SyntaxError: Unexpected token '+' at script.js:1:4"
+        );
+    }
+
+    #[test]
+    fn test_unexpected_tokens() {
+        assert_eq!(
+            parse_error("a +"),
+            "SyntaxError: Unexpected end of input at script.js:1:4"
+        );
+        assert_eq!(
+            parse_error("a b"),
+            "SyntaxError: Unexpected identifier 'b' at script.js:1:3"
+        );
+        assert_eq!(
+            parse_error("(a;"),
+            "SyntaxError: Unexpected token ';' at script.js:1:3"
+        );
+        assert_eq!(
+            parse_error("a ? b"),
+            "SyntaxError: Unexpected end of input at script.js:1:6"
+        );
+        assert_eq!(
+            parse_error("f(1 2)"),
+            "SyntaxError: Unexpected number at script.js:1:5"
+        );
+    }
+
+    /// The byte offsets that the node at `pointer` in the serde output of
+    /// `node` spans.
+    fn byte_range(node: &Node, pointer: &str) -> (u64, u64) {
+        let value = serde_json::to_value(node).unwrap();
+        let span = &value.pointer(pointer).unwrap()["span"];
+        (
+            span["start"]["index"].as_u64().unwrap(),
+            span["end"]["index"].as_u64().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_spans() {
+        let parse = |src| crate::parser::parse_expression(src, "script.js").unwrap();
+        let node = parse("(a + b) * c.d");
+        assert_eq!(byte_range(&node, ""), (0, 13));
+        // Parentheses aren't part of the expression inside them.
+        assert_eq!(byte_range(&node, "/lhs"), (1, 6));
+        assert_eq!(byte_range(&node, "/rhs"), (10, 13));
+        assert_eq!(byte_range(&node, "/rhs/property"), (12, 13));
+
+        let node = parse("new a.b(c)?.[d]");
+        assert_eq!(byte_range(&node, ""), (0, 15));
+        assert_eq!(byte_range(&node, "/object"), (0, 10));
+        assert_eq!(byte_range(&node, "/object/callee"), (4, 7));
+
+        let node = parse("async ({ a = 1 }) => `${a}!`");
+        assert_eq!(byte_range(&node, ""), (0, 28));
+        assert_eq!(byte_range(&node, "/params/0/pat/properties/0"), (9, 14));
+        assert_eq!(byte_range(&node, "/body"), (21, 28));
+        // Template elements don't include the backticks or braces around them.
+        assert_eq!(byte_range(&node, "/body/quasis/0"), (22, 22));
+        assert_eq!(byte_range(&node, "/body/quasis/1"), (26, 27));
+
+        let span = parse("a =\n  'é'").span();
+        assert_eq!((span.start.line, span.start.column), (1, 1));
+        assert_eq!((span.end.line, span.end.column, span.end.index), (2, 6, 10));
+    }
+}
@@ -0,0 +1,371 @@
+//! Parsing for JSX elements and fragments.  The lexer only produces JSX tokens
+//! for files that can contain JSX (see [`super::lexer_options`]), so nothing
+//! here is reachable for plain `.js` files.
+
+use miette::Result;
+
+use crate::{
+    ast::{
+        JSXAttribute, JSXElement, JSXExpressionContainer, JSXFragment, JSXIdentifier,
+        JSXMemberExpression, JSXNamespacedName, JSXSpreadAttribute, JSXText, Node, StringLiteral,
+    },
+    lexer::{OperatorType, PunctuationType, Token},
+    location::Position,
+};
+
+use super::Parser;
+
+/// Writes an element name the way it appears in the source, for matching up
+/// opening and closing tags.
+fn jsx_name(name: &Node) -> String {
+    match name {
+        Node::JSXIdentifier(ident) => ident.value.clone(),
+        Node::JSXMemberExpression(member) => {
+            format!("{}.{}", jsx_name(&member.object), member.property.value)
+        }
+        Node::JSXNamespacedName(name) => format!("{}:{}", name.namespace.value, name.name.value),
+        _ => String::new(),
+    }
+}
+
+impl Parser<'_> {
+    /// Parses a JSX element or fragment starting at `start`, assuming its `<`
+    /// has been consumed.
+    pub(super) fn parse_jsx_element(&mut self, start: Position) -> Result<Node> {
+        if self.eat_jsx_tag_end() {
+            let children = self.parse_jsx_children()?;
+            self.expect_jsx_tag_end()?;
+            return Ok(Node::JSXFragment(
+                JSXFragment::new(children).with_span(self.span_from(start)),
+            ));
+        }
+
+        let name = self.parse_jsx_element_name()?;
+        let mut attributes = Vec::new();
+        loop {
+            if self.eat_operator(OperatorType::Division) {
+                self.expect_jsx_tag_end()?;
+                return Ok(Node::JSXElement(
+                    JSXElement::new(name, attributes, Vec::new(), true)
+                        .with_span(self.span_from(start)),
+                ));
+            }
+
+            if self.eat_jsx_tag_end() {
+                break;
+            }
+
+            attributes.push(self.parse_jsx_attribute()?);
+        }
+
+        let children = self.parse_jsx_children()?;
+        let matches_opening_tag = match self.peek() {
+            Some(Token::JSXIdentifier(_)) => {
+                jsx_name(&self.parse_jsx_element_name()?) == jsx_name(&name)
+            }
+            _ => false,
+        };
+        if !matches_opening_tag {
+            return Err(self.error(format!(
+                "Expected corresponding JSX closing tag for <{}>",
+                jsx_name(&name)
+            )));
+        }
+        self.expect_jsx_tag_end()?;
+
+        Ok(Node::JSXElement(
+            JSXElement::new(name, attributes, children, false).with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Parses the children of an element or fragment, up to and including the
+    /// `</` that starts its closing tag.
+    fn parse_jsx_children(&mut self) -> Result<Vec<Node>> {
+        let mut children = Vec::new();
+
+        loop {
+            let start = self.start_position();
+            match self.next() {
+                Some(Token::JSXText(text)) => children.push(Node::JSXText(
+                    JSXText::new(text.lexeme.into_owned()).with_span(self.previous_span),
+                )),
+                Some(Token::Punctuation(p)) if p.kind == PunctuationType::OpenBrace => {
+                    children.push(self.parse_jsx_expression_container(start, true)?);
+                }
+                Some(Token::JSXTagStart(_)) => {
+                    if self.eat_operator(OperatorType::Division) {
+                        return Ok(children);
+                    }
+                    children.push(self.parse_jsx_element(start)?);
+                }
+                token => return Err(self.unexpected(token.as_ref())),
+            }
+        }
+    }
+
+    /// Parses `{expression}` starting at `start`, assuming the `{` has been
+    /// consumed.  Only children may leave out the expression.
+    fn parse_jsx_expression_container(&mut self, start: Position, is_child: bool) -> Result<Node> {
+        let expression = if self.is_punctuation(PunctuationType::CloseBrace) {
+            if !is_child {
+                return Err(
+                    self.error("JSX attributes must only be assigned a non-empty expression")
+                );
+            }
+            None
+        } else {
+            if is_child && self.is_punctuation(PunctuationType::Ellipsis) {
+                return Err(self.error("Spread children are not supported in JSX"));
+            }
+            Some(self.parse_expression()?)
+        };
+        self.expect_punctuation(PunctuationType::CloseBrace)?;
+
+        Ok(Node::JSXExpressionContainer(
+            JSXExpressionContainer::new(expression).with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Parses `name`, `name="value"`, or `{...spread}` in an opening tag.
+    fn parse_jsx_attribute(&mut self) -> Result<Node> {
+        let start = self.start_position();
+
+        if self.eat_punctuation(PunctuationType::OpenBrace) {
+            self.expect_punctuation(PunctuationType::Ellipsis)?;
+            let argument = self.parse_assignment()?;
+            self.expect_punctuation(PunctuationType::CloseBrace)?;
+            return Ok(Node::JSXSpreadAttribute(
+                JSXSpreadAttribute::new(argument).with_span(self.span_from(start)),
+            ));
+        }
+
+        let name = self.parse_jsx_identifier()?;
+        let name = if self.eat_punctuation(PunctuationType::Colon) {
+            let local = self.parse_jsx_identifier()?;
+            Node::JSXNamespacedName(
+                JSXNamespacedName::new(name, local).with_span(self.span_from(start)),
+            )
+        } else {
+            Node::JSXIdentifier(name)
+        };
+
+        let value = if self.eat_operator(OperatorType::Assignment) {
+            let value_start = self.start_position();
+            Some(match self.next() {
+                Some(Token::StringLiteral(string)) => Node::StringLiteral(
+                    StringLiteral::new(string.lexeme.into_owned()).with_span(self.previous_span),
+                ),
+                Some(Token::Punctuation(p)) if p.kind == PunctuationType::OpenBrace => {
+                    self.parse_jsx_expression_container(value_start, false)?
+                }
+                Some(Token::JSXTagStart(_)) => self.parse_jsx_element(value_start)?,
+                token => return Err(self.unexpected(token.as_ref())),
+            })
+        } else {
+            None
+        };
+
+        Ok(Node::JSXAttribute(
+            JSXAttribute::new(name, value).with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Parses an element name: `a`, `a.b.c`, or `a:b`.
+    fn parse_jsx_element_name(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        let first = self.parse_jsx_identifier()?;
+
+        if self.eat_punctuation(PunctuationType::Colon) {
+            let name = self.parse_jsx_identifier()?;
+            return Ok(Node::JSXNamespacedName(
+                JSXNamespacedName::new(first, name).with_span(self.span_from(start)),
+            ));
+        }
+
+        let mut name = Node::JSXIdentifier(first);
+        while self.eat_punctuation(PunctuationType::Dot) {
+            let property = self.parse_jsx_identifier()?;
+            name = Node::JSXMemberExpression(
+                JSXMemberExpression::new(name, property).with_span(self.span_from(start)),
+            );
+        }
+
+        Ok(name)
+    }
+
+    fn parse_jsx_identifier(&mut self) -> Result<JSXIdentifier> {
+        match self.next() {
+            Some(Token::JSXIdentifier(ident)) => {
+                Ok(JSXIdentifier::new(ident.lexeme.into_owned()).with_span(self.previous_span))
+            }
+            token => Err(self.unexpected(token.as_ref())),
+        }
+    }
+
+    fn eat_jsx_tag_end(&mut self) -> bool {
+        let matched = matches!(self.peek(), Some(Token::JSXTagEnd(_)));
+        if matched {
+            self.next();
+        }
+        matched
+    }
+
+    fn expect_jsx_tag_end(&mut self) -> Result<()> {
+        if self.eat_jsx_tag_end() {
+            Ok(())
+        } else {
+            let token = self.next();
+            Err(self.unexpected(token.as_ref()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::parser::{parse, parse_expression, without_spans::to_estree_json};
+
+    fn jsx(src: &str) -> serde_json::Value {
+        to_estree_json(&parse_expression(src, "component.jsx").unwrap())
+    }
+
+    fn name(name: &str) -> serde_json::Value {
+        json!({ "type": "JSXIdentifier", "name": name })
+    }
+
+    #[test]
+    fn test_elements() {
+        assert_eq!(
+            jsx(r#"<a href="b" hidden>c{d}</a>"#),
+            json!({
+                "type": "JSXElement",
+                "openingElement": {
+                    "type": "JSXOpeningElement",
+                    "name": name("a"),
+                    "attributes": [
+                        {
+                            "type": "JSXAttribute",
+                            "name": name("href"),
+                            "value": { "type": "Literal", "value": "b" },
+                        },
+                        { "type": "JSXAttribute", "name": name("hidden"), "value": null },
+                    ],
+                    "selfClosing": false,
+                },
+                "closingElement": { "type": "JSXClosingElement", "name": name("a") },
+                "children": [
+                    { "type": "JSXText", "value": "c", "raw": "c" },
+                    {
+                        "type": "JSXExpressionContainer",
+                        "expression": { "type": "Identifier", "name": "d" },
+                    },
+                ],
+            })
+        );
+
+        let element = jsx("<ui.Button {...props} on:click={() => 1} />");
+        assert_eq!(element["closingElement"], json!(null));
+        assert_eq!(
+            element["openingElement"]["name"],
+            json!({ "type": "JSXMemberExpression", "object": name("ui"), "property": name("Button") })
+        );
+        assert_eq!(
+            element["openingElement"]["attributes"][0],
+            json!({ "type": "JSXSpreadAttribute", "argument": { "type": "Identifier", "name": "props" } })
+        );
+        assert_eq!(
+            element["openingElement"]["attributes"][1]["name"],
+            json!({ "type": "JSXNamespacedName", "namespace": name("on"), "name": name("click") })
+        );
+        assert_eq!(
+            element["openingElement"]["attributes"][1]["value"]["expression"]["type"],
+            json!("ArrowFunctionExpression")
+        );
+
+        assert_eq!(
+            jsx("<a>{n-1}</a>")["children"][0]["expression"]["operator"],
+            json!("-")
+        );
+    }
+
+    #[test]
+    fn test_fragments_and_nesting() {
+        assert_eq!(
+            jsx("<><b>{/* c */}</b><br/></>"),
+            json!({
+                "type": "JSXFragment",
+                "openingFragment": { "type": "JSXOpeningFragment" },
+                "closingFragment": { "type": "JSXClosingFragment" },
+                "children": [
+                    {
+                        "type": "JSXElement",
+                        "openingElement": {
+                            "type": "JSXOpeningElement",
+                            "name": name("b"),
+                            "attributes": [],
+                            "selfClosing": false,
+                        },
+                        "closingElement": { "type": "JSXClosingElement", "name": name("b") },
+                        "children": [{
+                            "type": "JSXExpressionContainer",
+                            "expression": { "type": "JSXEmptyExpression" },
+                        }],
+                    },
+                    {
+                        "type": "JSXElement",
+                        "openingElement": {
+                            "type": "JSXOpeningElement",
+                            "name": name("br"),
+                            "attributes": [],
+                            "selfClosing": true,
+                        },
+                        "closingElement": null,
+                        "children": [],
+                    },
+                ],
+            })
+        );
+
+        // Elements are primary expressions, and can be attribute values.
+        let call = jsx("f(<a b=<c /> />, 1)");
+        assert_eq!(call["arguments"][0]["type"], json!("JSXElement"));
+        assert_eq!(
+            call["arguments"][0]["openingElement"]["attributes"][0]["value"]["type"],
+            json!("JSXElement")
+        );
+    }
+
+    #[test]
+    fn test_jsx_only_in_jsx_files() {
+        assert!(parse("const a = <b />;", "component.jsx").is_ok());
+        assert!(parse("const a = <b />;", "component.tsx").is_ok());
+        assert!(parse("const a = <b />;", "script.js").is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        for (src, expected) in [
+            (
+                "<a></b>",
+                "SyntaxError: Expected corresponding JSX closing tag for <a> at component.jsx:1:6",
+            ),
+            (
+                "<a.b></a>",
+                "SyntaxError: Expected corresponding JSX closing tag for <a.b> at component.jsx:1:8",
+            ),
+            (
+                "<a></>",
+                "SyntaxError: Expected corresponding JSX closing tag for <a> at component.jsx:1:5",
+            ),
+            (
+                "<a b={} />",
+                "SyntaxError: JSX attributes must only be assigned a non-empty expression at component.jsx:1:6",
+            ),
+            ("<a b=1 />", "Unexpected character '1' in JSX tag"),
+        ] {
+            let err = parse_expression(src, "component.jsx").unwrap_err();
+            assert!(err.to_string().contains(expected), "{}: {}", src, err);
+        }
+    }
+}
@@ -0,0 +1,465 @@
+//! Turns the token stream produced by the lexer into the tree in [`crate::ast`].
+//!
+//! This is a hand written recursive descent parser, with binary operators
+//! handled by precedence climbing (see `expression.rs`).  The parser works on
+//! a fully lexed token stream with comments already removed, which gives it
+//! as much lookahead as it wants for free.
+
+use std::collections::HashSet;
+
+use miette::{miette, ErrReport, LabeledSpan, NamedSource, Result, Severity};
+
+use self::scope::Scope;
+use crate::{
+    ast::{Node, Program},
+    lexer::{self, KeywordType, LexerOptions, OperatorType, PunctuationType, Token, TokenStream},
+    location::{Position, Span},
+};
+
+mod expression;
+mod jsx;
+mod module;
+mod pattern;
+mod scope;
+mod statement;
+mod typescript;
+
+/// Syntax extensions the parser accepts on top of standard JavaScript, all off
+/// by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParserOptions {
+    /// Accept TypeScript's type annotations and declarations, leaving them out
+    /// of the AST so that what's left is plain JavaScript.
+    pub typescript: bool,
+    /// Parse the source as a module, which is strict mode code where `await`
+    /// is reserved, even if it has no import or export declarations.
+    pub module: bool,
+}
+
+/// The name of `token` if it's an identifier.  Contextual keywords like `of`
+/// and `let` are ordinary identifiers everywhere the parser isn't looking for
+/// them as keywords.  So are `await` and `yield`, outside of the places that
+/// reserve them (see [`Parser::check_identifier_reference`]).
+fn identifier_name(token: &Token) -> Option<String> {
+    match token {
+        Token::Ident(ident) => Some(ident.lexeme.to_string()),
+        Token::Keyword(keyword) if keyword.kind.is_contextual() => Some(keyword.kind.to_string()),
+        Token::Operator(operator)
+            if matches!(operator.kind, OperatorType::Await | OperatorType::Yield) =>
+        {
+            Some(operator.kind.to_string())
+        }
+        _ => None,
+    }
+}
+
+pub struct Parser<'src> {
+    /// The tokens that have not been consumed yet, in reverse order so that
+    /// consuming the next one is just a pop off the end.
+    tokens: Vec<Token<'src>>,
+    /// Whether each of `tokens` comes after a line terminator, in the same
+    /// order.
+    line_breaks: Vec<bool>,
+    /// Where each of `tokens` is in the source, in the same order.
+    spans: Vec<Span>,
+    /// The span of the token consumed last, which is where the node being
+    /// parsed ends.
+    previous_span: Span,
+    /// The scopes we're currently inside of, innermost last.  The first one
+    /// is the top level of the program.
+    scopes: Vec<Scope>,
+    /// How many `{` we've consumed without the matching `}`, which lets
+    /// error recovery tell which block it's in.
+    brace_depth: usize,
+    /// Whether to keep going after a syntax error, collecting the errors in
+    /// `errors`, rather than stopping at the first one.
+    recover: bool,
+    errors: Vec<ErrReport>,
+    options: ParserOptions,
+    /// Whether the program is a module, either because the options say so
+    /// or because it has import or export declarations.
+    is_module: bool,
+    /// The names the module exports so far, each of which can only be
+    /// exported once.
+    exported_names: HashSet<String>,
+    /// The source the tokens were lexed from, for quoting in errors.
+    source: &'src str,
+    file_path: String,
+}
+
+impl<'src> Parser<'src> {
+    /// Creates a parser over the output of [`lexer::tokenize_stream`].
+    pub fn new(stream: TokenStream<'src>, file_path: impl Into<String>) -> Self {
+        Self::with_options(stream, file_path, ParserOptions::default())
+    }
+
+    /// Like [`Parser::new`], with syntax extensions turned on.
+    pub fn with_options(
+        stream: TokenStream<'src>,
+        file_path: impl Into<String>,
+        options: ParserOptions,
+    ) -> Self {
+        let mut tokens = Vec::new();
+        let mut line_breaks = Vec::new();
+        let mut spans = Vec::new();
+        for (token, line_break) in stream.tokens.into_iter().zip(stream.line_breaks).rev() {
+            if !matches!(token, Token::Comment(_)) {
+                spans.push(token.span());
+                tokens.push(token);
+                line_breaks.push(line_break);
+            }
+        }
+
+        Self {
+            tokens,
+            line_breaks,
+            spans,
+            previous_span: Span::default(),
+            scopes: vec![Scope::program()],
+            brace_depth: 0,
+            recover: false,
+            errors: Vec::new(),
+            options,
+            is_module: options.module,
+            exported_names: HashSet::new(),
+            source: stream.source,
+            file_path: file_path.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token<'src>> {
+        self.tokens.last()
+    }
+
+    /// Returns the token `n` tokens past the next one, so `peek_nth(0)` is the
+    /// same as `peek()`.
+    fn peek_nth(&self, n: usize) -> Option<&Token<'src>> {
+        self.tokens
+            .len()
+            .checked_sub(n + 1)
+            .and_then(|index| self.tokens.get(index))
+    }
+
+    /// Given that `peek_nth(n)` is an opening `(`, `[` or `{`, returns the
+    /// offset of the bracket that closes it, or `None` if the input ends
+    /// first.
+    fn find_matching_close(&self, n: usize) -> Option<usize> {
+        let (open, close) = match self.peek_nth(n) {
+            Some(Token::Punctuation(p)) => match p.kind {
+                PunctuationType::OpenParen => (p.kind, PunctuationType::CloseParen),
+                PunctuationType::OpenBracket => (p.kind, PunctuationType::CloseBracket),
+                PunctuationType::OpenBrace => (p.kind, PunctuationType::CloseBrace),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let mut depth = 0;
+        for offset in n.. {
+            match self.peek_nth(offset)? {
+                Token::Punctuation(p) if p.kind == open => depth += 1,
+                Token::Punctuation(p) if p.kind == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(offset);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn next(&mut self) -> Option<Token<'src>> {
+        self.line_breaks.pop();
+        if let Some(span) = self.spans.pop() {
+            self.previous_span = span;
+        }
+        let token = self.tokens.pop();
+        match &token {
+            Some(Token::Punctuation(p)) if p.kind == PunctuationType::OpenBrace => {
+                self.brace_depth += 1;
+            }
+            Some(Token::Punctuation(p)) if p.kind == PunctuationType::CloseBrace => {
+                self.brace_depth = self.brace_depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+        token
+    }
+
+    /// Where the next token starts, which is where a node parsed from it
+    /// starts.  At the end of input, this is where the last token ended.
+    fn start_position(&self) -> Position {
+        match self.spans.last() {
+            Some(span) => span.start,
+            None => self.previous_span.end,
+        }
+    }
+
+    /// The span from `start` to the end of the token consumed last.
+    fn span_from(&self, start: Position) -> Span {
+        Span::new(start, self.previous_span.end)
+    }
+
+    /// Whether there is a line terminator between the previous token and the
+    /// next one.
+    fn has_line_break_before(&self) -> bool {
+        self.has_line_break_before_nth(0)
+    }
+
+    /// Like [`Parser::has_line_break_before`], but for the token `n` tokens
+    /// past the next one.
+    fn has_line_break_before_nth(&self, n: usize) -> bool {
+        self.line_breaks
+            .len()
+            .checked_sub(n + 1)
+            .is_some_and(|index| self.line_breaks[index])
+    }
+
+    fn is_punctuation(&self, kind: PunctuationType) -> bool {
+        matches!(self.peek(), Some(Token::Punctuation(p)) if p.kind == kind)
+    }
+
+    fn is_operator(&self, kind: OperatorType) -> bool {
+        matches!(self.peek(), Some(Token::Operator(o)) if o.kind == kind)
+    }
+
+    fn is_keyword(&self, kind: KeywordType) -> bool {
+        matches!(self.peek(), Some(Token::Keyword(k)) if k.kind == kind)
+    }
+
+    /// Whether `peek_nth(n)` is an identifier, counting contextual keywords
+    /// (see [`identifier_name`]).
+    fn is_identifier_at(&self, n: usize) -> bool {
+        self.peek_nth(n).and_then(identifier_name).is_some()
+    }
+
+    /// Consumes the next token if it is the given punctuation, returning
+    /// whether it did.
+    fn eat_punctuation(&mut self, kind: PunctuationType) -> bool {
+        let matched = self.is_punctuation(kind);
+        if matched {
+            self.next();
+        }
+        matched
+    }
+
+    /// Consumes the next token if it is the given operator, returning whether
+    /// it did.
+    fn eat_operator(&mut self, kind: OperatorType) -> bool {
+        let matched = self.is_operator(kind);
+        if matched {
+            self.next();
+        }
+        matched
+    }
+
+    fn expect_punctuation(&mut self, kind: PunctuationType) -> Result<()> {
+        if self.eat_punctuation(kind) {
+            Ok(())
+        } else {
+            let token = self.next();
+            Err(self.unexpected(token.as_ref()))
+        }
+    }
+
+    /// Builds the error for a token (or the end of input, for `None`) that
+    /// isn't allowed where we found it.
+    fn unexpected(&self, token: Option<&Token>) -> ErrReport {
+        match token {
+            Some(token) => self.error_at(token.span(), format!("Unexpected {}", token)),
+            None => {
+                let end = self.previous_span.end;
+                self.error_at(Span::new(end, end), "Unexpected end of input")
+            }
+        }
+    }
+
+    /// Builds a syntax error about the token consumed last.
+    fn error(&self, err_msg: impl AsRef<str>) -> ErrReport {
+        self.error_at(self.previous_span, err_msg)
+    }
+
+    /// Builds a syntax error about the source at `span`, labeled and located
+    /// the same way as the lexer's errors.
+    fn error_at(&self, span: Span, err_msg: impl AsRef<str>) -> ErrReport {
+        let err_msg = err_msg.as_ref();
+        // Before any token has been consumed, the span is all zeroes, which
+        // is still the start of the file.
+        let line = span.start.line.max(1);
+        let column = span.start.column.max(1);
+        let label = LabeledSpan::at(span.start.index..span.end.index, err_msg);
+
+        miette!(
+            severity = Severity::Error,
+            code = "SyntaxError",
+            labels = vec![label],
+            "SyntaxError: {} at {}:{}:{}",
+            err_msg,
+            self.file_path,
+            line,
+            column
+        )
+        .with_source_code(NamedSource::new(
+            self.file_path.clone(),
+            self.source.to_string(),
+        ))
+    }
+}
+
+/// Whether `file_name` has one of TypeScript's extensions: `.ts`, `.tsx`,
+/// `.mts` or `.cts`.
+fn is_typescript_file(file_name: &str) -> bool {
+    [".ts", ".tsx", ".mts", ".cts"]
+        .iter()
+        .any(|extension| file_name.ends_with(extension))
+}
+
+/// The options to lex `file_name` with, going by its extension: `.jsx` and
+/// `.tsx` files can contain JSX, `.mjs` and `.mts` files are modules, and
+/// TypeScript files are lexed as TypeScript.
+pub fn lexer_options(file_name: &str) -> LexerOptions {
+    LexerOptions {
+        jsx: file_name.ends_with(".jsx") || file_name.ends_with(".tsx"),
+        typescript: is_typescript_file(file_name),
+        module: file_name.ends_with(".mjs") || file_name.ends_with(".mts"),
+        ..LexerOptions::default()
+    }
+}
+
+/// The syntax extensions to parse `file_name` with, going by its extension:
+/// TypeScript files (see [`lexer_options`]) are parsed as TypeScript, and
+/// `.mjs` and `.mts` files as modules.
+pub fn parser_options(file_name: &str) -> ParserOptions {
+    let lexer_options = lexer_options(file_name);
+    ParserOptions {
+        typescript: lexer_options.typescript,
+        module: lexer_options.module,
+    }
+}
+
+/// Parses `src` as a single expression, e.g. `a + b * c`.  It is an error for
+/// anything but comments to follow the expression.
+pub fn parse_expression(src: &str, file_name: impl Into<String>) -> Result<Node> {
+    let file_name = file_name.into();
+    let stream = lexer::tokenize_stream_with_options(src, &file_name, lexer_options(&file_name))?;
+    let options = parser_options(&file_name);
+    let mut parser = Parser::with_options(stream, file_name, options);
+
+    let expression = parser.parse_expression()?;
+    if let Some(token) = parser.peek() {
+        return Err(parser.unexpected(Some(token)));
+    }
+
+    Ok(expression)
+}
+
+/// Parses `src` as a script made up of statements.
+pub fn parse(src: &str, file_name: impl Into<String>) -> Result<Program> {
+    let file_name = file_name.into();
+    let stream = lexer::tokenize_stream_with_options(src, &file_name, lexer_options(&file_name))?;
+    let options = parser_options(&file_name);
+    let mut parser = Parser::with_options(stream, file_name, options);
+
+    parser.parse_program()
+}
+
+/// Like [`parse`], but rather than stopping at the first syntax error, skips
+/// the statement it's in and carries on.  Returns the statements that did
+/// parse, along with every error found, which is what editors and linters
+/// want.  Errors from the lexer still end parsing, since there are no tokens
+/// to carry on with.
+pub fn parse_with_recovery(src: &str, file_name: impl Into<String>) -> (Program, Vec<ErrReport>) {
+    let file_name = file_name.into();
+    let stream =
+        match lexer::tokenize_stream_with_options(src, &file_name, lexer_options(&file_name)) {
+            Ok(stream) => stream,
+            Err(err) => return (Program::default(), vec![err]),
+        };
+
+    let options = parser_options(&file_name);
+    let mut parser = Parser::with_options(stream, file_name, options);
+    parser.recover = true;
+    match parser.parse_program() {
+        Ok(program) => (program, parser.errors),
+        Err(err) => {
+            parser.errors.push(err);
+            (Program::default(), parser.errors)
+        }
+    }
+}
+
+/// Helpers shared by the parser's tests.
+#[cfg(test)]
+pub(crate) mod test_helpers {
+    use crate::ast::{Identifier, Node};
+
+    /// The message of the error from parsing `src` as a script.
+    pub(crate) fn parse_error(src: &str) -> String {
+        super::parse(src, "script.js").unwrap_err().to_string()
+    }
+
+    /// An identifier built by hand, with a default span.
+    pub(crate) fn ident(name: &str) -> Node {
+        Node::Identifier(Identifier::new(name.to_string()))
+    }
+}
+
+/// The parse functions for tests to compare the AST against one built by hand.
+/// Nodes built by hand have default spans, so these reset every span in the
+/// AST they return.
+#[cfg(test)]
+pub(crate) mod without_spans {
+    use miette::{ErrReport, Result};
+    use serde_json::Value;
+
+    use crate::ast::{
+        visit::{walk_node_mut, walk_program_mut, ClearSpans},
+        Node, Program,
+    };
+
+    pub(crate) fn clear_node(mut node: Node) -> Node {
+        walk_node_mut(&mut ClearSpans, &mut node);
+        node
+    }
+
+    pub(crate) fn clear_program(mut program: Program) -> Program {
+        walk_program_mut(&mut ClearSpans, &mut program);
+        program
+    }
+
+    pub(crate) fn parse_expression(src: &str, file_name: &str) -> Result<Node> {
+        super::parse_expression(src, file_name).map(clear_node)
+    }
+
+    pub(crate) fn parse(src: &str, file_name: &str) -> Result<Program> {
+        super::parse(src, file_name).map(clear_program)
+    }
+
+    pub(crate) fn parse_with_recovery(src: &str, file_name: &str) -> (Program, Vec<ErrReport>) {
+        let (program, errors) = super::parse_with_recovery(src, file_name);
+        (clear_program(program), errors)
+    }
+
+    /// Converts `node` to ESTree without the location fields.
+    pub(crate) fn to_estree_json(node: &Node) -> Value {
+        fn strip(value: &mut Value) {
+            match value {
+                Value::Object(object) => {
+                    for key in ["start", "end", "loc"] {
+                        object.remove(key);
+                    }
+                    object.values_mut().for_each(strip);
+                }
+                Value::Array(array) => array.iter_mut().for_each(strip),
+                _ => {}
+            }
+        }
+
+        let mut value = node.to_estree_json();
+        strip(&mut value);
+        value
+    }
+}
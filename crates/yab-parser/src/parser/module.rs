@@ -0,0 +1,668 @@
+//! Parsing for `import` and `export` declarations, which may only appear at
+//! the top level of a module.
+
+use miette::Result;
+
+use crate::{
+    ast::{
+        ExportAllDeclaration, ExportDefaultDeclaration, ExportNamedDeclaration, ExportSpecifier,
+        Identifier, ImportDeclaration, ImportDefaultSpecifier, ImportNamespaceSpecifier,
+        ImportSpecifier, Node, StringLiteral,
+    },
+    lexer::{KeywordType, OperatorType, PunctuationType, Token},
+    location::Span,
+};
+
+use super::{identifier_name, scope::BindingKind, Parser};
+
+/// A name in an import or export specifier list, which can be any identifier
+/// name (including reserved words) or a string literal.
+#[derive(Clone)]
+enum ModuleExportName {
+    /// An identifier that could also be used as a binding.
+    Binding(String),
+    Reserved(String),
+    String(String),
+}
+
+impl ModuleExportName {
+    fn name(&self) -> &str {
+        match self {
+            ModuleExportName::Binding(name)
+            | ModuleExportName::Reserved(name)
+            | ModuleExportName::String(name) => name,
+        }
+    }
+
+    fn into_node(self, span: Span) -> Node {
+        match self {
+            ModuleExportName::Binding(name) | ModuleExportName::Reserved(name) => {
+                Node::Identifier(Identifier::new(name).with_span(span))
+            }
+            ModuleExportName::String(value) => {
+                Node::StringLiteral(StringLiteral::new(value).with_span(span))
+            }
+        }
+    }
+}
+
+impl Parser<'_> {
+    pub(super) fn is_contextual_keyword(&self, name: &str) -> bool {
+        self.peek()
+            .and_then(identifier_name)
+            .is_some_and(|ident| ident == name)
+    }
+
+    /// Consumes the next token if it is an identifier spelled `name`, for the
+    /// words like `as` and `from` that are only keywords in certain places.
+    pub(super) fn eat_contextual_keyword(&mut self, name: &str) -> bool {
+        let matched = self.is_contextual_keyword(name);
+        if matched {
+            self.next();
+        }
+        matched
+    }
+
+    pub(super) fn expect_contextual_keyword(&mut self, name: &str) -> Result<()> {
+        if self.eat_contextual_keyword(name) {
+            Ok(())
+        } else {
+            let token = self.next();
+            Err(self.unexpected(token.as_ref()))
+        }
+    }
+
+    /// Whether the program has an import or export declaration, which makes it
+    /// a module rather than a script, and so strict mode code from the start.
+    /// Calls to `import()` and `import.meta` can be used in scripts too, as
+    /// can properties named like the keywords, as in `a.export`.
+    pub(super) fn has_module_syntax(&self) -> bool {
+        let mut depth = 0usize;
+        for n in 0..self.tokens.len() {
+            match self.peek_nth(n) {
+                Some(Token::Punctuation(p)) => match p.kind {
+                    PunctuationType::OpenParen
+                    | PunctuationType::OpenBracket
+                    | PunctuationType::OpenBrace => depth += 1,
+                    PunctuationType::CloseParen
+                    | PunctuationType::CloseBracket
+                    | PunctuationType::CloseBrace => depth = depth.saturating_sub(1),
+                    _ => {}
+                },
+                Some(Token::Keyword(keyword))
+                    if depth == 0
+                        && matches!(keyword.kind, KeywordType::Import | KeywordType::Export) =>
+                {
+                    let is_expression = matches!(
+                        self.peek_nth(n + 1),
+                        Some(Token::Punctuation(p))
+                            if matches!(p.kind, PunctuationType::OpenParen | PunctuationType::Dot)
+                    );
+                    let is_property = n > 0
+                        && match self.peek_nth(n - 1) {
+                            Some(Token::Punctuation(p)) => p.kind == PunctuationType::Dot,
+                            Some(Token::Operator(o)) => o.kind == OperatorType::OptionalChaining,
+                            _ => false,
+                        };
+                    if !is_expression && !is_property {
+                        return true;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        false
+    }
+
+    /// Records that the module exports `name`, which was written at `span`,
+    /// failing if it already exports something by that name.
+    fn declare_export(&mut self, name: &str, span: Span) -> Result<()> {
+        if !self.exported_names.insert(name.to_string()) {
+            return Err(self.error_at(span, format!("Duplicate export of '{}'", name)));
+        }
+        Ok(())
+    }
+
+    pub(super) fn parse_module_specifier(&mut self) -> Result<StringLiteral> {
+        match self.next() {
+            Some(Token::StringLiteral(string)) => {
+                Ok(StringLiteral::new(string.lexeme.into_owned())
+                    .with_code_units(string.code_units)
+                    .with_span(self.previous_span))
+            }
+            token => Err(self.unexpected(token.as_ref())),
+        }
+    }
+
+    fn parse_module_export_name(&mut self) -> Result<ModuleExportName> {
+        match self.peek() {
+            Some(_) if self.is_identifier_at(0) => {
+                Ok(ModuleExportName::Binding(self.parse_binding_identifier()?))
+            }
+            Some(Token::StringLiteral(_)) => {
+                let Some(Token::StringLiteral(string)) = self.next() else {
+                    unreachable!()
+                };
+                if string.code_units.is_some() {
+                    return Err(self.error("Export names can't contain lone surrogates"));
+                }
+                Ok(ModuleExportName::String(string.lexeme.into_owned()))
+            }
+            _ => Ok(ModuleExportName::Reserved(self.parse_identifier_name()?)),
+        }
+    }
+
+    /// ImportDeclaration:
+    ///   `import` ModuleSpecifier
+    ///   `import` ImportedDefaultBinding `from` ModuleSpecifier
+    ///   `import` ImportedDefaultBinding? `,`? NameSpaceImport `from` ModuleSpecifier
+    ///   `import` ImportedDefaultBinding? `,`? NamedImports `from` ModuleSpecifier
+    ///
+    /// In TypeScript, a declaration whose named imports are all marked `type`
+    /// imports nothing at runtime, so it's left out, and returns `None`.
+    pub(super) fn parse_import_declaration(&mut self) -> Result<Option<Node>> {
+        let start = self.start_position();
+        self.expect_keyword(KeywordType::Import)?;
+
+        if let Some(Token::StringLiteral(_)) = self.peek() {
+            let source = self.parse_module_specifier()?;
+            self.consume_semicolon()?;
+            return Ok(Some(Node::ImportDeclaration(
+                ImportDeclaration::new(Vec::new(), source).with_span(self.span_from(start)),
+            )));
+        }
+
+        let mut specifiers = Vec::new();
+        let mut has_type_only = false;
+        let has_default = self.is_identifier_at(0);
+        if has_default {
+            let local = self.parse_import_binding()?;
+            let span = local.span();
+            specifiers.push(Node::ImportDefaultSpecifier(
+                ImportDefaultSpecifier::new(local).with_span(span),
+            ));
+        }
+
+        if !has_default || self.eat_punctuation(PunctuationType::Comma) {
+            let namespace_start = self.start_position();
+            if self.eat_operator(OperatorType::Multiplication) {
+                self.expect_contextual_keyword("as")?;
+                let local = self.parse_import_binding()?;
+                specifiers.push(Node::ImportNamespaceSpecifier(
+                    ImportNamespaceSpecifier::new(local).with_span(self.span_from(namespace_start)),
+                ));
+            } else {
+                has_type_only = self.parse_named_imports(&mut specifiers)?;
+            }
+        }
+
+        self.expect_contextual_keyword("from")?;
+        let source = self.parse_module_specifier()?;
+        self.consume_semicolon()?;
+
+        if has_type_only && specifiers.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Node::ImportDeclaration(
+            ImportDeclaration::new(specifiers, source).with_span(self.span_from(start)),
+        )))
+    }
+
+    /// Parses the name an import is bound to, and declares it.
+    fn parse_import_binding(&mut self) -> Result<Identifier> {
+        let name = self.parse_binding_identifier()?;
+        let span = self.previous_span;
+        self.declare(&name, BindingKind::Lexical)?;
+        Ok(Identifier::new(name).with_span(span))
+    }
+
+    /// Parses `{ a, b as c, "d" as e }` in an import declaration, returning
+    /// whether any of them were type-only, and so left out.
+    fn parse_named_imports(&mut self, specifiers: &mut Vec<Node>) -> Result<bool> {
+        self.expect_punctuation(PunctuationType::OpenBrace)?;
+
+        let mut has_type_only = false;
+        while !self.eat_punctuation(PunctuationType::CloseBrace) {
+            let start = self.start_position();
+            let type_only = self.eat_type_only_marker();
+            let imported = self.parse_module_export_name()?;
+            let imported_span = self.previous_span;
+            let local = if self.eat_contextual_keyword("as") {
+                self.parse_binding_identifier()?
+            } else if let ModuleExportName::Binding(name) = &imported {
+                name.clone()
+            } else {
+                // `import { default } from "mod"` would bind a reserved word.
+                let token = self.next();
+                return Err(self.unexpected(token.as_ref()));
+            };
+
+            let local_span = self.previous_span;
+            has_type_only |= type_only;
+            if !type_only {
+                self.declare(&local, BindingKind::Lexical)?;
+                specifiers.push(Node::ImportSpecifier(
+                    ImportSpecifier::new(
+                        imported.into_node(imported_span),
+                        Identifier::new(local).with_span(local_span),
+                    )
+                    .with_span(self.span_from(start)),
+                ));
+            }
+
+            if !self.eat_punctuation(PunctuationType::Comma) {
+                self.expect_punctuation(PunctuationType::CloseBrace)?;
+                break;
+            }
+        }
+
+        Ok(has_type_only)
+    }
+
+    /// Parses an export declaration, which like an import declaration is
+    /// `None` if it only exported types.
+    pub(super) fn parse_export_declaration(&mut self) -> Result<Option<Node>> {
+        let start = self.start_position();
+        self.expect_keyword(KeywordType::Export)?;
+
+        if self.eat_operator(OperatorType::Multiplication) {
+            let exported = if self.eat_contextual_keyword("as") {
+                let name = self.parse_module_export_name()?;
+                self.declare_export(name.name(), self.previous_span)?;
+                Some(name.into_node(self.previous_span))
+            } else {
+                None
+            };
+            self.expect_contextual_keyword("from")?;
+            let source = self.parse_module_specifier()?;
+            self.consume_semicolon()?;
+
+            return Ok(Some(Node::ExportAllDeclaration(
+                ExportAllDeclaration::new(exported, source).with_span(self.span_from(start)),
+            )));
+        }
+
+        if self.is_keyword(KeywordType::Default) {
+            self.next();
+            self.declare_export("default", self.previous_span)?;
+            let declaration = if self.is_keyword(KeywordType::Function) {
+                self.parse_function_declaration(true)?
+            } else {
+                let expression = self.parse_assignment()?;
+                self.consume_semicolon()?;
+                expression
+            };
+
+            return Ok(Some(Node::ExportDefaultDeclaration(
+                ExportDefaultDeclaration::new(declaration).with_span(self.span_from(start)),
+            )));
+        }
+
+        if self.is_punctuation(PunctuationType::OpenBrace) {
+            let declaration = self.parse_export_specifiers()?;
+            return Ok(declaration.map(|declaration| declaration.with_span(self.span_from(start))));
+        }
+
+        let declaration = match self.peek() {
+            Some(Token::Keyword(keyword))
+                if matches!(
+                    keyword.kind,
+                    KeywordType::Var | KeywordType::Let | KeywordType::Const
+                ) =>
+            {
+                let declaration = self.parse_variable_declaration()?;
+                self.consume_semicolon()?;
+                declaration
+            }
+            Some(Token::Keyword(keyword)) if keyword.kind == KeywordType::Function => {
+                self.parse_function_declaration(false)?
+            }
+            _ => {
+                let token = self.next();
+                return Err(self.unexpected(token.as_ref()));
+            }
+        };
+
+        let mut names = Vec::new();
+        match &declaration {
+            Node::VariableDeclaration(variables) => {
+                for declarator in &variables.declarations {
+                    bound_identifiers(&declarator.id, &mut names);
+                }
+            }
+            Node::FunctionDeclaration(function) => names.extend(&function.identifier),
+            _ => {}
+        }
+        for name in names {
+            self.declare_export(name.name(), name.span)?;
+        }
+
+        Ok(Some(Node::ExportNamedDeclaration(
+            ExportNamedDeclaration::new(Some(declaration), Vec::new(), None)
+                .with_span(self.span_from(start)),
+        )))
+    }
+
+    /// Parses `{ a, b as c } from "mod"`, where the `from` clause is optional.
+    /// Returns `None` if every specifier was type-only.
+    fn parse_export_specifiers(&mut self) -> Result<Option<Node>> {
+        self.expect_punctuation(PunctuationType::OpenBrace)?;
+
+        let mut specifiers = Vec::new();
+        let mut has_type_only = false;
+        let mut first_non_binding = None;
+        while !self.eat_punctuation(PunctuationType::CloseBrace) {
+            let start = self.start_position();
+            let type_only = self.eat_type_only_marker();
+            let local = self.parse_module_export_name()?;
+            let local_span = self.previous_span;
+            let exported = if self.eat_contextual_keyword("as") {
+                self.parse_module_export_name()?
+            } else {
+                local.clone()
+            };
+            let exported_span = self.previous_span;
+            if !matches!(local, ModuleExportName::Binding(_)) && first_non_binding.is_none() {
+                first_non_binding = Some((local.clone(), local_span));
+            }
+            has_type_only |= type_only;
+            if !type_only {
+                self.declare_export(exported.name(), exported_span)?;
+                specifiers.push(Node::ExportSpecifier(
+                    ExportSpecifier::new(
+                        local.into_node(local_span),
+                        exported.into_node(exported_span),
+                    )
+                    .with_span(self.span_from(start)),
+                ));
+            }
+
+            if !self.eat_punctuation(PunctuationType::Comma) {
+                self.expect_punctuation(PunctuationType::CloseBrace)?;
+                break;
+            }
+        }
+
+        let source = if self.eat_contextual_keyword("from") {
+            Some(self.parse_module_specifier()?)
+        } else {
+            // Without a `from`, the local names refer to bindings in this
+            // module, so they have to be names that could be bound.
+            match first_non_binding {
+                Some((ModuleExportName::Reserved(name), span)) => {
+                    return Err(self.error_at(span, format!("Unexpected reserved word '{}'", name)));
+                }
+                Some((ModuleExportName::String(_), span)) => {
+                    return Err(self.error_at(
+                        span,
+                        "A string literal cannot be used as an exported binding without `from`",
+                    ));
+                }
+                _ => None,
+            }
+        };
+        self.consume_semicolon()?;
+
+        if has_type_only && specifiers.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Node::ExportNamedDeclaration(
+            ExportNamedDeclaration::new(None, specifiers, source),
+        )))
+    }
+}
+
+/// Collects the identifiers that `pattern` binds, leaving out the ones in
+/// default values.
+fn bound_identifiers<'a>(pattern: &'a Node, identifiers: &mut Vec<&'a Identifier>) {
+    match pattern {
+        Node::Identifier(identifier) => identifiers.push(identifier),
+        Node::ObjectPattern(object) => {
+            for property in &object.properties {
+                bound_identifiers(property, identifiers);
+            }
+        }
+        Node::Property(property) => bound_identifiers(&property.value, identifiers),
+        Node::ArrayPattern(array) => {
+            for element in array.elements.iter().flatten() {
+                bound_identifiers(element, identifiers);
+            }
+        }
+        Node::RestElement(rest) => bound_identifiers(&rest.argument, identifiers),
+        Node::AssignmentPattern(assignment) => bound_identifiers(&assignment.lhs, identifiers),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{FunctionDeclaration, NumericLiteral, VariableDeclaration, VariableDeclarator},
+        parser::{
+            test_helpers::{ident, parse_error},
+            without_spans::clear_node,
+        },
+    };
+
+    use super::*;
+
+    fn parse_item(src: &str) -> Node {
+        let stream = crate::lexer::tokenize_stream(src, "script.js").unwrap();
+        let mut parser = Parser::new(stream, "script.js");
+        let item = if parser.is_keyword(KeywordType::Import) {
+            parser.parse_import_declaration().unwrap().unwrap()
+        } else {
+            parser.parse_export_declaration().unwrap().unwrap()
+        };
+        assert!(parser.peek().is_none());
+        clear_node(item)
+    }
+
+    fn identifier(name: &str) -> Identifier {
+        Identifier::new(name.to_string())
+    }
+
+    fn source(value: &str) -> StringLiteral {
+        StringLiteral::new(value.to_string())
+    }
+
+    #[test]
+    fn test_side_effect_import() {
+        assert_eq!(
+            parse_item("import './polyfill.js';"),
+            Node::ImportDeclaration(ImportDeclaration::new(vec![], source("./polyfill.js")))
+        );
+    }
+
+    #[test]
+    fn test_default_and_named_imports() {
+        assert_eq!(
+            parse_item("import a, { b, c as d, default as e, 'f g' as h, } from 'mod'"),
+            Node::ImportDeclaration(ImportDeclaration::new(
+                vec![
+                    Node::ImportDefaultSpecifier(ImportDefaultSpecifier::new(identifier("a"))),
+                    Node::ImportSpecifier(ImportSpecifier::new(ident("b"), identifier("b"))),
+                    Node::ImportSpecifier(ImportSpecifier::new(ident("c"), identifier("d"))),
+                    Node::ImportSpecifier(ImportSpecifier::new(ident("default"), identifier("e"))),
+                    Node::ImportSpecifier(ImportSpecifier::new(
+                        Node::StringLiteral(source("f g")),
+                        identifier("h")
+                    )),
+                ],
+                source("mod")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_namespace_imports() {
+        assert_eq!(
+            parse_item("import a, * as ns from 'mod';"),
+            Node::ImportDeclaration(ImportDeclaration::new(
+                vec![
+                    Node::ImportDefaultSpecifier(ImportDefaultSpecifier::new(identifier("a"))),
+                    Node::ImportNamespaceSpecifier(ImportNamespaceSpecifier::new(identifier("ns"))),
+                ],
+                source("mod")
+            ))
+        );
+        assert_eq!(
+            parse_item("import * as ns from 'mod'"),
+            Node::ImportDeclaration(ImportDeclaration::new(
+                vec![Node::ImportNamespaceSpecifier(
+                    ImportNamespaceSpecifier::new(identifier("ns"))
+                )],
+                source("mod")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_invalid_imports() {
+        assert_eq!(
+            parse_error("import { default } from 'mod'"),
+            "SyntaxError: Unexpected token '}' at script.js:1:18"
+        );
+        assert_eq!(
+            parse_error("import * from 'mod'"),
+            "SyntaxError: Unexpected identifier 'from' at script.js:1:10"
+        );
+        assert_eq!(
+            parse_error("import a 'mod'"),
+            "SyntaxError: Unexpected string at script.js:1:10"
+        );
+        assert_eq!(
+            parse_error("if (a) { import 'mod' }"),
+            "SyntaxError: Unexpected token 'import' at script.js:1:10"
+        );
+    }
+
+    #[test]
+    fn test_export_declarations() {
+        assert_eq!(
+            parse_item("export const a = 1;"),
+            Node::ExportNamedDeclaration(ExportNamedDeclaration::new(
+                Some(Node::VariableDeclaration(VariableDeclaration::new(
+                    "const".to_string(),
+                    vec![VariableDeclarator::new(
+                        ident("a"),
+                        Some(Node::NumericLiteral(NumericLiteral::new(1.0)))
+                    )]
+                ))),
+                vec![],
+                None
+            ))
+        );
+        assert_eq!(
+            parse_item("export function foo() {}"),
+            Node::ExportNamedDeclaration(ExportNamedDeclaration::new(
+                Some(Node::FunctionDeclaration(FunctionDeclaration::new(
+                    "foo".to_string()
+                ))),
+                vec![],
+                None
+            ))
+        );
+    }
+
+    #[test]
+    fn test_export_default() {
+        assert_eq!(
+            parse_item("export default function () {}"),
+            Node::ExportDefaultDeclaration(ExportDefaultDeclaration::new(
+                Node::FunctionDeclaration(FunctionDeclaration::anonymous())
+            ))
+        );
+        assert_eq!(
+            parse_item("export default a;"),
+            Node::ExportDefaultDeclaration(ExportDefaultDeclaration::new(ident("a")))
+        );
+    }
+
+    #[test]
+    fn test_export_specifiers() {
+        assert_eq!(
+            parse_item("export { a, b as default, c as 'd e' };"),
+            Node::ExportNamedDeclaration(ExportNamedDeclaration::new(
+                None,
+                vec![
+                    Node::ExportSpecifier(ExportSpecifier::new(ident("a"), ident("a"))),
+                    Node::ExportSpecifier(ExportSpecifier::new(ident("b"), ident("default"))),
+                    Node::ExportSpecifier(ExportSpecifier::new(
+                        ident("c"),
+                        Node::StringLiteral(source("d e"))
+                    )),
+                ],
+                None
+            ))
+        );
+        assert_eq!(
+            parse_item("export { default, 'a b' as c } from 'mod'"),
+            Node::ExportNamedDeclaration(ExportNamedDeclaration::new(
+                None,
+                vec![
+                    Node::ExportSpecifier(ExportSpecifier::new(ident("default"), ident("default"))),
+                    Node::ExportSpecifier(ExportSpecifier::new(
+                        Node::StringLiteral(source("a b")),
+                        ident("c")
+                    )),
+                ],
+                Some(source("mod"))
+            ))
+        );
+
+        assert_eq!(
+            parse_error("export { default };"),
+            "SyntaxError: Unexpected reserved word 'default' at script.js:1:10"
+        );
+        assert_eq!(
+            parse_error("export { 'a' as b };"),
+            "SyntaxError: A string literal cannot be used as an exported binding without `from` at script.js:1:10"
+        );
+        assert_eq!(
+            parse_error(r"export { a as '\uD800' };"),
+            "SyntaxError: Export names can't contain lone surrogates at script.js:1:15"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_exports() {
+        assert_eq!(
+            parse_error("export default 1; export default 2;"),
+            "SyntaxError: Duplicate export of 'default' at script.js:1:26"
+        );
+        assert_eq!(
+            parse_error("export const a = 1; export { b as a };"),
+            "SyntaxError: Duplicate export of 'a' at script.js:1:35"
+        );
+        assert_eq!(
+            parse_error("export { a as default }; export default function () {}"),
+            "SyntaxError: Duplicate export of 'default' at script.js:1:33"
+        );
+        assert_eq!(
+            parse_error("export const { a, b: [c = d] } = e; export { a as c };"),
+            "SyntaxError: Duplicate export of 'c' at script.js:1:51"
+        );
+        assert_eq!(
+            parse_error("export * as ns from 'mod'; export { a as 'ns' } from 'mod';"),
+            "SyntaxError: Duplicate export of 'ns' at script.js:1:42"
+        );
+        // Only the names that are exported have to be unique.
+        crate::parser::parse("export { a, a as b }; export * from 'mod';", "module.mjs").unwrap();
+    }
+
+    #[test]
+    fn test_export_all() {
+        assert_eq!(
+            parse_item("export * from 'mod';"),
+            Node::ExportAllDeclaration(ExportAllDeclaration::new(None, source("mod")))
+        );
+        assert_eq!(
+            parse_item("export * as ns from 'mod';"),
+            Node::ExportAllDeclaration(ExportAllDeclaration::new(Some(ident("ns")), source("mod")))
+        );
+    }
+}
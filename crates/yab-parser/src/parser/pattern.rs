@@ -0,0 +1,397 @@
+//! Destructuring patterns, like the `{ a, b: [c] }` in `const { a, b: [c] } =
+//! d`.  The same grammar shows up in declarations, in parameter lists, and on
+//! the left of an `=`, with the difference that declarations and parameters
+//! bind new names, while assignments can also store into member expressions.
+
+use miette::Result;
+
+use crate::{
+    ast::{
+        ArrayPattern, AssignmentPattern, Identifier, Node, ObjectPattern, Property, RestElement,
+    },
+    lexer::{OperatorType, PunctuationType, Token},
+};
+
+use super::{expression::is_simple_assignment_target, identifier_name, scope::BindingKind, Parser};
+
+/// Which kind of pattern is being parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PatternKind {
+    /// In a declaration or a parameter list, where every target has to be a
+    /// plain identifier, and is declared as the given kind of binding.
+    Binding(BindingKind),
+    /// On the left of an `=`, where a target can also be something like
+    /// `a.b` or `a[0]`.
+    Assignment,
+}
+
+impl Parser<'_> {
+    /// Whether the upcoming `[` or `{` starts an assignment pattern rather
+    /// than an array or object literal, which we can only tell from the `=`
+    /// after the closing bracket.
+    pub(super) fn is_assignment_pattern_ahead(&self) -> bool {
+        self.find_matching_close(0).is_some_and(|close| {
+            matches!(self.peek_nth(close + 1), Some(Token::Operator(op)) if op.kind == OperatorType::Assignment)
+        })
+    }
+
+    /// Like [`Parser::is_assignment_pattern_ahead`], but for a `[` or `{`
+    /// nested inside an assignment pattern, where the pattern can also be
+    /// followed by the `,` or closing bracket of the outer one.  Anything
+    /// else makes it an expression, like the `[a][0]` in `[[a][0]] = b`.
+    fn is_nested_assignment_pattern_ahead(&self) -> bool {
+        self.find_matching_close(0)
+            .is_some_and(|close| match self.peek_nth(close + 1) {
+                Some(Token::Operator(op)) => op.kind == OperatorType::Assignment,
+                Some(Token::Punctuation(p)) => matches!(
+                    p.kind,
+                    PunctuationType::Comma
+                        | PunctuationType::CloseBracket
+                        | PunctuationType::CloseBrace
+                ),
+                _ => false,
+            })
+    }
+
+    /// Parses a pattern with an optional default value, like `a = 1` or
+    /// `[a, b] = []`.
+    pub(super) fn parse_pattern_element(&mut self, kind: PatternKind) -> Result<Node> {
+        let start = self.start_position();
+        let target = self.parse_pattern(kind)?;
+        if kind == PatternKind::Binding(BindingKind::Parameter) {
+            self.skip_parameter_type()?;
+        }
+        if !self.eat_operator(OperatorType::Assignment) {
+            return Ok(target);
+        }
+
+        let default = self.parse_assignment()?;
+        Ok(Node::AssignmentPattern(
+            AssignmentPattern::new(target, default).with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Parses an identifier, an array or object pattern, or for assignments,
+    /// a member expression.
+    pub(super) fn parse_pattern(&mut self, kind: PatternKind) -> Result<Node> {
+        let start = self.start_position();
+        let is_pattern =
+            matches!(kind, PatternKind::Binding(_)) || self.is_nested_assignment_pattern_ahead();
+        if is_pattern && self.eat_punctuation(PunctuationType::OpenBracket) {
+            let pattern = self.parse_array_pattern(kind)?;
+            return Ok(pattern.with_span(self.span_from(start)));
+        }
+        if is_pattern && self.eat_punctuation(PunctuationType::OpenBrace) {
+            let pattern = self.parse_object_pattern(kind)?;
+            return Ok(pattern.with_span(self.span_from(start)));
+        }
+
+        self.parse_simple_target(kind)
+    }
+
+    fn parse_simple_target(&mut self, kind: PatternKind) -> Result<Node> {
+        match kind {
+            PatternKind::Binding(binding) => {
+                let name = self.parse_binding_identifier()?;
+                let span = self.previous_span;
+                self.declare(&name, binding)?;
+                Ok(Node::Identifier(Identifier::new(name).with_span(span)))
+            }
+            PatternKind::Assignment => {
+                let target = self.parse_left_hand_side()?;
+                if !is_simple_assignment_target(&target) {
+                    return Err(
+                        self.error_at(target.span(), "Invalid destructuring assignment target")
+                    );
+                }
+                self.check_assignment_target(&target)?;
+                Ok(target)
+            }
+        }
+    }
+
+    /// Parses an array pattern, assuming the `[` has been consumed.
+    fn parse_array_pattern(&mut self, kind: PatternKind) -> Result<Node> {
+        let mut elements = Vec::new();
+
+        while !self.eat_punctuation(PunctuationType::CloseBracket) {
+            if self.eat_punctuation(PunctuationType::Comma) {
+                elements.push(None);
+                continue;
+            }
+
+            let start = self.start_position();
+            if self.eat_punctuation(PunctuationType::Ellipsis) {
+                let argument = self.parse_pattern(kind)?;
+                let span = self.span_from(start);
+                elements.push(Some(Node::RestElement(
+                    RestElement::new(argument).with_span(span),
+                )));
+                if !self.eat_punctuation(PunctuationType::CloseBracket) {
+                    return Err(self.error_at(span, "Rest element must be last element"));
+                }
+                break;
+            }
+
+            elements.push(Some(self.parse_pattern_element(kind)?));
+            if !self.eat_punctuation(PunctuationType::Comma) {
+                self.expect_punctuation(PunctuationType::CloseBracket)?;
+                break;
+            }
+        }
+
+        Ok(Node::ArrayPattern(ArrayPattern::new(elements)))
+    }
+
+    /// Parses an object pattern, assuming the `{` has been consumed.
+    fn parse_object_pattern(&mut self, kind: PatternKind) -> Result<Node> {
+        let mut properties = Vec::new();
+
+        while !self.eat_punctuation(PunctuationType::CloseBrace) {
+            // Unlike in an array pattern, the rest element of an object
+            // pattern can't be another pattern.
+            let start = self.start_position();
+            if self.eat_punctuation(PunctuationType::Ellipsis) {
+                let argument = self.parse_simple_target(kind)?;
+                let span = self.span_from(start);
+                properties.push(Node::RestElement(
+                    RestElement::new(argument).with_span(span),
+                ));
+                if !self.eat_punctuation(PunctuationType::CloseBrace) {
+                    return Err(self.error_at(span, "Rest element must be last element"));
+                }
+                break;
+            }
+
+            properties.push(self.parse_pattern_property(kind)?);
+            if !self.eat_punctuation(PunctuationType::Comma) {
+                self.expect_punctuation(PunctuationType::CloseBrace)?;
+                break;
+            }
+        }
+
+        Ok(Node::ObjectPattern(ObjectPattern::new(properties)))
+    }
+
+    /// PatternProperty:
+    ///   PropertyName `:` PatternElement
+    ///   Identifier ( `=` AssignmentExpression )?
+    fn parse_pattern_property(&mut self, kind: PatternKind) -> Result<Node> {
+        let start = self.start_position();
+        let shorthand = self.peek().and_then(identifier_name);
+
+        let (key, computed) = self.parse_property_key()?;
+        if let Some(name) = shorthand {
+            if !self.is_punctuation(PunctuationType::Colon) {
+                if let PatternKind::Binding(binding) = kind {
+                    self.declare(&name, binding)?;
+                }
+                let mut value = Node::Identifier(Identifier::new(name).with_span(key.span()));
+                if kind == PatternKind::Assignment {
+                    self.check_assignment_target(&value)?;
+                }
+                if self.eat_operator(OperatorType::Assignment) {
+                    let default = self.parse_assignment()?;
+                    value = Node::AssignmentPattern(
+                        AssignmentPattern::new(value, default).with_span(self.span_from(start)),
+                    );
+                }
+                return Ok(Node::Property(
+                    Property::new(key, value, false, true).with_span(self.span_from(start)),
+                ));
+            }
+        }
+
+        self.expect_punctuation(PunctuationType::Colon)?;
+        let value = self.parse_pattern_element(kind)?;
+
+        Ok(Node::Property(
+            Property::new(key, value, computed, false).with_span(self.span_from(start)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{
+            ArrayExpression, ArrowFunctionExpression, AssignmentExpression, FunctionDeclaration,
+            MemberExpression, NumericLiteral, Parameter, Program, VariableDeclaration,
+            VariableDeclarator,
+        },
+        parser::{
+            test_helpers::{ident, parse_error},
+            without_spans::{parse, parse_expression},
+        },
+    };
+
+    use super::*;
+
+    fn num(value: f64) -> Node {
+        Node::NumericLiteral(NumericLiteral::new(value))
+    }
+
+    fn shorthand(name: &str, value: Node) -> Node {
+        Node::Property(Property::new(ident(name), value, false, true))
+    }
+
+    fn property(key: &str, value: Node) -> Node {
+        Node::Property(Property::new(ident(key), value, false, false))
+    }
+
+    fn array(elements: Vec<Option<Node>>) -> Node {
+        Node::ArrayPattern(ArrayPattern::new(elements))
+    }
+
+    fn object(properties: Vec<Node>) -> Node {
+        Node::ObjectPattern(ObjectPattern::new(properties))
+    }
+
+    fn default(target: Node, value: Node) -> Node {
+        Node::AssignmentPattern(AssignmentPattern::new(target, value))
+    }
+
+    fn rest(argument: Node) -> Node {
+        Node::RestElement(RestElement::new(argument))
+    }
+
+    fn declaration(src: &str) -> Program {
+        parse(&format!("let {} = x;", src), "script.js").unwrap()
+    }
+
+    fn declares(pattern: Node) -> Program {
+        let mut program = Program::default();
+        program.append(Node::VariableDeclaration(VariableDeclaration::new(
+            "let".to_string(),
+            vec![VariableDeclarator::new(pattern, Some(ident("x")))],
+        )));
+        program
+    }
+
+    #[test]
+    fn test_declaration_patterns() {
+        assert_eq!(
+            declaration("{ a, b: c, d = 1 }"),
+            declares(object(vec![
+                shorthand("a", ident("a")),
+                property("b", ident("c")),
+                shorthand("d", default(ident("d"), num(1.0))),
+            ]))
+        );
+        assert_eq!(
+            declaration("[a, , [b, c] = [], ...d]"),
+            declares(array(vec![
+                Some(ident("a")),
+                None,
+                Some(default(
+                    array(vec![Some(ident("b")), Some(ident("c"))]),
+                    Node::ArrayExpression(ArrayExpression::new(vec![]))
+                )),
+                Some(rest(ident("d"))),
+            ]))
+        );
+        assert_eq!(
+            declaration("{ a: { b: [c] }, ...d }"),
+            declares(object(vec![
+                property(
+                    "a",
+                    object(vec![property("b", array(vec![Some(ident("c"))]))])
+                ),
+                rest(ident("d")),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_parameter_patterns() {
+        let mut function = FunctionDeclaration::new("f".to_string());
+        function.args_append(Parameter::new(default(ident("a"), num(1.0))));
+        function.args_append(Parameter::new(object(vec![shorthand("b", ident("b"))])));
+        function.args_append(Parameter::new(rest(array(vec![Some(ident("c"))]))));
+
+        let mut program = Program::default();
+        program.append(Node::FunctionDeclaration(function));
+        assert_eq!(
+            parse("function f(a = 1, { b }, ...[c]) {}", "script.js").unwrap(),
+            program
+        );
+
+        assert_eq!(
+            parse_expression("({ a }, ...b) => a", "script.js").unwrap(),
+            Node::ArrowFunctionExpression(ArrowFunctionExpression::new(
+                vec![
+                    Parameter::new(object(vec![shorthand("a", ident("a"))])),
+                    Parameter::new(rest(ident("b"))),
+                ],
+                ident("a"),
+                false
+            ))
+        );
+    }
+
+    #[test]
+    fn test_assignment_patterns() {
+        let member = Node::MemberExpression(MemberExpression::new(ident("a"), ident("b"), false));
+        assert_eq!(
+            parse_expression("[a.b, { c = 1 }, ...d] = x", "script.js").unwrap(),
+            Node::AssignmentExpression(AssignmentExpression::new(
+                array(vec![
+                    Some(member),
+                    Some(object(vec![shorthand("c", default(ident("c"), num(1.0)))])),
+                    Some(rest(ident("d"))),
+                ]),
+                ident("x"),
+                "=".to_string()
+            ))
+        );
+
+        // `[a][0]` is a member expression, not a nested pattern.
+        let member = Node::MemberExpression(MemberExpression::new(
+            Node::ArrayExpression(ArrayExpression::new(vec![Some(ident("a"))])),
+            num(0.0),
+            true,
+        ));
+        assert_eq!(
+            parse_expression("[[a][0]] = x", "script.js").unwrap(),
+            Node::AssignmentExpression(AssignmentExpression::new(
+                array(vec![Some(member)]),
+                ident("x"),
+                "=".to_string()
+            ))
+        );
+
+        // Without the `=` these are still plain literals.
+        assert!(matches!(
+            parse_expression("[a, b]", "script.js").unwrap(),
+            Node::ArrayExpression(_)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert_eq!(
+            parse_error("let { a };"),
+            "SyntaxError: Missing initializer in destructuring declaration at script.js:1:5"
+        );
+        assert_eq!(
+            parse_error("let [...a, b] = c;"),
+            "SyntaxError: Rest element must be last element at script.js:1:6"
+        );
+        assert_eq!(
+            parse_error("function f(...a, b) {}"),
+            "SyntaxError: Rest parameter must be last formal parameter at script.js:1:12"
+        );
+        assert_eq!(
+            parse_error("[f()] = c;"),
+            "SyntaxError: Invalid destructuring assignment target at script.js:1:2"
+        );
+        assert_eq!(
+            parse_error("let { ...[a] } = b;"),
+            "SyntaxError: Unexpected token '[' at script.js:1:10"
+        );
+        assert_eq!(
+            parse_error("let [a.b] = c;"),
+            "SyntaxError: Unexpected token '.' at script.js:1:7"
+        );
+    }
+}
@@ -0,0 +1,535 @@
+//! Tracks the names declared in each scope, for the early errors that the
+//! spec leaves to the parser: redeclared bindings, duplicate parameters, and
+//! the extra restrictions of strict mode.
+
+use std::collections::HashSet;
+
+use miette::Result;
+
+use crate::{ast::Node, location::Span};
+
+use super::Parser;
+
+/// How a name is being declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum BindingKind {
+    /// `var`, which is scoped to the enclosing function.
+    Var,
+    /// `let`, `const` and imports, which are scoped to the enclosing block.
+    Lexical,
+    /// A function declaration, which acts like `var` at the top level of a
+    /// function and like `let` inside a block.
+    Function,
+    Parameter,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Scope {
+    /// Whether this is the top level of a function or the program, as
+    /// opposed to a block.
+    is_function: bool,
+    /// Whether this is the top level of an async function, where `await`
+    /// is an operator.
+    is_async: bool,
+    strict: bool,
+    /// Whether this is inside the body of a loop in the same function, where
+    /// `break` and `continue` are allowed.
+    in_loop: bool,
+    lexical: HashSet<String>,
+    /// Function declarations directly inside this block, which sloppy mode
+    /// lets you repeat.
+    functions: HashSet<String>,
+    /// Names declared with `var` in this scope or any block inside it, along
+    /// with parameters and top level function declarations.
+    var: HashSet<String>,
+    /// The parameters of a function, in order, with where they were declared.
+    /// These are checked once its body has been parsed, since a `"use
+    /// strict"` in the body applies to them too.
+    params: Vec<(String, Span)>,
+    /// Whether any parameter has a default, a pattern, or is a rest element.
+    has_non_simple_params: bool,
+}
+
+impl Scope {
+    pub(super) fn program() -> Self {
+        Self {
+            is_function: true,
+            ..Self::default()
+        }
+    }
+}
+
+fn is_eval_or_arguments(name: &str) -> bool {
+    name == "eval" || name == "arguments"
+}
+
+/// The words only reserved in strict mode code.
+fn is_strict_reserved_word(name: &str) -> bool {
+    matches!(
+        name,
+        "implements"
+            | "interface"
+            | "let"
+            | "package"
+            | "private"
+            | "protected"
+            | "public"
+            | "static"
+            | "yield"
+    )
+}
+
+impl Parser<'_> {
+    fn scope(&self) -> &Scope {
+        self.scopes
+            .last()
+            .expect("the program scope is never popped")
+    }
+
+    fn scope_mut(&mut self) -> &mut Scope {
+        self.scopes
+            .last_mut()
+            .expect("the program scope is never popped")
+    }
+
+    pub(super) fn is_strict(&self) -> bool {
+        self.scope().strict
+    }
+
+    /// Whether we're inside a function, rather than at the top level of the
+    /// program, which also counts as a function scope for hoisting `var`.
+    pub(super) fn in_function(&self) -> bool {
+        self.scopes[1..].iter().any(|scope| scope.is_function)
+    }
+
+    /// Switches the current function to strict mode, for a `"use strict"`
+    /// directive at `directive`.
+    pub(super) fn enter_strict_mode(&mut self, directive: Span) -> Result<()> {
+        if self.scope().has_non_simple_params {
+            return Err(self.error_at(
+                directive,
+                "Illegal 'use strict' directive in function with non-simple parameter list",
+            ));
+        }
+
+        self.scope_mut().strict = true;
+        Ok(())
+    }
+
+    /// Enters a function, whose parameters should be declared next.  It
+    /// starts out strict if the code around it is.
+    pub(super) fn push_function_scope(&mut self, is_async: bool) {
+        let strict = self.is_strict();
+        self.scopes.push(Scope {
+            is_function: true,
+            is_async,
+            strict,
+            ..Scope::default()
+        });
+    }
+
+    /// Whether `await` is an operator here rather than an identifier, which
+    /// it is in modules and directly inside async functions.
+    pub(super) fn is_await_reserved(&self) -> bool {
+        self.is_module
+            || self
+                .scopes
+                .iter()
+                .rev()
+                .find(|scope| scope.is_function)
+                .is_some_and(|scope| scope.is_async)
+    }
+
+    pub(super) fn push_block_scope(&mut self) {
+        let strict = self.is_strict();
+        let in_loop = self.in_loop();
+        self.scopes.push(Scope {
+            strict,
+            in_loop,
+            ..Scope::default()
+        });
+    }
+
+    /// Enters the body of a loop, which is popped like a block.
+    pub(super) fn push_loop_scope(&mut self) {
+        self.push_block_scope();
+        self.scope_mut().in_loop = true;
+    }
+
+    /// Whether a `break` or `continue` here has a loop to leave.  Function
+    /// scopes start outside of any loop, even inside a loop's body.
+    pub(super) fn in_loop(&self) -> bool {
+        self.scope().in_loop
+    }
+
+    pub(super) fn pop_block_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    pub(super) fn mark_non_simple_params(&mut self) {
+        self.scope_mut().has_non_simple_params = true;
+    }
+
+    /// Leaves a function, once its body has been parsed, and checks its name
+    /// and parameters against the rules that depend on its strictness.
+    /// Arrow functions never allow duplicate parameters.
+    pub(super) fn pop_function_scope(
+        &mut self,
+        name: Option<(&str, Span)>,
+        is_arrow: bool,
+    ) -> Result<()> {
+        let scope = self.scopes.pop().expect("function scopes are pushed first");
+
+        if scope.strict {
+            let mut names = name.into_iter().chain(
+                scope
+                    .params
+                    .iter()
+                    .map(|(param, span)| (param.as_str(), *span)),
+            );
+            if let Some((name, span)) =
+                names.find(|(name, _)| is_eval_or_arguments(name) || is_strict_reserved_word(name))
+            {
+                return Err(if is_eval_or_arguments(name) {
+                    self.error_at(span, "Unexpected eval or arguments in strict mode")
+                } else {
+                    self.error_at(span, "Unexpected strict mode reserved word")
+                });
+            }
+        }
+
+        if scope.strict || is_arrow || scope.has_non_simple_params {
+            let mut seen = HashSet::new();
+            if let Some((_, span)) = scope.params.iter().find(|(param, _)| !seen.insert(param)) {
+                return Err(self.error_at(
+                    *span,
+                    "Duplicate parameter name not allowed in this context",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Declares `name`, which was the token consumed last, in the current
+    /// scope, failing if it clashes with a name already declared there.
+    pub(super) fn declare(&mut self, name: &str, kind: BindingKind) -> Result<()> {
+        if kind != BindingKind::Parameter && self.is_strict() && is_eval_or_arguments(name) {
+            return Err(self.error("Unexpected eval or arguments in strict mode"));
+        }
+        if kind != BindingKind::Parameter || name == "await" {
+            // Unlike the strict mode reserved words, whether `await` is
+            // reserved is known before the body of the function.
+            self.check_identifier_reference(name)?;
+        }
+        if kind == BindingKind::Lexical && name == "let" {
+            return Err(self.error("let is disallowed as a lexically bound name"));
+        }
+
+        let strict = self.is_strict();
+        let kind = match kind {
+            BindingKind::Function if self.scope().is_function => BindingKind::Var,
+            kind => kind,
+        };
+
+        let redeclared = match kind {
+            BindingKind::Parameter => {
+                let span = self.previous_span;
+                let scope = self.scope_mut();
+                scope.params.push((name.to_string(), span));
+                scope.var.insert(name.to_string());
+                false
+            }
+            BindingKind::Lexical => {
+                let scope = self.scope_mut();
+                let redeclared = scope.lexical.contains(name)
+                    || scope.functions.contains(name)
+                    || scope.var.contains(name);
+                scope.lexical.insert(name.to_string());
+                redeclared
+            }
+            BindingKind::Function => {
+                let scope = self.scope_mut();
+                let redeclared = scope.lexical.contains(name)
+                    || scope.var.contains(name)
+                    || (strict && scope.functions.contains(name));
+                scope.functions.insert(name.to_string());
+                redeclared
+            }
+            // A `var` is hoisted out of every block up to the function, and
+            // clashes with anything declared in them on the way.
+            BindingKind::Var => {
+                let mut redeclared = false;
+                for scope in self.scopes.iter_mut().rev() {
+                    redeclared |= scope.lexical.contains(name) || scope.functions.contains(name);
+                    scope.var.insert(name.to_string());
+                    if scope.is_function {
+                        break;
+                    }
+                }
+                redeclared
+            }
+        };
+
+        if redeclared {
+            return Err(self.error(format!("Identifier '{}' has already been declared", name)));
+        }
+
+        Ok(())
+    }
+
+    /// `await` can't be used as an identifier where it's reserved (see
+    /// [`Parser::is_await_reserved`]), and in strict mode, neither can the
+    /// future reserved words like `private` and `yield`.
+    pub(super) fn check_identifier_reference(&self, name: &str) -> Result<()> {
+        if name == "await" && self.is_await_reserved() {
+            return Err(self.error("Unexpected reserved word"));
+        }
+        if self.is_strict() && is_strict_reserved_word(name) {
+            return Err(self.error("Unexpected strict mode reserved word"));
+        }
+        Ok(())
+    }
+
+    /// In strict mode, `eval` and `arguments` can't be assigned to.
+    pub(super) fn check_assignment_target(&self, target: &Node) -> Result<()> {
+        match target {
+            Node::Identifier(ident) if self.is_strict() && is_eval_or_arguments(ident.name()) => {
+                Err(self.error_at(ident.span(), "Unexpected eval or arguments in strict mode"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse, test_helpers::parse_error};
+
+    fn assert_parses(src: &str) {
+        if let Err(err) = parse(src, "script.js") {
+            panic!("{} failed to parse: {}", src, err);
+        }
+    }
+
+    #[test]
+    fn test_redeclarations() {
+        for (src, location) in [
+            ("let a; let a;", "1:12"),
+            ("let a; var a;", "1:12"),
+            ("var a; const a = 1;", "1:14"),
+            ("let a; function a() {}", "1:17"),
+            ("{ var a; } let a;", "1:16"),
+            ("{ function a() {} var a; }", "1:23"),
+            ("function f(a) { let a; }", "1:21"),
+            ("import a from 'a'; let a;", "1:24"),
+            ("import { a, b as a } from 'a';", "1:18"),
+            ("let [a, { b: a }] = c;", "1:14"),
+            ("(a) => { let a; }", "1:14"),
+        ] {
+            assert_eq!(
+                parse_error(src),
+                format!(
+                    "SyntaxError: Identifier 'a' has already been declared at script.js:{}",
+                    location
+                ),
+                "{}",
+                src
+            );
+        }
+
+        for src in [
+            "var a; var a;",
+            "var a; function a() {}",
+            "let a; { let a; }",
+            "{ let a; } { let a; }",
+            "function f(a) { var a; }",
+            "{ function a() {} function a() {} }",
+        ] {
+            assert_parses(src);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_parameters() {
+        assert_parses("function f(a, a) {}");
+
+        for (src, location) in [
+            ("function f(a, a) { 'use strict'; }", "1:15"),
+            ("'use strict'; function f(a, a) {}", "1:29"),
+            ("function f(a, [a]) {}", "1:16"),
+            ("function f(a, a = 1) {}", "1:15"),
+            ("(a, a) => a", "1:5"),
+        ] {
+            assert_eq!(
+                parse_error(src),
+                format!(
+                    "SyntaxError: Duplicate parameter name not allowed in this context at script.js:{}",
+                    location
+                ),
+                "{}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn test_strict_mode_restrictions() {
+        assert_parses("eval = 1; arguments++; delete a; var eval;");
+        assert_parses("'use strict'; delete a.b; a.eval = 1;");
+        // Only a directive at the start of the body counts.
+        assert_parses("a(); 'use strict'; eval = 1;");
+        assert_parses("'use strict'.length; eval = 1;");
+        assert_parses(r"'use\x20strict'; eval = 1;");
+        assert_parses(r#""use\u0020strict"; eval = 1;"#);
+
+        for (src, location) in [
+            ("'use strict'; eval = 1;", "1:15"),
+            ("'use strict'; arguments++;", "1:15"),
+            ("'use strict'; [eval] = a;", "1:16"),
+            ("'use strict'; ({ eval } = a);", "1:18"),
+            ("'use strict'; var arguments;", "1:19"),
+            ("function eval() { 'use strict'; }", "1:10"),
+            ("function f(arguments) { 'use strict'; }", "1:12"),
+            (
+                "function f() { 'use strict'; return () => { eval = 1; }; }",
+                "1:45",
+            ),
+        ] {
+            assert_eq!(
+                parse_error(src),
+                format!(
+                    "SyntaxError: Unexpected eval or arguments in strict mode at script.js:{}",
+                    location
+                ),
+                "{}",
+                src
+            );
+        }
+
+        assert_eq!(
+            parse_error("'use strict'; delete a;"),
+            "SyntaxError: Delete of an unqualified identifier in strict mode at script.js:1:22"
+        );
+        assert_eq!(
+            parse_error("function f(a = 1) { 'use strict'; }"),
+            "SyntaxError: Illegal 'use strict' directive in function with non-simple \
+             parameter list at script.js:1:21"
+        );
+    }
+
+    #[test]
+    fn test_strict_mode_reserved_words_and_octals() {
+        assert_parses("var private = 017; a = '\\01' + '\\0';");
+        assert_parses("'use strict'; a.private = { public: 1 }; b = '\\0';");
+        // Properties named like the keywords don't make a module.
+        assert_parses("a.export = 1; var private;");
+        assert_parses("a.import; x = 017;");
+        assert_parses("a?.export; var private;");
+
+        for (src, expected, location) in [
+            (
+                "'use strict'; var private;",
+                "Unexpected strict mode reserved word",
+                "1:19",
+            ),
+            (
+                "'use strict'; a = { static };",
+                "Unexpected strict mode reserved word",
+                "1:21",
+            ),
+            (
+                "function f(public) { 'use strict'; }",
+                "Unexpected strict mode reserved word",
+                "1:12",
+            ),
+            (
+                "import a from 'a'; package;",
+                "Unexpected strict mode reserved word",
+                "1:20",
+            ),
+            (
+                "export let interface;",
+                "Unexpected strict mode reserved word",
+                "1:12",
+            ),
+            (
+                "'use strict'; a = 017;",
+                "Octal literals are not allowed in strict mode",
+                "1:19",
+            ),
+            (
+                "function f() { 'use strict'; return '\\01'; }",
+                "Octal escape sequences are not allowed in strict mode",
+                "1:37",
+            ),
+            (
+                "'use strict'; a = '\\8';",
+                "Octal escape sequences are not allowed in strict mode",
+                "1:19",
+            ),
+            (
+                "export {}; with (a) {}",
+                "Strict mode code may not include a with statement",
+                "1:12",
+            ),
+        ] {
+            assert_eq!(
+                parse_error(src),
+                format!("SyntaxError: {} at script.js:{}", expected, location),
+                "{}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn test_await_yield_and_let_as_identifiers() {
+        assert_parses("var yield = 1, await = 2; yield + await;");
+        assert_parses("function f() { var await = 1; return await; }");
+        assert_parses("var let = 1; let = 2;");
+        assert_parses("async () => await a; async (a) => { await a; };");
+        assert!(parse("await a;", "module.mjs").is_ok());
+
+        for (src, expected, location) in [
+            (
+                "let let = 1;",
+                "let is disallowed as a lexically bound name",
+                "1:5",
+            ),
+            (
+                "const let = 1;",
+                "let is disallowed as a lexically bound name",
+                "1:7",
+            ),
+            (
+                "'use strict'; var yield;",
+                "Unexpected strict mode reserved word",
+                "1:19",
+            ),
+            ("export {}; var await;", "Unexpected reserved word", "1:16"),
+            (
+                "async () => { var await; }",
+                "Unexpected reserved word",
+                "1:19",
+            ),
+            ("async (await) => 1", "Unexpected reserved word", "1:8"),
+            (
+                "function f() { await x; }",
+                "Unexpected identifier 'x'",
+                "1:22",
+            ),
+        ] {
+            assert_eq!(
+                parse_error(src),
+                format!("SyntaxError: {} at script.js:{}", expected, location),
+                "{}",
+                src
+            );
+        }
+        assert!(parse("var await;", "module.mjs")
+            .unwrap_err()
+            .to_string()
+            .contains("Unexpected reserved word"));
+    }
+}
@@ -0,0 +1,1005 @@
+//! Statement and declaration parsing.
+
+use miette::Result;
+
+use crate::{
+    ast::{
+        BlockStatement, BreakStatement, ContinueStatement, DoWhileStatement, EmptyStatement,
+        ExpressionStatement, FunctionDeclaration, Identifier, IfStatement, Node, Parameter,
+        Program, RestElement, ReturnStatement, ThrowStatement, VariableDeclaration,
+        VariableDeclarator, WhileStatement,
+    },
+    lexer::{KeywordType, OperatorType, Punctuation, PunctuationType, Token},
+};
+
+use super::{identifier_name, pattern::PatternKind, scope::BindingKind, Parser};
+
+/// Whether a statement can start with `keyword`, which makes it a good place
+/// to pick up again after a syntax error.
+fn starts_statement(keyword: KeywordType) -> bool {
+    use KeywordType::*;
+
+    matches!(
+        keyword,
+        Break
+            | Class
+            | Const
+            | Continue
+            | Debugger
+            | Do
+            | Export
+            | For
+            | Function
+            | If
+            | Import
+            | Let
+            | Return
+            | Switch
+            | Throw
+            | Try
+            | Var
+            | While
+            | With
+    )
+}
+
+impl Parser<'_> {
+    /// Parses statements until the end of the input.
+    pub fn parse_program(&mut self) -> Result<Program> {
+        let start = self.start_position();
+        let mut program = Program::default();
+        self.is_module |= self.has_module_syntax();
+        if self.is_module {
+            self.enter_strict_mode(self.previous_span)?;
+        }
+        for directive in self.parse_directives()? {
+            program.append(directive);
+        }
+        for item in self.parse_statement_list(true)? {
+            program.append(item);
+        }
+
+        Ok(program.with_span(self.span_from(start)))
+    }
+
+    /// Parses a statement, or an import or export declaration, which is
+    /// `None` if it was left out for only importing or exporting types.
+    fn parse_module_item(&mut self) -> Result<Option<Node>> {
+        match self.peek() {
+            Some(Token::Keyword(keyword)) if keyword.kind == KeywordType::Import => {
+                self.parse_import_declaration()
+            }
+            Some(Token::Keyword(keyword)) if keyword.kind == KeywordType::Export => {
+                self.parse_export_declaration()
+            }
+            _ => self.parse_statement().map(Some),
+        }
+    }
+
+    /// Parses statements up to the `}` that ends the enclosing block, or at
+    /// the top level, up to the end of the input, where import and export
+    /// declarations are allowed too.
+    ///
+    /// When recovering from errors, a statement that fails to parse is
+    /// recorded and skipped, and parsing carries on with the next one.
+    fn parse_statement_list(&mut self, top_level: bool) -> Result<Vec<Node>> {
+        let mut statements = Vec::new();
+        while self.peek().is_some()
+            && (top_level || !self.is_punctuation(PunctuationType::CloseBrace))
+        {
+            let remaining = self.tokens.len();
+            let brace_depth = self.brace_depth;
+            let scope_depth = self.scopes.len();
+
+            let statement = if self.is_type_declaration(top_level) {
+                self.skip_type_declaration().map(|()| None)
+            } else if top_level {
+                self.parse_module_item()
+            } else {
+                self.parse_statement().map(Some)
+            };
+
+            match statement {
+                Ok(Some(statement)) => statements.push(statement),
+                Ok(None) => {}
+                Err(err) if self.recover => {
+                    self.errors.push(err);
+                    self.scopes.truncate(scope_depth);
+                    if self.tokens.len() == remaining {
+                        self.next();
+                    }
+                    self.synchronize(brace_depth);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(statements)
+    }
+
+    /// Skips ahead to where the next statement probably starts, after a
+    /// syntax error in a statement that started `brace_depth` braces deep:
+    /// past the next `;`, or up to a `}` that closes the enclosing block, a
+    /// keyword that starts a statement, or the start of a new line.
+    fn synchronize(&mut self, brace_depth: usize) {
+        // The token the error was reported at may have been the `}` closing
+        // the enclosing block, which has to be put back for the block to end.
+        while self.brace_depth < brace_depth {
+            self.tokens.push(Token::Punctuation(Punctuation::new(
+                PunctuationType::CloseBrace,
+            )));
+            self.line_breaks.push(false);
+            self.spans.push(self.previous_span);
+            self.brace_depth += 1;
+        }
+
+        while let Some(token) = self.peek() {
+            if self.brace_depth == brace_depth {
+                match token {
+                    Token::Punctuation(p) if p.kind == PunctuationType::Semicolon => {
+                        self.next();
+                        return;
+                    }
+                    Token::Punctuation(p) if p.kind == PunctuationType::CloseBrace => return,
+                    Token::Keyword(keyword) if starts_statement(keyword.kind) => return,
+                    _ if self.has_line_break_before() => return,
+                    _ => {}
+                }
+            }
+
+            let closes_block = self.is_punctuation(PunctuationType::CloseBrace)
+                && self.brace_depth == brace_depth + 1;
+            self.next();
+            if closes_block {
+                return;
+            }
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        let keyword = match self.peek() {
+            Some(Token::Punctuation(p)) if p.kind == PunctuationType::OpenBrace => {
+                return Ok(Node::BlockStatement(self.parse_block()?));
+            }
+            Some(Token::Punctuation(p)) if p.kind == PunctuationType::Semicolon => {
+                self.next();
+                return Ok(Node::EmptyStatement(
+                    EmptyStatement::default().with_span(self.previous_span),
+                ));
+            }
+            Some(Token::Keyword(keyword)) => keyword.kind,
+            _ => return self.parse_expression_statement(),
+        };
+
+        match keyword {
+            // `let` only starts a declaration when a binding follows it,
+            // otherwise it's an identifier.
+            KeywordType::Let
+                if !self.is_identifier_at(1)
+                    && !matches!(
+                        self.peek_nth(1),
+                        Some(Token::Punctuation(p))
+                            if matches!(p.kind, PunctuationType::OpenBracket | PunctuationType::OpenBrace)
+                    ) =>
+            {
+                self.parse_expression_statement()
+            }
+            KeywordType::Var | KeywordType::Let | KeywordType::Const => {
+                let declaration = self.parse_variable_declaration()?;
+                self.consume_semicolon()?;
+                Ok(declaration.with_span(self.span_from(start)))
+            }
+            KeywordType::Function => self.parse_function_declaration(false),
+            KeywordType::If => self.parse_if_statement(),
+            KeywordType::While => self.parse_while_statement(),
+            KeywordType::Do => self.parse_do_while_statement(),
+            KeywordType::Return => self.parse_return_statement(),
+            KeywordType::Throw => self.parse_throw_statement(),
+            KeywordType::Break | KeywordType::Continue => self.parse_break_or_continue(),
+            // The parser doesn't support with statements, but it can at least
+            // reject them where they aren't allowed.
+            KeywordType::With if self.is_strict() => {
+                self.next();
+                Err(self.error("Strict mode code may not include a with statement"))
+            }
+            _ => self.parse_expression_statement(),
+        }
+    }
+
+    /// Consumes the `;` that ends a statement.  Automatic semicolon insertion
+    /// lets it be left out before a `}`, at the end of the input, or when the
+    /// next token is on a new line.  The restricted productions, where a line
+    /// break ends the statement early, are handled where they come up.
+    pub(super) fn consume_semicolon(&mut self) -> Result<()> {
+        if self.eat_punctuation(PunctuationType::Semicolon) || self.can_insert_semicolon() {
+            return Ok(());
+        }
+
+        let token = self.next();
+        Err(self.unexpected(token.as_ref()))
+    }
+
+    /// Whether a statement may end here without a `;`.
+    fn can_insert_semicolon(&self) -> bool {
+        self.is_punctuation(PunctuationType::CloseBrace)
+            || self.peek().is_none()
+            || self.has_line_break_before()
+    }
+
+    pub(super) fn expect_keyword(&mut self, kind: KeywordType) -> Result<()> {
+        if self.is_keyword(kind) {
+            self.next();
+            Ok(())
+        } else {
+            let token = self.next();
+            Err(self.unexpected(token.as_ref()))
+        }
+    }
+
+    pub(super) fn parse_binding_identifier(&mut self) -> Result<String> {
+        let token = self.next();
+        match token.as_ref().and_then(identifier_name) {
+            Some(name) => Ok(name),
+            None => Err(self.unexpected(token.as_ref())),
+        }
+    }
+
+    pub(super) fn parse_block(&mut self) -> Result<BlockStatement> {
+        let start = self.start_position();
+        self.expect_punctuation(PunctuationType::OpenBrace)?;
+        self.push_block_scope();
+        let statements = self.parse_statement_list(false)?;
+        self.expect_punctuation(PunctuationType::CloseBrace)?;
+        self.pop_block_scope();
+
+        Ok(BlockStatement::new(statements).with_span(self.span_from(start)))
+    }
+
+    /// Parses the braced body of a function or arrow function, in the scope
+    /// its parameters were declared in.
+    pub(super) fn parse_function_body(&mut self) -> Result<Vec<Node>> {
+        self.expect_punctuation(PunctuationType::OpenBrace)?;
+
+        let mut statements = self.parse_directives()?;
+        statements.extend(self.parse_statement_list(false)?);
+        self.expect_punctuation(PunctuationType::CloseBrace)?;
+
+        Ok(statements)
+    }
+
+    /// Parses the directive prologue at the start of the program or a
+    /// function body: the string literal statements, like `"use strict";`,
+    /// that come before anything else.
+    fn parse_directives(&mut self) -> Result<Vec<Node>> {
+        let mut statements = Vec::new();
+        while let Some(Token::StringLiteral(string)) = self.peek() {
+            let string_span = string.span;
+            // The directive has to be spelled out exactly, so an escape like
+            // `"use\x20strict"` doesn't count, even though it has the same
+            // value.
+            let raw = &self.source[string_span.start.index..string_span.end.index];
+            let is_use_strict = raw == "'use strict'" || raw == "\"use strict\"";
+
+            // Something like `"use strict".length;` is just an expression
+            // that happens to start with a string, and ends the prologue.
+            let statement = self.parse_statement()?;
+            let is_directive = matches!(
+                &statement,
+                Node::ExpressionStatement(ExpressionStatement { expression, .. })
+                    if matches!(**expression, Node::StringLiteral(_))
+                        && expression.span() == string_span
+            );
+            statements.push(statement);
+            if !is_directive {
+                break;
+            }
+
+            if is_use_strict {
+                self.enter_strict_mode(string_span)?;
+            }
+        }
+
+        Ok(statements)
+    }
+
+    fn parse_expression_statement(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        let expression = self.parse_expression()?;
+        self.consume_semicolon()?;
+
+        Ok(Node::ExpressionStatement(
+            ExpressionStatement::new(expression).with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Parses a `var`, `let` or `const` declaration, not including the
+    /// trailing semicolon.
+    pub(super) fn parse_variable_declaration(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        let kind = match self.next() {
+            Some(Token::Keyword(keyword)) => keyword.kind,
+            token => return Err(self.unexpected(token.as_ref())),
+        };
+        let binding = match kind {
+            KeywordType::Var => BindingKind::Var,
+            _ => BindingKind::Lexical,
+        };
+
+        let mut declarations = Vec::new();
+        loop {
+            let declarator_start = self.start_position();
+            let id = self.parse_pattern(PatternKind::Binding(binding))?;
+            self.skip_declarator_type()?;
+            let init = if self.eat_operator(OperatorType::Assignment) {
+                Some(self.parse_assignment()?)
+            } else if !matches!(id, Node::Identifier(_)) {
+                return Err(self.error_at(
+                    id.span(),
+                    "Missing initializer in destructuring declaration",
+                ));
+            } else if kind == KeywordType::Const {
+                return Err(self.error_at(id.span(), "Missing initializer in const declaration"));
+            } else {
+                None
+            };
+            declarations.push(
+                VariableDeclarator::new(id, init).with_span(self.span_from(declarator_start)),
+            );
+
+            if !self.eat_punctuation(PunctuationType::Comma) {
+                break;
+            }
+        }
+
+        Ok(Node::VariableDeclaration(
+            VariableDeclaration::new(kind.to_string(), declarations)
+                .with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Parses a function declaration.  The name can only be left out after
+    /// `export default`, which is what `allow_anonymous` is for.
+    pub(super) fn parse_function_declaration(&mut self, allow_anonymous: bool) -> Result<Node> {
+        let start = self.start_position();
+        self.expect_keyword(KeywordType::Function)?;
+        let name = if allow_anonymous && self.is_punctuation(PunctuationType::OpenParen) {
+            None
+        } else {
+            let name = self.parse_binding_identifier()?;
+            self.declare(&name, BindingKind::Function)?;
+            Some(name)
+        };
+        let name_span = self.previous_span;
+        self.skip_type_parameters()?;
+
+        self.push_function_scope(false);
+        let params = self.parse_formal_parameters()?;
+        self.skip_type_annotation()?;
+        let body_start = self.start_position();
+        let body = self.parse_function_body()?;
+        let body_span = self.span_from(body_start);
+        self.pop_function_scope(name.as_deref().map(|name| (name, name_span)), false)?;
+
+        let mut function = match name {
+            Some(name) => FunctionDeclaration::new(name),
+            None => FunctionDeclaration::anonymous(),
+        };
+        for param in params {
+            function.args_append(param);
+        }
+        for statement in body {
+            function.body_append(statement);
+        }
+        function.set_inner_spans(name_span, body_span);
+
+        Ok(Node::FunctionDeclaration(
+            function.with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Parses a parenthesized parameter list, like `(a, { b } = {}, ...c)`,
+    /// declaring the parameters in the current scope.
+    pub(super) fn parse_formal_parameters(&mut self) -> Result<Vec<Parameter>> {
+        self.expect_punctuation(PunctuationType::OpenParen)?;
+
+        let mut params = Vec::new();
+        while !self.eat_punctuation(PunctuationType::CloseParen) {
+            let start = self.start_position();
+            if self.skip_this_parameter()? {
+                if !self.eat_punctuation(PunctuationType::Comma) {
+                    self.expect_punctuation(PunctuationType::CloseParen)?;
+                    break;
+                }
+                continue;
+            }
+
+            if self.eat_punctuation(PunctuationType::Ellipsis) {
+                self.mark_non_simple_params();
+                let argument = self.parse_pattern(PatternKind::Binding(BindingKind::Parameter))?;
+                self.skip_type_annotation()?;
+                let span = self.span_from(start);
+                let rest = Node::RestElement(RestElement::new(argument).with_span(span));
+                params.push(Parameter::new(rest).with_span(span));
+                if !self.eat_punctuation(PunctuationType::CloseParen) {
+                    return Err(self.error_at(span, "Rest parameter must be last formal parameter"));
+                }
+                break;
+            }
+
+            let param = self.parse_pattern_element(PatternKind::Binding(BindingKind::Parameter))?;
+            if !matches!(param, Node::Identifier(_)) {
+                self.mark_non_simple_params();
+            }
+            params.push(Parameter::new(param).with_span(self.span_from(start)));
+
+            if !self.eat_punctuation(PunctuationType::Comma) {
+                self.expect_punctuation(PunctuationType::CloseParen)?;
+                break;
+            }
+        }
+
+        Ok(params)
+    }
+
+    /// Parses the parenthesized condition of an `if`, `while` or `do-while`.
+    fn parse_parenthesized_test(&mut self) -> Result<Node> {
+        self.expect_punctuation(PunctuationType::OpenParen)?;
+        let test = self.parse_expression()?;
+        self.expect_punctuation(PunctuationType::CloseParen)?;
+
+        Ok(test)
+    }
+
+    /// An `else` belongs to the closest `if` without one, which falls out of
+    /// parsing it greedily here.
+    fn parse_if_statement(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        self.expect_keyword(KeywordType::If)?;
+        let test = self.parse_parenthesized_test()?;
+        let consequent = self.parse_statement()?;
+
+        let alternate = if self.is_keyword(KeywordType::Else) {
+            self.next();
+            Some(self.parse_statement()?)
+        } else {
+            None
+        };
+
+        Ok(Node::IfStatement(
+            IfStatement::new(test, consequent, alternate).with_span(self.span_from(start)),
+        ))
+    }
+
+    fn parse_while_statement(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        self.expect_keyword(KeywordType::While)?;
+        let test = self.parse_parenthesized_test()?;
+        self.push_loop_scope();
+        let body = self.parse_statement()?;
+        self.pop_block_scope();
+
+        Ok(Node::WhileStatement(
+            WhileStatement::new(test, body).with_span(self.span_from(start)),
+        ))
+    }
+
+    fn parse_do_while_statement(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        self.expect_keyword(KeywordType::Do)?;
+        self.push_loop_scope();
+        let body = self.parse_statement()?;
+        self.pop_block_scope();
+        self.expect_keyword(KeywordType::While)?;
+        let test = self.parse_parenthesized_test()?;
+
+        // The semicolon after a do-while is always optional.
+        self.eat_punctuation(PunctuationType::Semicolon);
+
+        Ok(Node::DoWhileStatement(
+            DoWhileStatement::new(body, test).with_span(self.span_from(start)),
+        ))
+    }
+
+    /// A line break straight after `return` ends the statement, so
+    /// `return\na` returns nothing.
+    fn parse_return_statement(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        self.expect_keyword(KeywordType::Return)?;
+        if !self.in_function() {
+            return Err(self.error("Illegal return statement"));
+        }
+
+        let argument =
+            if self.is_punctuation(PunctuationType::Semicolon) || self.can_insert_semicolon() {
+                None
+            } else {
+                Some(self.parse_expression()?)
+            };
+        self.consume_semicolon()?;
+
+        Ok(Node::ReturnStatement(
+            ReturnStatement::new(argument).with_span(self.span_from(start)),
+        ))
+    }
+
+    /// Unlike `return`, `throw` needs an argument, so a line break straight
+    /// after it is an error rather than the end of the statement.
+    fn parse_throw_statement(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        self.expect_keyword(KeywordType::Throw)?;
+        if self.has_line_break_before() {
+            return Err(self.error("Illegal newline after throw"));
+        }
+
+        let argument = self.parse_expression()?;
+        self.consume_semicolon()?;
+
+        Ok(Node::ThrowStatement(
+            ThrowStatement::new(argument).with_span(self.span_from(start)),
+        ))
+    }
+
+    /// The label of a `break` or `continue` has to be on the same line.
+    fn parse_break_or_continue(&mut self) -> Result<Node> {
+        let start = self.start_position();
+        let is_break = self.is_keyword(KeywordType::Break);
+        self.next();
+        let keyword_span = self.previous_span;
+
+        let label = match self.peek() {
+            Some(_) if self.is_identifier_at(0) && !self.has_line_break_before() => {
+                let name = self.parse_binding_identifier()?;
+                Some(Identifier::new(name).with_span(self.previous_span))
+            }
+            _ => None,
+        };
+
+        // There are no labeled statements yet, so no label is ever defined.
+        if let Some(label) = &label {
+            return Err(self.error_at(label.span, format!("Undefined label '{}'", label.value)));
+        }
+        if !self.in_loop() {
+            let keyword = if is_break { "break" } else { "continue" };
+            return Err(self.error_at(keyword_span, format!("Illegal {} statement", keyword)));
+        }
+        self.consume_semicolon()?;
+
+        let span = self.span_from(start);
+        Ok(if is_break {
+            Node::BreakStatement(BreakStatement::new(label).with_span(span))
+        } else {
+            Node::ContinueStatement(ContinueStatement::new(label).with_span(span))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{
+            AssignmentExpression, BinaryExpression, CallExpression, NumericLiteral,
+            UpdateExpression,
+        },
+        parser::{
+            test_helpers::{ident, parse_error},
+            without_spans::{parse, parse_with_recovery},
+        },
+    };
+
+    use super::*;
+
+    fn program(statements: Vec<Node>) -> Program {
+        let mut program = Program::default();
+        for statement in statements {
+            program.append(statement);
+        }
+        program
+    }
+
+    fn num(value: f64) -> Node {
+        Node::NumericLiteral(NumericLiteral::new(value))
+    }
+
+    fn expr_stmt(expression: Node) -> Node {
+        Node::ExpressionStatement(ExpressionStatement::new(expression))
+    }
+
+    fn block(statements: Vec<Node>) -> Node {
+        Node::BlockStatement(BlockStatement::new(statements))
+    }
+
+    fn call(callee: &str) -> Node {
+        Node::CallExpression(CallExpression::new(ident(callee), vec![]))
+    }
+
+    #[test]
+    fn test_variable_declarations() {
+        assert_eq!(
+            parse("var a; let b = 1, c; const d = b;", "script.js").unwrap(),
+            program(vec![
+                Node::VariableDeclaration(VariableDeclaration::new(
+                    "var".to_string(),
+                    vec![VariableDeclarator::new(ident("a"), None)]
+                )),
+                Node::VariableDeclaration(VariableDeclaration::new(
+                    "let".to_string(),
+                    vec![
+                        VariableDeclarator::new(ident("b"), Some(num(1.0))),
+                        VariableDeclarator::new(ident("c"), None),
+                    ]
+                )),
+                Node::VariableDeclaration(VariableDeclaration::new(
+                    "const".to_string(),
+                    vec![VariableDeclarator::new(ident("d"), Some(ident("b")))]
+                )),
+            ])
+        );
+
+        assert_eq!(
+            parse_error("const a;"),
+            "SyntaxError: Missing initializer in const declaration at script.js:1:7"
+        );
+    }
+
+    #[test]
+    fn test_function_declarations() {
+        let mut function = FunctionDeclaration::new("foo".to_string());
+        function.args_append(Parameter::new(ident("a")));
+        function.args_append(Parameter::new(ident("b")));
+        function.body_append(Node::ReturnStatement(ReturnStatement::new(Some(
+            Node::BinaryExpression(BinaryExpression::new(
+                ident("a"),
+                ident("b"),
+                "+".to_string(),
+            )),
+        ))));
+
+        assert_eq!(
+            parse("function foo(a, b) { return a + b }", "script.js").unwrap(),
+            program(vec![Node::FunctionDeclaration(function)])
+        );
+
+        let mut function = FunctionDeclaration::new("bar".to_string());
+        function.body_append(Node::ReturnStatement(ReturnStatement::new(None)));
+        assert_eq!(
+            parse("function bar() { return; }", "script.js").unwrap(),
+            program(vec![Node::FunctionDeclaration(function)])
+        );
+    }
+
+    #[test]
+    fn test_if_statements() {
+        assert_eq!(
+            parse("if (a) b(); else if (c) { d() } else e()", "script.js").unwrap(),
+            program(vec![Node::IfStatement(IfStatement::new(
+                ident("a"),
+                expr_stmt(call("b")),
+                Some(Node::IfStatement(IfStatement::new(
+                    ident("c"),
+                    block(vec![expr_stmt(call("d"))]),
+                    Some(expr_stmt(call("e")))
+                )))
+            ))])
+        );
+
+        // The else belongs to the inner if.
+        assert_eq!(
+            parse("if (a) if (b) c(); else d();", "script.js").unwrap(),
+            program(vec![Node::IfStatement(IfStatement::new(
+                ident("a"),
+                Node::IfStatement(IfStatement::new(
+                    ident("b"),
+                    expr_stmt(call("c")),
+                    Some(expr_stmt(call("d")))
+                )),
+                None
+            ))])
+        );
+    }
+
+    #[test]
+    fn test_loops() {
+        assert_eq!(
+            parse("while (a) { a = b; } do ; while (c) d()", "script.js").unwrap(),
+            program(vec![
+                Node::WhileStatement(WhileStatement::new(
+                    ident("a"),
+                    block(vec![expr_stmt(Node::AssignmentExpression(
+                        AssignmentExpression::new(ident("a"), ident("b"), "=".to_string())
+                    ))])
+                )),
+                Node::DoWhileStatement(DoWhileStatement::new(
+                    Node::EmptyStatement(EmptyStatement::default()),
+                    ident("c")
+                )),
+                expr_stmt(call("d")),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_semicolons() {
+        assert_eq!(
+            parse("{ a() } b()", "script.js").unwrap(),
+            program(vec![
+                block(vec![expr_stmt(call("a"))]),
+                expr_stmt(call("b"))
+            ])
+        );
+        assert_eq!(
+            parse_error("a() b()"),
+            "SyntaxError: Unexpected identifier 'b' at script.js:1:5"
+        );
+    }
+
+    #[test]
+    fn test_automatic_semicolon_insertion() {
+        assert_eq!(
+            parse("a = b\nc()\n", "script.js").unwrap(),
+            program(vec![
+                expr_stmt(Node::AssignmentExpression(AssignmentExpression::new(
+                    ident("a"),
+                    ident("b"),
+                    "=".to_string()
+                ))),
+                expr_stmt(call("c")),
+            ])
+        );
+
+        // A block comment spanning lines counts as a line break.
+        assert_eq!(
+            parse("a() /*\n*/ b()", "script.js").unwrap(),
+            program(vec![expr_stmt(call("a")), expr_stmt(call("b"))])
+        );
+
+        // No semicolon is inserted where the next line continues the
+        // statement.
+        assert_eq!(
+            parse("a\n(b)", "script.js").unwrap(),
+            program(vec![expr_stmt(Node::CallExpression(CallExpression::new(
+                ident("a"),
+                vec![ident("b")]
+            )))])
+        );
+    }
+
+    #[test]
+    fn test_restricted_productions() {
+        let mut function = FunctionDeclaration::new("f".to_string());
+        function.body_append(Node::ReturnStatement(ReturnStatement::new(None)));
+        function.body_append(expr_stmt(ident("a")));
+        assert_eq!(
+            parse("function f() { return\na }", "script.js").unwrap(),
+            program(vec![Node::FunctionDeclaration(function)])
+        );
+
+        assert_eq!(
+            parse("a\n++b", "script.js").unwrap(),
+            program(vec![
+                expr_stmt(ident("a")),
+                expr_stmt(Node::UpdateExpression(UpdateExpression::new(
+                    ident("b"),
+                    "++".to_string(),
+                    true
+                ))),
+            ])
+        );
+
+        assert_eq!(
+            parse("while (a) { break\nfoo; continue }", "script.js").unwrap(),
+            program(vec![Node::WhileStatement(WhileStatement::new(
+                ident("a"),
+                Node::BlockStatement(BlockStatement::new(vec![
+                    Node::BreakStatement(BreakStatement::new(None)),
+                    expr_stmt(ident("foo")),
+                    Node::ContinueStatement(ContinueStatement::new(None)),
+                ])),
+            ))])
+        );
+        assert_eq!(
+            parse_error("while (a) { continue foo }"),
+            "SyntaxError: Undefined label 'foo' at script.js:1:22"
+        );
+
+        assert_eq!(
+            parse("throw a", "script.js").unwrap(),
+            program(vec![Node::ThrowStatement(ThrowStatement::new(ident("a")))])
+        );
+        assert_eq!(
+            parse_error("throw\na"),
+            "SyntaxError: Illegal newline after throw at script.js:1:1"
+        );
+    }
+
+    #[test]
+    fn test_break_and_continue_outside_loop() {
+        for (src, expected) in [
+            ("break;", "Illegal break statement at script.js:1:1"),
+            (
+                "if (a) {\n  continue;\n}",
+                "Illegal continue statement at script.js:2:3",
+            ),
+            (
+                "while (a) { function f() { break; } }",
+                "Illegal break statement at script.js:1:28",
+            ),
+            (
+                "do { () => { continue } } while (a)",
+                "Illegal continue statement at script.js:1:14",
+            ),
+            (
+                "while (a) { break foo; }",
+                "Undefined label 'foo' at script.js:1:19",
+            ),
+        ] {
+            assert_eq!(parse_error(src), format!("SyntaxError: {}", expected));
+        }
+
+        assert!(parse(
+            "while (a) { if (b) break; { continue } }\ndo break; while (a)",
+            "script.js"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_return_outside_function() {
+        for (src, location) in [
+            ("return;", "1:1"),
+            ("if (a) {\n  return 1;\n}", "2:3"),
+            ("function f() {}\nreturn", "2:1"),
+        ] {
+            assert_eq!(
+                parse_error(src),
+                format!(
+                    "SyntaxError: Illegal return statement at script.js:{}",
+                    location
+                )
+            );
+        }
+
+        assert!(parse(
+            "() => { return 1 }; function f() { { return } }",
+            "script.js"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_contextual_keywords() {
+        assert_eq!(
+            parse("var of = 1; let = of; async(static);", "script.js").unwrap(),
+            program(vec![
+                Node::VariableDeclaration(VariableDeclaration::new(
+                    "var".to_string(),
+                    vec![VariableDeclarator::new(ident("of"), Some(num(1.0)))],
+                )),
+                expr_stmt(Node::AssignmentExpression(AssignmentExpression::new(
+                    ident("let"),
+                    ident("of"),
+                    "=".to_string(),
+                ))),
+                expr_stmt(Node::CallExpression(CallExpression::new(
+                    ident("async"),
+                    vec![ident("static")],
+                ))),
+            ])
+        );
+        assert!(parse("let { c } = d; x = { of, let: 1 };", "script.js").is_ok());
+
+        assert_eq!(
+            parse_error("var class = 1;"),
+            "SyntaxError: Unexpected token 'class' at script.js:1:5"
+        );
+        assert_eq!(
+            parse_error("'use strict'; let = 1;"),
+            "SyntaxError: Unexpected strict mode reserved word at script.js:1:15"
+        );
+    }
+
+    #[test]
+    fn test_unterminated_block() {
+        assert_eq!(
+            parse_error("function foo() { if (a) {"),
+            "SyntaxError: Unexpected end of input at script.js:1:26"
+        );
+        assert_eq!(
+            parse_error("while a {}"),
+            "SyntaxError: Unexpected identifier 'a' at script.js:1:7"
+        );
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let recover = |src: &str| {
+            let (program, errors) = parse_with_recovery(src, "script.js");
+            let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+            (program, errors)
+        };
+
+        assert_eq!(
+            recover("a(); var = 1;\nb();"),
+            (
+                program(vec![expr_stmt(call("a")), expr_stmt(call("b"))]),
+                vec!["SyntaxError: Unexpected token '=' at script.js:1:10".to_string()]
+            )
+        );
+
+        // The `}` that ended the bad expression still ends the block.
+        assert_eq!(
+            recover("{ a + } b();"),
+            (
+                program(vec![block(vec![]), expr_stmt(call("b"))]),
+                vec!["SyntaxError: Unexpected token '}' at script.js:1:7".to_string()]
+            )
+        );
+
+        // A bad statement is skipped along with any block inside it.
+        assert_eq!(
+            recover("if (a +) { b(); } c();\nd("),
+            (
+                program(vec![expr_stmt(call("c"))]),
+                vec![
+                    "SyntaxError: Unexpected token ')' at script.js:1:8".to_string(),
+                    "SyntaxError: Unexpected end of input at script.js:2:3".to_string(),
+                ]
+            )
+        );
+
+        let mut function = FunctionDeclaration::new("f".to_string());
+        function.body_append(expr_stmt(call("b")));
+        assert_eq!(
+            recover("function f() {\n    a +;\n    b();\n}\nlet a; let a;"),
+            (
+                program(vec![
+                    Node::FunctionDeclaration(function),
+                    Node::VariableDeclaration(VariableDeclaration::new(
+                        "let".to_string(),
+                        vec![VariableDeclarator::new(ident("a"), None)]
+                    )),
+                ]),
+                vec![
+                    "SyntaxError: Unexpected token ';' at script.js:2:8".to_string(),
+                    "SyntaxError: Identifier 'a' has already been declared at script.js:5:12"
+                        .to_string(),
+                ]
+            )
+        );
+
+        let (program, errors) = recover("'unterminated");
+        assert_eq!(program, Program::default());
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_spans() {
+        let src = "let a = 1;\nfunction f(b) {\n  if (b) return;\n}\nexport { a };";
+        let value = serde_json::to_value(crate::parser::parse(src, "script.js").unwrap()).unwrap();
+        let span = |pointer: &str| {
+            let span = &value.pointer(pointer).unwrap()["span"];
+            let position = |position: &serde_json::Value| {
+                (
+                    position["line"].as_u64().unwrap(),
+                    position["column"].as_u64().unwrap(),
+                )
+            };
+            (position(&span["start"]), position(&span["end"]))
+        };
+
+        assert_eq!(span(""), ((1, 1), (5, 14)));
+        assert_eq!(span("/body/0"), ((1, 1), (1, 11)));
+        assert_eq!(span("/body/0/declarations/0"), ((1, 5), (1, 10)));
+        assert_eq!(span("/body/1"), ((2, 1), (4, 2)));
+        assert_eq!(span("/body/1/identifier"), ((2, 10), (2, 11)));
+        assert_eq!(span("/body/1/params/0"), ((2, 12), (2, 13)));
+        assert_eq!(span("/body/1/body"), ((2, 15), (4, 2)));
+        assert_eq!(span("/body/1/body/statements/0"), ((3, 3), (3, 17)));
+        assert_eq!(
+            span("/body/1/body/statements/0/consequent"),
+            ((3, 10), (3, 17))
+        );
+        assert_eq!(span("/body/2"), ((5, 1), (5, 14)));
+        assert_eq!(span("/body/2/specifiers/0"), ((5, 10), (5, 11)));
+    }
+}
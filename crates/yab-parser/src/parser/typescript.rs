@@ -0,0 +1,649 @@
+//! TypeScript support, which only goes as far as stripping types: type
+//! annotations, interfaces, type aliases, generics, `as` and `satisfies` are
+//! all accepted and left out of the AST, so what's left is plain JavaScript.
+//! Nothing here does anything unless [`super::ParserOptions::typescript`] is
+//! set.
+//!
+//! Types are never parsed into nodes.  Instead, the `*_end` functions scan
+//! ahead over a type without consuming anything, returning the offset (as
+//! for [`Parser::peek_nth`]) just past it, and the `skip_*` functions consume
+//! what they scanned.  That's enough to find where a type ends, which is all
+//! stripping needs, and it lets callers speculate about whether something
+//! is a type without having to backtrack.
+
+use miette::Result;
+
+use crate::lexer::{KeywordType, OperatorType, PunctuationType, Token};
+
+use super::Parser;
+
+/// Words that can start a type and apply to the type that follows them, like
+/// `keyof T` or `readonly string[]`.
+fn is_type_operator(word: &str) -> bool {
+    matches!(
+        word,
+        "keyof" | "unique" | "readonly" | "infer" | "asserts" | "abstract"
+    )
+}
+
+/// Whether `token` can start the type a type operator applies to.
+fn starts_type(token: Option<&Token>) -> bool {
+    match token {
+        Some(Token::Ident(_) | Token::Keyword(_)) => true,
+        Some(Token::Punctuation(p)) => matches!(
+            p.kind,
+            PunctuationType::OpenParen | PunctuationType::OpenBracket | PunctuationType::OpenBrace
+        ),
+        Some(Token::Operator(op)) => op.kind == OperatorType::TypeOf,
+        _ => false,
+    }
+}
+
+impl Parser<'_> {
+    fn is_typescript_word_at(&self, n: usize, word: &str) -> bool {
+        matches!(self.peek_nth(n), Some(Token::Ident(ident)) if ident.lexeme == word)
+    }
+
+    /// Consumes the next `count` tokens.
+    fn skip_tokens(&mut self, count: usize) {
+        for _ in 0..count {
+            self.next();
+        }
+    }
+
+    /// Returns the offset just past the type starting at `peek_nth(n)`, or
+    /// `None` if there isn't a type there.
+    ///
+    /// The scan alternates between expecting an operand, like `string`, `{
+    /// a: T }` or `(a: T) => U`, and an operator joining it to the next one,
+    /// like `|` or `extends` in a conditional type.  It ends at the first
+    /// token that can't continue the type.
+    fn type_end(&self, mut n: usize) -> Option<usize> {
+        let is_arrow_at = |n| matches!(self.peek_nth(n), Some(Token::Operator(op)) if op.kind == OperatorType::Arrow);
+
+        let mut expect_operand = true;
+        // How many conditional types are waiting for their `?` or `:`.
+        let mut conditionals = 0;
+        loop {
+            let token = self.peek_nth(n);
+            if expect_operand {
+                n = match token? {
+                    // A leading `|` or `&`, a type query, or a negative number.
+                    Token::Operator(op)
+                        if matches!(
+                            op.kind,
+                            OperatorType::BitwiseOr
+                                | OperatorType::BitwiseAnd
+                                | OperatorType::TypeOf
+                                | OperatorType::Minus
+                        ) =>
+                    {
+                        n + 1
+                    }
+                    // The type parameters of a generic function type.
+                    Token::Operator(op) if op.kind == OperatorType::LessThan => {
+                        self.type_arguments_end(n)?
+                    }
+                    Token::Operator(op) if op.kind == OperatorType::Void => {
+                        expect_operand = false;
+                        n + 1
+                    }
+                    Token::Ident(ident)
+                        if is_type_operator(&ident.lexeme) && starts_type(self.peek_nth(n + 1)) =>
+                    {
+                        n + 1
+                    }
+                    // A constructor type, `new () => T`.
+                    Token::Keyword(keyword) if keyword.kind == KeywordType::New => n + 1,
+                    Token::Ident(_)
+                    | Token::Keyword(_)
+                    | Token::ValueLiteral(_)
+                    | Token::StringLiteral(_)
+                    | Token::NumericLiteral(_) => {
+                        expect_operand = false;
+                        n + 1
+                    }
+                    Token::TemplateLiteralString(string) => {
+                        expect_operand = false;
+                        if string.complete {
+                            n + 1
+                        } else {
+                            self.template_type_end(n)?
+                        }
+                    }
+                    Token::Punctuation(p) => match p.kind {
+                        PunctuationType::OpenBrace | PunctuationType::OpenBracket => {
+                            expect_operand = false;
+                            self.find_matching_close(n)? + 1
+                        }
+                        // Either a function type's parameters, or a type in
+                        // parentheses.
+                        PunctuationType::OpenParen => {
+                            let close = self.find_matching_close(n)?;
+                            if is_arrow_at(close + 1) {
+                                close + 2
+                            } else {
+                                expect_operand = false;
+                                close + 1
+                            }
+                        }
+                        _ => return None,
+                    },
+                    _ => return None,
+                };
+                continue;
+            }
+
+            match token {
+                // A qualified name, like `a.B`.
+                Some(Token::Punctuation(p)) if p.kind == PunctuationType::Dot => {
+                    expect_operand = true;
+                    n += 1;
+                }
+                // An array type, `T[]`, or an indexed access, `T["a"]`.
+                Some(Token::Punctuation(p))
+                    if p.kind == PunctuationType::OpenBracket
+                        && !self.has_line_break_before_nth(n) =>
+                {
+                    n = self.find_matching_close(n)? + 1;
+                }
+                Some(Token::Operator(op)) if op.kind == OperatorType::LessThan => {
+                    n = self.type_arguments_end(n)?;
+                }
+                Some(Token::Operator(op))
+                    if matches!(op.kind, OperatorType::BitwiseOr | OperatorType::BitwiseAnd) =>
+                {
+                    expect_operand = true;
+                    n += 1;
+                }
+                // A type predicate, `a is T`.
+                Some(Token::Ident(ident)) if ident.lexeme == "is" => {
+                    expect_operand = true;
+                    n += 1;
+                }
+                Some(Token::Keyword(keyword)) if keyword.kind == KeywordType::Extends => {
+                    conditionals += 1;
+                    expect_operand = true;
+                    n += 1;
+                }
+                Some(Token::Operator(op))
+                    if op.kind == OperatorType::Ternary && conditionals > 0 =>
+                {
+                    expect_operand = true;
+                    n += 1;
+                }
+                Some(Token::Punctuation(p))
+                    if p.kind == PunctuationType::Colon && conditionals > 0 =>
+                {
+                    conditionals -= 1;
+                    expect_operand = true;
+                    n += 1;
+                }
+                _ => return Some(n),
+            }
+        }
+    }
+
+    /// Given that `peek_nth(n)` is the head of a template literal type with
+    /// substitutions, like `` `a${T}b` ``, returns the offset just past its
+    /// tail.
+    fn template_type_end(&self, n: usize) -> Option<usize> {
+        let mut depth = 0;
+        for offset in n + 1.. {
+            match self.peek_nth(offset)? {
+                Token::TemplateLiteralExprOpen(_) => depth += 1,
+                Token::TemplateLiteralExprClose(_) => depth -= 1,
+                Token::TemplateLiteralString(string) if depth == 0 && string.complete => {
+                    return Some(offset + 1);
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    /// Given that `peek_nth(n)` is a `<`, returns the offset just past the
+    /// `>` that closes it, if everything in between could be part of type
+    /// arguments or parameters.  Being strict about what's in between is what
+    /// tells `f<T>(a)` apart from comparisons like `a < b && c > (d)`.
+    fn type_arguments_end(&self, mut n: usize) -> Option<usize> {
+        let mut depth = 0i32;
+        loop {
+            match self.peek_nth(n)? {
+                Token::Operator(op) => match op.kind {
+                    OperatorType::LessThan => depth += 1,
+                    OperatorType::GreaterThan => depth -= 1,
+                    OperatorType::BitwiseShiftRight => depth -= 2,
+                    OperatorType::BitwiseShiftRightUnsigned => depth -= 3,
+                    OperatorType::BitwiseOr
+                    | OperatorType::BitwiseAnd
+                    | OperatorType::Assignment
+                    | OperatorType::Arrow
+                    | OperatorType::Ternary
+                    | OperatorType::Minus
+                    | OperatorType::TypeOf
+                    | OperatorType::Void
+                    | OperatorType::In => {}
+                    _ => return None,
+                },
+                Token::Punctuation(p) => match p.kind {
+                    PunctuationType::OpenParen
+                    | PunctuationType::OpenBracket
+                    | PunctuationType::OpenBrace => n = self.find_matching_close(n)?,
+                    PunctuationType::Comma
+                    | PunctuationType::Dot
+                    | PunctuationType::Colon
+                    | PunctuationType::Ellipsis => {}
+                    _ => return None,
+                },
+                Token::Ident(_)
+                | Token::Keyword(_)
+                | Token::ValueLiteral(_)
+                | Token::StringLiteral(_)
+                | Token::NumericLiteral(_)
+                | Token::TemplateLiteralString(_)
+                | Token::TemplateLiteralExprOpen(_)
+                | Token::TemplateLiteralExprClose(_) => {}
+                _ => return None,
+            }
+
+            n += 1;
+            // A `>>` can close more than one level, but never more than are
+            // open.
+            if depth <= 0 {
+                return (depth == 0).then_some(n);
+            }
+        }
+    }
+
+    /// Returns the offset just past the type parameters of a generic arrow
+    /// function starting at `peek_nth(n)`, which is `n` itself if there are
+    /// none.
+    pub(super) fn type_parameters_end(&self, n: usize) -> Option<usize> {
+        match self.peek_nth(n) {
+            Some(Token::Operator(op))
+                if self.options.typescript && op.kind == OperatorType::LessThan =>
+            {
+                self.type_arguments_end(n)
+            }
+            _ => Some(n),
+        }
+    }
+
+    /// Returns the offset just past the return type annotation of an arrow
+    /// function starting at `peek_nth(n)`, which is `n` itself if there is
+    /// none.
+    pub(super) fn return_type_end(&self, n: usize) -> Option<usize> {
+        match self.peek_nth(n) {
+            Some(Token::Punctuation(p))
+                if self.options.typescript && p.kind == PunctuationType::Colon =>
+            {
+                self.type_end(n + 1)
+            }
+            _ => Some(n),
+        }
+    }
+
+    /// Consumes a type, which has to be next.
+    fn skip_type(&mut self) -> Result<()> {
+        match self.type_end(0) {
+            Some(end) => {
+                self.skip_tokens(end);
+                Ok(())
+            }
+            None => {
+                let token = self.next();
+                Err(self.unexpected(token.as_ref()))
+            }
+        }
+    }
+
+    /// Consumes a `: T` annotation, if there is one.
+    pub(super) fn skip_type_annotation(&mut self) -> Result<()> {
+        if self.options.typescript && self.eat_punctuation(PunctuationType::Colon) {
+            self.skip_type()?;
+        }
+        Ok(())
+    }
+
+    /// Consumes the type parameters of a generic function, like `<T, U
+    /// extends T = T>`, if there are any.
+    pub(super) fn skip_type_parameters(&mut self) -> Result<()> {
+        if !self.options.typescript || !self.is_operator(OperatorType::LessThan) {
+            return Ok(());
+        }
+
+        match self.type_arguments_end(0) {
+            Some(end) => {
+                self.skip_tokens(end);
+                Ok(())
+            }
+            None => {
+                let token = self.next();
+                Err(self.unexpected(token.as_ref()))
+            }
+        }
+    }
+
+    /// Consumes what can follow a parameter's pattern: a `?` marking it
+    /// optional, then its type annotation.
+    pub(super) fn skip_parameter_type(&mut self) -> Result<()> {
+        if self.options.typescript {
+            self.eat_operator(OperatorType::Ternary);
+        }
+        self.skip_type_annotation()
+    }
+
+    /// Consumes what can follow a variable declarator's pattern: a `!`
+    /// asserting it's definitely assigned, then its type annotation.
+    pub(super) fn skip_declarator_type(&mut self) -> Result<()> {
+        if self.options.typescript {
+            self.eat_operator(OperatorType::LogicalNot);
+        }
+        self.skip_type_annotation()
+    }
+
+    /// Consumes a `this: T` parameter, which only types `this` in the body
+    /// and isn't a real parameter, returning whether there was one.
+    pub(super) fn skip_this_parameter(&mut self) -> Result<bool> {
+        let is_this_parameter = self.options.typescript
+            && self.is_keyword(KeywordType::This)
+            && matches!(self.peek_nth(1), Some(Token::Punctuation(p)) if p.kind == PunctuationType::Colon);
+        if is_this_parameter {
+            self.next();
+            self.skip_type_annotation()?;
+        }
+        Ok(is_this_parameter)
+    }
+
+    /// Consumes a non-null assertion, `a!`, or the type arguments of a call or
+    /// tagged template, `f<T>()`, returning whether there was either.
+    pub(super) fn skip_type_suffix(&mut self) -> bool {
+        if !self.options.typescript || self.has_line_break_before() {
+            return false;
+        }
+
+        if self.eat_operator(OperatorType::LogicalNot) {
+            return true;
+        }
+
+        let end = match self.peek() {
+            Some(Token::Operator(op)) if op.kind == OperatorType::LessThan => {
+                self.type_arguments_end(0)
+            }
+            _ => None,
+        };
+        match end {
+            Some(end)
+                if matches!(
+                    self.peek_nth(end),
+                    Some(Token::Punctuation(p)) if p.kind == PunctuationType::OpenParen
+                ) || matches!(self.peek_nth(end), Some(Token::TemplateLiteralString(_))) =>
+            {
+                self.skip_tokens(end);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Consumes `as T`, `as const` or `satisfies T` following an expression,
+    /// returning whether there was one.  These bind like relational
+    /// operators, which is for the caller to check.
+    pub(super) fn skip_type_assertion(&mut self) -> Result<bool> {
+        if !self.options.typescript || self.has_line_break_before() {
+            return Ok(false);
+        }
+
+        if self.eat_contextual_keyword("as") {
+            if self.is_keyword(KeywordType::Const) {
+                self.next();
+            } else {
+                self.skip_type()?;
+            }
+            return Ok(true);
+        }
+
+        if self.eat_contextual_keyword("satisfies") {
+            self.skip_type()?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Whether the next statement is only there for the type checker: an
+    /// interface, a type alias, or at the top level, `import type` and
+    /// `export type`, any of which may also be exported.
+    pub(super) fn is_type_declaration(&self, top_level: bool) -> bool {
+        if !self.options.typescript {
+            return false;
+        }
+
+        let is_export = top_level && self.is_keyword(KeywordType::Export);
+        let n = usize::from(is_export);
+        if self.is_typescript_word_at(n, "interface") && self.is_identifier_at(n + 1) {
+            return true;
+        }
+        if self.is_typescript_word_at(n, "type") {
+            // `type A = B` or `type A<T> = B`.
+            if self.is_identifier_at(n + 1)
+                && matches!(
+                    self.peek_nth(n + 2),
+                    Some(Token::Operator(op))
+                        if matches!(op.kind, OperatorType::Assignment | OperatorType::LessThan)
+                )
+            {
+                return true;
+            }
+            // `export type { A }` or `export type * from "a"`.
+            if is_export && self.is_type_only_clause_at(n + 1) {
+                return true;
+            }
+        }
+
+        // `import type A from "a"` and the like, but not `import type from
+        // "a"` or `import type, { a } from "a"`, which import a binding
+        // called `type`.
+        top_level
+            && self.is_keyword(KeywordType::Import)
+            && self.is_typescript_word_at(1, "type")
+            && (self.is_type_only_clause_at(2)
+                || (self.is_identifier_at(2) && !self.is_typescript_word_at(2, "from")))
+    }
+
+    fn is_type_only_clause_at(&self, n: usize) -> bool {
+        match self.peek_nth(n) {
+            Some(Token::Punctuation(p)) => p.kind == PunctuationType::OpenBrace,
+            Some(Token::Operator(op)) => op.kind == OperatorType::Multiplication,
+            _ => false,
+        }
+    }
+
+    /// Consumes the statement [`Parser::is_type_declaration`] found.
+    pub(super) fn skip_type_declaration(&mut self) -> Result<()> {
+        if self.is_keyword(KeywordType::Import) {
+            self.skip_tokens(2);
+            while !self.is_contextual_keyword("from") {
+                match self.find_matching_close(0) {
+                    Some(close) => self.skip_tokens(close + 1),
+                    None if self.peek().is_some() => self.skip_tokens(1),
+                    None => return Err(self.unexpected(None)),
+                }
+            }
+            self.next();
+            self.parse_module_specifier()?;
+            return self.consume_semicolon();
+        }
+
+        if self.is_keyword(KeywordType::Export) {
+            self.next();
+            if self.is_type_only_clause_at(1) {
+                self.next();
+                match self.find_matching_close(0) {
+                    Some(close) => self.skip_tokens(close + 1),
+                    // `* as ns`, or just `*`.
+                    None => {
+                        self.next();
+                        if self.eat_contextual_keyword("as") {
+                            self.next();
+                        }
+                    }
+                }
+                if self.eat_contextual_keyword("from") {
+                    self.parse_module_specifier()?;
+                }
+                return self.consume_semicolon();
+            }
+        }
+
+        if self.eat_contextual_keyword("interface") {
+            self.next();
+            self.skip_type_parameters()?;
+            if self.is_keyword(KeywordType::Extends) {
+                self.next();
+                loop {
+                    self.skip_type()?;
+                    if !self.eat_punctuation(PunctuationType::Comma) {
+                        break;
+                    }
+                }
+            }
+            return match self.find_matching_close(0) {
+                Some(close) if self.is_punctuation(PunctuationType::OpenBrace) => {
+                    self.skip_tokens(close + 1);
+                    Ok(())
+                }
+                _ => {
+                    let token = self.next();
+                    Err(self.unexpected(token.as_ref()))
+                }
+            };
+        }
+
+        self.expect_contextual_keyword("type")?;
+        self.next();
+        self.skip_type_parameters()?;
+        if !self.eat_operator(OperatorType::Assignment) {
+            let token = self.next();
+            return Err(self.unexpected(token.as_ref()));
+        }
+        self.skip_type()?;
+        self.consume_semicolon()
+    }
+
+    /// Consumes the `type` in `import { type A }` or `export { type A }`,
+    /// returning whether the specifier was marked type-only.  On its own,
+    /// `{ type }` names something called `type`, and `{ type as A }` renames
+    /// it.
+    pub(super) fn eat_type_only_marker(&mut self) -> bool {
+        let is_marker = self.options.typescript
+            && self.is_typescript_word_at(0, "type")
+            && !self.is_typescript_word_at(1, "as")
+            && !matches!(
+                self.peek_nth(1),
+                Some(Token::Punctuation(p))
+                    if matches!(p.kind, PunctuationType::Comma | PunctuationType::CloseBrace)
+            );
+        if is_marker {
+            self.next();
+        }
+        is_marker
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::without_spans::{parse, parse_expression};
+
+    /// Asserts that `ts` parses as TypeScript to the same tree as `js` does as
+    /// JavaScript.
+    fn assert_strips_to(ts: &str, js: &str) {
+        let stripped = parse(ts, "module.ts").unwrap_or_else(|err| panic!("{}: {}", ts, err));
+        assert_eq!(stripped, parse(js, "module.js").unwrap(), "{}", ts);
+    }
+
+    #[test]
+    fn test_annotations() {
+        assert_strips_to("let a: number = 1;", "let a = 1;");
+        assert_strips_to("let a!: string, b: T[] | null;", "let a, b;");
+        assert_strips_to(
+            "const { a }: { a: Map<string, () => void> } = b;",
+            "const { a } = b;",
+        );
+        assert_strips_to(
+            "function f(this: Window, a?: number, b: string = '', ...c: any[]): a is string {}",
+            "function f(a, b = '', ...c) {}",
+        );
+        assert_strips_to(
+            "function f<T extends keyof U, U = {}>(a: T): Promise<Array<T>> { return a; }",
+            "function f(a) { return a; }",
+        );
+        assert_strips_to(
+            "let a: T extends (infer U)[] ? U : `a${T}b` = 1;",
+            "let a = 1;",
+        );
+    }
+
+    #[test]
+    fn test_arrow_functions() {
+        assert_strips_to(
+            "const f = (a: number, b?: string): void => {};",
+            "const f = (a, b) => {};",
+        );
+        assert_strips_to(
+            "const f = async <T,>(a: T): Promise<T> => a;",
+            "const f = async (a) => a;",
+        );
+        assert_strips_to("a ? (b) : c;", "a ? (b) : c;");
+    }
+
+    #[test]
+    fn test_expressions() {
+        assert_strips_to("a as unknown as B;", "a;");
+        assert_strips_to("const a = [1] as const;", "const a = [1];");
+        assert_strips_to("a satisfies B || c;", "a || c;");
+        assert_strips_to("a! + b!.c![0];", "a + b.c[0];");
+        assert_strips_to("a! / b / c;", "a / b / c;");
+        assert_strips_to("f<T>(a); new Map<K, V>();", "f(a); new Map();");
+        assert_strips_to("a < b && c > (d);", "a < b && c > (d);");
+        assert_strips_to("a\nas\nb;", "a\nas\nb;");
+    }
+
+    #[test]
+    fn test_declarations() {
+        assert_strips_to(
+            "interface A<T> extends B<T>, C { a: T; b(): void }\nlet a;",
+            "let a;",
+        );
+        assert_strips_to("type A<T> = { a: T } | B;\nlet a;", "let a;");
+        assert_strips_to("export interface A {}\nexport type B = A;", "");
+        assert_strips_to(
+            "import type { A } from 'a';\nimport type B from 'b';\nexport type { A };",
+            "",
+        );
+        assert_strips_to(
+            "import { type A, b } from 'a';\nexport { type A, b };",
+            "import { b } from 'a';\nexport { b };",
+        );
+        assert_strips_to(
+            "import { type A } from 'a';\nexport { type B } from 'b';\nexport { type A };",
+            "",
+        );
+        assert_strips_to(
+            "import A, { type B } from 'a';\nimport {} from 'b';",
+            "import A from 'a';\nimport {} from 'b';",
+        );
+        assert_strips_to("import type from 'a';", "import type from 'a';");
+        assert_strips_to("let type = 1; type = 2;", "let type = 1; type = 2;");
+    }
+
+    #[test]
+    fn test_types_only_in_typescript_files() {
+        assert!(parse("let a: number = 1;", "module.js").is_err());
+        assert!(parse("let a: number = 1;", "module.mts").is_ok());
+        assert!(parse_expression("a as B", "module.js").is_err());
+        assert!(parse("interface A {}", "module.js").is_err());
+    }
+}
@@ -0,0 +1,754 @@
+//! Scope analysis.  [`analyze`] walks a parsed program and builds a
+//! [`ScopeTree`]: the scopes it contains, the bindings declared in each, and
+//! every reference to a name, resolved to the binding it refers to.
+//!
+//! `var` and function declarations at the top level of a function are hoisted
+//! to the function, `let`, `const` and function declarations in blocks are
+//! scoped to the block, and imports are scoped to the module.  References are
+//! resolved once the whole program has been walked, so a reference can refer
+//! to a binding declared after it, which for `let` and `const` means it is in
+//! the temporal dead zone.
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::{Node, Program},
+    location::Span,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScopeId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BindingId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ReferenceId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Program,
+    /// The parameters and body of a function or arrow function.
+    Function,
+    Block,
+}
+
+#[derive(Debug)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    /// `None` for the program scope.
+    pub parent: Option<ScopeId>,
+    /// The span of the program, function or block.
+    pub span: Span,
+    /// The bindings declared in this scope, in the order they're declared.
+    pub bindings: Vec<BindingId>,
+    names: HashMap<String, BindingId>,
+}
+
+impl Scope {
+    /// Looks up a binding declared directly in this scope.
+    pub fn get(&self, name: &str) -> Option<BindingId> {
+        self.names.get(name).copied()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    Var,
+    Let,
+    Const,
+    Function,
+    Parameter,
+    Import,
+}
+
+#[derive(Debug)]
+pub struct Binding {
+    pub name: String,
+    pub kind: BindingKind,
+    pub scope: ScopeId,
+    /// The span of the first identifier that declares it.
+    pub span: Span,
+    pub references: Vec<ReferenceId>,
+    /// Whether a function nested inside the one declaring the binding refers
+    /// to it, which keeps it alive after its own function returns.
+    pub is_captured: bool,
+    /// The byte offset where a `let` or `const` is initialized, which is the
+    /// end of its declarator.
+    initialized_at: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    Read,
+    Write,
+    /// A compound assignment like `a += 1`, or an update like `a++`.
+    ReadWrite,
+}
+
+#[derive(Debug)]
+pub struct Reference {
+    pub name: String,
+    pub span: Span,
+    /// The scope the reference is in.
+    pub scope: ScopeId,
+    pub kind: ReferenceKind,
+    /// `None` for a name that isn't declared anywhere, i.e. a global.
+    pub binding: Option<BindingId>,
+    /// Whether this refers to a `let` or `const` before its declaration has
+    /// run, from the same function, which throws a `ReferenceError`.  A
+    /// reference from a nested function isn't, since the function might be
+    /// called after the declaration runs.
+    pub in_tdz: bool,
+}
+
+#[derive(Debug)]
+pub struct ScopeTree {
+    scopes: Vec<Scope>,
+    bindings: Vec<Binding>,
+    references: Vec<Reference>,
+}
+
+impl ScopeTree {
+    /// The program scope.
+    pub fn root(&self) -> ScopeId {
+        ScopeId(0)
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.0]
+    }
+
+    pub fn binding(&self, id: BindingId) -> &Binding {
+        &self.bindings[id.0]
+    }
+
+    pub fn reference(&self, id: ReferenceId) -> &Reference {
+        &self.references[id.0]
+    }
+
+    pub fn scopes(&self) -> impl Iterator<Item = (ScopeId, &Scope)> {
+        self.scopes.iter().enumerate().map(|(i, s)| (ScopeId(i), s))
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (BindingId, &Binding)> {
+        self.bindings
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (BindingId(i), b))
+    }
+
+    pub fn references(&self) -> impl Iterator<Item = (ReferenceId, &Reference)> {
+        self.references
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (ReferenceId(i), r))
+    }
+
+    /// References to names that aren't declared anywhere in the program.
+    pub fn unresolved(&self) -> impl Iterator<Item = &Reference> {
+        self.references.iter().filter(|r| r.binding.is_none())
+    }
+
+    /// Finds the binding `name` refers to in `scope`, looking outwards.
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<BindingId> {
+        let mut scope = Some(scope);
+        while let Some(id) = scope {
+            let current = self.scope(id);
+            if let Some(binding) = current.get(name) {
+                return Some(binding);
+            }
+            scope = current.parent;
+        }
+        None
+    }
+
+    /// The function or program scope that `scope` is in, which is where its
+    /// `var` declarations go.
+    pub fn function_scope(&self, mut scope: ScopeId) -> ScopeId {
+        loop {
+            let current = self.scope(scope);
+            match current.parent {
+                Some(parent) if current.kind == ScopeKind::Block => scope = parent,
+                _ => return scope,
+            }
+        }
+    }
+
+    /// The bindings from outside a function that it refers to, where
+    /// `function` is the function's scope.
+    pub fn captures(&self, function: ScopeId) -> Vec<BindingId> {
+        let mut captures = Vec::new();
+        for reference in &self.references {
+            let Some(binding) = reference.binding else {
+                continue;
+            };
+            if self.is_inside(reference.scope, function)
+                && !self.is_inside(self.binding(binding).scope, function)
+                && !captures.contains(&binding)
+            {
+                captures.push(binding);
+            }
+        }
+        captures
+    }
+
+    /// Whether `scope` is `ancestor` or inside it.
+    fn is_inside(&self, scope: ScopeId, ancestor: ScopeId) -> bool {
+        let mut scope = Some(scope);
+        while let Some(id) = scope {
+            if id == ancestor {
+                return true;
+            }
+            scope = self.scope(id).parent;
+        }
+        false
+    }
+}
+
+/// Builds the scope tree of `program`.  Spans come from the AST, so the
+/// temporal dead zone is only meaningful for a program that was parsed.
+pub fn analyze(program: &Program) -> ScopeTree {
+    let mut analyzer = Analyzer {
+        tree: ScopeTree {
+            scopes: Vec::new(),
+            bindings: Vec::new(),
+            references: Vec::new(),
+        },
+        current: ScopeId(0),
+    };
+
+    analyzer.push_scope(ScopeKind::Program, program.span);
+    for statement in &program.body {
+        analyzer.visit(statement);
+    }
+    analyzer.resolve();
+
+    analyzer.tree
+}
+
+/// What the identifiers in a pattern do.
+#[derive(Clone, Copy)]
+enum Target {
+    /// Declare a binding, along with where it's initialized.
+    Declare(BindingKind, usize),
+    Assign(ReferenceKind),
+}
+
+struct Analyzer {
+    tree: ScopeTree,
+    current: ScopeId,
+}
+
+impl Analyzer {
+    fn push_scope(&mut self, kind: ScopeKind, span: Span) {
+        let parent = (!self.tree.scopes.is_empty()).then_some(self.current);
+        self.tree.scopes.push(Scope {
+            kind,
+            parent,
+            span,
+            bindings: Vec::new(),
+            names: HashMap::new(),
+        });
+        self.current = ScopeId(self.tree.scopes.len() - 1);
+    }
+
+    fn pop_scope(&mut self) {
+        self.current = self
+            .tree
+            .scope(self.current)
+            .parent
+            .expect("the program scope is never popped");
+    }
+
+    fn declare(&mut self, name: &str, kind: BindingKind, span: Span, initialized_at: usize) {
+        let scope = match kind {
+            BindingKind::Var => self.tree.function_scope(self.current),
+            _ => self.current,
+        };
+        // Redeclaring a `var`, or a function in sloppy mode, is allowed and
+        // refers to the same binding.
+        if self.tree.scope(scope).get(name).is_some() {
+            return;
+        }
+
+        let id = BindingId(self.tree.bindings.len());
+        self.tree.bindings.push(Binding {
+            name: name.to_string(),
+            kind,
+            scope,
+            span,
+            references: Vec::new(),
+            is_captured: false,
+            initialized_at,
+        });
+        let scope = &mut self.tree.scopes[scope.0];
+        scope.bindings.push(id);
+        scope.names.insert(name.to_string(), id);
+    }
+
+    fn reference(&mut self, name: &str, span: Span, kind: ReferenceKind) {
+        self.tree.references.push(Reference {
+            name: name.to_string(),
+            span,
+            scope: self.current,
+            kind,
+            binding: None,
+            in_tdz: false,
+        });
+    }
+
+    /// Resolves every reference, now that every binding has been declared.
+    fn resolve(&mut self) {
+        for index in 0..self.tree.references.len() {
+            let reference = &self.tree.references[index];
+            let Some(id) = self.tree.lookup(reference.scope, &reference.name) else {
+                continue;
+            };
+
+            let binding = self.tree.binding(id);
+            let same_function = self.tree.function_scope(reference.scope)
+                == self.tree.function_scope(binding.scope);
+            let in_tdz = same_function
+                && matches!(binding.kind, BindingKind::Let | BindingKind::Const)
+                && reference.span.start.index < binding.initialized_at;
+
+            let reference = &mut self.tree.references[index];
+            reference.binding = Some(id);
+            reference.in_tdz = in_tdz;
+
+            let binding = &mut self.tree.bindings[id.0];
+            binding.references.push(ReferenceId(index));
+            binding.is_captured |= !same_function;
+        }
+    }
+
+    fn visit_all<'a>(&mut self, nodes: impl IntoIterator<Item = &'a Node>) {
+        for node in nodes {
+            self.visit(node);
+        }
+    }
+
+    /// Visits a node in expression or statement position.
+    fn visit(&mut self, node: &Node) {
+        match node {
+            Node::Identifier(ident) => {
+                self.reference(&ident.value, ident.span, ReferenceKind::Read)
+            }
+            Node::Program(program) => self.visit_all(&program.body),
+            Node::SequenceExpression(sequence) => self.visit_all(&sequence.expressions),
+            Node::AssignmentExpression(assignment) => {
+                let kind = if assignment.operator == "=" {
+                    ReferenceKind::Write
+                } else {
+                    ReferenceKind::ReadWrite
+                };
+                self.pattern(&assignment.lhs, Target::Assign(kind));
+                self.visit(&assignment.rhs);
+            }
+            Node::ConditionalExpression(conditional) => {
+                self.visit(&conditional.test);
+                self.visit(&conditional.consequent);
+                self.visit(&conditional.alternate);
+            }
+            Node::LogicalExpression(logical) => {
+                self.visit(&logical.lhs);
+                self.visit(&logical.rhs);
+            }
+            Node::BinaryExpression(binary) => {
+                self.visit(&binary.lhs);
+                self.visit(&binary.rhs);
+            }
+            Node::UnaryExpression(unary) => self.visit(&unary.argument),
+            Node::UpdateExpression(update) => {
+                self.pattern(&update.argument, Target::Assign(ReferenceKind::ReadWrite))
+            }
+            Node::AwaitExpression(await_expression) => self.visit(&await_expression.argument),
+            Node::CallExpression(call) => {
+                self.visit(&call.callee);
+                self.visit_all(&call.arguments);
+            }
+            Node::NewExpression(new) => {
+                self.visit(&new.callee);
+                self.visit_all(&new.arguments);
+            }
+            Node::OptionalCallExpression(call) => {
+                self.visit(&call.callee);
+                self.visit_all(&call.arguments);
+            }
+            Node::MemberExpression(member) => {
+                self.visit(&member.object);
+                if member.computed {
+                    self.visit(&member.property);
+                }
+            }
+            Node::OptionalMemberExpression(member) => {
+                self.visit(&member.object);
+                if member.computed {
+                    self.visit(&member.property);
+                }
+            }
+            Node::TaggedTemplateExpression(tagged) => {
+                self.visit(&tagged.tag);
+                self.visit_all(&tagged.quasi.expressions);
+            }
+            Node::TemplateLiteral(template) => self.visit_all(&template.expressions),
+            Node::ArrayExpression(array) => self.visit_all(array.elements.iter().flatten()),
+            Node::ObjectExpression(object) => self.visit_all(&object.properties),
+            Node::Property(property) => {
+                if property.computed {
+                    self.visit(&property.key);
+                }
+                self.visit(&property.value);
+            }
+            // Patterns only show up as the target of an assignment.
+            Node::ObjectPattern(_)
+            | Node::ArrayPattern(_)
+            | Node::RestElement(_)
+            | Node::AssignmentPattern(_) => {
+                self.pattern(node, Target::Assign(ReferenceKind::Write))
+            }
+            Node::ThisExpression(_)
+            | Node::NumericLiteral(_)
+            | Node::BigIntLiteral(_)
+            | Node::StringLiteral(_)
+            | Node::BooleanLiteral(_)
+            | Node::NullLiteral(_)
+            | Node::RegExpLiteral(_)
+            | Node::EmptyStatement(_)
+            | Node::BreakStatement(_)
+            | Node::ContinueStatement(_)
+            | Node::ExportAllDeclaration(_) => {}
+            Node::ImportDeclaration(import) => {
+                for specifier in &import.specifiers {
+                    let local = match specifier {
+                        Node::ImportSpecifier(specifier) => &specifier.local,
+                        Node::ImportDefaultSpecifier(specifier) => &specifier.local,
+                        Node::ImportNamespaceSpecifier(specifier) => &specifier.local,
+                        _ => continue,
+                    };
+                    self.declare(&local.value, BindingKind::Import, local.span, 0);
+                }
+            }
+            Node::ImportSpecifier(_)
+            | Node::ImportDefaultSpecifier(_)
+            | Node::ImportNamespaceSpecifier(_) => {}
+            Node::ExportNamedDeclaration(export) => {
+                if let Some(declaration) = &export.declaration {
+                    self.visit(declaration);
+                }
+                // Without a `from`, the specifiers export local bindings.
+                if export.source.is_none() {
+                    self.visit_all(&export.specifiers);
+                }
+            }
+            Node::ExportSpecifier(specifier) => self.visit(&specifier.local),
+            Node::ExportDefaultDeclaration(export) => self.visit(&export.declaration),
+            Node::FunctionDeclaration(function) => {
+                if let Some(ident) = &function.identifier {
+                    self.declare(&ident.value, BindingKind::Function, ident.span, 0);
+                }
+                self.push_scope(ScopeKind::Function, function.span);
+                for param in &function.params {
+                    self.pattern(&param.pat, Target::Declare(BindingKind::Parameter, 0));
+                }
+                self.visit_all(&function.body.statements);
+                self.pop_scope();
+            }
+            Node::ArrowFunctionExpression(arrow) => {
+                self.push_scope(ScopeKind::Function, arrow.span);
+                for param in &arrow.params {
+                    self.pattern(&param.pat, Target::Declare(BindingKind::Parameter, 0));
+                }
+                match &*arrow.body {
+                    Node::BlockStatement(body) => self.visit_all(&body.statements),
+                    body => self.visit(body),
+                }
+                self.pop_scope();
+            }
+            Node::VariableDeclaration(declaration) => {
+                let kind = match declaration.kind.as_str() {
+                    "let" => BindingKind::Let,
+                    "const" => BindingKind::Const,
+                    _ => BindingKind::Var,
+                };
+                for declarator in &declaration.declarations {
+                    let initialized_at = declarator.span.end.index;
+                    self.pattern(&declarator.id, Target::Declare(kind, initialized_at));
+                    if let Some(init) = &declarator.init {
+                        self.visit(init);
+                    }
+                }
+            }
+            Node::BlockStatement(block) => {
+                self.push_scope(ScopeKind::Block, block.span);
+                self.visit_all(&block.statements);
+                self.pop_scope();
+            }
+            Node::IfStatement(statement) => {
+                self.visit(&statement.test);
+                self.visit(&statement.consequent);
+                if let Some(alternate) = &statement.alternate {
+                    self.visit(alternate);
+                }
+            }
+            Node::WhileStatement(statement) => {
+                self.visit(&statement.test);
+                self.visit(&statement.body);
+            }
+            Node::DoWhileStatement(statement) => {
+                self.visit(&statement.body);
+                self.visit(&statement.test);
+            }
+            Node::ReturnStatement(statement) => {
+                if let Some(argument) = &statement.argument {
+                    self.visit(argument);
+                }
+            }
+            Node::ThrowStatement(statement) => self.visit(&statement.argument),
+            Node::ExpressionStatement(statement) => self.visit(&statement.expression),
+            Node::Parameter(parameter) => {
+                self.pattern(&parameter.pat, Target::Declare(BindingKind::Parameter, 0))
+            }
+            Node::JSXElement(element) => {
+                self.jsx_element_name(&element.name);
+                self.visit_all(&element.attributes);
+                self.visit_all(&element.children);
+            }
+            Node::JSXFragment(fragment) => self.visit_all(&fragment.children),
+            // Attribute names are never references.
+            Node::JSXAttribute(attribute) => {
+                if let Some(value) = &attribute.value {
+                    self.visit(value);
+                }
+            }
+            Node::JSXSpreadAttribute(spread) => self.visit(&spread.argument),
+            Node::JSXExpressionContainer(container) => {
+                if let Some(expression) = &container.expression {
+                    self.visit(expression);
+                }
+            }
+            Node::JSXText(_)
+            | Node::JSXIdentifier(_)
+            | Node::JSXMemberExpression(_)
+            | Node::JSXNamespacedName(_) => {}
+        }
+    }
+
+    /// An element name refers to a variable if it's capitalized, like `<App />`,
+    /// or dotted, like `<ui.Button />`, where it's the leftmost name.  Other
+    /// names, like `<div>`, are built in elements.
+    fn jsx_element_name(&mut self, name: &Node) {
+        match name {
+            Node::JSXIdentifier(ident) => {
+                let is_component = !ident.value.starts_with(|c: char| c.is_ascii_lowercase())
+                    && !ident.value.contains('-');
+                if is_component {
+                    self.reference(&ident.value, ident.span, ReferenceKind::Read);
+                }
+            }
+            Node::JSXMemberExpression(member) => {
+                let mut object = &*member.object;
+                while let Node::JSXMemberExpression(member) = object {
+                    object = &member.object;
+                }
+                if let Node::JSXIdentifier(ident) = object {
+                    if ident.value != "this" {
+                        self.reference(&ident.value, ident.span, ReferenceKind::Read);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Visits a pattern, declaring or assigning to the names in it.  Default
+    /// values and computed keys are visited as expressions.
+    fn pattern(&mut self, node: &Node, target: Target) {
+        match node {
+            Node::Identifier(ident) => match target {
+                Target::Declare(kind, initialized_at) => {
+                    self.declare(&ident.value, kind, ident.span, initialized_at)
+                }
+                Target::Assign(kind) => self.reference(&ident.value, ident.span, kind),
+            },
+            Node::ObjectPattern(pattern) => {
+                for property in &pattern.properties {
+                    match property {
+                        Node::Property(property) => {
+                            if property.computed {
+                                self.visit(&property.key);
+                            }
+                            self.pattern(&property.value, target);
+                        }
+                        _ => self.pattern(property, target),
+                    }
+                }
+            }
+            Node::ArrayPattern(pattern) => {
+                for element in pattern.elements.iter().flatten() {
+                    self.pattern(element, target);
+                }
+            }
+            Node::RestElement(rest) => self.pattern(&rest.argument, target),
+            Node::AssignmentPattern(pattern) => {
+                self.pattern(&pattern.lhs, target);
+                self.visit(&pattern.rhs);
+            }
+            // A member expression, which reads its object rather than
+            // assigning to a binding.
+            _ => self.visit(node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::*;
+
+    fn analyze_src(src: &str) -> ScopeTree {
+        analyze(&parse(src, "script.js").unwrap())
+    }
+
+    /// The references to `name`, in source order.
+    fn references<'a>(tree: &'a ScopeTree, name: &str) -> Vec<&'a Reference> {
+        tree.references()
+            .map(|(_, reference)| reference)
+            .filter(|reference| reference.name == name)
+            .collect()
+    }
+
+    #[test]
+    fn test_hoisting() {
+        let tree = analyze_src(
+            "a(); function a() {}\nb; var b = 1;\n{ var c; let e; function d() {} }\nc; d; e;",
+        );
+        let root = tree.root();
+        let names: Vec<_> = tree
+            .scope(root)
+            .bindings
+            .iter()
+            .map(|id| tree.binding(*id).name.as_str())
+            .collect();
+        assert_eq!(names, ["a", "b", "c"]);
+
+        for name in ["a", "b", "c"] {
+            let binding = tree.scope(root).get(name);
+            assert!(references(&tree, name).iter().all(|r| r.binding == binding));
+        }
+
+        // Declarations in a block other than `var` stay in the block.
+        let unresolved: Vec<_> = tree.unresolved().map(|r| r.name.as_str()).collect();
+        assert_eq!(unresolved, ["d", "e"]);
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let tree = analyze_src("let a; function f(a) { a; { let a; a; } } a;");
+        let a = references(&tree, "a");
+        let kinds: Vec<_> = a
+            .iter()
+            .map(|r| tree.binding(r.binding.unwrap()).kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [BindingKind::Parameter, BindingKind::Let, BindingKind::Let]
+        );
+        assert_ne!(a[1].binding, a[2].binding);
+        assert_eq!(tree.binding(a[2].binding.unwrap()).scope, tree.root());
+    }
+
+    #[test]
+    fn test_temporal_dead_zone() {
+        let tree = analyze_src("{ x; let x = x; x; }\nfunction f() { return y; }\nconst y = 1;");
+        let x: Vec<_> = references(&tree, "x").iter().map(|r| r.in_tdz).collect();
+        assert_eq!(x, [true, true, false]);
+
+        // `f` might only be called once `y` is initialized.
+        let y = references(&tree, "y");
+        assert!(!y[0].in_tdz);
+        assert!(tree.binding(y[0].binding.unwrap()).is_captured);
+    }
+
+    #[test]
+    fn test_closures() {
+        let tree =
+            analyze_src("let g;\nfunction outer(p) { let a = 1, b = 2; return () => a + p + g; }");
+        let arrow = tree
+            .scopes()
+            .filter(|(_, scope)| scope.kind == ScopeKind::Function)
+            .last()
+            .unwrap()
+            .0;
+        let captured: Vec<_> = tree
+            .captures(arrow)
+            .into_iter()
+            .map(|id| tree.binding(id).name.as_str())
+            .collect();
+        assert_eq!(captured, ["a", "p", "g"]);
+
+        let outer = tree.scope(tree.root()).get("outer").unwrap();
+        let b = tree.lookup(tree.binding(outer).scope, "b");
+        assert_eq!(b, None);
+        let b = tree
+            .bindings()
+            .find(|(_, binding)| binding.name == "b")
+            .unwrap()
+            .1;
+        assert!(!b.is_captured);
+    }
+
+    #[test]
+    fn test_reference_kinds() {
+        let tree = analyze_src("let a, b, c; a = 1; a += 2; a++; [b, ...c.d] = [c];");
+        let kinds: Vec<_> = tree
+            .references()
+            .map(|(_, r)| (r.name.as_str(), r.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("a", ReferenceKind::Write),
+                ("a", ReferenceKind::ReadWrite),
+                ("a", ReferenceKind::ReadWrite),
+                ("b", ReferenceKind::Write),
+                ("c", ReferenceKind::Read),
+                ("c", ReferenceKind::Read),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_modules() {
+        let tree = analyze(
+            &parse(
+                "import a, { b as c } from 'mod';\nexport { c };\nexport { d } from 'other';",
+                "module.js",
+            )
+            .unwrap(),
+        );
+        let c = tree.scope(tree.root()).get("c").unwrap();
+        assert_eq!(tree.binding(c).kind, BindingKind::Import);
+        assert_eq!(tree.binding(c).references.len(), 1);
+        assert!(tree.scope(tree.root()).get("a").is_some());
+        assert_eq!(tree.unresolved().count(), 0);
+    }
+
+    #[test]
+    fn test_jsx_references() {
+        let tree = analyze(
+            &parse(
+                "import App from './app';
+<App title={t}><div data-a={b} /><ui.Button /></App>",
+                "index.jsx",
+            )
+            .unwrap(),
+        );
+        let names: Vec<_> = tree.references().map(|(_, r)| r.name.as_str()).collect();
+        assert_eq!(names, ["App", "t", "b", "ui"]);
+        let app = tree.scope(tree.root()).get("App").unwrap();
+        assert_eq!(tree.binding(app).references.len(), 1);
+    }
+}
@@ -0,0 +1,153 @@
+//! Reads source files into UTF-8.  Most files are UTF-8 already, but some
+//! Windows editors save UTF-16 with a byte order mark, which
+//! `fs::read_to_string` rejects outright, and UTF-8 files sometimes start with
+//! a byte order mark, which would otherwise end up as the first character the
+//! lexer sees.
+
+use std::path::Path;
+
+use miette::{miette, ErrReport, IntoDiagnostic, Result, Severity};
+
+/// The contents of a source file, decoded to UTF-8 with any byte order mark
+/// removed, along with warnings about how it was decoded.
+#[derive(Debug)]
+pub struct SourceFile {
+    pub contents: String,
+    pub warnings: Vec<ErrReport>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Utf16 {
+    LittleEndian,
+    BigEndian,
+}
+
+fn decode_utf16(bytes: &[u8], endianness: Utf16, file_path: &str) -> Result<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(miette!(
+            "{} has a UTF-16 byte order mark, but an odd number of bytes",
+            file_path
+        ));
+    }
+
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| match endianness {
+            Utf16::LittleEndian => u16::from_le_bytes([pair[0], pair[1]]),
+            Utf16::BigEndian => u16::from_be_bytes([pair[0], pair[1]]),
+        })
+        .collect();
+
+    String::from_utf16(&units).map_err(|_| miette!("{} is not valid UTF-16", file_path))
+}
+
+/// Decodes the bytes of a source file, going by its byte order mark: UTF-16
+/// (either byte order) is transcoded with a warning, and anything without a
+/// UTF-16 byte order mark has to be UTF-8.
+pub fn decode_source(bytes: Vec<u8>, file_name: impl Into<String>) -> Result<SourceFile> {
+    let file_path = file_name.into();
+    let mut warnings = Vec::new();
+
+    let utf16 = match bytes.get(..2) {
+        Some([0xFF, 0xFE]) => Some((Utf16::LittleEndian, "UTF-16LE")),
+        Some([0xFE, 0xFF]) => Some((Utf16::BigEndian, "UTF-16BE")),
+        _ => None,
+    };
+
+    let contents = match utf16 {
+        Some((endianness, name)) => {
+            warnings.push(miette!(
+                severity = Severity::Warning,
+                "{} is encoded as {}, and was converted to UTF-8",
+                file_path,
+                name
+            ));
+            decode_utf16(&bytes[2..], endianness, &file_path)?
+        }
+        None => String::from_utf8(bytes).map_err(|err| {
+            miette!(
+                "{} is not valid UTF-8 (invalid byte at offset {})",
+                file_path,
+                err.utf8_error().valid_up_to()
+            )
+        })?,
+    };
+
+    let contents = match contents.strip_prefix('\u{feff}') {
+        Some(stripped) => stripped.to_string(),
+        None => contents,
+    };
+
+    Ok(SourceFile { contents, warnings })
+}
+
+/// Reads a source file from disk.  See [`decode_source`].
+pub fn read_source_file(path: impl AsRef<Path>) -> Result<SourceFile> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path).into_diagnostic()?;
+
+    decode_source(bytes, path.to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(src: &str, endianness: Utf16) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for unit in "\u{feff}".encode_utf16().chain(src.encode_utf16()) {
+            match endianness {
+                Utf16::LittleEndian => bytes.extend(unit.to_le_bytes()),
+                Utf16::BigEndian => bytes.extend(unit.to_be_bytes()),
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_utf8() {
+        let source = decode_source("let a = 'é';".into(), "a.js").unwrap();
+        assert_eq!(source.contents, "let a = 'é';");
+        assert!(source.warnings.is_empty());
+
+        let source = decode_source("\u{feff}let a;".into(), "a.js").unwrap();
+        assert_eq!(source.contents, "let a;");
+        assert!(source.warnings.is_empty());
+
+        assert_eq!(
+            decode_source(vec![b'a', 0xC3], "a.js")
+                .unwrap_err()
+                .to_string(),
+            "a.js is not valid UTF-8 (invalid byte at offset 1)"
+        );
+    }
+
+    #[test]
+    fn test_utf16() {
+        for (endianness, name) in [
+            (Utf16::LittleEndian, "UTF-16LE"),
+            (Utf16::BigEndian, "UTF-16BE"),
+        ] {
+            let source = decode_source(utf16("let a = '😀';", endianness), "a.js").unwrap();
+            assert_eq!(source.contents, "let a = '😀';");
+            assert_eq!(source.warnings.len(), 1);
+            assert_eq!(
+                source.warnings[0].to_string(),
+                format!("a.js is encoded as {}, and was converted to UTF-8", name)
+            );
+            assert_eq!(source.warnings[0].severity(), Some(Severity::Warning));
+        }
+
+        let mut odd = utf16("a", Utf16::LittleEndian);
+        odd.push(0);
+        assert!(decode_source(odd, "a.js").is_err());
+
+        // A lone surrogate.
+        assert_eq!(
+            decode_source(vec![0xFF, 0xFE, 0x00, 0xD8], "a.js")
+                .unwrap_err()
+                .to_string(),
+            "a.js is not valid UTF-16"
+        );
+    }
+}
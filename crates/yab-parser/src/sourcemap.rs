@@ -0,0 +1,442 @@
+//! A reader for version 3 source maps, for mapping a position in emitted code
+//! back to the original source: decoding production stack traces, and
+//! checking the maps we generate ourselves.
+//!
+//! See: https://tc39.es/source-map/
+
+use miette::{miette, Result};
+
+pub mod stack_trace;
+
+use crate::json::{self, JsonDocument, JsonValue, JsonValueKind};
+
+/// A position in an original source file.  Lines and columns count from zero,
+/// and columns are in UTF-16 code units, as in the source map itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OriginalLocation<'a> {
+    pub source: &'a str,
+    pub line: usize,
+    pub column: usize,
+    /// The original name of the identifier at this position, if the map
+    /// records one (usually because a minifier renamed it).
+    pub name: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Segment {
+    generated_column: usize,
+    original: Option<OriginalSegment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct OriginalSegment {
+    source: usize,
+    line: usize,
+    column: usize,
+    name: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SourceMap {
+    /// The emitted file this map is for, if it says.
+    pub file: Option<String>,
+    /// The original sources, with the map's `sourceRoot` already prepended.
+    pub sources: Vec<String>,
+    names: Vec<String>,
+    /// The decoded mappings for each line of the emitted file, sorted by
+    /// column.
+    lines: Vec<Vec<Segment>>,
+}
+
+impl SourceMap {
+    /// Parses a source map.  Index maps (the ones with `sections`) aren't
+    /// supported.
+    pub fn parse(src: &str, file_name: impl Into<String>) -> Result<Self> {
+        let doc = json::parse(src, file_name)?;
+        let root = &doc.root;
+        if root.as_object().is_none() {
+            return Err(doc.error_at(root, "Expected a source map object"));
+        }
+
+        match root.get("version") {
+            Some(JsonValue {
+                kind: JsonValueKind::Number(version),
+                ..
+            }) if *version == 3.0 => {}
+            Some(version) => return Err(doc.error_at(version, "Unsupported source map version")),
+            None => return Err(doc.error_at(root, "Missing source map version")),
+        }
+        if let Some(sections) = root.get("sections") {
+            return Err(doc.error_at(sections, "Index source maps are not supported"));
+        }
+
+        let source_root = optional_string(&doc, root.get("sourceRoot"))?.unwrap_or_default();
+        let sources = string_array(&doc, root.get("sources"))?
+            .into_iter()
+            .map(|source| join_source_root(source_root, source.unwrap_or_default()))
+            .collect();
+        let names = string_array(&doc, root.get("names"))?
+            .into_iter()
+            .map(|name| name.unwrap_or_default().to_string())
+            .collect();
+
+        let mappings = match root.get("mappings") {
+            Some(mappings) => mappings,
+            None => return Err(doc.error_at(root, "Missing source map mappings")),
+        };
+        let lines = match mappings.as_str() {
+            Some(encoded) => decode_mappings(encoded)
+                .ok_or_else(|| doc.error_at(mappings, "Invalid source map mappings"))?,
+            None => return Err(doc.error_at(mappings, "Expected mappings to be a string")),
+        };
+
+        let source_map = Self {
+            file: optional_string(&doc, root.get("file"))?.map(str::to_string),
+            sources,
+            names,
+            lines,
+        };
+        source_map.check_indices(&doc, mappings)?;
+
+        Ok(source_map)
+    }
+
+    fn check_indices(&self, doc: &JsonDocument, mappings: &JsonValue) -> Result<()> {
+        let out_of_range = self
+            .lines
+            .iter()
+            .flatten()
+            .filter_map(|segment| segment.original)
+            .any(|original| {
+                original.source >= self.sources.len()
+                    || original.name.is_some_and(|name| name >= self.names.len())
+            });
+        if out_of_range {
+            return Err(doc.error_at(
+                mappings,
+                "Source map mappings refer to a source or name that doesn't exist",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Finds where the code at the given (zero-based) line and UTF-16 column
+    /// of the emitted file came from: the closest mapping at or before the
+    /// column on that line.  Returns `None` if there isn't one, or if it maps
+    /// to nothing, as generated code like a bundle's runtime does.
+    pub fn lookup(&self, line: usize, column: usize) -> Option<OriginalLocation<'_>> {
+        let segments = self.lines.get(line)?;
+        let index = segments.partition_point(|segment| segment.generated_column <= column);
+        let original = segments.get(index.checked_sub(1)?)?.original?;
+
+        Some(OriginalLocation {
+            source: &self.sources[original.source],
+            line: original.line,
+            column: original.column,
+            name: original.name.map(|name| self.names[name].as_str()),
+        })
+    }
+}
+
+fn optional_string<'a>(
+    doc: &JsonDocument,
+    value: Option<&'a JsonValue>,
+) -> Result<Option<&'a str>> {
+    match value {
+        None
+        | Some(JsonValue {
+            kind: JsonValueKind::Null,
+            ..
+        }) => Ok(None),
+        Some(value) => match value.as_str() {
+            Some(string) => Ok(Some(string)),
+            None => Err(doc.error_at(
+                value,
+                &format!("Expected a string, found {}", value.type_name()),
+            )),
+        },
+    }
+}
+
+/// Reads an array of strings, where any entry may also be null.
+fn string_array<'a>(
+    doc: &JsonDocument,
+    value: Option<&'a JsonValue>,
+) -> Result<Vec<Option<&'a str>>> {
+    let Some(value) = value else {
+        return Ok(Vec::new());
+    };
+    let Some(values) = value.as_array() else {
+        return Err(doc.error_at(
+            value,
+            &format!("Expected an array, found {}", value.type_name()),
+        ));
+    };
+
+    values
+        .iter()
+        .map(|value| optional_string(doc, Some(value)))
+        .collect()
+}
+
+fn join_source_root(source_root: &str, source: &str) -> String {
+    if source_root.is_empty() {
+        return source.to_string();
+    }
+
+    format!("{}/{}", source_root.trim_end_matches('/'), source)
+}
+
+fn base64_value(c: u8) -> Option<u32> {
+    let value = match c {
+        b'A'..=b'Z' => c - b'A',
+        b'a'..=b'z' => c - b'a' + 26,
+        b'0'..=b'9' => c - b'0' + 52,
+        b'+' => 62,
+        b'/' => 63,
+        _ => return None,
+    };
+
+    Some(value.into())
+}
+
+/// Decodes one base64 VLQ from the front of `chars`.  Each digit holds five
+/// bits of the value, least significant first, plus a continuation bit, and
+/// the lowest bit of the result is the sign.
+fn decode_vlq(chars: &mut std::iter::Peekable<std::slice::Iter<u8>>) -> Option<i64> {
+    let mut value: i64 = 0;
+    let mut shift = 0;
+    loop {
+        let digit = base64_value(*chars.next()?)?;
+        value |= i64::from(digit & 0b11111).checked_shl(shift)?;
+        if digit & 0b100000 == 0 {
+            break;
+        }
+        shift += 5;
+        if shift > 60 {
+            return None;
+        }
+    }
+
+    Some(if value & 1 == 1 {
+        -(value >> 1)
+    } else {
+        value >> 1
+    })
+}
+
+fn apply_delta(value: &mut i64, delta: i64) -> Option<usize> {
+    *value = value.checked_add(delta)?;
+    usize::try_from(*value).ok()
+}
+
+/// Decodes the `mappings` field into segments for each generated line.  Every
+/// field is relative to the previous segment's, except that the generated
+/// column starts over on each line.
+fn decode_mappings(encoded: &str) -> Option<Vec<Vec<Segment>>> {
+    let mut lines = Vec::new();
+    let (mut source, mut line, mut column, mut name) = (0, 0, 0, 0);
+
+    for encoded_line in encoded.split(';') {
+        let mut segments = Vec::new();
+        let mut generated_column = 0;
+
+        for encoded_segment in encoded_line.split(',').filter(|s| !s.is_empty()) {
+            let mut chars = encoded_segment.as_bytes().iter().peekable();
+            let mut fields = Vec::with_capacity(5);
+            while chars.peek().is_some() {
+                fields.push(decode_vlq(&mut chars)?);
+            }
+
+            let generated_column = apply_delta(&mut generated_column, fields[0])?;
+            let original = match fields[1..] {
+                [] => None,
+                [source_delta, line_delta, column_delta, ref name_delta @ ..]
+                    if name_delta.len() <= 1 =>
+                {
+                    Some(OriginalSegment {
+                        source: apply_delta(&mut source, source_delta)?,
+                        line: apply_delta(&mut line, line_delta)?,
+                        column: apply_delta(&mut column, column_delta)?,
+                        name: match name_delta {
+                            [delta] => Some(apply_delta(&mut name, *delta)?),
+                            _ => None,
+                        },
+                    })
+                }
+                _ => return None,
+            };
+
+            segments.push(Segment {
+                generated_column,
+                original,
+            });
+        }
+
+        segments.sort_by_key(|segment| segment.generated_column);
+        lines.push(segments);
+    }
+
+    Some(lines)
+}
+
+/// Finds the URL in the last `//# sourceMappingURL=` comment of some emitted
+/// code, which is where its source map lives.
+pub fn source_mapping_url(code: &str) -> Option<&str> {
+    code.lines().rev().find_map(|line| {
+        let comment = line.trim().strip_prefix("//")?;
+        let url = comment
+            .strip_prefix("# sourceMappingURL=")
+            .or_else(|| comment.strip_prefix("@ sourceMappingURL="))?;
+        Some(url.trim())
+    })
+}
+
+/// Decodes an inline source map from a base64 `data:` URL.
+pub fn decode_data_url(url: &str) -> Result<String> {
+    let (header, data) = url
+        .strip_prefix("data:")
+        .and_then(|url| url.split_once(','))
+        .ok_or_else(|| miette!("Expected a data URL"))?;
+    if !header.ends_with(";base64") {
+        return Err(miette!("Only base64 data URLs are supported"));
+    }
+
+    let mut bytes = Vec::with_capacity(data.len() / 4 * 3);
+    let mut buffer = 0;
+    let mut bits = 0;
+    for c in data.trim_end_matches('=').bytes() {
+        let value = base64_value(c).ok_or_else(|| miette!("Invalid base64 in data URL"))?;
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    String::from_utf8(bytes).map_err(|_| miette!("Data URL is not valid UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Maps `a.js` to itself, with the original names of two renamed
+    // identifiers, plus a second line of generated code that maps nowhere.
+    const MAP: &str = r#"{
+        "version": 3,
+        "file": "out.js",
+        "sourceRoot": "src/",
+        "sources": ["a.js", "b.js"],
+        "names": ["foo", "bar"],
+        "mappings": "AAAAA,IAAIC,GACED;CCAA,C"
+    }"#;
+
+    fn location<'a>(
+        source: &'a str,
+        line: usize,
+        column: usize,
+        name: Option<&'a str>,
+    ) -> OriginalLocation<'a> {
+        OriginalLocation {
+            source,
+            line,
+            column,
+            name,
+        }
+    }
+
+    #[test]
+    fn test_decode_vlq() {
+        for (encoded, value) in [
+            ("A", 0),
+            ("C", 1),
+            ("D", -1),
+            ("gB", 16),
+            ("hB", -16),
+            ("+/D", 2047),
+        ] {
+            let bytes = encoded.as_bytes();
+            assert_eq!(decode_vlq(&mut bytes.iter().peekable()), Some(value));
+        }
+
+        // A continuation bit with nothing after it.
+        assert_eq!(decode_vlq(&mut b"g".iter().peekable()), None);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let map = SourceMap::parse(MAP, "out.js.map").unwrap();
+        assert_eq!(map.file.as_deref(), Some("out.js"));
+        assert_eq!(map.sources, vec!["src/a.js", "src/b.js"]);
+
+        assert_eq!(
+            map.lookup(0, 0),
+            Some(location("src/a.js", 0, 0, Some("foo")))
+        );
+        assert_eq!(
+            map.lookup(0, 3),
+            Some(location("src/a.js", 0, 0, Some("foo")))
+        );
+        assert_eq!(
+            map.lookup(0, 4),
+            Some(location("src/a.js", 0, 4, Some("bar")))
+        );
+        assert_eq!(
+            map.lookup(0, 99),
+            Some(location("src/a.js", 1, 6, Some("foo")))
+        );
+        assert_eq!(map.lookup(1, 0), None);
+        assert_eq!(map.lookup(1, 1), Some(location("src/b.js", 1, 6, None)));
+        assert_eq!(map.lookup(1, 2), None);
+        assert_eq!(map.lookup(2, 0), None);
+    }
+
+    #[test]
+    fn test_invalid_source_maps() {
+        let error = |src: &str| SourceMap::parse(src, "out.js.map").unwrap_err().to_string();
+
+        assert_eq!(
+            error(r#"{ "version": 2, "sources": [], "mappings": "" }"#),
+            "Unsupported source map version at out.js.map:1:14"
+        );
+        assert_eq!(
+            error(r#"{ "version": 3, "sections": [] }"#),
+            "Index source maps are not supported at out.js.map:1:29"
+        );
+        assert_eq!(
+            error(r#"{ "version": 3, "sources": [], "mappings": "A!" }"#),
+            "Invalid source map mappings at out.js.map:1:44"
+        );
+        assert_eq!(
+            error(r#"{ "version": 3, "sources": [], "mappings": "AAAA" }"#),
+            "Source map mappings refer to a source or name that doesn't exist at \
+             out.js.map:1:44"
+        );
+    }
+
+    #[test]
+    fn test_source_mapping_url() {
+        assert_eq!(
+            source_mapping_url("a();\n//# sourceMappingURL=out.js.map\n"),
+            Some("out.js.map")
+        );
+        assert_eq!(
+            source_mapping_url("//# sourceMappingURL=old.map\n//@ sourceMappingURL=new.map"),
+            Some("new.map")
+        );
+        assert_eq!(source_mapping_url("a();"), None);
+    }
+
+    #[test]
+    fn test_decode_data_url() {
+        assert_eq!(
+            decode_data_url("data:application/json;base64,eyJ2ZXJzaW9uIjozfQ==").unwrap(),
+            r#"{"version":3}"#
+        );
+        assert!(decode_data_url("data:application/json,{}").is_err());
+    }
+}
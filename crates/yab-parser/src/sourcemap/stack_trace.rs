@@ -0,0 +1,132 @@
+//! Rewrites JavaScript stack traces from bundled code to point at the
+//! original sources.  This understands the frame formats of V8 (`at f
+//! (file:1:2)`) and of Firefox and Safari (`f@file:1:2`).
+
+use std::ops::Range;
+
+use super::SourceMap;
+
+/// A location in a stack frame.  Lines and columns count from one, as they do
+/// in stack traces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameLocation<'a> {
+    /// Usually a URL, like `http://localhost:3000/main.js`.
+    pub file: &'a str,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Finds the `file:line:column` at the end of a stack frame, along with where
+/// it is in the frame.
+pub fn parse_frame_location(frame: &str) -> Option<(FrameLocation<'_>, Range<usize>)> {
+    let trimmed = frame.trim_end();
+    let end = trimmed.strip_suffix(')').unwrap_or(trimmed).len();
+
+    let mut parts = frame[..end].rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let rest = parts.next()?;
+
+    let start = if let Some(paren) = rest.rfind('(') {
+        paren + 1
+    } else if let Some(at) = rest.rfind('@') {
+        at + 1
+    } else if let Some(at) = rest.find("at ") {
+        at + 3
+    } else {
+        rest.len() - rest.trim_start().len()
+    };
+
+    let location = FrameLocation {
+        file: &rest[start..],
+        line,
+        column,
+    };
+    Some((location, start..end))
+}
+
+/// Rewrites each frame of `stack` whose file has a source map, as given by
+/// `source_map_for`, to the original source location.  Anything else, like
+/// the error message or frames from files without a map, is left as it is.
+pub fn remap_stack_trace<'m, F>(stack: &str, mut source_map_for: F) -> String
+where
+    F: FnMut(&str) -> Option<&'m SourceMap>,
+{
+    stack
+        .lines()
+        .map(|frame| {
+            let Some((location, range)) = parse_frame_location(frame) else {
+                return frame.to_string();
+            };
+            let original = location
+                .line
+                .checked_sub(1)
+                .zip(location.column.checked_sub(1));
+            let original = source_map_for(location.file)
+                .zip(original)
+                .and_then(|(source_map, (line, column))| source_map.lookup(line, column));
+
+            match original {
+                Some(original) => format!(
+                    "{}{}:{}:{}{}",
+                    &frame[..range.start],
+                    original.source,
+                    original.line + 1,
+                    original.column + 1,
+                    &frame[range.end..]
+                ),
+                None => frame.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(file: &str, line: usize, column: usize) -> FrameLocation<'_> {
+        FrameLocation { file, line, column }
+    }
+
+    #[test]
+    fn test_parse_frame_location() {
+        assert_eq!(
+            parse_frame_location("    at f (http://localhost:3000/main.js:10:15)"),
+            Some((location("http://localhost:3000/main.js", 10, 15), 10..45))
+        );
+        assert_eq!(
+            parse_frame_location("    at http://localhost:3000/main.js:1:2"),
+            Some((location("http://localhost:3000/main.js", 1, 2), 7..40))
+        );
+        assert_eq!(
+            parse_frame_location("f@http://localhost:3000/main.js:1:2"),
+            Some((location("http://localhost:3000/main.js", 1, 2), 2..35))
+        );
+        assert_eq!(parse_frame_location("TypeError: a is undefined"), None);
+        assert_eq!(
+            parse_frame_location("    at new Promise (<anonymous>)"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_remap_stack_trace() {
+        // Maps column 1 of line 1 to `src/a.js:3:5`.
+        let source_map = SourceMap::parse(
+            r#"{ "version": 3, "sources": ["src/a.js"], "mappings": "AAEI" }"#,
+            "main.js.map",
+        )
+        .unwrap();
+        let stack = "Error: oops\n    at f (http://localhost/main.js:1:1)\n    \
+                     at g (http://localhost/vendor.js:1:1)\nf@http://localhost/main.js:1:3";
+
+        assert_eq!(
+            remap_stack_trace(stack, |file| (file == "http://localhost/main.js")
+                .then_some(&source_map)),
+            "Error: oops\n    at f (src/a.js:3:5)\n    at g (http://localhost/vendor.js:1:1)\n\
+             f@src/a.js:3:5"
+        );
+    }
+}
@@ -0,0 +1 @@
+const mask = 0x;
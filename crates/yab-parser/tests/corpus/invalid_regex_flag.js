@@ -0,0 +1,2 @@
+const ok = /^a+$/gim;
+const bad = /^a+$/gq;
@@ -0,0 +1 @@
+const smile = "\u{1F60G}";
@@ -0,0 +1,5 @@
+function greet(name) {
+	if (name) {
+	    return "hi \x4G" + name;
+	}
+}
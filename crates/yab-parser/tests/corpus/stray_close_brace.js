@@ -0,0 +1,5 @@
+export function foo() {
+  return 1;
+}
+}
+export const bar = 2;
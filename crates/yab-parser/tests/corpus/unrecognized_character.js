@@ -0,0 +1 @@
+const total = price @ quantity;
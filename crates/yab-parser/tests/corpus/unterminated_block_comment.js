@@ -0,0 +1,2 @@
+/* TODO: finish this comment
+export const answer = 42;
@@ -0,0 +1,2 @@
+const slash = /\/path\/to;
+console.log(slash);
@@ -0,0 +1,2 @@
+const html = `<div class="card">
+  <h1>${title}</h1>
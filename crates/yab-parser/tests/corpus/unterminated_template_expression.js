@@ -0,0 +1,2 @@
+const label = `count: ${items.length;
+render(label);
@@ -0,0 +1,75 @@
+//! Snapshot tests for the diagnostics we produce on intentionally broken
+//! source files.  Each `corpus/<name>.js` file is run through the front end,
+//! and the rendered report (or just "ok" if it was accepted) is compared
+//! against `corpus/<name>.snap`.
+//!
+//! To accept changed output after an intentional change to a diagnostic, run
+//! with `YAB_UPDATE_SNAPSHOTS=1` (or `just update_snapshots`) and review the
+//! diff of the .snap files.
+
+use std::{fs, path::Path};
+
+use miette::{GraphicalReportHandler, GraphicalTheme};
+
+fn render_diagnostics(src: &str, file_name: &str) -> String {
+    match yab_parser::parser::parse(src, file_name) {
+        Ok(_) => "ok\n".to_string(),
+        Err(report) => {
+            let mut rendered = String::new();
+            GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor())
+                .with_links(false)
+                .with_width(100)
+                .render_report(&mut rendered, report.as_ref())
+                .unwrap();
+            rendered
+        }
+    }
+}
+
+#[test]
+fn test_diagnostics_corpus() {
+    let corpus_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let update = std::env::var_os("YAB_UPDATE_SNAPSHOTS").is_some();
+
+    let mut sources = fs::read_dir(&corpus_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "js"))
+        .collect::<Vec<_>>();
+    sources.sort();
+    assert!(!sources.is_empty(), "no corpus files found");
+
+    let mut failures = Vec::new();
+    for source_path in sources {
+        let file_name = format!(
+            "corpus/{}",
+            source_path.file_name().unwrap().to_string_lossy()
+        );
+        let src = fs::read_to_string(&source_path).unwrap();
+        let actual = render_diagnostics(&src, &file_name);
+
+        let snapshot_path = source_path.with_extension("snap");
+        if update {
+            fs::write(&snapshot_path, &actual).unwrap();
+            continue;
+        }
+
+        match fs::read_to_string(&snapshot_path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{}: snapshot mismatch\n--- expected\n{}\n+++ actual\n{}",
+                file_name, expected, actual
+            )),
+            Err(_) => failures.push(format!(
+                "{}: missing snapshot, actual output was:\n{}",
+                file_name, actual
+            )),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\n\nrerun with YAB_UPDATE_SNAPSHOTS=1 to accept the new output",
+        failures.join("\n\n")
+    );
+}